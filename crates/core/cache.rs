@@ -0,0 +1,404 @@
+#![deny(warnings)]
+
+use crate::{
+    cask,
+    checksum::{self, Algorithm},
+    retry::retry_async,
+    util::file_sha256,
+};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use downloader::{DownloadOption, FetchResult, Validators};
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// the environment variable to specify the cache folder, eg. a shared mount across machines
+const ENV_CACHE_DIR: &str = "CASK_CACHE_DIR";
+
+// the cache folder of downloaded resources
+pub fn cache_dir(cask: &cask::Cask) -> PathBuf {
+    match env::var_os(ENV_CACHE_DIR) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => cask.shared_root_dir().join("cache"),
+    }
+}
+
+// the resource is stored by the SHA256 of its content
+fn blob_path(cask: &cask::Cask, hash: &str) -> PathBuf {
+    cache_dir(cask).join("sha256").join(hash)
+}
+
+// the file records the SHA256 of resource that downloaded from the URL
+fn url_index_path(cask: &cask::Cask, url: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(url);
+
+    cache_dir(cask)
+        .join("url")
+        .join(format!("{:x}", hasher.finalize()))
+}
+
+// the file caches the tags of repository
+fn tags_path(cask: &cask::Cask, repository: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(repository);
+
+    cache_dir(cask)
+        .join("tags")
+        .join(format!("{:x}", hasher.finalize()))
+}
+
+// the file caches the response of URL. eg. the release API
+fn response_path(cask: &cask::Cask, url: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(url);
+
+    cache_dir(cask)
+        .join("http")
+        .join(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,          // the ETag header of response
+    last_modified: Option<String>, // the Last-Modified header of response
+    body: String,
+}
+
+// whether the file is modified within the ttl
+fn is_fresh(filepath: &Path, ttl: Duration) -> bool {
+    fs::metadata(filepath)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.elapsed().ok())
+        .map(|elapsed| elapsed < ttl)
+        .unwrap_or(false)
+}
+
+pub fn load_tags(cask: &cask::Cask, repository: &str) -> Option<Vec<String>> {
+    fs::read_to_string(tags_path(cask, repository))
+        .ok()
+        .map(|content| content.lines().map(|line| line.to_string()).collect())
+}
+
+// load the cached tags if they are fetched within the ttl
+pub fn load_fresh_tags(cask: &cask::Cask, repository: &str, ttl: Duration) -> Option<Vec<String>> {
+    if is_fresh(&tags_path(cask, repository), ttl) {
+        load_tags(cask, repository)
+    } else {
+        None
+    }
+}
+
+pub fn store_tags(cask: &cask::Cask, repository: &str, tags: &[String]) -> Result<(), Report> {
+    let filepath = tags_path(cask, repository);
+
+    if let Some(dir) = filepath.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(filepath, tags.join("\n"))?;
+
+    Ok(())
+}
+
+fn store_response(filepath: &Path, response: &CachedResponse) -> Result<(), Report> {
+    if let Some(dir) = filepath.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(filepath, serde_json::to_string(response)?)?;
+
+    Ok(())
+}
+
+// fetch the text content of URL, return None if the resource does not exist.
+// the cached response is used within the ttl, otherwise it is revalidated by ETag/Last-Modified
+pub async fn fetch_text(
+    cask: &cask::Cask,
+    url: &str,
+    option: &DownloadOption,
+    ttl: Duration,
+) -> Result<Option<String>, Report> {
+    let filepath = response_path(cask, url);
+
+    let cached = fs::read_to_string(&filepath)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CachedResponse>(&content).ok());
+
+    if let Some(cached) = cached.as_ref() {
+        if cask.settings().offline || is_fresh(&filepath, ttl) {
+            return Ok(Some(cached.body.clone()));
+        }
+    }
+
+    let validators = cached
+        .as_ref()
+        .map(|c| Validators {
+            etag: c.etag.clone(),
+            last_modified: c.last_modified.clone(),
+        })
+        .unwrap_or_default();
+
+    let result = retry_async(
+        &cask.settings().get_retry_policy(),
+        downloader::is_transient,
+        || downloader::fetch_text_conditional(url, option, &validators),
+    )
+    .await?;
+
+    match (result, cached) {
+        (FetchResult::Modified(body, validators), _) => {
+            let response = CachedResponse {
+                etag: validators.etag,
+                last_modified: validators.last_modified,
+                body,
+            };
+
+            store_response(&filepath, &response)?;
+
+            Ok(Some(response.body))
+        }
+        (FetchResult::NotModified, Some(cached)) => {
+            // write it again so that it is fresh in the next ttl
+            store_response(&filepath, &cached)?;
+
+            Ok(Some(cached.body))
+        }
+        (FetchResult::NotModified, None) => Err(eyre::format_err!(
+            "Fetch {} fail: the server responds 304 for unconditional request",
+            url
+        )),
+        (FetchResult::NotFound, _) => {
+            fs::remove_file(&filepath).ok();
+
+            Ok(None)
+        }
+    }
+}
+
+// link the file to the destination, copy it if they are in different file system
+fn link_or_copy(src: &Path, dest: &Path) -> Result<(), Report> {
+    if dest.exists() {
+        fs::remove_file(dest)?;
+    }
+
+    if fs::hard_link(src, dest).is_err() {
+        fs::copy(src, dest)?;
+    }
+
+    Ok(())
+}
+
+// restore the cached resource to the file, return false if it is not cached
+pub fn restore(
+    cask: &cask::Cask,
+    url: &str,
+    checksum: Option<&str>,
+    filepath: &Path,
+) -> Result<bool, Report> {
+    let expected = checksum.map(checksum::parse).transpose()?;
+
+    // the cache is addressed by SHA256, the resource of another algorithm is found by its URL
+    let hash = match &expected {
+        Some(c) if c.algorithm == Algorithm::Sha256 => c.hash.clone(),
+        _ => match fs::read_to_string(url_index_path(cask, url)) {
+            Ok(hash) => hash.trim().to_string(),
+            Err(_) => return Ok(false),
+        },
+    };
+
+    let blob = blob_path(cask, &hash);
+
+    if !blob.is_file() {
+        return Ok(false);
+    }
+
+    // the cache may be corrupted
+    if file_sha256(&blob)? != hash {
+        fs::remove_file(&blob).ok();
+        return Ok(false);
+    }
+
+    // the resource of URL may be changed since it is cached
+    if let Some(c) = expected.filter(|c| c.algorithm != Algorithm::Sha256) {
+        if checksum::file_hash(&blob, c.algorithm)? != c.hash {
+            return Ok(false);
+        }
+    }
+
+    link_or_copy(&blob, filepath)?;
+
+    Ok(true)
+}
+
+// store the downloaded resource into cache
+pub fn store(cask: &cask::Cask, url: &str, filepath: &Path) -> Result<(), Report> {
+    let hash = file_sha256(filepath)?;
+
+    let blob = blob_path(cask, &hash);
+
+    if !blob.exists() {
+        let blob_dir = blob
+            .parent()
+            .ok_or_else(|| eyre::format_err!("can not get parent of '{}'", blob.display()))?;
+
+        fs::create_dir_all(blob_dir)?;
+
+        // write to the temp file first, so that the blob is always complete
+        let temp_file = blob_dir.join(format!("{}.tmp", hash));
+
+        link_or_copy(filepath, &temp_file)?;
+        fs::rename(&temp_file, &blob)?;
+    }
+
+    let index = url_index_path(cask, url);
+
+    if let Some(index_dir) = index.parent() {
+        fs::create_dir_all(index_dir)?;
+    }
+
+    fs::write(index, &hash)?;
+
+    Ok(())
+}
+
+// the cached resources that are not referenced by any URL, and the URL records of missing resources.
+// the temp files are left if the process was interrupted while storing
+pub fn list_orphaned_files(cask: &cask::Cask) -> Result<Vec<PathBuf>, Report> {
+    let blob_dir = cache_dir(cask).join("sha256");
+    let index_dir = cache_dir(cask).join("url");
+
+    let read_dir = |dir: &Path| -> Result<Vec<PathBuf>, Report> {
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect())
+    };
+
+    let mut orphaned = vec![];
+    let mut referenced = vec![];
+
+    for index in read_dir(&index_dir)? {
+        let hash = fs::read_to_string(&index)?.trim().to_string();
+
+        if blob_path(cask, &hash).is_file() {
+            referenced.push(hash);
+        } else {
+            orphaned.push(index);
+        }
+    }
+
+    for blob in read_dir(&blob_dir)? {
+        let name = blob
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !referenced.contains(&name) {
+            orphaned.push(blob);
+        }
+    }
+
+    Ok(orphaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use crate::{cache, cask};
+
+    #[test]
+    fn test_is_fresh() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        assert!(cache::is_fresh(file.path(), Duration::from_secs(300)));
+        assert!(!cache::is_fresh(file.path(), Duration::ZERO));
+        assert!(!cache::is_fresh(
+            &file.path().with_extension("not_exist"),
+            Duration::from_secs(300)
+        ));
+    }
+
+    #[test]
+    fn test_store_and_restore() {
+        let root = tempfile::tempdir().unwrap();
+        let cask = cask::new(root.path());
+
+        let url = "https://example.com/foo.tar.gz";
+
+        let file = root.path().join("foo.tar.gz");
+        fs::write(&file, "hello").unwrap();
+
+        let restored = root.path().join("restored.tar.gz");
+
+        assert!(!cache::restore(&cask, url, None, &restored).unwrap());
+
+        cache::store(&cask, url, &file).unwrap();
+
+        assert!(cache::restore(&cask, url, None, &restored).unwrap());
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "hello");
+
+        // the same content is shared by another URL with checksum
+        let checksum = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(
+            cache::restore(&cask, "https://example.com/bar", Some(checksum), &restored).unwrap()
+        );
+
+        // the checksum of another algorithm is verified after the resource is found by URL
+        let sha512 = "sha512:9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043";
+        assert!(cache::restore(&cask, url, Some(sha512), &restored).unwrap());
+        assert!(
+            !cache::restore(&cask, "https://example.com/bar", Some(sha512), &restored).unwrap()
+        );
+        assert!(!cache::restore(
+            &cask,
+            url,
+            Some(&format!("sha512:{}", "0".repeat(128))),
+            &restored
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_list_orphaned_files() {
+        let root = tempfile::tempdir().unwrap();
+        let cask = cask::new(root.path());
+
+        assert!(cache::list_orphaned_files(&cask).unwrap().is_empty());
+
+        let file = root.path().join("foo.tar.gz");
+        fs::write(&file, "hello").unwrap();
+
+        cache::store(&cask, "https://example.com/foo.tar.gz", &file).unwrap();
+
+        assert!(cache::list_orphaned_files(&cask).unwrap().is_empty());
+
+        let blob_dir = cache::cache_dir(&cask).join("sha256");
+        let orphaned_blob = blob_dir.join("abc");
+
+        fs::write(&orphaned_blob, "orphaned").unwrap();
+
+        assert_eq!(
+            cache::list_orphaned_files(&cask).unwrap(),
+            vec![orphaned_blob]
+        );
+
+        // the URL record is orphaned once the resource is removed
+        fs::remove_dir_all(&blob_dir).unwrap();
+
+        assert_eq!(cache::list_orphaned_files(&cask).unwrap().len(), 1);
+    }
+}
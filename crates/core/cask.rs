@@ -0,0 +1,310 @@
+#![deny(warnings)]
+
+use crate::{config::Config, formula, index, lock, receipt, settings::Settings};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Report;
+use semver::Version;
+use sha2::{Digest, Sha256};
+pub struct Cask {
+    root: PathBuf,        // the root of the cask
+    shared_root: PathBuf, // the root of config, build-in formula and cache, it is the user root for project-local packages
+    bin: Option<PathBuf>, // the folder that the executables are linked into, it is the 'bin' of root by default
+    config: Config, // the config that is loaded once, so that it does not change while running
+    settings: Settings, // the settings that are resolved from the flags, environment variables and config
+}
+
+fn hash_package_name(package_name: &str) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(package_name);
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn new(root: &Path) -> Cask {
+    Cask {
+        root: root.to_path_buf(),
+        shared_root: root.to_path_buf(),
+        bin: None,
+        config: Config::default(),
+        settings: Settings::default(),
+    }
+}
+
+// the project-local packages are installed into the project, they share the config and cache of user root
+pub fn new_local(root: &Path, user_root: &Path) -> Cask {
+    Cask {
+        root: root.to_path_buf(),
+        shared_root: user_root.to_path_buf(),
+        bin: None,
+        config: Config::default(),
+        settings: Settings::default(),
+    }
+}
+
+impl Cask {
+    // override the bin folder. eg. '/usr/local/bin' that is in $PATH already
+    pub fn with_bin_dir(mut self, bin: Option<PathBuf>) -> Self {
+        self.bin = bin;
+        self
+    }
+
+    // use the config that is loaded from the config file of root. see `config::load`
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // use the settings that are resolved once. see `settings::resolve`
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // init the cask folder
+    pub fn init(&self) -> Result<(), Report> {
+        if !self.root_dir().exists() {
+            fs::create_dir_all(self.root_dir())?;
+        }
+
+        if !self.bin_dir().exists() {
+            fs::create_dir_all(self.bin_dir())?;
+        }
+
+        if !self.formula_dir().exists() {
+            fs::create_dir_all(self.formula_dir())?;
+        }
+
+        Ok(())
+    }
+
+    // check bin path of Cask
+    pub fn check_bin_path(&self) -> Result<(), Report> {
+        let key = "PATH";
+
+        let paths = env::var_os(key)
+            .ok_or_else(|| eyre::format_err!("{} is not defined in the environment.", key))?;
+
+        for path in env::split_paths(&paths) {
+            let abs_path_str = path.as_os_str().to_string_lossy().replace(
+                '~',
+                &dirs::home_dir()
+                    .ok_or_else(|| eyre::format_err!("can not get home dir"))?
+                    .as_os_str()
+                    .to_string_lossy(),
+            );
+
+            let abs_path = Path::new(&abs_path_str);
+
+            if format!("{}", abs_path.display()) == format!("{}", self.bin_dir().display()) {
+                return Ok(());
+            }
+        }
+
+        let msg = format!(
+            r#"REQUIREMENT:
+
+            make sure '{}' has been add to your $PATH environment variable.
+
+            manually add the directory to your $HOME/.bash_profile (or similar)
+
+            or run 'cask env --apply' to add it, the user PATH in registry is updated on Windows
+
+            then create a new session in terminal
+            "#,
+            self.bin_dir().display()
+        )
+        .lines()
+        .map(|s| s.trim_start().to_owned())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+        Err(eyre::format_err!(msg))
+    }
+
+    pub fn root_dir(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    pub fn bin_dir(&self) -> PathBuf {
+        self.bin
+            .clone()
+            .unwrap_or_else(|| self.root_dir().join("bin"))
+    }
+
+    // the folder of man pages, man finds it by the bin folder in $PATH without setting $MANPATH
+    pub fn man_dir(&self) -> PathBuf {
+        self.root_dir().join("share").join("man")
+    }
+
+    pub fn formula_dir(&self) -> PathBuf {
+        self.root_dir().join("formula")
+    }
+
+    // the root of the shared data, it is the same as the root unless the packages are project-local
+    pub fn shared_root_dir(&self) -> PathBuf {
+        self.shared_root.clone()
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.shared_root_dir().join("config.toml")
+    }
+
+    pub fn build_in_formula_dir(&self) -> PathBuf {
+        self.shared_root_dir().join("build-in")
+    }
+
+    // the folder of the formula repositories of taps, a folder per tap
+    pub fn taps_dir(&self) -> PathBuf {
+        self.shared_root_dir().join("taps")
+    }
+
+    // the folder of the state of Cask. eg. the index of installed packages
+    pub fn state_dir(&self) -> PathBuf {
+        self.root_dir().join("state")
+    }
+
+    // the folder of operation logs, a file per run
+    pub fn logs_dir(&self) -> PathBuf {
+        self.shared_root_dir().join("logs")
+    }
+
+    // the folder of lock files, they are not put into the package folder which may be removed while it is locked
+    pub fn lock_dir(&self) -> PathBuf {
+        self.root_dir().join("locks")
+    }
+
+    // the lock file of the shared folders. eg. the bin folder
+    pub fn lock_file(&self) -> PathBuf {
+        self.lock_dir().join("cask.lock")
+    }
+
+    // the package folder
+    pub fn package_dir(&self, package_name: &str) -> PathBuf {
+        self.formula_dir().join(hash_package_name(package_name))
+    }
+
+    // the lock file of package, the package folder is named by the hash of package name
+    pub fn package_lock_file(&self, package_dir_name: &str) -> PathBuf {
+        self.lock_dir().join(format!("{}.lock", package_dir_name))
+    }
+
+    // lock the package, so that it is not installed or removed by another cask process at the same time
+    pub fn lock_package(&self, package_name: &str) -> Result<lock::Lock, Report> {
+        lock::acquire(
+            &self.package_lock_file(&hash_package_name(package_name)),
+            &format!("package '{}'", package_name),
+        )
+    }
+
+    // lock the shared folders for a short time. eg. create the symlink in bin folder
+    pub fn lock(&self) -> Result<lock::Lock, Report> {
+        lock::acquire(&self.lock_file(), "cask folder")
+    }
+
+    // the folder that the resources are downloaded to
+    pub fn package_version_dir(&self, package_name: &str) -> PathBuf {
+        self.package_dir(package_name).join("version")
+    }
+
+    // the folder of installed versions, the versions are kept side by side. eg. versions/1.0.0/bin
+    pub fn package_versions_dir(&self, package_name: &str) -> PathBuf {
+        self.package_dir(package_name).join("versions")
+    }
+
+    // the folder that the version is installed into
+    pub fn package_installed_dir(&self, package_name: &str, version: &str) -> PathBuf {
+        self.package_versions_dir(package_name).join(version)
+    }
+
+    // the installed versions of package, the version is complete only if its receipt is written
+    pub fn list_installed_versions(&self, package_name: &str) -> Result<Vec<String>, Report> {
+        let versions_dir = self.package_versions_dir(package_name);
+
+        if !versions_dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut versions = vec![];
+
+        for entry in fs::read_dir(versions_dir)?.filter_map(|f| f.ok()) {
+            if receipt::path(&entry.path()).exists() {
+                versions.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+
+        // the newer version comes first, the non-semver versions come last. eg. nightly
+        versions.sort_by(|a, b| {
+            let parse = |v: &String| Version::parse(v.trim_start_matches('v')).ok();
+
+            parse(b).cmp(&parse(a))
+        });
+
+        Ok(versions)
+    }
+
+    pub fn init_package(&self, package_name: &str) -> Result<(), Report> {
+        let package_dir = self.package_dir(package_name);
+        let package_version_dir = self.package_version_dir(package_name);
+
+        if !package_dir.exists() {
+            fs::create_dir_all(package_dir)?;
+        }
+
+        if !package_version_dir.exists() {
+            fs::create_dir_all(package_version_dir)?;
+        }
+
+        Ok(())
+    }
+
+    // find the installed package by the package name or the executable name, only its formula is parsed
+    pub fn find_formula(&self, name_or_bin: &str) -> Result<Option<formula::Formula>, Report> {
+        match index::find(self, name_or_bin)? {
+            Some(entry) => Ok(Some(formula::new(
+                &self.package_dir(&entry.name).join("Cask.toml"),
+                "",
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_formula(&self) -> Result<Vec<formula::Formula>, Report> {
+        let formula_dir = self.formula_dir();
+        let mut list: Vec<formula::Formula> = vec![];
+
+        let dir = fs::read_dir(formula_dir)?;
+
+        for entry in dir.into_iter().filter_map(|f| f.ok()) {
+            let p = entry.path();
+
+            if !p.is_dir() {
+                continue;
+            }
+
+            let cask_file_path = p.join("Cask.toml");
+
+            if !cask_file_path.exists() {
+                continue;
+            }
+
+            let package_formula = formula::new(&cask_file_path, "")?;
+
+            list.push(package_formula);
+        }
+
+        Ok(list)
+    }
+}
@@ -0,0 +1,349 @@
+#![deny(warnings)]
+
+use crate::{cache, cask, config::Config, source};
+
+use std::{fmt, fs::File, io, path::Path};
+
+use eyre::Report;
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256, Sha512};
+use url::Url;
+
+// the hash algorithm of checksum, SHA256 is the default for the checksum without prefix
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl Algorithm {
+    // the prefix of checksum. eg. 'sha512:<hex>'
+    fn prefix(&self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    // the length of hex digest
+    fn hex_len(&self) -> usize {
+        match self {
+            Algorithm::Sha256 | Algorithm::Blake3 => 64,
+            Algorithm::Sha512 => 128,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.prefix().to_uppercase())
+    }
+}
+
+// the checksum of resource in the formula, the algorithm is specified by the prefix.
+// eg. 'sha512:<hex>' or 'blake3:<hex>', the bare hex is SHA256 for compatibility
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub hash: String, // the lowercase hex digest
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            Algorithm::Sha256 => write!(f, "{}", self.hash),
+            _ => write!(f, "{}:{}", self.algorithm.prefix(), self.hash),
+        }
+    }
+}
+
+pub fn parse(checksum: &str) -> Result<Checksum, Report> {
+    let checksum = checksum.trim();
+
+    let (algorithm, hash) = match checksum.split_once(':') {
+        Some((prefix, hash)) => {
+            let algorithm = [Algorithm::Sha256, Algorithm::Sha512, Algorithm::Blake3]
+                .into_iter()
+                .find(|a| a.prefix().eq_ignore_ascii_case(prefix))
+                .ok_or_else(|| {
+                    eyre::format_err!(
+                        "The checksum algorithm '{}' is not supported, use sha256, sha512 or blake3",
+                        prefix
+                    )
+                })?;
+
+            (algorithm, hash)
+        }
+        None => (Algorithm::Sha256, checksum),
+    };
+
+    if hash.len() != algorithm.hex_len() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(eyre::format_err!(
+            "The checksum '{}' is not a valid {} digest",
+            checksum,
+            algorithm
+        ));
+    }
+
+    Ok(Checksum {
+        algorithm,
+        hash: hash.to_lowercase(),
+    })
+}
+
+// the incremental hasher of the algorithm, so that the resource is verified while downloading
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    // the lowercase hex digest
+    pub fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// the hex digest of file by the algorithm
+pub fn file_hash(filepath: &Path, algorithm: Algorithm) -> Result<String, Report> {
+    let mut file = File::open(filepath)?;
+    let mut hasher = Hasher::new(algorithm);
+
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher.finalize())
+}
+
+// get the file name from the URL. eg. https://example.com/v1.0.0/foo.tar.gz -> foo.tar.gz
+pub fn get_filename_from_url(url: &str) -> Option<String> {
+    let u = Url::parse(url).ok()?;
+    let filename = u.path_segments()?.next_back()?;
+
+    percent_decode_str(filename)
+        .decode_utf8()
+        .ok()
+        .map(|f| f.to_string())
+        .filter(|f| !f.is_empty())
+}
+
+// the checksum in the checksums file, the algorithm is detected by the length of digest.
+// the digest of 64 hex is SHA256, BLAKE3 is only detected by the tag of BSD format
+fn parse_digest(hash: &str, algorithm: Option<Algorithm>) -> Option<String> {
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm,
+        None if hash.len() == Algorithm::Sha512.hex_len() => Algorithm::Sha512,
+        None => Algorithm::Sha256,
+    };
+
+    parse(&format!("{}:{}", algorithm.prefix(), hash))
+        .ok()
+        .map(|c| c.to_string())
+}
+
+// find the checksum of file in the checksums file. the format of following is supported:
+// GNU: <hash>  <filename> or <hash> *<filename>
+// BSD: SHA256 (<filename>) = <hash>, SHA512 and BLAKE3 as well
+fn find_checksum(content: &str, filename: &str) -> Option<String> {
+    for line in content.lines().map(|l| l.trim()) {
+        let tagged = [Algorithm::Sha256, Algorithm::Sha512, Algorithm::Blake3]
+            .into_iter()
+            .find_map(|a| line.strip_prefix(&format!("{} (", a)).map(|rest| (a, rest)));
+
+        if let Some((algorithm, rest)) = tagged {
+            if let Some((name, hash)) = rest.split_once(") = ") {
+                if name == filename {
+                    if let Some(checksum) = parse_digest(hash.trim(), Some(algorithm)) {
+                        return Some(checksum);
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+
+        let (hash, name) = match (parts.next(), parts.next()) {
+            (Some(hash), Some(name)) => (hash, name.trim().trim_start_matches('*')),
+            _ => continue,
+        };
+
+        // the file name may be prefixed with the folder. eg. ./dist/foo.tar.gz
+        let name = name.rsplit('/').next().unwrap_or(name);
+
+        if name == filename {
+            if let Some(checksum) = parse_digest(hash, None) {
+                return Some(checksum);
+            }
+        }
+    }
+
+    None
+}
+
+// download the checksums file and get the checksum of file
+pub async fn fetch_checksum(
+    cask: &cask::Cask,
+    config: &Config,
+    checksum_url: &str,
+    filename: &str,
+    is_verbose: bool,
+) -> Result<String, Report> {
+    let request = source::resolve(&config.rewrite_url(checksum_url))?;
+
+    let mut option = cask
+        .settings()
+        .get_download_option(config, &request.url, is_verbose);
+    option.headers.extend(request.headers);
+
+    let content = cache::fetch_text(cask, &request.url, &option, cask.settings().cache_ttl)
+        .await?
+        .ok_or_else(|| eyre::format_err!("The checksums file '{}' does not exist", checksum_url))?;
+
+    find_checksum(&content, filename).ok_or_else(|| {
+        eyre::format_err!(
+            "Can not find the checksum of '{}' in '{}'",
+            filename,
+            checksum_url
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::checksum::{self, find_checksum, get_filename_from_url, Algorithm};
+
+    #[test]
+    fn test_find_checksum() {
+        let hash = "15f841b9b8f60033528dfdce5883e622145911ede1f59d1f302042ded4c565a4";
+
+        let content = format!(
+            "{}  foo_linux_amd64.tar.gz.sbom\n{}  foo_darwin_amd64.tar.gz\n{} *./dist/foo_linux_amd64.tar.gz\n",
+            "0".repeat(64),
+            "1".repeat(64),
+            hash
+        );
+
+        assert_eq!(
+            find_checksum(&content, "foo_linux_amd64.tar.gz"),
+            Some(hash.to_string())
+        );
+        assert_eq!(find_checksum(&content, "foo_windows_amd64.zip"), None);
+
+        let content = format!(
+            "SHA256 (foo_linux_amd64.tar.gz) = {}\n",
+            hash.to_uppercase()
+        );
+
+        assert_eq!(
+            find_checksum(&content, "foo_linux_amd64.tar.gz"),
+            Some(hash.to_string())
+        );
+
+        // the digest of 128 hex is SHA512
+        let content = format!(
+            "{}  foo_linux_amd64.tar.gz\nBLAKE3 (foo_darwin_amd64.tar.gz) = {}\n",
+            "a".repeat(128),
+            "b".repeat(64)
+        );
+
+        assert_eq!(
+            find_checksum(&content, "foo_linux_amd64.tar.gz"),
+            Some(format!("sha512:{}", "a".repeat(128)))
+        );
+        assert_eq!(
+            find_checksum(&content, "foo_darwin_amd64.tar.gz"),
+            Some(format!("blake3:{}", "b".repeat(64)))
+        );
+    }
+
+    #[test]
+    fn test_parse_and_hash() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let sha512 = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e";
+        let blake3 = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262";
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("empty");
+
+        fs::write(&file, "").unwrap();
+
+        for (value, algorithm, hash) in [
+            (sha256.to_uppercase(), Algorithm::Sha256, sha256),
+            (format!("sha256:{}", sha256), Algorithm::Sha256, sha256),
+            (format!("SHA512:{}", sha512), Algorithm::Sha512, sha512),
+            (format!("blake3:{}", blake3), Algorithm::Blake3, blake3),
+        ] {
+            let c = checksum::parse(&value).unwrap();
+
+            assert_eq!(c.algorithm, algorithm);
+            assert_eq!(c.hash, hash);
+            assert_eq!(checksum::file_hash(&file, algorithm).unwrap(), hash);
+        }
+
+        // the SHA256 is written without prefix
+        assert_eq!(
+            checksum::parse(&format!("sha256:{}", sha256))
+                .unwrap()
+                .to_string(),
+            sha256
+        );
+
+        assert!(checksum::parse(&format!("md5:{}", &sha256[..32])).is_err());
+        assert!(checksum::parse(&format!("sha512:{}", sha256)).is_err());
+        assert!(checksum::parse("xyz").is_err());
+    }
+
+    #[test]
+    fn test_get_filename_from_url() {
+        assert_eq!(
+            get_filename_from_url("https://example.com/v1.0.0/foo%20bar.tar.gz"),
+            Some("foo bar.tar.gz".to_string())
+        );
+        assert_eq!(
+            get_filename_from_url("s3://bucket/tools/foo.tar.gz"),
+            Some("foo.tar.gz".to_string())
+        );
+        assert_eq!(get_filename_from_url("https://example.com/"), None);
+    }
+}
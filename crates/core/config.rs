@@ -0,0 +1,760 @@
+#![deny(warnings)]
+
+use crate::{cask, hooker, symlink, util};
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use extractor::DecompressOption;
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// the environment variables of token, they take precedence over the config
+const ENV_GITHUB_TOKEN: &str = "CASK_GITHUB_TOKEN";
+const ENV_GITLAB_TOKEN: &str = "CASK_GITLAB_TOKEN";
+
+// the environment variable of the tokens for git repositories on other hosts.
+// eg. 'https://git.example.com/=user:token https://git.corp.example/=token'
+const ENV_GIT_TOKENS: &str = "CASK_GIT_TOKENS";
+
+// the environment variable of git credential helper, it takes precedence over the config
+const ENV_CREDENTIAL_HELPER: &str = "CASK_CREDENTIAL_HELPER";
+
+// the username of git token if it is not provided, it is accepted by GitLab and Gitea
+const DEFAULT_GIT_USERNAME: &str = "oauth2";
+
+// the default retention of unused versions, the newest one is kept for 30 days
+const DEFAULT_RETENTION_KEEP: usize = 1;
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>, // The proxy for downloading and git operation. eg. http://127.0.0.1:7890
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub mirror: HashMap<String, String>, // The URL rewrite rules, the URL prefix is replaced by the mirror
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>, // The token for private repository and release asset on GitHub
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gitlab_token: Option<String>, // The token for private repository and release asset on GitLab
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub git_tokens: HashMap<String, String>, // The token of git repository URL prefix, in the format of 'user:token' or 'token'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>, // The git credential helper for private repository. eg. store, osxkeychain, manager
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>, // The timeout in seconds of connecting to the server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>, // The timeout in seconds of waiting for the response data or git command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>, // The extra CA certificates file in PEM format. eg. the CA of enterprise proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_rate: Option<String>, // The max download speed per second. eg. 2M
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decompress_memory: Option<String>, // The max memory of decompressing the xz and zstd archives. eg. 64M
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decompress_threads: Option<u32>, // The threads of decompressing the multi-block xz archives, 0 for the number of CPUs
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, HashMap<String, String>>, // The extra request headers of URL prefix. eg. the token of artifact store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u64>, // The time to live in seconds of cached versions and API responses, 0 to disable
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hosts: HashMap<String, HostMapping>, // The alternate bases of the host or package name prefix. eg. the GitHub mirror in company
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<hooker::Policy>, // Whether to run the hooks of formula, 'run', 'confirm' or 'skip'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>, // Whether to show the desktop notification when the long-running operation finishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<symlink::Strategy>, // How the executable is linked into the bin folder, 'symlink', 'hardlink', 'copy' or 'shim'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<ChecksumPolicy>, // Whether the resource without checksum is installed, 'verify' or 'require'
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub defaults: Defaults, // The default flags of commands
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub retention: Retention, // The retention of unused versions for `cask gc`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<String>, // The folder that the executables are linked into, it is the 'bin' of Cask root by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_bin: Option<String>, // The git binary that is used when the repository can not be accessed natively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<bool>, // Whether to show the progress bars, the plain status lines are printed if it is false
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub taps: Vec<Tap>, // The formula repositories besides the build-in formula, they are searched by the priority
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_ttl: Option<u64>, // The seconds between the automatic updates of formula sources, 0 to disable
+    #[serde(skip)]
+    vars: Option<HashMap<String, String>>, // The environment variables that override the config, they are of the process if not set
+}
+
+// how the checksum of resource is verified
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumPolicy {
+    Verify,  // verify the checksum if it is provided by the formula or the checksums file
+    Require, // the resource without checksum is not installed
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>, // The max number of concurrent downloads when installing multiple packages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre: Option<bool>, // Allow to install and upgrade to the pre-release version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_archive: Option<bool>, // Download the archive into cache instead of extracting while downloading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yes: Option<bool>, // Answer yes to the prompts. eg. the confirmation of hooks
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Retention {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>, // The number of the newest versions to keep besides the one in use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<u64>, // The days to keep the unused versions
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Tap {
+    pub name: String, // The name of tap, it is shown by `cask info`. eg. corp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>, // The git repository of formula, it is synced by `cask remote sync`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>, // The local folder of formula, it is not synced. eg. the checkout of personal tap
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub priority: i64, // The tap of higher priority is searched first, the priority of build-in formula is 0
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HostMapping {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>, // The base of git repositories. eg. https://git.corp.example/
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>, // The base of release assets and other resources
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>, // The base of release API. eg. https://git.corp.example/api/v3
+}
+
+// join the base and the path, the base may be an SSH address. eg. 'git@git.corp.example:'
+fn join_base(base: &str, path: &str) -> String {
+    if base.ends_with('/') || base.ends_with(':') {
+        format!("{}{}", base, path)
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+// load the config of Cask, the default config is used if the file does not exist
+pub fn load(cask: &cask::Cask) -> Result<Config, Report> {
+    let config_file_path = cask.config_file();
+
+    if !config_file_path.exists() {
+        return Ok(Config::default());
+    }
+
+    let content = fs::read_to_string(&config_file_path)?;
+
+    toml::from_str(&content)
+        .map_err(|e| eyre::format_err!("parse '{}' fail: {}", config_file_path.display(), e))
+}
+
+pub fn save(cask: &cask::Cask, config: &Config) -> Result<(), Report> {
+    write_private(&cask.config_file(), &toml::to_string(config)?)
+}
+
+// the config may contain the tokens, so it is only readable by the owner on Unix
+pub fn write_private(filepath: &Path, content: &str) -> Result<(), Report> {
+    let mut options = OpenOptions::new();
+
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(filepath)?;
+
+    // the mode only applies to the new file
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(content.as_bytes())?;
+
+    Ok(())
+}
+
+impl Config {
+    // use the environment variables instead of the ones of process. eg. in the tests
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = Some(vars);
+        self
+    }
+
+    // the environment variable that overrides the config
+    fn var(&self, key: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(key).cloned(),
+            None => env::var(key).ok(),
+        }
+    }
+
+    fn get_token(&self, env_key: &str, token: &Option<String>) -> Option<String> {
+        self.var(env_key)
+            .or_else(|| token.clone())
+            .filter(|t| !t.is_empty())
+    }
+
+    pub fn get_github_token(&self) -> Option<String> {
+        self.get_token(ENV_GITHUB_TOKEN, &self.github_token)
+    }
+
+    pub fn get_gitlab_token(&self) -> Option<String> {
+        self.get_token(ENV_GITLAB_TOKEN, &self.gitlab_token)
+    }
+
+    // the tokens of git repository URL prefix, the environment variable takes precedence over the config
+    pub fn get_git_tokens(&self) -> Vec<(String, String, String)> {
+        let mut tokens = self.git_tokens.clone();
+
+        if let Some(value) = self.var(ENV_GIT_TOKENS) {
+            for entry in value.split_whitespace() {
+                if let Some((prefix, token)) = entry.split_once('=') {
+                    tokens.insert(prefix.to_string(), token.to_string());
+                }
+            }
+        }
+
+        let mut tokens = tokens
+            .into_iter()
+            .filter(|(prefix, token)| !prefix.is_empty() && !token.is_empty())
+            .map(|(prefix, token)| match token.split_once(':') {
+                Some((username, token)) => (prefix, username.to_string(), token.to_string()),
+                None => (prefix, DEFAULT_GIT_USERNAME.to_string(), token),
+            })
+            .collect::<Vec<_>>();
+
+        tokens.sort();
+
+        tokens
+    }
+
+    pub fn get_credential_helper(&self) -> Option<String> {
+        self.var(ENV_CREDENTIAL_HELPER)
+            .or_else(|| self.credential_helper.clone())
+            .filter(|h| !h.is_empty())
+    }
+
+    // the shim is the default on Windows, because the symlink requires the Developer Mode or administrator
+    pub fn get_link_strategy(&self) -> symlink::Strategy {
+        self.link.unwrap_or_default()
+    }
+
+    // the memory limit and threads of decompressing the xz and zstd archives.
+    // there is no memory limit if not set, and the archive is decompressed in the current thread
+    pub fn get_decompress_option(&self) -> Result<DecompressOption, Report> {
+        let memory_limit = match &self.decompress_memory {
+            Some(size) if !size.trim().is_empty() => util::parse_size(size)
+                .map_err(|e| eyre::format_err!("invalid decompress memory limit: {}", e))?,
+            _ => 0,
+        };
+
+        let threads = self.decompress_threads.map(|t| match t {
+            0 => thread::available_parallelism().map_or(1, |n| n.get() as u32),
+            t => t,
+        });
+
+        Ok(DecompressOption {
+            memory_limit: Some(memory_limit).filter(|m| *m > 0),
+            threads: threads.filter(|t| *t > 1),
+        })
+    }
+
+    // the decompress option is shared by all extractions of the process
+    pub fn apply_decompress(&self) -> Result<(), Report> {
+        extractor::set_decompress_option(self.get_decompress_option()?);
+
+        Ok(())
+    }
+
+    // the number of unused versions to keep and the time to keep them
+    pub fn get_retention(&self) -> (usize, Duration) {
+        (
+            self.retention.keep.unwrap_or(DEFAULT_RETENTION_KEEP),
+            Duration::from_secs(
+                self.retention.older_than.unwrap_or(DEFAULT_RETENTION_DAYS) * 24 * 60 * 60,
+            ),
+        )
+    }
+
+    pub fn is_checksum_required(&self) -> bool {
+        self.checksum == Some(ChecksumPolicy::Require)
+    }
+
+    // the authorization headers for downloading the URL, the token is only sent to its own host
+    pub fn get_auth_headers(&self, url: &str) -> Vec<(String, String)> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+
+        // the headers of all matched URL prefixes
+        let mut prefixes = self
+            .headers
+            .iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .collect::<Vec<_>>();
+
+        prefixes.sort_by_key(|(prefix, _)| prefix.len());
+
+        let custom_headers = prefixes
+            .into_iter()
+            .flat_map(|(_, headers)| headers.iter())
+            .map(|(name, value)| (name.clone(), value.clone()));
+
+        let token_headers = match host.as_str() {
+            "github.com" | "api.github.com" => self
+                .get_github_token()
+                .map(|t| vec![("Authorization".to_string(), format!("token {}", t))])
+                .unwrap_or_default(),
+            "gitlab.com" => self
+                .get_gitlab_token()
+                .map(|t| vec![("PRIVATE-TOKEN".to_string(), t)])
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+
+        // the custom headers take precedence over the token
+        token_headers
+            .into_iter()
+            .filter(|(name, _)| {
+                !custom_headers
+                    .clone()
+                    .any(|(custom_name, _)| custom_name.eq_ignore_ascii_case(name))
+            })
+            .chain(custom_headers.clone())
+            .collect()
+    }
+
+    // the config entries that pass the token and credential helper to git, so that the private repository can be cloned.
+    // they are set on the git process only, so that the hooks and other programs never see the token
+    pub fn get_git_config(&self) -> Vec<(String, String)> {
+        let mut credentials = vec![
+            (
+                "https://github.com/".to_string(),
+                "x-access-token".to_string(),
+                self.get_github_token(),
+            ),
+            (
+                "https://gitlab.com/".to_string(),
+                "oauth2".to_string(),
+                self.get_gitlab_token(),
+            ),
+        ];
+
+        credentials.extend(
+            self.get_git_tokens()
+                .into_iter()
+                .map(|(prefix, username, token)| (prefix, username, Some(token))),
+        );
+
+        let mut entries = vec![];
+
+        for (url, username, token) in credentials {
+            if let Some(token) = token {
+                let basic = BASE64.encode(format!("{}:{}", username, token));
+
+                entries.push((
+                    format!("http.{}.extraHeader", url),
+                    format!("Authorization: Basic {}", basic),
+                ));
+            }
+        }
+
+        if let Some(helper) = self.get_credential_helper() {
+            entries.push(("credential.helper".to_string(), helper));
+        }
+
+        entries
+    }
+
+    // find the base of URL in the host mappings that define it, and the rest path of URL.
+    // the longest matched host or package name prefix wins. eg. 'github.com' or 'github.com/org' matches 'https://github.com/org/tool.git'
+    fn find_host_base<'a>(
+        &'a self,
+        url: &str,
+        field: impl Fn(&'a HostMapping) -> &'a Option<String>,
+    ) -> Option<(&'a str, String)> {
+        let u = Url::parse(&util::to_https_url(url)).ok()?;
+
+        let mut name = format!("{}{}", u.host_str()?, u.path());
+
+        if let Some(query) = u.query() {
+            name = format!("{}?{}", name, query);
+        }
+
+        self.hosts
+            .iter()
+            .filter_map(|(prefix, mapping)| {
+                let prefix = prefix.split_once("://").map_or(prefix.as_str(), |(_, p)| p);
+
+                field(mapping)
+                    .as_deref()
+                    .map(|base| (prefix.trim_end_matches('/'), base))
+            })
+            .filter(|(prefix, _)| {
+                !prefix.is_empty() && (name == *prefix || name.starts_with(&format!("{}/", prefix)))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, base)| {
+                (
+                    base,
+                    name[prefix.len()..].trim_start_matches('/').to_string(),
+                )
+            })
+    }
+
+    // rewrite the URL with the mirror, the longest matched prefix wins
+    fn rewrite_mirror(&self, url: &str) -> String {
+        self.mirror
+            .iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, mirror)| format!("{}{}", mirror, &url[prefix.len()..]))
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    // rewrite the URL of resource with the release base of host mapping and the mirror
+    pub fn rewrite_url(&self, url: &str) -> String {
+        let url = match self.find_host_base(url, |h| &h.release) {
+            Some((base, path)) => join_base(base, &path),
+            None => url.to_string(),
+        };
+
+        self.rewrite_mirror(&url)
+    }
+
+    // rewrite the URL of git repository with the git base of host mapping and the mirror
+    pub fn rewrite_git_url(&self, url: &str) -> String {
+        let url = match self.find_host_base(url, |h| &h.git) {
+            Some((base, path)) => join_base(base, &path),
+            None => url.to_string(),
+        };
+
+        self.rewrite_mirror(&url)
+    }
+
+    // whether the git repository is mapped to another host, so the raw files of origin host are not accessible
+    pub fn is_git_mapped(&self, url: &str) -> bool {
+        self.find_host_base(url, |h| &h.git).is_some()
+    }
+
+    // the base of release API of the repository, the default API of the code hosting service is used if not set
+    pub fn get_api_base(&self, repository: &str) -> Option<String> {
+        self.find_host_base(repository, |h| &h.api)
+            .map(|(base, _)| base.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        config::{Config, ENV_GITHUB_TOKEN, ENV_GIT_TOKENS},
+        hooker, symlink,
+    };
+
+    use extractor::DecompressOption;
+
+    // the config without the environment variables of process, so that the tests are not affected by them
+    fn parse(content: &str) -> Config {
+        toml::from_str::<Config>(content)
+            .unwrap()
+            .with_vars(HashMap::new())
+    }
+
+    fn vars(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(r#"proxy = "http://127.0.0.1:7890""#).unwrap();
+
+        assert_eq!(config.proxy, Some("http://127.0.0.1:7890".to_string()));
+
+        let config: Config = toml::from_str("").unwrap();
+
+        assert_eq!(config, Config::default());
+
+        assert!(toml::from_str::<Config>(r#"proxi = "http://127.0.0.1:7890""#).is_err());
+    }
+
+    #[test]
+    fn test_get_auth_headers() {
+        let config = Config {
+            github_token: Some("ghp_xxx".to_string()),
+            ..Default::default()
+        }
+        .with_vars(HashMap::new());
+
+        assert_eq!(
+            config.get_auth_headers(
+                "https://github.com/org/tool/releases/download/v1.0.0/tool.tar.gz"
+            ),
+            vec![("Authorization".to_string(), "token ghp_xxx".to_string())]
+        );
+        assert!(config
+            .get_auth_headers("https://ghproxy.example/github.com/org/tool.tar.gz")
+            .is_empty());
+
+        // the environment variable takes precedence over the config
+        let config = config.with_vars(vars(&[(ENV_GITHUB_TOKEN, "ghp_yyy")]));
+
+        assert_eq!(config.get_github_token(), Some("ghp_yyy".to_string()));
+    }
+
+    #[test]
+    fn test_get_git_tokens() {
+        let config = parse(
+            r#"
+            [git_tokens]
+            "https://git.example.com/" = "bot:xxx"
+            "https://git.corp.example/" = "yyy"
+            "#,
+        );
+
+        assert_eq!(
+            config.get_git_tokens(),
+            vec![
+                (
+                    "https://git.corp.example/".to_string(),
+                    "oauth2".to_string(),
+                    "yyy".to_string()
+                ),
+                (
+                    "https://git.example.com/".to_string(),
+                    "bot".to_string(),
+                    "xxx".to_string()
+                ),
+            ]
+        );
+
+        let config = config.with_vars(vars(&[(ENV_GIT_TOKENS, "https://git.example.com/=zzz")]));
+
+        assert_eq!(
+            config.get_git_tokens()[1],
+            (
+                "https://git.example.com/".to_string(),
+                "oauth2".to_string(),
+                "zzz".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_custom_headers() {
+        let config = parse(
+            r#"
+            [headers."https://artifacts.example.com/"]
+            X-JFrog-Art-Api = "xxx"
+            "#,
+        );
+
+        assert_eq!(
+            config.get_auth_headers("https://artifacts.example.com/tools/foo.tar.gz"),
+            vec![("X-JFrog-Art-Api".to_string(), "xxx".to_string())]
+        );
+        assert!(config
+            .get_auth_headers("https://example.com/tools/foo.tar.gz")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_decompress_option() {
+        assert_eq!(
+            parse("").get_decompress_option().unwrap(),
+            DecompressOption::default()
+        );
+
+        let option = parse("decompress_memory = \"64M\"\ndecompress_threads = 4")
+            .get_decompress_option()
+            .unwrap();
+
+        assert_eq!(option.memory_limit, Some(64 * 1024 * 1024));
+        assert_eq!(option.threads, Some(4));
+
+        // the single thread is the current thread
+        let option = parse("decompress_threads = 1")
+            .get_decompress_option()
+            .unwrap();
+
+        assert_eq!(option.threads, None);
+
+        assert!(parse("decompress_memory = \"64X\"")
+            .get_decompress_option()
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_hook_policy() {
+        assert_eq!(parse("").hooks, None);
+        assert_eq!(parse(r#"hooks = "skip""#).hooks, Some(hooker::Policy::Skip));
+
+        assert!(toml::from_str::<Config>(r#"hooks = "never""#).is_err());
+    }
+
+    #[test]
+    fn test_defaults_and_retention() {
+        let config = parse("");
+
+        assert_eq!(
+            config.get_retention(),
+            (1, Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert!(!config.is_checksum_required());
+
+        let config = parse(
+            r#"
+checksum = "require"
+
+[defaults]
+jobs = 8
+pre = true
+
+[retention]
+older_than = 7
+"#,
+        );
+
+        assert_eq!(config.defaults.jobs, Some(8));
+        assert_eq!(config.defaults.pre, Some(true));
+        assert_eq!(
+            config.get_retention(),
+            (1, Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert!(config.is_checksum_required());
+
+        // the empty tables are not saved
+        assert_eq!(toml::to_string(&Config::default()).unwrap(), "".to_string());
+
+        assert!(toml::from_str::<Config>("[defaults]\nforce = true").is_err());
+    }
+
+    #[test]
+    fn test_get_link_strategy() {
+        let expected = if cfg!(windows) {
+            symlink::Strategy::Shim
+        } else {
+            symlink::Strategy::Symlink
+        };
+
+        assert_eq!(parse("").get_link_strategy(), expected);
+
+        let config = parse(r#"link = "shim""#);
+
+        assert_eq!(config.get_link_strategy(), symlink::Strategy::Shim);
+        assert!(toml::from_str::<Config>(r#"link = "junction""#).is_err());
+    }
+
+    #[test]
+    fn test_host_mapping() {
+        let config = parse(
+            r#"
+            [mirror]
+            "https://git.corp.example/" = "https://cache.corp.example/"
+
+            [hosts."github.com"]
+            git = "https://git.corp.example/"
+            release = "https://artifacts.corp.example/github"
+            api = "https://git.corp.example/api/v3"
+
+            [hosts."https://github.com/axetroy/"]
+            git = "git@git.corp.example:axetroy-mirror/"
+            "#,
+        );
+
+        assert_eq!(
+            config.rewrite_git_url("https://github.com/org/tool.git"),
+            "https://cache.corp.example/org/tool.git"
+        );
+        assert_eq!(
+            config.rewrite_git_url("git@github.com:axetroy/dvm.git"),
+            "git@git.corp.example:axetroy-mirror/dvm.git"
+        );
+        assert_eq!(
+            config.rewrite_url("https://github.com/org/tool/releases/download/v1.0.0/tool.tar.gz"),
+            "https://artifacts.corp.example/github/org/tool/releases/download/v1.0.0/tool.tar.gz"
+        );
+        // the release base of host is used if the package name prefix does not define it
+        assert_eq!(
+            config
+                .rewrite_url("https://github.com/axetroy/dvm/releases/download/v1.0.0/dvm.tar.gz"),
+            "https://artifacts.corp.example/github/axetroy/dvm/releases/download/v1.0.0/dvm.tar.gz"
+        );
+        assert_eq!(
+            config.rewrite_git_url("https://github.company.example/org/tool.git"),
+            "https://github.company.example/org/tool.git"
+        );
+
+        assert!(config.is_git_mapped("https://github.com/org/tool.git"));
+        assert!(!config.is_git_mapped("https://gitlab.com/org/tool.git"));
+
+        assert_eq!(
+            config.get_api_base("https://github.com/org/tool"),
+            Some("https://git.corp.example/api/v3".to_string())
+        );
+        assert_eq!(config.get_api_base("https://gitlab.com/axetroy/dvm"), None);
+    }
+
+    #[test]
+    fn test_rewrite_url() {
+        let config = parse(
+            r#"
+            [mirror]
+            "https://github.com/" = "https://ghproxy.example/github.com/"
+            "https://github.com/axetroy/" = "https://axetroy.example/"
+            "#,
+        );
+
+        assert_eq!(
+            config.rewrite_url("https://github.com/foo/bar/releases/download/v1.0.0/bar.tar.gz"),
+            "https://ghproxy.example/github.com/foo/bar/releases/download/v1.0.0/bar.tar.gz"
+        );
+        assert_eq!(
+            config.rewrite_url("https://github.com/axetroy/dvm.git"),
+            "https://axetroy.example/dvm.git"
+        );
+        assert_eq!(
+            config.rewrite_url("https://gitlab.com/foo/bar.git"),
+            "https://gitlab.com/foo/bar.git"
+        );
+    }
+}
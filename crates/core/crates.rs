@@ -0,0 +1,515 @@
+#![deny(warnings)]
+
+use crate::{cache, cask, error::CaskError, formula::Formula, retry::retry_async};
+
+use std::{collections::HashMap, env, fs::File, io::Read, path::Path};
+
+use eyre::Report;
+use libflate::gzip::Decoder;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::debug;
+
+// the packages of crates.io are named by the host. eg. 'crates:ripgrep' -> 'crates.io/ripgrep'
+pub const HOST: &str = "crates.io";
+
+#[derive(Deserialize)]
+struct CrateInfo {
+    name: String,
+    description: Option<String>,
+    repository: Option<String>,
+    homepage: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CrateVersion {
+    num: String,
+    yanked: bool,
+    bin_names: Option<Vec<String>>, // the executables of the version, it is empty for the library
+    license: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    info: CrateInfo,
+    versions: Vec<CrateVersion>, // the newest first
+}
+
+// the metadata of cargo-binstall in Cargo.toml, see https://github.com/cargo-bins/cargo-binstall/blob/main/SUPPORT.md
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct BinstallMeta {
+    pkg_url: Option<String>, // the URL template of the resource. eg. "{ repo }/releases/download/v{ version }/{ name }-{ target }.tgz"
+    pkg_fmt: Option<String>, // the format of the resource, it is 'tgz' by default
+    bin_dir: Option<String>, // the path template of the executable in the resource
+    #[serde(default)]
+    overrides: HashMap<String, BinstallMeta>, // the metadata of the target triples
+}
+
+impl BinstallMeta {
+    // the metadata of target, the override takes precedence
+    fn for_target(mut self, target: &str) -> BinstallMeta {
+        if let Some(o) = self.overrides.remove(target) {
+            self.pkg_url = o.pkg_url.or(self.pkg_url);
+            self.pkg_fmt = o.pkg_fmt.or(self.pkg_fmt);
+            self.bin_dir = o.bin_dir.or(self.bin_dir);
+        }
+
+        self
+    }
+}
+
+// the target triple of current platform. eg. x86_64-unknown-linux-gnu
+fn get_target() -> String {
+    let arch = match env::consts::ARCH {
+        "x86" => "i686",
+        "arm" => "armv7",
+        arch => arch,
+    };
+
+    let rest = if cfg!(target_os = "macos") {
+        "apple-darwin"
+    } else if cfg!(target_os = "windows") {
+        "pc-windows-msvc"
+    } else if cfg!(target_os = "freebsd") {
+        "unknown-freebsd"
+    } else if cfg!(all(target_arch = "arm", target_env = "musl")) {
+        "unknown-linux-musleabihf"
+    } else if cfg!(target_arch = "arm") {
+        "unknown-linux-gnueabihf"
+    } else if cfg!(target_env = "musl") {
+        "unknown-linux-musl"
+    } else {
+        "unknown-linux-gnu"
+    };
+
+    format!("{}-{}", arch, rest)
+}
+
+// the section name of current platform in formula
+fn get_os_name() -> &'static str {
+    match env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+// the file suffix of the resource format, binstall tries the others if the first one does not exist
+fn get_archive_suffix(pkg_fmt: &str) -> Result<&'static str, Report> {
+    match pkg_fmt {
+        "tgz" => Ok(".tgz"),
+        "tar" => Ok(".tar"),
+        "tbz2" => Ok(".tbz2"),
+        "txz" => Ok(".tar.xz"),
+        "tzstd" => Ok(".tar.zst"),
+        "zip" => Ok(".zip"),
+        "bin" => Ok(if cfg!(windows) { ".exe" } else { "" }),
+        _ => Err(eyre::format_err!(
+            "not support the binstall format '{}'",
+            pkg_fmt
+        )),
+    }
+}
+
+// render the template of binstall, eg. '{ name }-{ target }'.
+// the version is kept as the template of formula, so that the formula works with all the versions
+fn render(template: &str, vars: &HashMap<&str, String>) -> Result<String, Report> {
+    let re = Regex::new(r"\{\s*([\w-]+)\s*\}").unwrap();
+
+    let mut result = String::new();
+    let mut last = 0;
+
+    for caps in re.captures_iter(template) {
+        let m = caps.get(0).unwrap();
+        let name = &caps[1];
+
+        let value = match name {
+            "version" => "{version}",
+            _ => vars.get(name).ok_or_else(|| {
+                eyre::format_err!(
+                    "unknown variable '{}' in binstall template '{}'",
+                    name,
+                    template
+                )
+            })?,
+        };
+
+        result.push_str(&template[last..m.start()]);
+        result.push_str(value);
+        last = m.end();
+    }
+
+    result.push_str(&template[last..]);
+
+    Ok(result)
+}
+
+// generate the formula content from the binstall metadata for the current platform
+fn generate_formula(
+    response: &CrateResponse,
+    meta: BinstallMeta,
+    target: &str,
+) -> Result<String, Report> {
+    let info = &response.info;
+    let versions = response
+        .versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .collect::<Vec<&CrateVersion>>();
+
+    let latest = versions.first().ok_or_else(|| {
+        CaskError::VersionNotFound(format!("there is no version of crate '{}'", info.name))
+    })?;
+
+    // the executable of the same name as the crate is preferred. eg. cargo-binstall
+    let bin_names = latest.bin_names.clone().unwrap_or_default();
+    let bin = bin_names
+        .iter()
+        .find(|b| **b == info.name)
+        .or_else(|| bin_names.first())
+        .ok_or_else(|| eyre::format_err!("the crate '{}' has no executable", info.name))?;
+
+    let meta = meta.for_target(target);
+
+    let pkg_url = meta.pkg_url.ok_or_else(|| {
+        CaskError::UnsupportedPlatform(format!(
+            "the crate '{}' does not provide the prebuilt binary by the binstall metadata",
+            info.name
+        ))
+    })?;
+
+    let pkg_fmt = meta.pkg_fmt.unwrap_or_else(|| "tgz".to_string());
+    let archive_suffix = get_archive_suffix(&pkg_fmt)?;
+
+    let repository = info
+        .repository
+        .clone()
+        .unwrap_or_else(|| format!("https://{}/crates/{}", HOST, info.name));
+
+    let mut target_parts = target.split('-');
+    let target_arch = target_parts.next().unwrap_or_default();
+    let target_vendor = target_parts.next().unwrap_or_default();
+    let target_libc = target_parts
+        .nth(1)
+        .unwrap_or_default()
+        .trim_end_matches("eabihf");
+
+    let vars = HashMap::from([
+        ("name", info.name.clone()),
+        ("repo", repository.trim_end_matches(".git").to_string()),
+        ("target", target.to_string()),
+        (
+            "archive-format",
+            archive_suffix.trim_start_matches('.').to_string(),
+        ),
+        ("format", archive_suffix.trim_start_matches('.').to_string()),
+        ("archive-suffix", archive_suffix.to_string()),
+        (
+            "binary-ext",
+            if cfg!(windows) { ".exe" } else { "" }.to_string(),
+        ),
+        ("bin", bin.clone()),
+        ("target-family", env::consts::FAMILY.to_string()),
+        ("target-arch", target_arch.to_string()),
+        ("target-vendor", target_vendor.to_string()),
+        ("target-libc", target_libc.to_string()),
+        ("subcrate", "".to_string()),
+    ]);
+
+    let url = render(&pkg_url, &vars)?;
+
+    let mut resource = toml::Table::new();
+
+    if pkg_fmt == "bin" {
+        resource.insert("executable".to_string(), url.into());
+    } else {
+        resource.insert("url".to_string(), url.into());
+
+        // the folder of the executable in the archive
+        if let Some(bin_dir) = meta.bin_dir {
+            let bin_path = render(&bin_dir, &vars)?;
+            let folder = Path::new(&bin_path)
+                .parent()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+
+            resource.insert("path".to_string(), format!("/{}", folder).into());
+        }
+    }
+
+    let mut package = toml::Table::new();
+
+    package.insert("name".to_string(), format!("{}/{}", HOST, info.name).into());
+    package.insert("bin".to_string(), bin.clone().into());
+    package.insert("repository".to_string(), repository.into());
+    package.insert(
+        "description".to_string(),
+        info.description.clone().unwrap_or_default().trim().into(),
+    );
+    package.insert(
+        "versions".to_string(),
+        versions
+            .iter()
+            .map(|v| toml::Value::from(v.num.clone()))
+            .collect::<Vec<toml::Value>>()
+            .into(),
+    );
+
+    if let Some(homepage) = &info.homepage {
+        package.insert("homepage".to_string(), homepage.clone().into());
+    }
+
+    if let Some(license) = &latest.license {
+        package.insert("license".to_string(), license.clone().into());
+    }
+
+    let mut platform = toml::Table::new();
+    platform.insert(env::consts::ARCH.to_string(), resource.into());
+
+    let mut content = toml::Table::new();
+    content.insert("package".to_string(), package.into());
+    content.insert(get_os_name().to_string(), platform.into());
+
+    Ok(format!(
+        "# The formula is generated from the binstall metadata of crate '{}'\n{}",
+        info.name,
+        toml::to_string(&content)?
+    ))
+}
+
+// read the Cargo.toml in the package of crate
+fn read_cargo_toml(crate_file: &Path, dir_name: &str) -> Result<String, Report> {
+    let mut archive = tar::Archive::new(Decoder::new(File::open(crate_file)?)?);
+    let manifest_path = Path::new(dir_name).join("Cargo.toml");
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if entry.path()? == manifest_path {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+
+            return Ok(content);
+        }
+    }
+
+    Err(eyre::format_err!(
+        "can not found '{}' in the crate",
+        manifest_path.display()
+    ))
+}
+
+fn get_binstall_meta(cargo_toml: &str) -> Result<BinstallMeta, Report> {
+    let manifest = toml::from_str::<toml::Value>(cargo_toml)?;
+
+    match manifest
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("binstall"))
+    {
+        Some(meta) => Ok(meta.clone().try_into()?),
+        None => Ok(BinstallMeta::default()),
+    }
+}
+
+// generate the formula of crate, there is no formula file in the crate
+pub async fn fetch(
+    cask: &cask::Cask,
+    crate_name: &str,
+    is_verbose: bool,
+) -> Result<Formula, Report> {
+    let config = cask.config();
+
+    let api_url = config.rewrite_url(&format!("https://{}/api/v1/crates/{}", HOST, crate_name));
+    let option = cask
+        .settings()
+        .get_download_option(config, &api_url, is_verbose);
+
+    let content = cache::fetch_text(cask, &api_url, &option, cask.settings().cache_ttl)
+        .await?
+        .ok_or_else(|| {
+            CaskError::PackageNotFound(format!(
+                "can not found the crate '{}' on {}",
+                crate_name, HOST
+            ))
+        })?;
+
+    let response = serde_json::from_str::<CrateResponse>(&content)
+        .map_err(|e| eyre::format_err!("parse the crate '{}' fail: {}", crate_name, e))?;
+
+    // the metadata of the newest version is used for all the versions
+    let latest = response
+        .versions
+        .iter()
+        .find(|v| !v.yanked)
+        .ok_or_else(|| {
+            CaskError::VersionNotFound(format!("there is no version of crate '{}'", crate_name))
+        })?;
+
+    let dir_name = format!("{}-{}", response.info.name, latest.num);
+    let crate_url = config.rewrite_url(&format!(
+        "https://static.{}/crates/{}/{}.crate",
+        HOST, response.info.name, dir_name
+    ));
+
+    debug!("read the binstall metadata from {}", crate_url);
+
+    let temp_dir = tempfile::Builder::new().prefix("cask_crate_").tempdir()?;
+    let crate_file = temp_dir.path().join(format!("{}.crate", dir_name));
+    let option = cask
+        .settings()
+        .get_download_option(config, &crate_url, is_verbose);
+
+    retry_async(
+        &cask.settings().get_retry_policy(),
+        downloader::is_transient,
+        || downloader::download(&crate_url, &crate_file, &option),
+    )
+    .await?;
+
+    let meta = get_binstall_meta(&read_cargo_toml(&crate_file, &dir_name)?)?;
+    let target = get_target();
+
+    debug!("the binstall metadata of {}: {:?}", target, meta);
+
+    let file_content = generate_formula(&response, meta, &target)?;
+
+    let mut f = toml::from_str::<Formula>(&file_content).map_err(|e| {
+        eyre::format_err!("generate the formula of crate '{}' fail: {}", crate_name, e)
+    })?;
+
+    f.file_content = file_content;
+    f.repository = format!("https://{}/crates/{}", HOST, response.info.name);
+
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::crates::{
+        generate_formula, get_binstall_meta, render, BinstallMeta, CrateInfo, CrateResponse,
+        CrateVersion,
+    };
+    use crate::formula::Formula;
+
+    #[test]
+    fn test_render() {
+        let vars = HashMap::from([
+            ("name", "foo".to_string()),
+            ("target", "x86_64-unknown-linux-gnu".to_string()),
+        ]);
+
+        assert_eq!(
+            render("{ name }-v{version}-{target}.tgz", &vars).unwrap(),
+            "foo-v{version}-x86_64-unknown-linux-gnu.tgz"
+        );
+        assert!(render("{ unknown }", &vars).is_err());
+    }
+
+    #[test]
+    fn test_get_binstall_meta() {
+        let meta = get_binstall_meta(
+            r#"
+[package]
+name = "foo"
+
+[package.metadata.binstall]
+pkg-url = "{ repo }/releases/download/v{ version }/{ name }-{ target }{ archive-suffix }"
+bin-dir = "{ name }-{ target }/{ bin }{ binary-ext }"
+
+[package.metadata.binstall.overrides.x86_64-pc-windows-msvc]
+pkg-fmt = "zip"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(meta.overrides.len(), 1);
+
+        // the override of target takes precedence
+        let windows = meta.for_target("x86_64-pc-windows-msvc");
+
+        assert_eq!(windows.pkg_fmt.as_deref(), Some("zip"));
+        assert_eq!(
+            windows.bin_dir.as_deref(),
+            Some("{ name }-{ target }/{ bin }{ binary-ext }")
+        );
+
+        // the crate without binstall metadata
+        assert_eq!(
+            get_binstall_meta("[package]\nname = \"foo\"\n").unwrap(),
+            BinstallMeta::default()
+        );
+    }
+
+    #[test]
+    fn test_generate_formula() {
+        let response = CrateResponse {
+            info: CrateInfo {
+                name: "foo".to_string(),
+                description: Some("The foo tool\n".to_string()),
+                repository: Some("https://github.com/axetroy/foo.git".to_string()),
+                homepage: None,
+            },
+            versions: vec![
+                CrateVersion {
+                    num: "2.0.0".to_string(),
+                    yanked: true,
+                    bin_names: Some(vec!["foo".to_string()]),
+                    license: None,
+                },
+                CrateVersion {
+                    num: "1.1.0".to_string(),
+                    yanked: false,
+                    bin_names: Some(vec!["foo-cli".to_string(), "foo".to_string()]),
+                    license: Some("MIT".to_string()),
+                },
+                CrateVersion {
+                    num: "1.0.0".to_string(),
+                    yanked: false,
+                    bin_names: Some(vec!["foo".to_string()]),
+                    license: Some("MIT".to_string()),
+                },
+            ],
+        };
+
+        let meta = BinstallMeta {
+            pkg_url: Some(
+                "{ repo }/releases/download/v{ version }/{ name }-{ target }.{ archive-format }"
+                    .to_string(),
+            ),
+            pkg_fmt: None,
+            bin_dir: Some("{ name }-v{ version }/{ bin }{ binary-ext }".to_string()),
+            overrides: HashMap::new(),
+        };
+
+        let content = generate_formula(&response, meta, "x86_64-unknown-linux-gnu").unwrap();
+        let f = toml::from_str::<Formula>(&content).unwrap();
+
+        assert_eq!(f.package.name, "crates.io/foo");
+        assert_eq!(f.package.bin, "foo");
+        assert_eq!(f.package.description, "The foo tool");
+        assert_eq!(
+            f.package.versions,
+            Some(vec!["1.1.0".to_string(), "1.0.0".to_string()])
+        );
+
+        let target = f.get_current_download_url("1.1.0", None).unwrap();
+
+        assert_eq!(
+            target.url,
+            "https://github.com/axetroy/foo/releases/download/v1.1.0/foo-x86_64-unknown-linux-gnu.tgz"
+        );
+        assert_eq!(target.path, "/foo-v1.1.0");
+
+        // the crate without binstall metadata
+        assert!(generate_formula(
+            &response,
+            BinstallMeta::default(),
+            "x86_64-unknown-linux-gnu"
+        )
+        .is_err());
+    }
+}
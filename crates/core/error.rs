@@ -0,0 +1,98 @@
+#![deny(warnings)]
+
+use std::fmt;
+
+use eyre::Report;
+
+// the exit code of the error that is not classified
+pub const EXIT_FAILURE: i32 = 1;
+
+// the errors that the scripts can tell apart by the exit code, the codes are documented in README
+#[derive(Debug, PartialEq)]
+pub enum CaskError {
+    PackageNotFound(String),     // the formula of package can not be found
+    NotInstalled(String),        // the package is not installed
+    VersionNotFound(String),     // there is no version that matches the requirement
+    UnsupportedPlatform(String), // the package does not provide the resource of current platform
+    Network(String),             // the server can not be reached, it may be recovered by retrying
+    Integrity(String),           // the checksum or signature of resource does not match
+    HookFailed(String),          // the hook of formula exits with error
+}
+
+impl CaskError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CaskError::PackageNotFound(_) => 3,
+            CaskError::NotInstalled(_) => 4,
+            CaskError::VersionNotFound(_) => 5,
+            CaskError::UnsupportedPlatform(_) => 6,
+            CaskError::Network(_) => 7,
+            CaskError::Integrity(_) => 8,
+            CaskError::HookFailed(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for CaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaskError::PackageNotFound(msg)
+            | CaskError::NotInstalled(msg)
+            | CaskError::VersionNotFound(msg)
+            | CaskError::UnsupportedPlatform(msg)
+            | CaskError::Network(msg)
+            | CaskError::Integrity(msg)
+            | CaskError::HookFailed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CaskError {}
+
+// the exit code of error, the transient errors of downloading and git are network errors
+pub fn get_exit_code(err: &Report) -> i32 {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<CaskError>() {
+            return e.exit_code();
+        }
+
+        if cause
+            .downcast_ref::<git::GitError>()
+            .is_some_and(|e| e.is_transient())
+        {
+            return CaskError::Network(cause.to_string()).exit_code();
+        }
+    }
+
+    if downloader::is_transient(err) {
+        return CaskError::Network(err.to_string()).exit_code();
+    }
+
+    EXIT_FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::Report;
+
+    use crate::error::{self, CaskError};
+
+    #[test]
+    fn test_get_exit_code() {
+        let err: Report = CaskError::NotInstalled("foo".to_string()).into();
+
+        assert_eq!(error::get_exit_code(&err), 4);
+        assert_eq!(err.to_string(), "foo");
+
+        // the error is wrapped with the context
+        assert_eq!(
+            error::get_exit_code(&err.wrap_err("uninstall package fail")),
+            4
+        );
+
+        assert_eq!(
+            error::get_exit_code(&eyre::format_err!("unknown")),
+            error::EXIT_FAILURE
+        );
+    }
+}
@@ -0,0 +1,284 @@
+#![deny(warnings)]
+
+use crate::util;
+
+use url::{form_urlencoded::byte_serialize, Url};
+
+// the code hosting service of repository
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Gitea, // Gitea/Forgejo, eg. codeberg.org
+}
+
+// the shorthand prefixes of package name. eg. 'gitlab:group/project'
+const SHORTHANDS: [(&str, &str); 6] = [
+    ("github:", "github.com"),
+    ("gitlab:", "gitlab.com"),
+    ("bitbucket:", "bitbucket.org"),
+    ("codeberg:", "codeberg.org"),
+    ("gitea:", "gitea.com"),
+    ("crates:", "crates.io"),
+];
+
+fn detect(host: &str) -> Option<Forge> {
+    match host {
+        "github.com" => Some(Forge::GitHub),
+        "gitlab.com" => Some(Forge::GitLab),
+        "bitbucket.org" => Some(Forge::Bitbucket),
+        "codeberg.org" | "gitea.com" => Some(Forge::Gitea),
+        // the self-hosted instances are recognized by the sub domain
+        h if h.starts_with("gitlab.") => Some(Forge::GitLab),
+        h if h.starts_with("gitea.") || h.starts_with("forgejo.") => Some(Forge::Gitea),
+        _ => None,
+    }
+}
+
+fn encode(s: &str) -> String {
+    byte_serialize(s.as_bytes()).collect()
+}
+
+// the repository on the known code hosting service
+#[derive(Debug, PartialEq)]
+pub struct ForgeRepository {
+    pub forge: Forge,
+    pub origin: String, // the scheme and host of repository. eg. https://gitlab.com
+    pub path: String,   // the path of repository without '.git'. eg. group/sub/project
+    pub api: String,    // the root of API. eg. https://gitlab.com/api/v4
+}
+
+// parse the repository URL, the SSH address is parsed as HTTPS
+pub fn parse(repository: &str) -> Option<ForgeRepository> {
+    let u = Url::parse(&util::to_https_url(repository)).ok()?;
+    let host = u.host_str()?;
+
+    let path = u
+        .path()
+        .trim_matches('/')
+        .trim_end_matches(".git")
+        .to_string();
+
+    if path.is_empty() {
+        return None;
+    }
+
+    let forge = detect(host)?;
+    let origin = format!("{}://{}", u.scheme(), host);
+
+    let api = match forge {
+        Forge::GitHub => "https://api.github.com".to_string(),
+        Forge::GitLab => format!("{}/api/v4", origin),
+        Forge::Bitbucket => "https://api.bitbucket.org/2.0".to_string(),
+        Forge::Gitea => format!("{}/api/v1", origin),
+    };
+
+    Some(ForgeRepository {
+        forge,
+        origin,
+        path,
+        api,
+    })
+}
+
+// expand the shorthand and strip the path of web page from the package name.
+// eg. 'gitlab:group/sub/project' or 'gitlab.com/group/sub/project/-/tree/main' -> 'gitlab.com/group/sub/project'
+pub fn normalize_package_name(package_name: &str) -> String {
+    let shorthand = SHORTHANDS.iter().find_map(|(prefix, host)| {
+        package_name
+            .strip_prefix(prefix)
+            .map(|path| format!("{}/{}", host, path.trim_start_matches('/')))
+    });
+
+    // the repository URL is kept as it is
+    let name = match shorthand {
+        Some(name) => name,
+        None if package_name.contains("://") || util::is_ssh_url(package_name) => {
+            return package_name.to_string()
+        }
+        None => package_name.to_string(),
+    };
+
+    let name = name.trim_end_matches('/').trim_end_matches(".git");
+
+    let (host, path) = match name.split_once('/') {
+        Some((host, path)) => (host, path),
+        None => return name.to_string(),
+    };
+
+    let path = match detect(host) {
+        // the project may be in the nested groups, the page path starts with '/-/'
+        Some(Forge::GitLab) => path.split("/-/").next().unwrap_or(path).to_string(),
+        Some(_) => path.splitn(3, '/').take(2).collect::<Vec<&str>>().join("/"),
+        None => path.to_string(),
+    };
+
+    format!("{}/{}", host, path.trim_end_matches(".git"))
+}
+
+impl ForgeRepository {
+    // the raw URL of file in the repository, return None if the host does not serve it by reference
+    pub fn raw_url(&self, git_ref: &str, file: &str) -> Option<String> {
+        match self.forge {
+            Forge::GitHub => Some(format!(
+                "https://raw.githubusercontent.com/{}/{}/{}",
+                self.path, git_ref, file
+            )),
+            Forge::GitLab => Some(format!(
+                "{}/{}/-/raw/{}/{}",
+                self.origin, self.path, git_ref, file
+            )),
+            Forge::Bitbucket => Some(format!(
+                "{}/{}/raw/{}/{}",
+                self.origin, self.path, git_ref, file
+            )),
+            // the default branch can not be referred as 'HEAD' in the raw URL of Gitea
+            Forge::Gitea => None,
+        }
+    }
+
+    // the API URL of the release assets of tag
+    pub fn release_api_url(&self, tag: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea => format!(
+                "{}/repos/{}/releases/tags/{}",
+                self.api,
+                self.path,
+                encode(tag)
+            ),
+            Forge::GitLab => format!(
+                "{}/projects/{}/releases/{}",
+                self.api,
+                encode(&self.path),
+                encode(tag)
+            ),
+            // the downloads of Bitbucket do not belong to a tag
+            Forge::Bitbucket => format!(
+                "{}/repositories/{}/downloads?pagelen=100",
+                self.api, self.path
+            ),
+        }
+    }
+
+    // the URL prefix of the release assets of tag. eg. https://github.com/foo/bar/releases/download/v1.0.0
+    pub fn release_download_url(&self, tag: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea => {
+                format!("{}/{}/releases/download/{}", self.origin, self.path, tag)
+            }
+            Forge::GitLab => format!("{}/{}/-/releases/{}/downloads", self.origin, self.path, tag),
+            Forge::Bitbucket => format!("{}/{}/downloads", self.origin, self.path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::forge::{normalize_package_name, parse, Forge, ForgeRepository};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("https://gitlab.com/group/sub/tool.git"),
+            Some(ForgeRepository {
+                forge: Forge::GitLab,
+                origin: "https://gitlab.com".to_string(),
+                path: "group/sub/tool".to_string(),
+                api: "https://gitlab.com/api/v4".to_string()
+            })
+        );
+        assert_eq!(
+            parse("git@codeberg.org:foo/bar.git").map(|r| (r.forge, r.path)),
+            Some((Forge::Gitea, "foo/bar".to_string()))
+        );
+        assert_eq!(
+            parse("https://gitlab.example.com/foo/bar").map(|r| r.origin),
+            Some("https://gitlab.example.com".to_string())
+        );
+        assert_eq!(parse("https://example.com/foo/bar"), None);
+        assert_eq!(parse("https://github.com"), None);
+    }
+
+    #[test]
+    fn test_normalize_package_name() {
+        assert_eq!(
+            normalize_package_name("github.com/axetroy/dvm"),
+            "github.com/axetroy/dvm"
+        );
+        assert_eq!(
+            normalize_package_name("gitlab:group/sub/tool"),
+            "gitlab.com/group/sub/tool"
+        );
+        assert_eq!(
+            normalize_package_name("gitlab.com/group/sub/tool/-/tree/main/"),
+            "gitlab.com/group/sub/tool"
+        );
+        assert_eq!(
+            normalize_package_name("crates:ripgrep"),
+            "crates.io/ripgrep"
+        );
+        assert_eq!(
+            normalize_package_name("codeberg:foo/bar.git"),
+            "codeberg.org/foo/bar"
+        );
+        assert_eq!(
+            normalize_package_name("bitbucket.org/foo/bar/src/main"),
+            "bitbucket.org/foo/bar"
+        );
+        assert_eq!(
+            normalize_package_name("github.com/axetroy/dvm/tree/master"),
+            "github.com/axetroy/dvm"
+        );
+        assert_eq!(
+            normalize_package_name("example.com/foo/bar/baz"),
+            "example.com/foo/bar/baz"
+        );
+        assert_eq!(normalize_package_name("dvm"), "dvm");
+        assert_eq!(
+            normalize_package_name("https://gitlab.com/group/tool/-/tree/main"),
+            "https://gitlab.com/group/tool/-/tree/main"
+        );
+        assert_eq!(
+            normalize_package_name("git@github.com:axetroy/dvm.git"),
+            "git@github.com:axetroy/dvm.git"
+        );
+    }
+
+    #[test]
+    fn test_urls() {
+        let gitlab = parse("https://gitlab.com/group/sub/tool").unwrap();
+
+        assert_eq!(
+            gitlab.raw_url("HEAD", "Cask.toml").unwrap(),
+            "https://gitlab.com/group/sub/tool/-/raw/HEAD/Cask.toml"
+        );
+        assert_eq!(
+            gitlab.release_download_url("v1.0.0"),
+            "https://gitlab.com/group/sub/tool/-/releases/v1.0.0/downloads"
+        );
+
+        let gitea = parse("https://codeberg.org/foo/bar").unwrap();
+
+        assert!(gitea.raw_url("HEAD", "Cask.toml").is_none());
+        assert_eq!(
+            gitea.release_api_url("v1.0.0"),
+            "https://codeberg.org/api/v1/repos/foo/bar/releases/tags/v1.0.0"
+        );
+        assert_eq!(
+            gitea.release_download_url("v1.0.0"),
+            "https://codeberg.org/foo/bar/releases/download/v1.0.0"
+        );
+
+        let bitbucket = parse("https://bitbucket.org/foo/bar").unwrap();
+
+        assert_eq!(
+            bitbucket.release_api_url("v1.0.0"),
+            "https://api.bitbucket.org/2.0/repositories/foo/bar/downloads?pagelen=100"
+        );
+        assert_eq!(
+            bitbucket.release_download_url("v1.0.0"),
+            "https://bitbucket.org/foo/bar/downloads"
+        );
+    }
+}
@@ -0,0 +1,2106 @@
+#![deny(warnings)]
+
+use crate::{
+    cache, cask,
+    error::CaskError,
+    forge, hooker, provider, registry,
+    retry::{retry_async, retry_blocking},
+    tap, util,
+};
+use std::collections::HashMap;
+
+use std::{
+    env, fs,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+use git::GitError;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use tinytemplate::TinyTemplate;
+use tracing::{debug, info};
+use url::Url;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Formula {
+    #[serde(skip)]
+    pub file_content: String, // The file content of this formula
+    #[serde(skip)]
+    pub repository: String, // The repository of this formula
+    #[serde(skip)]
+    pub filepath: PathBuf, // The filepath of this formula
+    #[serde(skip)]
+    pub source: String, // The provider or the tap that resolved this formula. eg. build-in
+
+    pub cask: Option<Cask>, // The cask information that generated by cask. This field is only available after the package is installed.
+    pub package: Package,   // The package information
+    pub context: Option<HashMap<String, String>>, // The hash map for renderer template
+    pub windows: Option<Platform>, // The windows target information
+    pub darwin: Option<Platform>, // The macOS target information
+    pub linux: Option<Platform>, // The linux target information
+    pub freebsd: Option<Platform>, // The freebsd target information
+    pub dependencies: Option<HashMap<String, Dependencies>>, // TODO: The dependencies of the package
+    pub channel: Option<HashMap<String, Channel>>, // The release channels of the package. eg. beta/nightly
+    pub resource: Option<Resource>, // The auxiliary files installed besides the binary. eg. the data folder
+
+    // The hooks defined
+    pub hook: Option<hooker::Hook>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Cask {
+    pub name: String,            // The package name. eg github.com/axetroy/gpm.rs
+    pub created_at: String,      // The package installed date
+    pub version: String,         // The version is using for package
+    pub repository: String,      // The package installed from the repository url
+    pub channel: Option<String>, // The release channel is using for package
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>, // The branch, tag or commit of formula repository is using for package
+    pub requirement: Option<String>, // The version requirement that the package is upgraded within. eg. ^1.2
+    pub files: Option<Vec<String>>,  // The auxiliary files installed in the folder of version
+    pub aliases: Option<Vec<String>>, // The short names that the installed package is found by. eg. ["ripgrep"]
+    pub source: Option<String>, // The provider or the tap that resolved the formula. eg. build-in
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Channel {
+    pub pattern: Option<String>, // The regex of versions that belong to the channel. eg. "-beta"
+    pub version: Option<String>, // The fixed version of a rolling release. eg. "nightly"
+    pub windows: Option<Platform>, // The windows target information of the channel
+    pub darwin: Option<Platform>, // The macOS target information of the channel
+    pub linux: Option<Platform>, // The linux target information of the channel
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Resource {
+    pub files: Vec<ResourceFile>, // The files that extracted from the tarball besides the binary
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceFile {
+    pub path: String, // The file path in the tarball. eg. "/share/tool/data.json"
+    pub dest: String, // The destination relative to the package folder, the file name is kept if it ends with '/'. eg. "share/tool/"
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Dependencies {
+    Detail(DependenciesDetail), // More information of the package
+    Simple(String),             // The version of the package
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DependenciesDetail {
+    pub version: String, // The version of the package
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub name: String,                    // The package name
+    pub bin: String,                     // The binary name of the package
+    pub repository: String,              // The repository url
+    pub description: String,             // The description of the package
+    pub versions: Option<Vec<String>>, // The version of package. If versions are not provide, cask will automatically get the versions from the repository tags.
+    pub authors: Option<Vec<String>>,  // The author of package
+    pub keywords: Option<Vec<String>>, // The keywords of the package
+    pub aliases: Option<Vec<String>>, // The short names of the package besides the executable name. eg. ["ripgrep"]
+    pub license: Option<String>,      // The license of the package
+    pub homepage: Option<String>,     // The homepage of the package
+    pub deprecated: Option<bool>,     // Whether the package is no longer maintained
+    pub replaced_by: Option<String>,  // The package name that replaces this deprecated package
+    pub allow_prerelease: Option<bool>, // Whether the pre-release versions are resolved by default
+    pub version_pattern: Option<String>, // The regex of tags that should be treated as versions. eg. "^tool-v"
+    pub version_extract: Option<String>, // The regex to extract version from tag, the first capture group is used. eg. "^tool-v(.+)$"
+    pub public_key: Option<String>, // The minisign or GPG public key to verify the signature of download resource
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Platform {
+    pub x86: Option<ResourceTarget>,
+    pub x86_64: Option<ResourceTarget>,
+    pub arm: Option<ResourceTarget>,
+    pub armv7: Option<ResourceTarget>,
+    pub aarch64: Option<ResourceTarget>,
+    pub mips: Option<ResourceTarget>,
+    pub mips64: Option<ResourceTarget>,
+    pub mips64el: Option<ResourceTarget>,
+    pub riscv64: Option<ResourceTarget>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ResourceTarget {
+    Detailed(ResourceTargetDetail),
+    Executable(ResourceTargetExecutable),
+    Asset(ResourceTargetAsset),
+    Simple(String),
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceTargetDetail {
+    pub url: String,                  // The url will be download when install the package
+    pub checksum: Option<String>, // The checksum of download resource, SHA256 by default. eg. "sha512:<hex>" or "blake3:<hex>"
+    pub checksum_url: Option<String>, // The url of checksums file. eg. SHA256SUMS
+    pub signature_url: Option<String>, // The url of signature file. eg. .minisig or .sig
+    pub extension: Option<extractor::Extension>, // The extension name of download resource. optional value: ".tar.gz" ".gz" ".tar.bz2" ".tar.xz" ".tar.zst" ".zst" ".tar" ".zip" ".7z" ".deb" ".rpm" ".dmg"
+    pub path: Option<String>, // The folder that binary file locate in the tarball
+    pub inner_path: Option<String>, // The path of inner archive in the tarball, the binary is extracted from it. eg. "/dist/tool.tar.gz"
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceTargetExecutable {
+    pub executable: String, // The url will be download when install the package
+    pub checksum: Option<String>, // The checksum of download resource, SHA256 by default. eg. "sha512:<hex>" or "blake3:<hex>"
+    pub checksum_url: Option<String>, // The url of checksums file. eg. SHA256SUMS
+    pub signature_url: Option<String>, // The url of signature file. eg. .minisig or .sig
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceTargetAsset {
+    pub asset_pattern: String, // The regex of asset name in the GitHub/GitLab release of the version
+    pub checksum: Option<String>, // The checksum of download resource, SHA256 by default. eg. "sha512:<hex>" or "blake3:<hex>"
+    pub checksum_url: Option<String>, // The url of checksums file. eg. SHA256SUMS
+    pub signature_url: Option<String>, // The url of signature file. eg. .minisig or .sig
+    pub extension: Option<extractor::Extension>, // The extension name of download resource. optional value: ".tar.gz" ".gz" ".tar.bz2" ".tar.xz" ".tar.zst" ".zst" ".tar" ".zip" ".7z" ".deb" ".rpm" ".dmg"
+    pub path: Option<String>, // The folder that binary file locate in the tarball
+    pub inner_path: Option<String>, // The path of inner archive in the tarball, the binary is extracted from it. eg. "/dist/tool.tar.gz"
+    pub executable: Option<bool>,   // Whether the asset is a executable file not a tarball
+}
+
+#[derive(Serialize)]
+pub struct URLTemplateContext<'a> {
+    version: &'a str,
+    package: &'a Package,
+    context: Option<&'a HashMap<String, String>>,
+    os: &'static str,   // The current os. eg. linux/macos/windows
+    arch: &'static str, // The current arch. eg. x86_64/aarch64
+    is_windows: bool,
+    is_macos: bool,
+    is_linux: bool,
+    is_freebsd: bool,
+    is_musl: bool,
+    major_version: Option<u64>, // The major version if version is a valid semver
+    minor_version: Option<u64>, // The minor version if version is a valid semver
+    patch_version: Option<u64>, // The patch version if version is a valid semver
+    release_download_url: Option<String>, // The URL prefix of the assets of release 'v{version}' on the known code hosting service
+}
+
+// The supported formula file names, the former take precedence
+pub const FORMULA_FILE_NAMES: [&str; 3] = ["Cask.toml", "Cask.yaml", "Cask.json"];
+
+// find the formula file in the folder
+pub fn find_formula_file(dir: &Path) -> Option<PathBuf> {
+    FORMULA_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|p| p.exists())
+}
+
+fn is_toml_file(formula_file: &Path) -> bool {
+    !matches!(
+        formula_file.extension().and_then(|ext| ext.to_str()),
+        Some("json") | Some("yaml") | Some("yml")
+    )
+}
+
+// find the closest valid key from the serde error. eg. "unknown field `darw`, expected one of `darwin`, `linux`"
+fn suggest_unknown_field(msg: &str) -> Option<String> {
+    let rest = msg.split("unknown field `").nth(1)?;
+    let unknown = rest.split('`').next()?;
+    let expected = rest.split("expected ").nth(1)?.lines().next()?;
+
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (util::levenshtein(unknown, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+pub fn new(formula_file: &Path, repo: &str) -> Result<Formula, Report> {
+    let mut file = match File::open(formula_file) {
+        Ok(f) => f,
+        Err(e) => match e.kind() {
+            ErrorKind::NotFound => Err(eyre::format_err!("the formula does not exist")),
+            other_error => Err(eyre::format_err!("{:?}", other_error)),
+        }?,
+    };
+
+    let mut file_content = String::new();
+
+    match file.read_to_string(&mut file_content) {
+        Ok(_) => {}
+        Err(e) => {
+            return Err(eyre::Report::from(e));
+        }
+    };
+
+    drop(file);
+
+    let parsed: Result<Formula, String> =
+        match formula_file.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&file_content).map_err(|e| e.to_string()),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&file_content).map_err(|e| e.to_string())
+            }
+            _ => toml::from_str(&file_content).map_err(|e| e.to_string()),
+        };
+
+    let mut f = parsed.map_err(|msg| {
+        let suggestion = suggest_unknown_field(&msg)
+            .map(|key| format!("\nDid you mean `{}`?", key))
+            .unwrap_or_default();
+
+        eyre::format_err!(
+            "can not parse formula '{}': {}{}",
+            formula_file.display(),
+            msg.trim_end(),
+            suggestion
+        )
+    })?;
+
+    f.filepath = formula_file.to_path_buf();
+    f.repository = repo.to_string();
+    f.file_content = file_content;
+
+    Ok(f)
+}
+
+pub struct DownloadTarget {
+    pub url: String,
+    pub path: String,
+    pub checksum: Option<String>,
+    pub ext: String,
+    pub executable: bool, // if target is a executable file not a tarball
+    pub asset_pattern: Option<String>, // the url is resolved from the release assets that match the pattern
+    pub checksum_url: Option<String>, // the checksum is looked up from the checksums file if not provided
+    pub signature_url: Option<String>, // the resource is verified with the signature before extracting
+    pub inner_path: Option<String>, // the binary is extracted from the inner archive of the resource
+}
+
+// get the extension name from the file name of URL, '.tar.gz' is used if unknown
+pub fn get_ext_name_from_url(url: &str) -> Result<&'static str, Report> {
+    let u = Url::parse(url)?;
+
+    let default_ext = extractor::Extension::TarGz;
+
+    let filename = match u.path_segments() {
+        Some(mut sep) => sep.next_back().unwrap_or_default().to_string(),
+        None => return Ok(default_ext.as_str()),
+    };
+
+    let ext = [
+        extractor::Extension::TarGz,
+        extractor::Extension::Tgz,
+        extractor::Extension::Gz,
+        extractor::Extension::TarBiz2,
+        extractor::Extension::Tbz2,
+        extractor::Extension::TarXz,
+        extractor::Extension::TarZst,
+        extractor::Extension::Zst,
+        extractor::Extension::Tar,
+        extractor::Extension::Zip,
+        extractor::Extension::SevenZip,
+        extractor::Extension::Deb,
+        extractor::Extension::Rpm,
+        extractor::Extension::Dmg,
+    ]
+    .into_iter()
+    .find(|ext| filename.ends_with(ext.as_str()))
+    .unwrap_or(default_ext);
+
+    Ok(ext.as_str())
+}
+
+// the extension name of executable file
+fn get_executable_ext() -> String {
+    #[cfg(unix)]
+    {
+        "".to_string()
+    }
+    #[cfg(windows)]
+    {
+        ".exe".to_string()
+    }
+}
+
+pub(crate) fn get_formula_git_url(package_name: &str) -> String {
+    format!("https://{}.git", package_name)
+}
+
+// the hint of credentials is appended if the private repository is rejected
+fn format_git_error(e: GitError) -> Report {
+    match e {
+        GitError::AuthenticationError { .. } => eyre::format_err!(
+            "{}\nIf it is a private repository, set the token in `git_tokens` of '~/.cask/config.toml' or configure the `credential_helper`",
+            e
+        ),
+        e => eyre::format_err!("{}", e),
+    }
+}
+
+fn print_publishing_msg() {
+    let msg = r#"It looks like the package does not support Cask
+                        If you are the package owner, see our documentation for how to publish a package:
+                        https://github.com/cask-pkg/cask.rs/blob/main/DESIGN.md#how-do-i-publish-package"
+                    "#
+    .lines()
+    .map(|s| s.trim_start().to_owned())
+    .collect::<Vec<String>>()
+    .join("\n");
+
+    info!("{}", msg);
+}
+
+// sort the versions in descending semver order without duplicates, the 'v' prefix is stripped.
+// the versions that are not semver are kept in the original order after them. eg. nightly
+fn sort_versions(versions: &[String], include_prerelease: bool) -> Vec<String> {
+    let mut semvers: Vec<Version> = vec![];
+    let mut others: Vec<String> = vec![];
+
+    for version in versions {
+        match Version::parse(version.trim_start_matches('v')) {
+            Ok(v) => {
+                if include_prerelease || v.pre.is_empty() {
+                    semvers.push(v);
+                }
+            }
+            Err(_) => {
+                if !others.contains(version) {
+                    others.push(version.clone());
+                }
+            }
+        }
+    }
+
+    semvers.sort_by(|a, b| b.cmp(a));
+    semvers.dedup();
+
+    semvers
+        .into_iter()
+        .map(|v| v.to_string())
+        .chain(others)
+        .collect()
+}
+
+// parse the version requirement, the partial version matches the versions with the same prefix. eg. 1.2 -> 1.2.x
+fn parse_version_req(spec: &str) -> Result<VersionReq, Report> {
+    let spec = spec.trim_start_matches('v');
+    let is_partial = !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit() || c == '.');
+
+    let req = if is_partial {
+        format!("={}", spec)
+    } else {
+        spec.to_string()
+    };
+
+    VersionReq::parse(&req)
+        .map_err(|e| eyre::format_err!("invalid version requirement '{}': {}", spec, e))
+}
+
+// whether the specified version is a requirement rather than an exact version. eg. ^1.2, ~1.2.3, >=1.0, 1.x
+pub fn is_version_requirement(spec: &str) -> bool {
+    Version::parse(spec.trim_start_matches('v')).is_err() && parse_version_req(spec).is_ok()
+}
+
+// whether the specified version or requirement refers to a pre-release. eg. 2.0.0-beta.1 or ^2.0.0-beta
+pub fn is_prerelease_spec(spec: &str) -> bool {
+    match Version::parse(spec.trim_start_matches('v')) {
+        Ok(v) => !v.pre.is_empty(),
+        Err(_) => parse_version_req(spec)
+            .map(|req| req.comparators.iter().any(|c| !c.pre.is_empty()))
+            .unwrap_or(false),
+    }
+}
+
+// find the version that matches the exact version or requirement, the versions are sorted in descending order.
+pub fn resolve_version(versions: &[String], spec: &str) -> Result<Option<String>, Report> {
+    // the rolling release of channel is not a semver version. eg. nightly
+    if let Some(v) = versions.iter().find(|v| *v == spec) {
+        return Ok(Some(v.clone()));
+    }
+
+    let parse = |v: &String| Version::parse(v.trim_start_matches('v')).ok();
+
+    if let Ok(exact) = Version::parse(spec.trim_start_matches('v')) {
+        return Ok(versions
+            .iter()
+            .find(|v| parse(v).map(|v| v == exact).unwrap_or(false))
+            .cloned());
+    }
+
+    let req = parse_version_req(spec)?;
+
+    Ok(versions
+        .iter()
+        .find(|v| parse(v).map(|v| req.matches(&v)).unwrap_or(false))
+        .cloned())
+}
+
+// fetch the formula of package, the formula repository is checked out at the branch, tag or commit if specified.
+// the formula is acquired by the first provider that provides the package
+pub async fn fetch(
+    cask: &cask::Cask,
+    package_name: &str,
+    git_ref: Option<&str>,
+    temp: bool,
+    is_verbose: bool,
+) -> Result<Formula, Report> {
+    // eg. 'gitlab:group/project' -> 'gitlab.com/group/project'
+    let package_name = &forge::normalize_package_name(package_name);
+
+    if !cask.settings().offline {
+        info!("Fetching {} formula...", package_name);
+    }
+
+    let option = provider::FetchOption {
+        git_ref,
+        temp,
+        verbose: is_verbose,
+    };
+
+    for p in provider::list() {
+        if let Some(mut f) = p.fetch(cask, package_name, &option).await? {
+            debug!(
+                "the formula of '{}' is provided by '{}'",
+                package_name,
+                p.name()
+            );
+
+            // the build-in provider records the tap that provides the formula
+            if f.source.is_empty() {
+                f.source = p.name().to_string();
+            }
+
+            return Ok(f);
+        }
+    }
+
+    // the short name is resolved by the index of build-in formula
+    if registry::is_short_name(package_name) {
+        return Err(CaskError::PackageNotFound(format!(
+            "can not found package {}, try run 'cask search {}' or 'cask update-index'",
+            package_name, package_name
+        ))
+        .into());
+    }
+
+    Err(CaskError::PackageNotFound(format!("can not found package {}", package_name)).into())
+}
+
+// use the build-in formula or the formula repository cloned before
+pub(crate) fn fetch_from_cache(cask: &cask::Cask, package_name: &str) -> Result<Formula, Report> {
+    if let Some(f) = find_package_in_taps(cask, package_name)? {
+        return Ok(f);
+    }
+
+    let git_url = match Url::parse(package_name) {
+        _ if util::is_ssh_url(package_name) => package_name.to_string(),
+        Ok(url) => url.to_string(),
+        Err(_) => get_formula_git_url(package_name),
+    };
+
+    let cloned_dir = cask.package_dir(package_name).join("repository");
+
+    match find_formula_file(&cloned_dir) {
+        Some(cask_file_path) => new(&cask_file_path, &git_url),
+        None => Err(eyre::format_err!(
+            "The formula of '{}' is not in cache, it can not be fetched in offline mode",
+            package_name
+        )),
+    }
+}
+
+// find the formula in the build-in formula and the taps, the source of higher priority is searched first
+pub(crate) fn find_package_in_taps(
+    cask: &cask::Cask,
+    package_name: &str,
+) -> Result<Option<Formula>, Report> {
+    let config = cask.config();
+
+    for source in tap::list(cask, config)? {
+        if !source.dir.exists() {
+            continue;
+        }
+
+        let mut package_dir = source.dir;
+
+        for p in package_name.split('/') {
+            package_dir = package_dir.join(p)
+        }
+
+        if let Some(cask_file_path) = find_formula_file(&package_dir) {
+            let mut f = new(&cask_file_path, "")?;
+
+            f.source = source.name;
+
+            return Ok(Some(f));
+        }
+    }
+
+    Ok(None)
+}
+
+// the raw URLs of formula files on the known hosts, so that the repository does not need to be cloned
+fn get_formula_raw_urls(git_url: &str, git_ref: Option<&str>) -> Vec<(&'static str, String)> {
+    let repo = match forge::parse(git_url) {
+        Some(repo) => repo,
+        None => return vec![],
+    };
+
+    let git_ref = git_ref.unwrap_or("HEAD");
+
+    FORMULA_FILE_NAMES
+        .iter()
+        .filter_map(|name| repo.raw_url(git_ref, name).map(|url| (*name, url)))
+        .collect()
+}
+
+// fetch the formula file only instead of cloning the whole repository.
+// the raw file is downloaded from the known hosts, otherwise the repository is cloned sparsely
+async fn fetch_formula_file(
+    cask: &cask::Cask,
+    git_url: &str,
+    git_ref: Option<&str>,
+    dest_dir: &Path,
+    is_verbose: bool,
+) -> Result<Option<PathBuf>, Report> {
+    let config = cask.config();
+
+    // the raw files are served by the origin host, which may not be accessible if the repository is mapped
+    let raw_urls = if config.is_git_mapped(git_url) {
+        vec![]
+    } else {
+        get_formula_raw_urls(git_url, git_ref)
+    };
+
+    if !raw_urls.is_empty() {
+        for (name, url) in raw_urls {
+            let url = config.rewrite_url(&url);
+            let option = cask
+                .settings()
+                .get_download_option(config, &url, is_verbose);
+
+            let content = retry_async(
+                &cask.settings().get_retry_policy(),
+                downloader::is_transient,
+                || downloader::fetch_text(&url, &option),
+            )
+            .await?;
+
+            if let Some(content) = content {
+                fs::create_dir_all(dest_dir)?;
+
+                let formula_file = dest_dir.join(name);
+
+                fs::write(&formula_file, content)?;
+
+                return Ok(Some(formula_file));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    let repo = cask.settings().get_git_repository(config, git_url)?;
+
+    let sparse_dir = dest_dir.to_path_buf();
+    let reference = git_ref.map(|r| r.to_string());
+
+    retry_blocking(
+        &cask.settings().get_retry_policy(),
+        GitError::is_transient,
+        move || {
+            repo.clone(
+                &sparse_dir,
+                git::CloneOption {
+                    depth: Some(1),
+                    quiet: Some(true),
+                    verbose: Some(false),
+                    progress: Some(false),
+                    single_branch: Some(true),
+                    dissociate: Some(true),
+                    filter: None,
+                    sparse: Some(FORMULA_FILE_NAMES.iter().map(|n| n.to_string()).collect()),
+                    reference: reference.clone(),
+                },
+            )
+        },
+    )
+    .await
+    .map_err(format_git_error)?;
+
+    Ok(find_formula_file(dest_dir))
+}
+
+// fetch remote formula
+pub(crate) async fn fetch_with_git_url(
+    cask: &cask::Cask,
+    package_name: &str,
+    git_url: &str,
+    git_ref: Option<&str>,
+    temp: bool,
+    is_verbose: bool,
+) -> Result<Formula, Report> {
+    // the temp folder will be removed when it goes out of scope
+    let temp_dir = if temp {
+        Some(tempfile::Builder::new().prefix("cask_formula_").tempdir()?)
+    } else {
+        None
+    };
+
+    let formula_cloned_dir = match &temp_dir {
+        Some(dir) => dir.path().join("repository"),
+        None => cask.package_dir(package_name).join("repository"),
+    };
+
+    // the repository in the package folder is not updated by another cask process at the same time
+    let _package_lock = match &temp_dir {
+        Some(_) => None,
+        None => Some(cask.lock_package(package_name)?),
+    };
+
+    let config = cask.config();
+
+    // clone from the mirror, but the origin URL is recorded as the repository
+    let repo = cask.settings().get_git_repository(config, git_url)?;
+
+    let reference = git_ref.map(|r| r.to_string());
+
+    let clone_option = move || git::CloneOption {
+        depth: Some(1),
+        quiet: Some(!is_verbose),
+        verbose: Some(is_verbose),
+        progress: Some(!is_verbose),
+        single_branch: Some(true),
+        dissociate: Some(true),
+        filter: Some("tree:0".to_string()),
+        sparse: None,
+        reference: reference.clone(),
+    };
+
+    // the repository that is cloned for the hooks before is updated incrementally instead of cloned again
+    if formula_cloned_dir.exists() {
+        let dest_dir = formula_cloned_dir.clone();
+
+        let result = retry_blocking(
+            &cask.settings().get_retry_policy(),
+            GitError::is_transient,
+            move || repo.update(&dest_dir, clone_option()),
+        )
+        .await;
+
+        return load_cloned_formula(package_name, git_url, &formula_cloned_dir, result);
+    }
+
+    // the formula file is fetched into another folder, so that the repository folder always contains the whole repository
+    let formula_dir = tempfile::Builder::new().prefix("cask_formula_").tempdir()?;
+
+    // the hooks run in the repository, so the whole repository is cloned for them
+    match fetch_formula_file(cask, git_url, git_ref, formula_dir.path(), is_verbose).await {
+        Ok(Some(cask_file_path)) => {
+            let f = new(&cask_file_path, git_url)?;
+
+            if f.hook.is_none() {
+                return Ok(f);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            debug!(
+                "Fetch the formula file fail: {}, clone the repository instead",
+                e
+            );
+        }
+    }
+
+    let dest_dir = formula_cloned_dir.clone();
+
+    let result = retry_blocking(
+        &cask.settings().get_retry_policy(),
+        GitError::is_transient,
+        move || repo.clone(&dest_dir, clone_option()),
+    )
+    .await;
+
+    load_cloned_formula(package_name, git_url, &formula_cloned_dir, result)
+}
+
+// load the formula from the repository that is cloned or updated
+fn load_cloned_formula(
+    package_name: &str,
+    git_url: &str,
+    cloned_dir: &Path,
+    result: Result<(), GitError>,
+) -> Result<Formula, Report> {
+    match result {
+        Ok(()) => match find_formula_file(cloned_dir) {
+            Some(cask_file_path) => new(&cask_file_path, git_url),
+            None => {
+                print_publishing_msg();
+
+                Err(eyre::format_err!(
+                    "{} is not a valid formula!",
+                    package_name
+                ))
+            }
+        },
+        Err(e) => Err(format_git_error(e)),
+    }
+}
+
+impl<'a> Formula {
+    fn get_current_os<'c>(&'c self, channel: Option<&'c Channel>) -> Option<&'c Platform> {
+        // the platform defined in the channel take precedence
+        let (windows, darwin, linux) = match channel {
+            Some(c) => (
+                c.windows.as_ref().or(self.windows.as_ref()),
+                c.darwin.as_ref().or(self.darwin.as_ref()),
+                c.linux.as_ref().or(self.linux.as_ref()),
+            ),
+            None => (
+                self.windows.as_ref(),
+                self.darwin.as_ref(),
+                self.linux.as_ref(),
+            ),
+        };
+
+        if cfg!(target_os = "macos") {
+            darwin
+        } else if cfg!(target_os = "windows") {
+            windows
+        } else if cfg!(target_os = "linux") {
+            linux
+        } else if cfg!(target_os = "freebsd") {
+            self.freebsd.as_ref()
+        } else {
+            None
+        }
+    }
+    fn get_current_arch<'c>(&'c self, channel: Option<&'c Channel>) -> Option<&'c ResourceTarget> {
+        if let Some(os) = self.get_current_os(channel) {
+            if cfg!(target_arch = "x86") {
+                os.x86.as_ref()
+            } else if cfg!(target_arch = "x86_64") {
+                os.x86_64.as_ref()
+            } else if cfg!(target_arch = "arm") {
+                os.arm.as_ref()
+            } else if cfg!(target_arch = "armv7") {
+                os.armv7.as_ref()
+            } else if cfg!(target_arch = "aarch64") {
+                os.aarch64.as_ref()
+            } else if cfg!(target_arch = "mips") {
+                os.mips.as_ref()
+            } else if cfg!(target_arch = "mips64") {
+                os.mips64.as_ref()
+            } else if cfg!(target_arch = "mips64el") {
+                os.mips64el.as_ref()
+            } else if cfg!(target_arch = "riscv64") {
+                os.riscv64.as_ref()
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    // get the deprecation notice of package if it has been deprecated
+    pub fn get_deprecation_message(&self) -> Option<String> {
+        if !self.package.deprecated.unwrap_or(false) && self.package.replaced_by.is_none() {
+            return None;
+        }
+
+        let msg = match &self.package.replaced_by {
+            Some(replacement) => format!(
+                "The package '{}' has been deprecated, use '{}' instead",
+                self.package.name, replacement
+            ),
+            None => format!("The package '{}' has been deprecated", self.package.name),
+        };
+
+        Some(msg)
+    }
+
+    pub fn get_file_content(&self) -> String {
+        self.file_content.clone()
+    }
+
+    // get the file content in TOML format, the formula in JSON/YAML is converted
+    pub fn get_toml_content(&self) -> Result<String, Report> {
+        if is_toml_file(&self.filepath) {
+            Ok(self.get_file_content())
+        } else {
+            Ok(toml::to_string(self)?)
+        }
+    }
+
+    pub fn ger_renderer_context(&'a self, version: &'a str) -> URLTemplateContext<'a> {
+        let semver = Version::parse(version.trim_start_matches('v')).ok();
+
+        let render_context = URLTemplateContext {
+            version,
+            package: &self.package,
+            context: self.context.as_ref(),
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+            is_windows: cfg!(target_os = "windows"),
+            is_macos: cfg!(target_os = "macos"),
+            is_linux: cfg!(target_os = "linux"),
+            is_freebsd: cfg!(target_os = "freebsd"),
+            is_musl: cfg!(target_env = "musl"),
+            major_version: semver.as_ref().map(|v| v.major),
+            minor_version: semver.as_ref().map(|v| v.minor),
+            patch_version: semver.as_ref().map(|v| v.patch),
+            release_download_url: forge::parse(&self.package.repository).map(|repo| {
+                repo.release_download_url(&format!("v{}", version.trim_start_matches('v')))
+            }),
+        };
+
+        render_context
+    }
+
+    // get the auxiliary files of resource, the paths are rendered with the template variables
+    pub fn get_resource_files(&self, version: &str) -> Result<Vec<ResourceFile>, Report> {
+        let files = match &self.resource {
+            Some(resource) => &resource.files,
+            None => return Ok(vec![]),
+        };
+
+        let render_context = self.ger_renderer_context(version);
+
+        let mut list = vec![];
+
+        for file in files {
+            let mut tt = TinyTemplate::new();
+
+            tt.add_template("path_template", &file.path)?;
+            tt.add_template("dest_template", &file.dest)?;
+
+            let path = tt.render("path_template", &render_context)?;
+            let mut dest = tt.render("dest_template", &render_context)?;
+
+            // keep the file name if the destination is a folder
+            if dest.ends_with('/') {
+                let filename = Path::new(&path)
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                dest = format!("{}{}", dest, filename);
+            }
+
+            // the file can not be installed outside the package folder
+            if dest.is_empty()
+                || !Path::new(&dest)
+                    .components()
+                    .all(|c| matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(eyre::format_err!(
+                    "invalid destination '{}' of resource file '{}'",
+                    file.dest,
+                    file.path
+                ));
+            }
+
+            // the reserved names can not be accessed on Windows. eg. 'aux'
+            if cfg!(windows) {
+                dest = dest
+                    .split('/')
+                    .map(extractor::sanitize_file_name)
+                    .collect::<Vec<String>>()
+                    .join("/");
+            }
+
+            list.push(ResourceFile { path, dest });
+        }
+
+        Ok(list)
+    }
+
+    // get the release channel by name
+    pub fn get_channel(&self, name: &str) -> Result<&Channel, Report> {
+        self.channel
+            .as_ref()
+            .and_then(|channels| channels.get(name))
+            .ok_or_else(|| {
+                eyre::format_err!(
+                    "the package '{}' does not provide the channel '{}'",
+                    self.package.name,
+                    name
+                )
+            })
+    }
+
+    pub fn get_current_download_url(
+        &self,
+        version: &str,
+        channel: Option<&str>,
+    ) -> Result<DownloadTarget, Report> {
+        let channel = match channel {
+            Some(name) => Some(self.get_channel(name)?),
+            None => None,
+        };
+
+        if let Some(resource_target) = self.get_current_arch(channel) {
+            let render_context = self.ger_renderer_context(version);
+
+            let mut tt = TinyTemplate::new();
+
+            // the URL of release asset is resolved on install
+            let download_url = match resource_target {
+                ResourceTarget::Detailed(detail) => detail.url.clone(),
+                ResourceTarget::Executable(exe) => exe.executable.clone(),
+                ResourceTarget::Asset(_) => "".to_string(),
+                ResourceTarget::Simple(url) => url.to_string(),
+            };
+
+            tt.add_template("url_template", &download_url)?;
+
+            let renderer_url = tt.render("url_template", &render_context)?;
+
+            debug!(
+                "render the URL of {} {} on {}/{}: '{}' -> '{}'",
+                self.package.name,
+                version,
+                env::consts::OS,
+                env::consts::ARCH,
+                download_url,
+                renderer_url
+            );
+
+            let asset_pattern = match resource_target {
+                ResourceTarget::Asset(asset) => {
+                    tt.add_template("asset_pattern_template", &asset.asset_pattern)?;
+
+                    let pattern = tt.render("asset_pattern_template", &render_context)?;
+
+                    debug!("render the asset pattern: '{}'", pattern);
+
+                    Some(pattern)
+                }
+                _ => None,
+            };
+
+            let mut path = match resource_target {
+                ResourceTarget::Detailed(arch) => arch.path.clone(),
+                ResourceTarget::Executable(_) => None,
+                ResourceTarget::Asset(asset) => asset.path.clone(),
+                ResourceTarget::Simple(_) => None,
+            }
+            .unwrap_or_else(|| "/".to_string());
+
+            if path.trim().is_empty() {
+                path = "/".to_string();
+            }
+
+            tt.add_template("path_template", &path)?;
+
+            path = tt.render("path_template", &render_context)?;
+
+            let is_executable = match resource_target {
+                ResourceTarget::Executable(_) => true,
+                ResourceTarget::Asset(asset) => asset.executable.unwrap_or(false),
+                _ => false,
+            };
+
+            // the extension of release asset is updated once the asset is resolved
+            let ext_name = match resource_target {
+                _ if is_executable => get_executable_ext(),
+                ResourceTarget::Detailed(arch) => match &arch.extension {
+                    Some(ext) => ext.as_str().to_string(),
+                    None => get_ext_name_from_url(&renderer_url)?.to_string(),
+                },
+                ResourceTarget::Asset(asset) => asset
+                    .extension
+                    .as_ref()
+                    .map(|ext| ext.as_str().to_string())
+                    .unwrap_or_default(),
+                _ => get_ext_name_from_url(&renderer_url)?.to_string(),
+            };
+
+            let checksum = match resource_target {
+                ResourceTarget::Detailed(arch) => arch.checksum.clone(),
+                ResourceTarget::Executable(arch) => arch.checksum.clone(),
+                ResourceTarget::Asset(asset) => asset.checksum.clone(),
+                ResourceTarget::Simple(_) => None,
+            };
+
+            let checksum_url = match resource_target {
+                ResourceTarget::Detailed(arch) => arch.checksum_url.as_ref(),
+                ResourceTarget::Executable(arch) => arch.checksum_url.as_ref(),
+                ResourceTarget::Asset(asset) => asset.checksum_url.as_ref(),
+                ResourceTarget::Simple(_) => None,
+            };
+
+            let checksum_url = match checksum_url {
+                Some(url) => {
+                    let mut checksum_tt = TinyTemplate::new();
+
+                    checksum_tt.add_template("checksum_url_template", url)?;
+
+                    Some(checksum_tt.render("checksum_url_template", &render_context)?)
+                }
+                None => None,
+            };
+
+            let signature_url = match resource_target {
+                ResourceTarget::Detailed(arch) => arch.signature_url.as_ref(),
+                ResourceTarget::Executable(arch) => arch.signature_url.as_ref(),
+                ResourceTarget::Asset(asset) => asset.signature_url.as_ref(),
+                ResourceTarget::Simple(_) => None,
+            };
+
+            let signature_url = match signature_url {
+                Some(url) => {
+                    let mut signature_tt = TinyTemplate::new();
+
+                    signature_tt.add_template("signature_url_template", url)?;
+
+                    Some(signature_tt.render("signature_url_template", &render_context)?)
+                }
+                None => None,
+            };
+
+            let inner_path = match resource_target {
+                ResourceTarget::Detailed(arch) => arch.inner_path.as_ref(),
+                ResourceTarget::Asset(asset) => asset.inner_path.as_ref(),
+                _ => None,
+            };
+
+            let inner_path = match inner_path {
+                Some(inner_path) => {
+                    let mut inner_path_tt = TinyTemplate::new();
+
+                    inner_path_tt.add_template("inner_path_template", inner_path)?;
+
+                    Some(
+                        inner_path_tt
+                            .render("inner_path_template", &render_context)?
+                            .trim()
+                            .to_string(),
+                    )
+                }
+                None => None,
+            };
+
+            Ok(DownloadTarget {
+                url: renderer_url,
+                path: path.trim().to_string(),
+                checksum,
+                ext: ext_name,
+                executable: is_executable,
+                asset_pattern,
+                checksum_url,
+                signature_url,
+                inner_path,
+            })
+        } else {
+            Err(CaskError::UnsupportedPlatform(format!(
+                "the package '{}' not support your system",
+                self.package.name
+            ))
+            .into())
+        }
+    }
+
+    // get all remote versions
+    // the pre-release versions are excluded unless `include_prerelease` is true or the formula allows it
+    pub async fn get_versions(
+        &self,
+        cask: &cask::Cask,
+        include_prerelease: bool,
+    ) -> Result<Vec<String>, Report> {
+        let include_prerelease =
+            include_prerelease || self.package.allow_prerelease.unwrap_or(false);
+
+        if let Some(versions) = &self.package.versions {
+            Ok(sort_versions(versions, include_prerelease))
+        } else {
+            let tags = self.get_tags(cask).await?;
+
+            self.filter_versions_from_tags(&tags, include_prerelease)
+        }
+    }
+
+    // get the tags of repository, the cached tags are used in offline mode or within the cache ttl
+    async fn get_tags(&self, cask: &cask::Cask) -> Result<Vec<String>, Report> {
+        if cask.settings().offline {
+            return cache::load_tags(cask, &self.package.repository).ok_or_else(|| {
+                eyre::format_err!(
+                    "The versions of '{}' are not in cache, they can not be fetched in offline mode",
+                    self.package.name
+                )
+            });
+        }
+
+        let config = cask.config();
+
+        // the tags are fetched recently. eg. back-to-back `check-updates`
+        if let Some(tags) =
+            cache::load_fresh_tags(cask, &self.package.repository, cask.settings().cache_ttl)
+        {
+            return Ok(tags);
+        }
+
+        let repo = cask
+            .settings()
+            .get_git_repository(config, &self.package.repository)?;
+
+        let tags = retry_blocking(
+            &cask.settings().get_retry_policy(),
+            GitError::is_transient,
+            move || repo.tags(),
+        )
+        .await
+        .map_err(format_git_error)?
+        .into_iter()
+        .map(|t| t.tag)
+        .collect::<Vec<String>>();
+
+        cache::store_tags(cask, &self.package.repository, &tags)?;
+
+        Ok(tags)
+    }
+
+    // get the candidate tags of the version, the tag may have a 'v' prefix
+    pub async fn get_version_tags(
+        &self,
+        cask: &cask::Cask,
+        version: &str,
+    ) -> Result<Vec<String>, Report> {
+        if self.package.version_pattern.is_none() && self.package.version_extract.is_none() {
+            return Ok(vec![format!("v{}", version), version.to_string()]);
+        }
+
+        // the tag can not be guessed from the version, find it in the repository tags
+        let mut tags = vec![];
+
+        for tag in self.get_tags(cask).await? {
+            let versions = self.filter_versions_from_tags(std::slice::from_ref(&tag), true)?;
+
+            if versions
+                .iter()
+                .any(|v| v == version.trim_start_matches('v'))
+            {
+                tags.push(tag);
+            }
+        }
+
+        Ok(tags)
+    }
+
+    // filter and normalize tags into the semver versions, the latest version at the head
+    fn filter_versions_from_tags(
+        &self,
+        tags: &[String],
+        include_prerelease: bool,
+    ) -> Result<Vec<String>, Report> {
+        let compile = |field: &str, pattern: &Option<String>| -> Result<Option<Regex>, Report> {
+            pattern
+                .as_ref()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| eyre::format_err!("invalid {} '{}': {}", field, p, e))
+                })
+                .transpose()
+        };
+
+        let pattern = compile("version_pattern", &self.package.version_pattern)?;
+        let extract = compile("version_extract", &self.package.version_extract)?;
+
+        let mut versions: Vec<Version> = vec![];
+
+        for tag in tags {
+            if let Some(re) = &pattern {
+                if !re.is_match(tag) {
+                    continue;
+                }
+            }
+
+            let version_str = match &extract {
+                Some(re) => match re.captures(tag) {
+                    Some(caps) => caps.get(1).or_else(|| caps.get(0)).map(|m| m.as_str()),
+                    None => None,
+                },
+                None => Some(tag.as_str()),
+            };
+
+            if let Some(Ok(v)) = version_str.map(|s| Version::parse(s.trim_start_matches('v'))) {
+                if include_prerelease || v.pre.is_empty() {
+                    versions.push(v);
+                }
+            }
+        }
+
+        versions.sort_by(|a, b| b.cmp(a));
+        versions.dedup();
+
+        Ok(versions.into_iter().map(|v| v.to_string()).collect())
+    }
+
+    // get the versions of the release channel
+    pub async fn get_channel_versions(
+        &self,
+        cask: &cask::Cask,
+        name: &str,
+    ) -> Result<Vec<String>, Report> {
+        let channel = self.get_channel(name)?;
+
+        if let Some(version) = &channel.version {
+            return Ok(vec![version.clone()]);
+        }
+
+        let pattern = channel.pattern.as_ref().ok_or_else(|| {
+            eyre::format_err!(
+                "the channel '{}' must provide either 'pattern' or 'version'",
+                name
+            )
+        })?;
+
+        let re = Regex::new(pattern)
+            .map_err(|e| eyre::format_err!("invalid pattern of channel '{}': {}", name, e))?;
+
+        Ok(self
+            .get_versions(cask, true)
+            .await?
+            .into_iter()
+            .filter(|v| re.is_match(v))
+            .collect())
+    }
+
+    // get the latest version of package
+    pub async fn get_latest_version(
+        &self,
+        cask: &cask::Cask,
+        include_prerelease: bool,
+    ) -> Result<Option<String>, Report> {
+        let version = self.get_versions(cask, include_prerelease).await?;
+
+        Ok(version.first().map(|f| f.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::cask;
+    use crate::formula;
+
+    #[test]
+    fn test_read_default_config() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("default_Cask.toml");
+
+        let rc = formula::new(&config_path, "https://github.com/example/example.git").unwrap();
+
+        assert_eq!(rc.repository, "https://github.com/example/example.git");
+        assert_eq!(
+            format!("{}", rc.filepath.display()),
+            format!("{}", config_path.display())
+        );
+        assert_eq!(rc.package.name, "github.com/axetroy/gpm.rs");
+        assert_eq!(rc.package.bin, "gpm");
+        assert_eq!(
+            rc.package.versions.as_ref().unwrap(),
+            &vec!["0.1.12", "0.1.11"]
+        );
+        assert_eq!(
+            rc.package.authors.as_ref().unwrap(),
+            &vec!["Axetroy <axetroy.dev@gmail.com>"]
+        );
+        assert_eq!(
+            rc.package.keywords.as_ref().unwrap(),
+            &vec!["gpm", "git", "project", "manager"]
+        );
+        assert_eq!(rc.package.repository, "https://github.com/axetroy/gpm.rs");
+        assert_eq!(
+            rc.package.description,
+            "A command line tool, manage your hundreds of repository, written with Rust.\n"
+        );
+
+        let windows = &rc.windows.as_ref().unwrap();
+        let darwin = &rc.darwin.as_ref().unwrap();
+        let linux = &rc.linux.as_ref().unwrap();
+
+        // windows
+        match windows.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(arch) => {
+                assert_eq!(
+                    arch.url,
+                    "{package.repository}/releases/download/v{version}/gpm_windows_amd64.tar.gz"
+                );
+            }
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(_) => todo!(),
+        }
+
+        // darwin
+        match darwin.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(arch) => {
+                assert_eq!(
+                    arch.url,
+                    "{package.repository}/releases/download/v{version}/gpm_darwin_amd64.tar.gz"
+                );
+            }
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(_) => todo!(),
+        }
+        match darwin.aarch64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(arch) => {
+                assert_eq!(
+                    arch.url,
+                    "{package.repository}/releases/download/v{version}/gpm_darwin_arm64.tar.gz"
+                );
+            }
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(_) => todo!(),
+        }
+
+        #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
+        assert_eq!(
+            &rc.get_current_download_url("0.1.12", None)
+                .as_ref()
+                .unwrap()
+                .url,
+            "https://github.com/axetroy/gpm.rs/releases/download/v0.1.12/gpm_darwin_amd64.tar.gz"
+        );
+
+        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+        assert_eq!(
+            &rc.get_current_download_url("0.1.12", None)
+                .as_ref()
+                .unwrap()
+                .url,
+            "https://github.com/axetroy/gpm.rs/releases/download/v0.1.12/gpm_darwin_arm64.tar.gz"
+        );
+
+        // linux
+        match linux.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(arch) => {
+                assert_eq!(
+                    arch.url,
+                    "{package.repository}/releases/download/v{version}/gpm_linux_amd64.tar.gz"
+                );
+            }
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(_) => todo!(),
+        }
+        match linux.aarch64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(arch) => {
+                assert_eq!(
+                    arch.url,
+                    "{package.repository}/releases/download/v{version}/gpm_linux_arm64.tar.gz"
+                );
+            }
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(_) => todo!(),
+        }
+    }
+
+    #[test]
+    fn test_read_simple_config() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("simple_Cask.toml");
+
+        let rc = formula::new(&config_path, "https://github.com/example/example.git").unwrap();
+
+        assert_eq!(rc.repository, "https://github.com/example/example.git");
+        assert_eq!(
+            format!("{}", rc.filepath.display()),
+            format!("{}", config_path.display())
+        );
+        assert_eq!(rc.package.name, "github.com/axetroy/gpm.rs");
+        assert_eq!(rc.package.bin, "gpm");
+        assert_eq!(rc.package.versions.unwrap(), vec!["0.1.12", "0.1.11"]);
+        assert_eq!(
+            rc.package.authors.unwrap(),
+            vec!["Axetroy <axetroy.dev@gmail.com>"]
+        );
+        assert_eq!(
+            rc.package.keywords.unwrap(),
+            vec!["gpm", "git", "project", "manager"]
+        );
+        assert_eq!(rc.package.repository, "https://github.com/axetroy/gpm.rs");
+        assert_eq!(
+            rc.package.description,
+            "A command line tool, manage your hundreds of repository, written with Rust.\n"
+        );
+
+        let windows = &rc.windows.unwrap();
+        let darwin = &rc.darwin.unwrap();
+        let linux = &rc.linux.unwrap();
+
+        // windows
+        match windows.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(_) => todo!(),
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(url) => {
+                assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_windows_amd64.tar.gz"
+                )
+            }
+        }
+
+        // darwin
+        match darwin.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(_) => todo!(),
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(url) => {
+                assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_darwin_amd64.tar.gz"
+                )
+            }
+        }
+        match darwin.aarch64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(_) => todo!(),
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(url) => {
+                assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_darwin_arm64.tar.gz"
+                )
+            }
+        }
+
+        // linux
+        match linux.x86_64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(_) => todo!(),
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(url) => {
+                assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_linux_amd64.tar.gz"
+                )
+            }
+        }
+        match linux.aarch64.as_ref().unwrap() {
+            formula::ResourceTarget::Detailed(_) => todo!(),
+            formula::ResourceTarget::Executable(_) => todo!(),
+            formula::ResourceTarget::Asset(_) => todo!(),
+            formula::ResourceTarget::Simple(url) => {
+                assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_linux_arm64.tar.gz"
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_deprecated_config() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("deprecated_Cask.toml");
+
+        let rc = formula::new(&config_path, "").unwrap();
+
+        assert_eq!(rc.package.deprecated, Some(true));
+        assert_eq!(
+            rc.package.replaced_by.as_deref(),
+            Some("github.com/axetroy/gpm")
+        );
+        assert_eq!(
+            rc.get_deprecation_message().unwrap(),
+            "The package 'github.com/axetroy/gpm.rs' has been deprecated, use 'github.com/axetroy/gpm' instead"
+        );
+
+        let simple_config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("simple_Cask.toml");
+
+        let simple = formula::new(&simple_config_path, "").unwrap();
+
+        assert!(simple.get_deprecation_message().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_versions_with_prerelease() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("prerelease_Cask.toml");
+
+        let mut rc = formula::new(&config_path, "").unwrap();
+
+        assert_eq!(
+            rc.get_versions(&c, false).await.unwrap(),
+            vec!["0.1.12", "0.1.11"]
+        );
+        assert_eq!(
+            rc.get_versions(&c, true).await.unwrap(),
+            vec!["0.2.0-rc.1", "0.1.12", "0.1.11"]
+        );
+        assert_eq!(
+            rc.get_latest_version(&c, false).await.unwrap(),
+            Some("0.1.12".to_string())
+        );
+
+        rc.package.allow_prerelease = Some(true);
+
+        assert_eq!(
+            rc.get_latest_version(&c, false).await.unwrap(),
+            Some("0.2.0-rc.1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_channel_versions() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("channel_Cask.toml");
+
+        let rc = formula::new(&config_path, "").unwrap();
+
+        assert_eq!(
+            rc.get_channel_versions(&c, "beta").await.unwrap(),
+            vec!["0.2.0-beta.2"]
+        );
+        assert_eq!(
+            rc.get_channel_versions(&c, "nightly").await.unwrap(),
+            vec!["nightly"]
+        );
+        assert!(rc.get_channel_versions(&c, "not_exist").await.is_err());
+
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        {
+            assert_eq!(
+                rc.get_current_download_url("nightly", Some("nightly"))
+                    .unwrap()
+                    .url,
+                "https://github.com/axetroy/gpm.rs/releases/download/nightly/gpm_linux_amd64.tar.gz"
+            );
+            assert_eq!(
+                rc.get_current_download_url("0.2.0-beta.2", Some("beta"))
+                    .unwrap()
+                    .url,
+                "https://github.com/axetroy/gpm.rs/releases/download/v0.2.0-beta.2/gpm_linux_amd64.tar.gz"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sort_versions() {
+        let versions: Vec<String> = vec![
+            "0.1.9",
+            "v0.1.10",
+            "nightly",
+            "0.1.10",
+            "0.2.0-rc.1",
+            "0.2.0-rc.10",
+            "0.2.0-rc.2",
+            "1.0.0",
+        ]
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect();
+
+        assert_eq!(
+            formula::sort_versions(&versions, false),
+            vec!["1.0.0", "0.1.10", "0.1.9", "nightly"]
+        );
+        assert_eq!(
+            formula::sort_versions(&versions, true),
+            vec![
+                "1.0.0",
+                "0.2.0-rc.10",
+                "0.2.0-rc.2",
+                "0.2.0-rc.1",
+                "0.1.10",
+                "0.1.9",
+                "nightly"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_version() {
+        let versions: Vec<String> =
+            vec!["2.0.0-rc.1", "1.10.0", "1.2.3", "1.2.0", "0.9.0", "nightly"]
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect();
+
+        let resolve = |spec: &str| formula::resolve_version(&versions, spec).unwrap();
+
+        assert_eq!(resolve("nightly"), Some("nightly".to_string()));
+        assert_eq!(resolve("v1.2.0"), Some("1.2.0".to_string()));
+        assert_eq!(resolve("1.3.0"), None);
+        assert_eq!(resolve("^1.2"), Some("1.10.0".to_string()));
+        assert_eq!(resolve("~1.2.0"), Some("1.2.3".to_string()));
+        assert_eq!(resolve(">=0.9, <1.2"), Some("0.9.0".to_string()));
+        assert_eq!(resolve("1.x"), Some("1.10.0".to_string()));
+        assert_eq!(resolve("1.2"), Some("1.2.3".to_string()));
+        assert_eq!(resolve("*"), Some("1.10.0".to_string()));
+        assert_eq!(resolve("^2.0.0-rc"), Some("2.0.0-rc.1".to_string()));
+        assert_eq!(resolve("^3"), None);
+        assert!(formula::resolve_version(&versions, "^foo").is_err());
+
+        assert!(formula::is_version_requirement("^1.2"));
+        assert!(formula::is_version_requirement("1.x"));
+        assert!(!formula::is_version_requirement("v1.2.0"));
+        assert!(formula::is_prerelease_spec("2.0.0-beta.1"));
+        assert!(formula::is_prerelease_spec(">=2.0.0-beta"));
+        assert!(!formula::is_prerelease_spec("^1.2"));
+    }
+
+    #[test]
+    fn test_filter_versions_from_tags() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("simple_Cask.toml");
+
+        let mut rc = formula::new(&config_path, "").unwrap();
+
+        rc.package.version_pattern = Some("^tool-".to_string());
+        rc.package.version_extract = Some(r"^tool-v?(.+)$".to_string());
+
+        let tags: Vec<String> = vec![
+            "tool-v1.2.3",
+            "tool-1.10.0",
+            "tool-1.9.0",
+            "tool-v1.10.0",
+            "other-v9.9.9",
+            "tool-v2.0.0-rc.1",
+            "v1.2.3",
+            "tool-nightly",
+        ]
+        .into_iter()
+        .map(|t| t.to_string())
+        .collect();
+
+        assert_eq!(
+            rc.filter_versions_from_tags(&tags, false).unwrap(),
+            vec!["1.10.0", "1.9.0", "1.2.3"]
+        );
+        assert_eq!(
+            rc.filter_versions_from_tags(&tags, true).unwrap(),
+            vec!["2.0.0-rc.1", "1.10.0", "1.9.0", "1.2.3"]
+        );
+
+        rc.package.version_extract = Some("(".to_string());
+
+        assert!(rc.filter_versions_from_tags(&tags, false).is_err());
+    }
+
+    #[test]
+    fn test_read_json_and_yaml_config() {
+        for filename in ["simple_Cask.json", "simple_Cask.yaml"] {
+            let config_path = env::current_dir()
+                .unwrap()
+                .join("fixtures")
+                .join("config")
+                .join(filename);
+
+            let rc = formula::new(&config_path, "").unwrap();
+
+            assert_eq!(rc.package.name, "github.com/axetroy/gpm.rs");
+            assert_eq!(rc.package.bin, "gpm");
+            assert_eq!(
+                rc.package.versions.as_ref().unwrap(),
+                &vec!["0.1.12", "0.1.11"]
+            );
+            assert_eq!(
+                rc.package.description,
+                "A command line tool, manage your hundreds of repository, written with Rust.\n"
+            );
+
+            match rc.linux.as_ref().unwrap().x86_64.as_ref().unwrap() {
+                formula::ResourceTarget::Simple(url) => assert_eq!(
+                    url,
+                    "https://github.com/axetroy/gpm.rs/releases/download/v{version}/gpm_linux_amd64.tar.gz"
+                ),
+                _ => unreachable!(),
+            }
+
+            // the receipt of installed package is always written in TOML
+            let toml_content = rc.get_toml_content().unwrap();
+            let converted: formula::Formula = toml::from_str(&toml_content).unwrap();
+
+            assert_eq!(converted.package.name, rc.package.name);
+        }
+    }
+
+    #[test]
+    fn test_read_config_with_unknown_field() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("typo_Cask.toml");
+
+        let err = formula::new(&config_path, "").unwrap_err().to_string();
+
+        assert!(err.contains("line 10"));
+        assert!(err.contains("unknown field `darw`"));
+        assert!(err.contains("Did you mean `darwin`?"));
+    }
+
+    #[test]
+    fn test_render_conditional_url() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("simple_Cask.toml");
+
+        let mut rc = formula::new(&config_path, "").unwrap();
+
+        let url = "https://example.com/v{version}/gpm{{ if major_version }}_v{major_version}{{ else }}_legacy{{ endif }}_{os}_{arch}{{ if is_windows }}.zip{{ else }}.tar.gz{{ endif }}";
+
+        let platform = formula::Platform {
+            x86: None,
+            x86_64: Some(formula::ResourceTarget::Simple(url.to_string())),
+            arm: None,
+            armv7: None,
+            aarch64: Some(formula::ResourceTarget::Simple(url.to_string())),
+            mips: None,
+            mips64: None,
+            mips64el: None,
+            riscv64: None,
+        };
+
+        if cfg!(target_os = "linux") {
+            rc.linux = Some(platform);
+        } else if cfg!(target_os = "macos") {
+            rc.darwin = Some(platform);
+        } else if cfg!(target_os = "windows") {
+            rc.windows = Some(platform);
+        } else {
+            return;
+        }
+
+        if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+            return;
+        }
+
+        let ext = if cfg!(target_os = "windows") {
+            ".zip"
+        } else {
+            ".tar.gz"
+        };
+
+        assert_eq!(
+            rc.get_current_download_url("1.2.3", None).unwrap().url,
+            format!(
+                "https://example.com/v1.2.3/gpm_v1_{}_{}{}",
+                env::consts::OS,
+                env::consts::ARCH,
+                ext
+            )
+        );
+        assert_eq!(
+            rc.get_current_download_url("0.2.3", None).unwrap().url,
+            format!(
+                "https://example.com/v0.2.3/gpm_legacy_{}_{}{}",
+                env::consts::OS,
+                env::consts::ARCH,
+                ext
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_release_download_url() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("simple_Cask.toml");
+
+        let mut rc = formula::new(&config_path, "").unwrap();
+
+        assert_eq!(
+            rc.ger_renderer_context("1.2.3").release_download_url,
+            Some("https://github.com/axetroy/gpm.rs/releases/download/v1.2.3".to_string())
+        );
+
+        rc.package.repository = "https://gitlab.com/group/sub/gpm".to_string();
+
+        assert_eq!(
+            rc.ger_renderer_context("v1.2.3").release_download_url,
+            Some("https://gitlab.com/group/sub/gpm/-/releases/v1.2.3/downloads".to_string())
+        );
+
+        rc.package.repository = "https://example.com/gpm".to_string();
+
+        assert_eq!(rc.ger_renderer_context("1.2.3").release_download_url, None);
+    }
+
+    #[test]
+    fn test_get_ext_name_from_url() {
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64.tar.xz")
+                .unwrap(),
+            ".tar.xz"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64.tbz2")
+                .unwrap(),
+            ".tbz2"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64.tar.zst")
+                .unwrap(),
+            ".tar.zst"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64.zst")
+                .unwrap(),
+            ".zst"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool-linux-amd64.gz")
+                .unwrap(),
+            ".gz"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64.zip")
+                .unwrap(),
+            ".zip"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_windows_amd64.7z")
+                .unwrap(),
+            ".7z"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_1.0.0_amd64.deb")
+                .unwrap(),
+            ".deb"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool-1.0.0-1.x86_64.rpm")
+                .unwrap(),
+            ".rpm"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool-darwin.dmg").unwrap(),
+            ".dmg"
+        );
+        assert_eq!(
+            formula::get_ext_name_from_url("https://example.com/v1.0.0/tool_linux_amd64").unwrap(),
+            ".tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_resource_files() {
+        let content = r#"
+[package]
+name = "github.com/foo/tool"
+bin = "tool"
+repository = "https://github.com/foo/tool"
+description = "test"
+versions = ["1.2.3"]
+
+[[resource.files]]
+path = "/tool-{version}/share/data.json"
+dest = "share/tool/"
+
+[[resource.files]]
+path = "/tool-{version}/lib/libtool.so"
+dest = "lib/libtool.so.{version}"
+"#;
+
+        let rc: formula::Formula = toml::from_str(content).unwrap();
+
+        assert_eq!(
+            rc.get_resource_files("1.2.3").unwrap(),
+            vec![
+                formula::ResourceFile {
+                    path: "/tool-1.2.3/share/data.json".to_string(),
+                    dest: "share/tool/data.json".to_string(),
+                },
+                formula::ResourceFile {
+                    path: "/tool-1.2.3/lib/libtool.so".to_string(),
+                    dest: "lib/libtool.so.1.2.3".to_string(),
+                },
+            ]
+        );
+
+        for dest in ["../share/", "/usr/share/", ""] {
+            let content = format!(
+                r#"
+[package]
+name = "github.com/foo/tool"
+bin = "tool"
+repository = "https://github.com/foo/tool"
+description = "test"
+
+[[resource.files]]
+path = "/share/data.json"
+dest = "{}"
+"#,
+                dest
+            );
+
+            let rc: formula::Formula = toml::from_str(&content).unwrap();
+
+            assert!(rc.get_resource_files("1.2.3").is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_asset_pattern_target() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        let content = r#"
+[package]
+name = "github.com/foo/tool"
+bin = "tool"
+repository = "https://github.com/foo/tool"
+description = "test"
+versions = ["1.2.3"]
+
+[linux]
+x86_64 = { asset_pattern = "tool-{version}-linux-(amd64|x86_64)\\.zip$", inner_path = "/dist/tool-{version}.tar.gz", path = "/bin", checksum_url = "{package.repository}/releases/download/v{version}/SHA256SUMS", signature_url = "{package.repository}/releases/download/v{version}/tool-{version}-linux-amd64.tar.gz.minisig" }
+aarch64 = { asset_pattern = "tool-linux-arm64$", executable = true }
+"#;
+
+        let rc: formula::Formula = toml::from_str(content).unwrap();
+
+        if !cfg!(target_os = "linux") || !cfg!(target_arch = "x86_64") {
+            return;
+        }
+
+        let target = rc.get_current_download_url("1.2.3", None).unwrap();
+
+        assert_eq!(
+            target.asset_pattern,
+            Some(r"tool-1.2.3-linux-(amd64|x86_64)\.zip$".to_string())
+        );
+        assert_eq!(target.path, "/bin");
+        assert_eq!(
+            target.inner_path,
+            Some("/dist/tool-1.2.3.tar.gz".to_string())
+        );
+        assert_eq!(
+            target.checksum_url,
+            Some("https://github.com/foo/tool/releases/download/v1.2.3/SHA256SUMS".to_string())
+        );
+        assert_eq!(
+            target.signature_url,
+            Some(
+                "https://github.com/foo/tool/releases/download/v1.2.3/tool-1.2.3-linux-amd64.tar.gz.minisig"
+                    .to_string()
+            )
+        );
+        assert_eq!(target.ext, "");
+        assert!(!target.executable);
+
+        assert_eq!(
+            rc.get_version_tags(&c, "1.2.3").await.unwrap(),
+            vec!["v1.2.3".to_string(), "1.2.3".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_git_url() {
+        let root_dir = env::current_dir().unwrap().join("fixtures").join(".cask");
+        let c = cask::new(&root_dir);
+
+        let formula = formula::fetch(&c, "https://github.com/axetroy/prune.v", None, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(formula.package.name, "github.com/axetroy/prune.v")
+    }
+
+    #[test]
+    fn test_get_formula_raw_urls() {
+        assert_eq!(
+            formula::get_formula_raw_urls("https://github.com/axetroy/prune.v.git", None)[0],
+            (
+                "Cask.toml",
+                "https://raw.githubusercontent.com/axetroy/prune.v/HEAD/Cask.toml".to_string()
+            )
+        );
+        assert_eq!(
+            formula::get_formula_raw_urls("git@gitlab.com:group/tool.git", Some("v1.0.0"))[2],
+            (
+                "Cask.json",
+                "https://gitlab.com/group/tool/-/raw/v1.0.0/Cask.json".to_string()
+            )
+        );
+        assert!(
+            formula::get_formula_raw_urls("https://git.example.com/foo/bar.git", None).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_get_hook() {
+        let config_path = env::current_dir()
+            .unwrap()
+            .join("fixtures")
+            .join("config")
+            .join("hook_Cask.toml");
+
+        let rc = formula::new(&config_path, "https://github.com/example/example.git").unwrap();
+
+        let terminal_hook = rc.hook.as_ref().unwrap().resolve().unwrap();
+
+        if cfg!(target_family = "unix") {
+            assert_eq!(terminal_hook.terminal, shell::Terminal::Sh);
+            assert_eq!(
+                terminal_hook.hook.preinstall.as_ref().unwrap().script(),
+                r#"echo 'hello sh'"#
+            );
+        } else {
+            assert_eq!(terminal_hook.terminal, shell::Terminal::Cmd);
+            assert_eq!(
+                terminal_hook.hook.preinstall.as_ref().unwrap().script(),
+                r#"echo 'hello cmd'"#
+            );
+        }
+    }
+}
@@ -0,0 +1,759 @@
+#![deny(warnings)]
+
+use crate::{error::CaskError, i18n::t, util};
+
+use std::{collections::HashMap, path::Path, sync::OnceLock, time::Duration};
+
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use tinytemplate::TinyTemplate;
+use tracing::{debug, info, warn};
+
+// the hook is killed if it does not exit in time
+const DEFAULT_TIMEOUT: u64 = 600;
+
+// ask whether to run the script of hook with its name and script, eg. prompt in the terminal
+pub type Confirm = dyn Fn(&str, &str) -> Result<bool, Report> + Send + Sync;
+
+// the confirmation is set by the program that asks the user, the hooks are not confirmed without it
+static CONFIRM: OnceLock<Box<Confirm>> = OnceLock::new();
+
+pub fn set_confirm(confirm: Box<Confirm>) {
+    CONFIRM.set(confirm).ok();
+}
+
+// whether the hooks of formula run, they are shell scripts from the third-party
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    Run,     // run the hooks without asking
+    Confirm, // print the script and ask before running it, it is skipped in the non-interactive terminal
+    Skip,    // never run the hooks
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptDefinition {
+    pub script: String,         // The script to run
+    pub timeout: Option<u64>,   // The timeout in seconds, defaults to 600
+    pub required: Option<bool>, // Whether the install fails if the hook fails, defaults to true
+}
+
+// the hook is a script, or a table with the options of script
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Script {
+    Inline(String),
+    Detailed(ScriptDefinition),
+}
+
+impl Script {
+    pub fn script(&self) -> &str {
+        match self {
+            Script::Inline(script) => script,
+            Script::Detailed(d) => &d.script,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        let seconds = match self {
+            Script::Inline(_) => None,
+            Script::Detailed(d) => d.timeout,
+        };
+
+        Duration::from_secs(seconds.unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    pub fn is_required(&self) -> bool {
+        match self {
+            Script::Inline(_) => true,
+            Script::Detailed(d) => d.required.unwrap_or(true),
+        }
+    }
+}
+
+impl From<String> for Script {
+    fn from(script: String) -> Self {
+        Script::Inline(script)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HookDefinition {
+    pub preinstall: Option<Script>, // The script will run before install package
+    pub postinstall: Option<Script>, // The script will run after install package
+    pub preuninstall: Option<Script>, // The script will run before uninstall package
+    pub postuninstall: Option<Script>, // The script will run after uninstall package
+    pub postupgrade: Option<Script>, // The script will run after package is updated from another version
+}
+
+impl HookDefinition {
+    // the hooks that are not defined are taken from another definition
+    fn merge(self, other: HookDefinition) -> HookDefinition {
+        HookDefinition {
+            preinstall: self.preinstall.or(other.preinstall),
+            postinstall: self.postinstall.or(other.postinstall),
+            preuninstall: self.preuninstall.or(other.preuninstall),
+            postuninstall: self.postuninstall.or(other.postuninstall),
+            postupgrade: self.postupgrade.or(other.postupgrade),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Terminal {
+    pub cmd: Option<HookDefinition>,
+    pub powershell: Option<HookDefinition>,
+    pub pwsh: Option<HookDefinition>,
+    pub sh: Option<HookDefinition>,
+    pub bash: Option<HookDefinition>,
+}
+
+impl Terminal {
+    // the hooks in the order of preference, PowerShell is preferred to cmd.exe on Windows
+    fn candidates(&self) -> Vec<(shell::Terminal, &Option<HookDefinition>)> {
+        if cfg!(target_os = "windows") {
+            vec![
+                (shell::Terminal::Pwsh, &self.pwsh),
+                (shell::Terminal::PowerShell, &self.powershell),
+                (shell::Terminal::Cmd, &self.cmd),
+            ]
+        } else {
+            vec![
+                (shell::Terminal::Sh, &self.sh),
+                (shell::Terminal::Bash, &self.bash),
+                (shell::Terminal::Pwsh, &self.pwsh),
+            ]
+        }
+    }
+
+    // the hooks of the first installed terminal, the terminal that is not installed is skipped. eg. pwsh
+    fn resolve(&self) -> Option<TerminalHook> {
+        let candidates = self.candidates();
+
+        let defined = candidates
+            .iter()
+            .filter_map(|(terminal, hook)| hook.as_ref().map(|hook| (*terminal, hook)));
+
+        defined
+            .clone()
+            .find(|(terminal, _)| terminal.is_available())
+            .or_else(|| defined.clone().next())
+            .map(|(terminal, hook)| TerminalHook {
+                terminal,
+                hook: hook.clone(),
+            })
+    }
+}
+
+pub struct TerminalHook {
+    pub terminal: shell::Terminal,
+    pub hook: HookDefinition,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub windows: Option<Terminal>,
+    pub unix: Option<Terminal>,
+    pub linux: Option<Terminal>,
+    #[serde(alias = "darwin")]
+    pub macos: Option<Terminal>,
+    pub freebsd: Option<Terminal>,
+}
+
+// the environment variables of hook, so that the script knows where the package is installed.
+// the root of Cask is passed, so that the `cask` in the script uses the same root
+pub fn get_envs(
+    home: &Path,
+    package_name: &str,
+    version: &str,
+    package_dir: &Path,
+    bin_dir: &Path,
+    download_file: Option<&Path>,
+) -> HashMap<String, String> {
+    let mut envs = HashMap::from([
+        ("CASK_HOME".to_string(), home.display().to_string()),
+        ("CASK_PACKAGE_NAME".to_string(), package_name.to_string()),
+        ("CASK_PACKAGE_VERSION".to_string(), version.to_string()),
+        (
+            "CASK_PACKAGE_DIR".to_string(),
+            package_dir.display().to_string(),
+        ),
+        ("CASK_BIN_DIR".to_string(), bin_dir.display().to_string()),
+    ]);
+
+    // the resource is not downloaded before install, or it is extracted while downloading
+    if let Some(file) = download_file.filter(|f| f.exists()) {
+        envs.insert("CASK_DOWNLOAD_FILE".to_string(), file.display().to_string());
+    }
+
+    envs
+}
+
+// ask whether to run the script, the default answer is no
+fn confirm(hook_name: &str, script: &str) -> Result<bool, Report> {
+    match CONFIRM.get() {
+        Some(confirm) if util::is_interactive() => confirm(hook_name, script),
+        _ => {
+            warn!(
+                "Can not confirm the '{}' hook in the non-interactive terminal, run with '--yes' to allow it",
+                hook_name
+            );
+
+            Ok(false)
+        }
+    }
+}
+
+impl Hook {
+    // the hooks of current platform, and the hooks of its family that they override. eg. linux and unix
+    fn get_terminals(&self) -> (Option<&Terminal>, Option<&Terminal>) {
+        if cfg!(unix) {
+            let platform = if cfg!(target_os = "linux") {
+                self.linux.as_ref()
+            } else if cfg!(target_os = "macos") {
+                self.macos.as_ref()
+            } else if cfg!(target_os = "freebsd") {
+                self.freebsd.as_ref()
+            } else {
+                None
+            };
+
+            (platform, self.unix.as_ref())
+        } else {
+            (self.windows.as_ref(), None)
+        }
+    }
+
+    // the hooks of current platform override the hooks of its family one by one if they use the same terminal.
+    // eg. '[hook.linux.sh]' only defines the step for Linux, and the other hooks of '[hook.unix.sh]' still run
+    pub fn resolve(&self) -> Option<TerminalHook> {
+        let (platform, family) = self.get_terminals();
+
+        let platform_hook = platform.and_then(|t| t.resolve());
+        let family_hook = family.and_then(|t| t.resolve());
+
+        match (platform_hook, family_hook) {
+            (Some(p), Some(f)) if p.terminal == f.terminal => Some(TerminalHook {
+                terminal: p.terminal,
+                hook: p.hook.merge(f.hook),
+            }),
+            (Some(p), _) => Some(p),
+            (None, f) => f,
+        }
+    }
+
+    // run the hook, its output is printed only in verbose mode or if it fails.
+    // the failure of the hook that is not required is printed as a warning
+    pub fn run<C>(
+        &self,
+        hook_name: &str,
+        cwd: &Path,
+        render_context: C,
+        envs: HashMap<String, String>,
+        policy: Policy,
+        is_verbose: bool,
+    ) -> Result<(), Report>
+    where
+        C: Serialize,
+    {
+        let hook_op = self.resolve();
+
+        if let Some(terminal_hook) = hook_op {
+            let hook = terminal_hook.hook;
+
+            let script_op = match hook_name {
+                "preinstall" => Ok(&hook.preinstall),
+                "postinstall" => Ok(&hook.postinstall),
+                "preuninstall" => Ok(&hook.preuninstall),
+                "postuninstall" => Ok(&hook.postuninstall),
+                "postupgrade" => Ok(&hook.postupgrade),
+                _ => Err(eyre::format_err!(
+                    "trying to run a unknown hook, names {}",
+                    hook_name
+                )),
+            }?;
+
+            if let Some(script) = script_op {
+                let mut tt = TinyTemplate::new();
+
+                tt.add_template(hook_name, script.script())?;
+
+                let renderer_script = tt.render(hook_name, &render_context)?;
+
+                let is_allowed = match policy {
+                    Policy::Run => true,
+                    Policy::Confirm => confirm(hook_name, &renderer_script)?,
+                    Policy::Skip => false,
+                };
+
+                if !is_allowed {
+                    info!("{}", t!("hook-skipped", hook = hook_name));
+
+                    return Ok(());
+                }
+
+                info!("{}", t!("hook-running", hook = hook_name));
+
+                debug!(
+                    "run the script of '{}' hook:\n{}",
+                    hook_name, renderer_script
+                );
+
+                let mut buf: Vec<u8> = vec![];
+
+                let mut output = if is_verbose {
+                    shell::Output::Inherit
+                } else {
+                    shell::Output::Writer(&mut buf)
+                };
+
+                let result = shell::run_with_timeout(
+                    terminal_hook.terminal,
+                    cwd,
+                    &renderer_script,
+                    &mut output,
+                    envs,
+                    Some(script.timeout()),
+                );
+
+                // the output is printed to the terminal in verbose mode
+                if !buf.is_empty() {
+                    debug!(
+                        "the output of '{}' hook:\n{}",
+                        hook_name,
+                        String::from_utf8_lossy(&buf).trim_end()
+                    );
+                }
+
+                if let Err(e) = result {
+                    if !buf.is_empty() {
+                        info!("{}", String::from_utf8_lossy(&buf).trim_end());
+                    }
+
+                    if !script.is_required() {
+                        warn!("run '{}' hook fail: {}", hook_name, e);
+
+                        return Ok(());
+                    }
+
+                    return Err(CaskError::HookFailed(format!(
+                        "run '{}' hook fail: {}",
+                        hook_name, e
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env, path::Path};
+
+    use crate::hooker::{self, HookDefinition, Policy, Script, ScriptDefinition, Terminal};
+
+    fn echo_hook(script: &str) -> HookDefinition {
+        HookDefinition {
+            preinstall: Some(format!(r#"echo "preinstall {}""#, script).into()),
+            postinstall: Some(format!(r#"echo "postinstall {}""#, script).into()),
+            preuninstall: Some(format!(r#"echo "preuninstall {}""#, script).into()),
+            postuninstall: Some(format!(r#"echo "postuninstall {}""#, script).into()),
+            postupgrade: Some(format!(r#"echo "postupgrade {}""#, script).into()),
+        }
+    }
+
+    #[test]
+    fn test_run_hooker() {
+        let hook = hooker::Hook {
+            windows: Some(Terminal {
+                cmd: Some(echo_hook("cmd")),
+                powershell: None,
+                pwsh: None,
+                sh: None,
+                bash: None,
+            }),
+            unix: Some(Terminal {
+                cmd: None,
+                powershell: None,
+                pwsh: None,
+                sh: Some(echo_hook("sh")),
+                bash: None,
+            }),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        for name in [
+            "preinstall",
+            "postinstall",
+            "preuninstall",
+            "postuninstall",
+            "postupgrade",
+        ] {
+            let r = hook.run(
+                name,
+                &env::current_dir().unwrap(),
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Run,
+                false,
+            );
+
+            assert!(r.is_ok());
+        }
+
+        let r = hook.run(
+            "unknown",
+            &env::current_dir().unwrap(),
+            HashMap::<String, String>::from([]),
+            HashMap::new(),
+            Policy::Run,
+            false,
+        );
+
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_run_failed_hooker() {
+        let failed_hook = HookDefinition {
+            preinstall: None,
+            postinstall: Some("exit 3".to_string().into()),
+            preuninstall: None,
+            postuninstall: None,
+            postupgrade: None,
+        };
+
+        let hook = hooker::Hook {
+            windows: Some(Terminal {
+                cmd: Some(failed_hook.clone()),
+                powershell: None,
+                pwsh: None,
+                sh: None,
+                bash: None,
+            }),
+            unix: Some(Terminal {
+                cmd: None,
+                powershell: None,
+                pwsh: None,
+                sh: Some(failed_hook),
+                bash: None,
+            }),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        // the hook is not defined
+        assert!(hook
+            .run(
+                "preinstall",
+                &env::current_dir().unwrap(),
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Run,
+                false,
+            )
+            .is_ok());
+
+        assert!(hook
+            .run(
+                "postinstall",
+                &env::current_dir().unwrap(),
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Run,
+                false,
+            )
+            .is_err());
+
+        // the hook does not run
+        assert!(hook
+            .run(
+                "postinstall",
+                &env::current_dir().unwrap(),
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Skip,
+                false,
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_resolve_terminal() {
+        let terminal = Terminal {
+            cmd: Some(echo_hook("cmd")),
+            powershell: Some(echo_hook("powershell")),
+            pwsh: Some(echo_hook("pwsh")),
+            sh: Some(echo_hook("sh")),
+            bash: None,
+        };
+
+        let hook = hooker::Hook {
+            windows: Some(terminal.clone()),
+            unix: Some(terminal),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        let terminal_hook = hook.resolve().unwrap();
+
+        if cfg!(windows) {
+            let expected = if shell::Terminal::Pwsh.is_available() {
+                shell::Terminal::Pwsh
+            } else {
+                shell::Terminal::PowerShell
+            };
+
+            assert_eq!(terminal_hook.terminal, expected);
+        } else {
+            assert_eq!(terminal_hook.terminal, shell::Terminal::Sh);
+        }
+
+        // the only defined terminal is used even if it is not detected
+        let pwsh_only = Terminal {
+            cmd: None,
+            powershell: None,
+            pwsh: Some(echo_hook("pwsh")),
+            sh: None,
+            bash: None,
+        };
+
+        let hook = hooker::Hook {
+            windows: Some(pwsh_only.clone()),
+            unix: Some(pwsh_only),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        assert_eq!(hook.resolve().unwrap().terminal, shell::Terminal::Pwsh);
+    }
+
+    #[test]
+    fn test_run_hooker_with_envs() {
+        let check = HookDefinition {
+            preinstall: None,
+            postinstall: Some(if cfg!(windows) {
+                r#"if not "%CASK_PACKAGE_NAME%"=="foo" exit 1"#.to_string().into()
+            } else {
+                r#"test "$CASK_PACKAGE_NAME" = "foo" && test "$CASK_BIN_DIR" = "/cask/bin""#
+                    .to_string()
+                    .into()
+            }),
+            preuninstall: None,
+            postuninstall: None,
+            postupgrade: None,
+        };
+
+        let hook = hooker::Hook {
+            windows: Some(Terminal {
+                cmd: Some(check.clone()),
+                powershell: None,
+                pwsh: None,
+                sh: None,
+                bash: None,
+            }),
+            unix: Some(Terminal {
+                cmd: None,
+                powershell: None,
+                pwsh: None,
+                sh: Some(check),
+                bash: None,
+            }),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        let envs = hooker::get_envs(
+            Path::new("/cask"),
+            "foo",
+            "1.0.0",
+            Path::new("/cask/formula/foo/versions/1.0.0"),
+            Path::new("/cask/bin"),
+            Some(Path::new("/not_exist/foo.tar.gz")),
+        );
+
+        assert_eq!(envs.get("CASK_HOME").unwrap(), "/cask");
+        assert_eq!(envs.get("CASK_PACKAGE_VERSION").unwrap(), "1.0.0");
+        assert!(!envs.contains_key("CASK_DOWNLOAD_FILE"));
+
+        let cwd = env::current_dir().unwrap();
+
+        assert!(hook
+            .run(
+                "postinstall",
+                &cwd,
+                HashMap::<String, String>::from([]),
+                envs,
+                Policy::Run,
+                false
+            )
+            .is_ok());
+
+        assert!(hook
+            .run(
+                "postinstall",
+                &cwd,
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Run,
+                false
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_run_optional_hooker() {
+        let script = |required: Option<bool>, timeout: Option<u64>| {
+            Script::Detailed(ScriptDefinition {
+                script: match (timeout, cfg!(windows)) {
+                    (Some(_), true) => "ping -n 30 127.0.0.1 > nul".to_string(),
+                    (Some(_), false) => "sleep 30".to_string(),
+                    (None, _) => "exit 3".to_string(),
+                },
+                timeout,
+                required,
+            })
+        };
+
+        let hook_of = |definition: HookDefinition| hooker::Hook {
+            windows: Some(Terminal {
+                cmd: Some(definition.clone()),
+                powershell: None,
+                pwsh: None,
+                sh: None,
+                bash: None,
+            }),
+            unix: Some(Terminal {
+                cmd: None,
+                powershell: None,
+                pwsh: None,
+                sh: Some(definition),
+                bash: None,
+            }),
+            linux: None,
+            macos: None,
+            freebsd: None,
+        };
+
+        let hook = hook_of(HookDefinition {
+            preinstall: Some(script(Some(false), None)),
+            postinstall: Some(script(None, None)),
+            preuninstall: Some(script(Some(false), Some(1))),
+            postuninstall: Some(script(None, Some(1))),
+            postupgrade: None,
+        });
+
+        let run = |name: &str| {
+            hook.run(
+                name,
+                &env::current_dir().unwrap(),
+                HashMap::<String, String>::from([]),
+                HashMap::new(),
+                Policy::Run,
+                false,
+            )
+        };
+
+        // the failure of optional hook is ignored
+        assert!(run("preinstall").is_ok());
+        assert!(run("postinstall").is_err());
+
+        // the hung hook is killed
+        assert!(run("preuninstall").is_ok());
+        assert!(run("postuninstall")
+            .unwrap_err()
+            .to_string()
+            .contains("timeout"));
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let definition: HookDefinition = toml::from_str(
+            r#"
+preinstall = "echo 'hello'"
+
+[postinstall]
+script = "echo 'world'"
+timeout = 30
+required = false
+"#,
+        )
+        .unwrap();
+
+        let preinstall = definition.preinstall.unwrap();
+        let postinstall = definition.postinstall.unwrap();
+
+        assert_eq!(preinstall.script(), "echo 'hello'");
+        assert_eq!(preinstall.timeout().as_secs(), 600);
+        assert!(preinstall.is_required());
+
+        assert_eq!(postinstall.script(), "echo 'world'");
+        assert_eq!(postinstall.timeout().as_secs(), 30);
+        assert!(!postinstall.is_required());
+    }
+
+    #[test]
+    fn test_resolve_platform_hook() {
+        let hook: hooker::Hook = toml::from_str(
+            r#"
+[unix.sh]
+preinstall = "echo 'unix'"
+postinstall = "echo 'unix'"
+
+[linux.sh]
+postinstall = "echo 'linux'"
+
+[darwin.sh]
+postinstall = "echo 'darwin'"
+
+[windows.cmd]
+postinstall = "echo 'windows'"
+"#,
+        )
+        .unwrap();
+
+        let terminal_hook = hook.resolve().unwrap();
+        let hook_definition = terminal_hook.hook;
+
+        let script = |s: &Option<Script>| s.as_ref().map(|s| s.script().to_string());
+
+        if cfg!(target_os = "linux") {
+            assert_eq!(
+                script(&hook_definition.postinstall),
+                Some("echo 'linux'".to_string())
+            );
+        } else if cfg!(target_os = "macos") {
+            assert_eq!(
+                script(&hook_definition.postinstall),
+                Some("echo 'darwin'".to_string())
+            );
+        } else if cfg!(windows) {
+            assert_eq!(
+                script(&hook_definition.postinstall),
+                Some("echo 'windows'".to_string())
+            );
+            assert_eq!(script(&hook_definition.preinstall), None);
+        }
+
+        // the hook that is not overridden is kept
+        if cfg!(unix) {
+            assert_eq!(
+                script(&hook_definition.preinstall),
+                Some("echo 'unix'".to_string())
+            );
+        }
+    }
+}
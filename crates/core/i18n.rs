@@ -0,0 +1,152 @@
+#![deny(warnings)]
+
+use std::{collections::HashMap, env, fmt::Display, sync::OnceLock};
+
+// the environment variable of language, it takes precedence over the locale of system. eg. 'zh-CN' or 'en-US'
+pub const ENV_LANG: &str = "CASK_LANG";
+
+// the message catalogs, the English one is used for the missing messages
+const EN_US: &str = include_str!("locales/en-US.toml");
+const ZH_CN: &str = include_str!("locales/zh-CN.toml");
+
+struct Catalog {
+    messages: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+// the supported language of locale. eg. 'zh_CN.UTF-8' -> 'zh-CN', the unsupported one is English
+fn get_language(locale: &str) -> &'static str {
+    let locale = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-")
+        .to_lowercase();
+
+    match locale.as_str() {
+        "zh" | "zh-cn" | "zh-sg" | "zh-hans" | "zh-hans-cn" => "zh-CN",
+        _ => "en-US",
+    }
+}
+
+// the first locale that is set, see https://www.gnu.org/software/gettext/manual/html_node/Locale-Environment-Variables.html
+fn detect_language() -> &'static str {
+    [ENV_LANG, "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| get_language(&value))
+        .unwrap_or("en-US")
+}
+
+fn parse_catalog(content: &str) -> HashMap<String, String> {
+    toml::from_str(content).expect("parse the message catalog fail")
+}
+
+fn get_catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| {
+        let messages = match detect_language() {
+            "zh-CN" => ZH_CN,
+            _ => EN_US,
+        };
+
+        Catalog {
+            messages: parse_catalog(messages),
+            fallback: parse_catalog(EN_US),
+        }
+    })
+}
+
+// render the message with the variables, eg. '{name}' is replaced with the value of 'name'
+fn render(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(message.to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+}
+
+// the message of current language, the ID is returned if it does not exist
+pub fn tr(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    let catalog = get_catalog();
+
+    match catalog
+        .messages
+        .get(id)
+        .or_else(|| catalog.fallback.get(id))
+    {
+        Some(message) => render(message, args),
+        None => id.to_string(),
+    }
+}
+
+// translate the message. eg. t!("install-installed", name = name, version = version)
+#[macro_export]
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::tr($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr($id, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+    };
+}
+
+pub use t;
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::i18n;
+
+    #[test]
+    fn test_get_language() {
+        assert_eq!(i18n::get_language("zh_CN.UTF-8"), "zh-CN");
+        assert_eq!(i18n::get_language("zh-CN"), "zh-CN");
+        assert_eq!(i18n::get_language("zh"), "zh-CN");
+        assert_eq!(i18n::get_language("zh_TW.UTF-8"), "en-US");
+        assert_eq!(i18n::get_language("en_US.UTF-8"), "en-US");
+        assert_eq!(i18n::get_language("C"), "en-US");
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            i18n::render(
+                "The package '{name} {version}'",
+                &[("name", &"foo"), ("version", &1)]
+            ),
+            "The package 'foo 1'"
+        );
+        assert_eq!(i18n::tr("not-exist", &[]), "not-exist");
+    }
+
+    #[test]
+    fn test_catalogs() {
+        let en_us = i18n::parse_catalog(i18n::EN_US);
+        let zh_cn = i18n::parse_catalog(i18n::ZH_CN);
+        let variable = Regex::new(r"\{\w+\}").unwrap();
+
+        let get_variables = |message: &str| {
+            let mut variables = variable
+                .find_iter(message)
+                .map(|m| m.as_str().to_string())
+                .collect::<Vec<String>>();
+
+            variables.sort();
+            variables
+        };
+
+        // the translations have the same messages and variables
+        for (id, message) in &en_us {
+            let translation = zh_cn
+                .get(id)
+                .unwrap_or_else(|| panic!("the message '{}' is not translated", id));
+
+            assert_eq!(get_variables(message), get_variables(translation), "{}", id);
+        }
+
+        assert_eq!(en_us.len(), zh_cn.len());
+    }
+}
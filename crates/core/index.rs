@@ -0,0 +1,307 @@
+#![deny(warnings)]
+
+use crate::{cask, formula};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::UNIX_EPOCH,
+};
+
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+
+// the version of index format, the index of another version is rebuilt
+const INDEX_VERSION: u32 = 2;
+
+// the summary of installed package, so that the formula is not parsed to list the packages
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,       // the package name
+    pub bin: String,        // the executable name
+    pub version: String,    // the version in use
+    pub created_at: String, // the time of install
+    #[serde(default)]
+    pub aliases: Vec<String>, // the short names besides the executable name. eg. ripgrep
+    modified: u128, // the modified time of formula in nanoseconds, the entry is stale once it is changed
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Index {
+    version: u32,
+    packages: BTreeMap<String, Entry>, // the entries by the name of package folder
+}
+
+// the index file of installed packages
+pub fn path(cask: &cask::Cask) -> PathBuf {
+    cask.state_dir().join("index.json")
+}
+
+fn get_modified(file: &Path) -> Option<u128> {
+    fs::metadata(file)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+}
+
+fn read(cask: &cask::Cask) -> Index {
+    fs::read_to_string(path(cask))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Index>(&content).ok())
+        .filter(|index| index.version == INDEX_VERSION)
+        .unwrap_or_default()
+}
+
+// write to the temp file and then rename it, so that the index is never read half-written
+fn write(cask: &cask::Cask, index: &Index) -> Result<(), Report> {
+    let filepath = path(cask);
+    let temp_file = filepath.with_extension(format!("{}.tmp", process::id()));
+
+    fs::create_dir_all(cask.state_dir())?;
+    fs::write(&temp_file, serde_json::to_string(index)?)?;
+    fs::rename(&temp_file, &filepath)?;
+
+    Ok(())
+}
+
+// bring the index up to date with the package folders and return the entries.
+// only the new or changed formulas are parsed, the index is rebuilt if it is missing or broken
+pub fn refresh(cask: &cask::Cask) -> Result<Vec<Entry>, Report> {
+    let mut index = read(cask);
+    let mut packages = BTreeMap::new();
+    let mut is_changed = index.version != INDEX_VERSION;
+
+    if !cask.formula_dir().exists() {
+        return Ok(vec![]);
+    }
+
+    for dir_entry in fs::read_dir(cask.formula_dir())?.filter_map(|f| f.ok()) {
+        let cask_file_path = dir_entry.path().join("Cask.toml");
+
+        let modified = match get_modified(&cask_file_path) {
+            Some(modified) => modified,
+            None => continue,
+        };
+
+        let dir_name = dir_entry.file_name().to_string_lossy().to_string();
+
+        let entry = match index.packages.remove(&dir_name) {
+            Some(entry) if entry.modified == modified => entry,
+            _ => {
+                is_changed = true;
+
+                let package_formula = formula::new(&cask_file_path, "")?;
+
+                let cask_info = package_formula.cask.ok_or_else(|| {
+                    eyre::format_err!(
+                        "can not parse cask property of package '{}'",
+                        package_formula.package.name
+                    )
+                })?;
+
+                Entry {
+                    name: cask_info.name,
+                    bin: package_formula.package.bin,
+                    version: cask_info.version,
+                    created_at: cask_info.created_at,
+                    aliases: cask_info.aliases.unwrap_or_default(),
+                    modified,
+                }
+            }
+        };
+
+        packages.insert(dir_name, entry);
+    }
+
+    // the removed packages are left in the old index
+    is_changed = is_changed || !index.packages.is_empty();
+
+    let index = Index {
+        version: INDEX_VERSION,
+        packages,
+    };
+
+    if is_changed {
+        write(cask, &index)?;
+    }
+
+    Ok(index.packages.into_values().collect())
+}
+
+impl Entry {
+    // the last part of name and the aliases. eg. 'ripgrep' of 'github.com/BurntSushi/ripgrep'
+    fn is_named(&self, short_name: &str) -> bool {
+        self.name
+            .rsplit('/')
+            .next()
+            .into_iter()
+            .chain(self.aliases.iter().map(|a| a.as_str()))
+            .any(|n| n.eq_ignore_ascii_case(short_name))
+    }
+}
+
+// find the installed package by the package name, the executable name or the short name.
+// the short name that matches several packages must be replaced by the full name
+pub fn find(cask: &cask::Cask, name_or_bin: &str) -> Result<Option<Entry>, Report> {
+    let entries = refresh(cask)?;
+
+    if let Some(entry) = entries
+        .iter()
+        .find(|e| e.name == name_or_bin)
+        .or_else(|| entries.iter().find(|e| e.bin == name_or_bin))
+    {
+        return Ok(Some(entry.clone()));
+    }
+
+    let candidates = entries
+        .iter()
+        .filter(|e| e.is_named(name_or_bin))
+        .collect::<Vec<&Entry>>();
+
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [entry] => Ok(Some((*entry).clone())),
+        _ => Err(eyre::format_err!(
+            "'{}' matches several installed packages: {}. Use the full name instead",
+            name_or_bin,
+            candidates
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{cask, index};
+
+    fn write_formula(c: &cask::Cask, name: &str, bin: &str, version: &str) {
+        let package_dir = c.package_dir(name);
+
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("Cask.toml"),
+            format!(
+                r#"
+[cask]
+name = "{name}"
+created_at = "2022-01-01T00:00:00+00:00"
+version = "{version}"
+repository = "https://{name}"
+
+[package]
+name = "{name}"
+bin = "{bin}"
+repository = "https://{name}"
+description = "test"
+"#
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_refresh() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+
+        assert!(index::refresh(&c).unwrap().is_empty());
+        assert!(index::path(&c).exists());
+
+        write_formula(&c, "github.com/axetroy/foo", "foo", "1.0.0");
+        write_formula(&c, "github.com/axetroy/bar", "bar", "2.0.0");
+
+        let entries = index::refresh(&c).unwrap();
+
+        assert_eq!(entries.len(), 2);
+
+        let foo = index::find(&c, "foo").unwrap().unwrap();
+
+        assert_eq!(foo.name, "github.com/axetroy/foo");
+        assert_eq!(foo.version, "1.0.0");
+        assert_eq!(
+            index::find(&c, "github.com/axetroy/bar")
+                .unwrap()
+                .unwrap()
+                .bin,
+            "bar"
+        );
+        assert!(index::find(&c, "baz").unwrap().is_none());
+
+        // the changed formula is parsed again
+        write_formula(&c, "github.com/axetroy/foo", "foo", "1.1.0");
+
+        assert_eq!(index::find(&c, "foo").unwrap().unwrap().version, "1.1.0");
+
+        // the removed package is dropped
+        fs::remove_dir_all(c.package_dir("github.com/axetroy/bar")).unwrap();
+
+        assert_eq!(index::refresh(&c).unwrap().len(), 1);
+
+        // the broken index is rebuilt
+        fs::write(index::path(&c), "{").unwrap();
+
+        let entries = index::refresh(&c).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, "1.1.0");
+    }
+
+    #[test]
+    fn test_find_by_short_name() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+
+        write_formula(&c, "github.com/BurntSushi/ripgrep", "rg", "14.0.0");
+        write_formula(&c, "github.com/axetroy/fd", "fd-find", "1.0.0");
+        write_formula(&c, "github.com/sharkdp/fd", "fd", "9.0.0");
+
+        // the alias is recorded in the cask section when it is installed
+        let cask_file_path = c
+            .package_dir("github.com/BurntSushi/ripgrep")
+            .join("Cask.toml");
+        let content = fs::read_to_string(&cask_file_path).unwrap();
+
+        fs::write(
+            &cask_file_path,
+            content.replace("[package]", "aliases = [\"grep-rs\"]\n\n[package]"),
+        )
+        .unwrap();
+
+        for name in [
+            "github.com/BurntSushi/ripgrep",
+            "rg",
+            "ripgrep",
+            "RipGrep",
+            "grep-rs",
+        ] {
+            assert_eq!(
+                index::find(&c, name).unwrap().map(|e| e.name),
+                Some("github.com/BurntSushi/ripgrep".to_string()),
+                "{}",
+                name
+            );
+        }
+
+        // the executable name takes precedence over the short name
+        assert_eq!(
+            index::find(&c, "fd").unwrap().map(|e| e.name),
+            Some("github.com/sharkdp/fd".to_string())
+        );
+
+        write_formula(&c, "github.com/axetroy/ripgrep", "rg-fork", "1.0.0");
+
+        assert!(index::find(&c, "ripgrep").is_err());
+    }
+}
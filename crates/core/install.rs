@@ -0,0 +1,1500 @@
+#![deny(warnings)]
+
+use crate::{
+    cache, cask, checksum,
+    error::CaskError,
+    formula, hooker,
+    i18n::t,
+    index, lock, receipt, registry, release,
+    retry::retry_async,
+    signature, source, symlink, transaction,
+    util::{self, file_sha256, get_iso8601},
+};
+
+use std::{
+    collections::HashMap,
+    fs,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use eyre::Report;
+use futures::{stream, StreamExt, TryStreamExt};
+use semver::Version;
+use tracing::{debug, info, warn};
+
+// the package that has been installed, eg. the version that the requirement is resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installed {
+    pub name: String,
+    pub version: String,
+    pub bin: String,
+    pub bin_dir: PathBuf, // the folder that the executable is put into
+}
+
+pub struct InstallOption<'a> {
+    pub force: bool, // install the deprecated package, or replace the files in link folder that Cask does not own
+    pub prerelease: bool, // allow to resolve the pre-release versions
+    pub verbose: bool, // print verbose information
+    pub bin_dir: Option<&'a Path>, // put the executable into this folder without tracking it by Cask
+    pub link_dir: Option<&'a Path>, // link the executable into this folder instead of the bin folder of Cask
+    pub channel: Option<&'a str>,   // install from the release channel of package
+    pub git_ref: Option<&'a str>, // fetch the formula from the branch, tag or commit of repository
+    pub keep_archive: bool, // download the archive into cache instead of extracting while downloading
+    pub formula: Option<&'a str>, // install from the content of formula instead of fetching the package. eg. the Cask.toml piped into stdin
+}
+
+// parse the arguments of install command into the packages and versions.
+// eg. `<PACKAGE> [VERSION]` or `<PACKAGE>[@VERSION]...`
+pub fn parse_install_args<'a>(args: &[&'a str]) -> Vec<(&'a str, Option<&'a str>)> {
+    let is_version = |s: &str| {
+        let v = s.trim_start_matches('v');
+
+        // the requirement starts with an operator. eg. ^1.2, ~1.2.3, >=1.0 or *
+        v.starts_with(|c: char| c.is_ascii_digit() || "^~<>=*".contains(c))
+            || Version::parse(v).is_ok()
+    };
+
+    if let [package_name, version] = args {
+        if is_version(version) {
+            return vec![(package_name, Some(version))];
+        }
+    }
+
+    args.iter()
+        .map(|arg| match arg.rsplit_once('@') {
+            // the '@' of SSH address is not a version. eg. 'git@example.com:repo.git'
+            Some((name, version))
+                if !name.is_empty() && !version.contains('/') && !version.contains(':') =>
+            {
+                (name, Some(version))
+            }
+            _ => (*arg, None),
+        })
+        .collect()
+}
+
+// the package that has been resolved and is ready to download
+struct PreparedInstall {
+    formula: formula::Formula,                      // the formula of package
+    version: String,                                // the version to install
+    requirement: Option<String>, // the version requirement that is specified. eg. ^1.2
+    aliases: Vec<String>,        // the short names that the package is found by. eg. ripgrep
+    download_target: formula::DownloadTarget, // the resource of current platform
+    tar_file_path: PathBuf,      // the file that the resource is downloaded to
+    output_dir: PathBuf,         // the folder that the executable is put into
+    hook_cwd: PathBuf,           // the working directory of hooks
+    hook_policy: hooker::Policy, // whether the hooks run
+    temp_dir: Option<tempfile::TempDir>, // the temp folder of untracked install
+    is_streamable: bool,         // the executable can be extracted while downloading
+    is_streamed: AtomicBool,     // the executable has been extracted while downloading
+    is_installed: bool,          // the version has been installed, it is activated only
+    _formula_file: Option<tempfile::NamedTempFile>, // the temp file of the formula content that is passed in
+    _package_lock: Option<lock::Lock>, // the lock of package until the install finishes
+}
+
+// resolve the formula and version of package
+async fn prepare(
+    cask: &cask::Cask,
+    package_name: &str,
+    version: Option<&str>,
+    options: &InstallOption<'_>,
+) -> Result<PreparedInstall, Report> {
+    let is_verbose = options.verbose;
+    let untracked_bin_dir = options.bin_dir;
+
+    // the formula file of the content is removed once the install finishes
+    let (package_formula, formula_file) = if let Some(content) = options.formula {
+        let mut f: formula::Formula = toml::from_str(content.trim())?;
+
+        let mut cask_file = tempfile::Builder::new()
+            .prefix("Cask_")
+            .suffix(".toml")
+            .tempfile()?;
+        cask_file.write_all(content.as_bytes())?;
+
+        f.filepath = cask_file.path().to_path_buf();
+        f.repository = "".to_string();
+        f.file_content = content.to_string();
+
+        (f, Some(cask_file))
+    } else {
+        if package_name.is_empty() {
+            return Err(eyre::format_err!("<PACKAGE> required"));
+        }
+
+        let temp = untracked_bin_dir.is_some();
+
+        (
+            formula::fetch(cask, package_name, options.git_ref, temp, is_verbose).await?,
+            None,
+        )
+    };
+
+    if let Some(msg) = package_formula.get_deprecation_message() {
+        if !options.force {
+            return Err(eyre::format_err!(
+                "{}. Try run with '--force' if you still want to install it.",
+                msg
+            ));
+        }
+
+        warn!("{}", msg);
+    }
+
+    // the untracked install works in a temp folder, nothing is left in the cask root
+    let temp_dir = match untracked_bin_dir {
+        Some(_) => Some(tempfile::Builder::new().prefix("cask_install_").tempdir()?),
+        None => None,
+    };
+
+    let package_lock = match temp_dir {
+        Some(_) => None,
+        None => Some(cask.lock_package(&package_formula.package.name)?),
+    };
+
+    // detect binary name conflict
+    let installed_packages = match temp_dir {
+        Some(_) => vec![],
+        None => index::refresh(cask)?,
+    };
+
+    for entry in installed_packages {
+        if entry.bin == package_formula.package.bin && entry.name != package_formula.package.name {
+            return Err(eyre::format_err!(
+                r#"The package '{}' binary file name conflict with '{}'. Try uninstall '{}' and try again."#,
+                &package_formula.package.name,
+                &entry.name,
+                &entry.name
+            ));
+        }
+    }
+
+    // the hooks of the package that is installed for the first time are confirmed by default
+    let is_first_install = temp_dir.is_some()
+        || !receipt::path(&cask.package_dir(&package_formula.package.name)).exists();
+
+    let hook_policy = cask
+        .settings()
+        .get_hook_policy(cask.config(), is_first_install);
+
+    let hook_cwd = match &temp_dir {
+        Some(dir) => dir.path().join("repository"),
+        None => cask
+            .package_dir(&package_formula.package.name)
+            .join("repository"),
+    };
+
+    // the pre-release version is allowed if it is specified explicitly
+    let is_specified_prerelease = version.map(formula::is_prerelease_spec).unwrap_or(false);
+
+    let remote_versions = match options.channel {
+        Some(channel) => package_formula.get_channel_versions(cask, channel).await?,
+        None => {
+            package_formula
+                .get_versions(cask, options.prerelease || is_specified_prerelease)
+                .await?
+        }
+    };
+
+    if remote_versions.is_empty() {
+        return Err(CaskError::VersionNotFound(format!(
+            "can not found any version of '{}'",
+            package_name
+        ))
+        .into());
+    }
+
+    let download_version = match version {
+        Some(v) => formula::resolve_version(&remote_versions, v)?.ok_or_else(|| {
+            CaskError::VersionNotFound(format!("can not found version '{}' of formula", v))
+        })?,
+        None => remote_versions
+            .first()
+            .expect("can not found remote version")
+            .clone(),
+    };
+
+    debug!(
+        "resolve the version '{}' of {} to {}",
+        version.unwrap_or("latest"),
+        package_formula.package.name,
+        download_version
+    );
+
+    // the requirement is recorded so that the package is upgraded within it. eg. ^1.2
+    let requirement = version
+        .filter(|v| formula::is_version_requirement(v))
+        .map(|v| v.to_string());
+
+    let aliases = get_aliases(cask, package_name, &package_formula);
+
+    let (version_dir, output_dir) = match (&temp_dir, untracked_bin_dir) {
+        (Some(dir), Some(bin_dir)) => {
+            fs::create_dir_all(bin_dir)?;
+
+            (dir.path().to_path_buf(), bin_dir.to_path_buf())
+        }
+        _ => {
+            // init formula folder
+            cask.init_package(&package_formula.package.name)?;
+
+            (
+                cask.package_version_dir(&package_formula.package.name),
+                cask.package_installed_dir(&package_formula.package.name, &download_version)
+                    .join("bin"),
+            )
+        }
+    };
+
+    // the installed version is activated without downloading, except the rolling release. eg. nightly
+    let is_installed = temp_dir.is_none()
+        && Version::parse(download_version.trim_start_matches('v')).is_ok()
+        && output_dir
+            .parent()
+            .map(|dir| receipt::path(dir).exists())
+            .unwrap_or(false);
+
+    let mut download_target =
+        package_formula.get_current_download_url(&download_version, options.channel)?;
+
+    if download_target.executable && package_formula.resource.is_some() {
+        return Err(eyre::format_err!(
+            "The resource files of package '{}' can not be extracted from the executable",
+            package_formula.package.name
+        ));
+    }
+
+    if download_target.signature_url.is_some() && package_formula.package.public_key.is_none() {
+        return Err(eyre::format_err!(
+            "The 'public_key' of package '{}' is required to verify the signature",
+            package_formula.package.name
+        ));
+    }
+
+    // resolve the URL from the release assets
+    if let (false, Some(pattern)) = (is_installed, &download_target.asset_pattern) {
+        if cask.settings().offline {
+            return Err(eyre::format_err!(
+                "The release asset of '{}' can not be resolved in offline mode",
+                package_formula.package.name
+            ));
+        }
+
+        let asset = release::resolve_asset(
+            cask,
+            cask.config(),
+            &package_formula,
+            &download_version,
+            pattern,
+            is_verbose,
+        )
+        .await?;
+
+        if is_verbose {
+            info!("Resolved the release asset '{}'", asset.name);
+        }
+
+        if !download_target.executable && download_target.ext.is_empty() {
+            download_target.ext = formula::get_ext_name_from_url(&asset.url)?.to_string();
+        }
+
+        download_target.url = asset.url;
+    }
+
+    // look up the checksum from the checksums file, the cached resource is used in offline mode
+    if let (None, Some(checksum_url)) = (&download_target.checksum, &download_target.checksum_url) {
+        if !cask.settings().offline && !is_installed {
+            let filename =
+                checksum::get_filename_from_url(&download_target.url).ok_or_else(|| {
+                    eyre::format_err!(
+                        "Can not get the file name from '{}' to look up the checksum",
+                        download_target.url
+                    )
+                })?;
+
+            let hash =
+                checksum::fetch_checksum(cask, cask.config(), checksum_url, &filename, is_verbose)
+                    .await?;
+
+            download_target.checksum = Some(hash);
+        }
+    }
+
+    // the invalid checksum is reported before the resource is downloaded
+    if let Some(c) = &download_target.checksum {
+        checksum::parse(c)?;
+    }
+
+    // the resource can not be verified without checksum, it is refused by `checksum = "require"` of config
+    if download_target.checksum.is_none() && !is_installed && cask.config().is_checksum_required() {
+        return Err(CaskError::Integrity(format!(
+            "The checksum of '{}' is not provided by the formula, it is required by the config",
+            download_target.url
+        ))
+        .into());
+    }
+
+    let tar_file_path = version_dir.join(format!("{}{}", &download_version, download_target.ext));
+
+    // the archive must be verified or kept before extracting, eg. the signature
+    let is_streamable = !options.keep_archive
+        && !download_target.executable
+        && download_target.signature_url.is_none()
+        && download_target.inner_path.is_none()
+        && package_formula.resource.is_none()
+        && extractor::is_streamable(&download_target.ext);
+
+    debug!(
+        "the resource '{}' is executable: {}, streamable: {}",
+        tar_file_path.display(),
+        download_target.executable,
+        is_streamable
+    );
+
+    Ok(PreparedInstall {
+        formula: package_formula,
+        version: download_version,
+        requirement,
+        aliases,
+        download_target,
+        tar_file_path,
+        output_dir,
+        hook_cwd,
+        hook_policy,
+        temp_dir,
+        is_streamable,
+        is_streamed: AtomicBool::new(false),
+        is_installed,
+        _formula_file: formula_file,
+        _package_lock: package_lock,
+    })
+}
+
+// the short names of package that are defined by the formula and the index, the short name that the package
+// is installed by and the aliases of previous install are kept. the executable name and the last part of
+// package name are not recorded, they are always the short names
+fn get_aliases(cask: &cask::Cask, package_name: &str, f: &formula::Formula) -> Vec<String> {
+    let name = &f.package.name;
+
+    let previous_aliases = formula::new(&cask.package_dir(name).join("Cask.toml"), "")
+        .ok()
+        .and_then(|previous| previous.cask)
+        .and_then(|c| c.aliases)
+        .unwrap_or_default();
+
+    let index_aliases = registry::load(cask)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| &e.name == name)
+        .flat_map(|e| e.aliases);
+
+    let requested = Some(package_name.to_string()).filter(|n| registry::is_short_name(n));
+
+    let mut aliases = f
+        .package
+        .aliases
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .chain(index_aliases)
+        .chain(requested)
+        .chain(previous_aliases)
+        .filter(|alias| {
+            !alias.eq_ignore_ascii_case(&f.package.bin)
+                && !name
+                    .rsplit('/')
+                    .next()
+                    .map(|n| alias.eq_ignore_ascii_case(n))
+                    .unwrap_or(false)
+        })
+        .collect::<Vec<String>>();
+
+    aliases.sort();
+    aliases.dedup();
+
+    aliases
+}
+
+// download the resource from the source. eg. https://, s3:// or gs://
+async fn download_source(
+    cask: &cask::Cask,
+    url: &str,
+    filepath: &Path,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let request = source::resolve(url)?;
+
+    let mut download_option =
+        cask.settings()
+            .get_download_option(cask.config(), &request.url, is_verbose);
+    download_option.headers.extend(request.headers);
+
+    retry_async(
+        &cask.settings().get_retry_policy(),
+        downloader::is_transient,
+        || downloader::download(&request.url, filepath, &download_option),
+    )
+    .await
+}
+
+// download the resource and verify the checksum and signature
+async fn download(
+    cask: &cask::Cask,
+    prepared: &PreparedInstall,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    if prepared.is_installed {
+        return Ok(());
+    }
+
+    download_resource(cask, prepared, is_verbose).await?;
+
+    if let (Some(signature_url), Some(public_key)) = (
+        &prepared.download_target.signature_url,
+        &prepared.formula.package.public_key,
+    ) {
+        let tar_file_path = &prepared.tar_file_path;
+
+        let mut signature_file_path = tar_file_path.clone().into_os_string();
+        signature_file_path.push(".sig");
+        let signature_file_path = PathBuf::from(signature_file_path);
+
+        download_signature(cask, signature_url, &signature_file_path, is_verbose).await?;
+
+        let result = signature::verify(tar_file_path, &signature_file_path, public_key);
+
+        fs::remove_file(&signature_file_path).ok();
+
+        // fail closed, the unverified resource is never extracted
+        if let Err(e) = result {
+            fs::remove_file(tar_file_path).ok();
+            return Err(CaskError::Integrity(format!("{:#}", e)).into());
+        }
+    }
+
+    Ok(())
+}
+
+// download the signature file, the cached signature is used in offline mode
+async fn download_signature(
+    cask: &cask::Cask,
+    signature_url: &str,
+    filepath: &Path,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    if cask.settings().offline {
+        if cache::restore(cask, signature_url, None, filepath)? {
+            return Ok(());
+        }
+
+        return Err(eyre::format_err!(
+            "The signature '{}' is not in cache, it can not be downloaded in offline mode",
+            signature_url
+        ));
+    }
+
+    download_source(
+        cask,
+        &cask.config().rewrite_url(signature_url),
+        filepath,
+        is_verbose,
+    )
+    .await
+    .map_err(|e| eyre::format_err!("Download the signature '{}' fail: {}", signature_url, e))?;
+
+    cache::store(cask, signature_url, filepath)
+}
+
+// the file name of executable in the tarball
+fn get_executable_name(package_formula: &formula::Formula) -> String {
+    #[cfg(target_family = "unix")]
+    let executable_name = package_formula.package.bin.clone();
+    #[cfg(target_family = "windows")]
+    let executable_name = format!("{}.exe", &package_formula.package.bin);
+
+    executable_name
+}
+
+// the folder that the executable is extracted to while downloading, it is moved to output folder on finish
+fn get_stream_dir(tar_file_path: &Path) -> PathBuf {
+    tar_file_path
+        .parent()
+        .unwrap_or(tar_file_path)
+        .join(".stream")
+}
+
+// download the tarball and extract the executable from the stream, the archive is not written to disk.
+// the checksum is verified once the download finishes, the executable is removed if it mismatch
+async fn download_stream(
+    cask: &cask::Cask,
+    url: &str,
+    prepared: &PreparedInstall,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let request = source::resolve(url)?;
+
+    let mut download_option =
+        cask.settings()
+            .get_download_option(cask.config(), &request.url, is_verbose);
+    download_option.headers.extend(request.headers);
+
+    let download_target = &prepared.download_target;
+    let executable_name = get_executable_name(&prepared.formula);
+
+    let download_once = || async {
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+        let extracting = {
+            let ext = download_target.ext.clone();
+            let folder = download_target.path.clone();
+            let stream_dir = get_stream_dir(&prepared.tar_file_path);
+            let executable_name = executable_name.clone();
+
+            thread::spawn(move || {
+                extractor::extract_stream(
+                    util::ChannelReader::new(receiver),
+                    &ext,
+                    &stream_dir,
+                    &executable_name,
+                    &folder,
+                )
+            })
+        };
+
+        let expected = download_target
+            .checksum
+            .as_deref()
+            .map(checksum::parse)
+            .transpose()?;
+
+        let mut hasher =
+            checksum::Hasher::new(expected.as_ref().map(|c| c.algorithm).unwrap_or_default());
+
+        // the extracting thread stops reading once the executable is found
+        let downloaded = downloader::download_stream(&request.url, &download_option, |chunk| {
+            hasher.update(chunk);
+            sender.send(chunk.to_vec()).ok();
+            Ok(())
+        })
+        .await;
+
+        drop(sender);
+
+        let extracted = extracting
+            .join()
+            .map_err(|_| eyre::format_err!("the extracting thread panicked"))?;
+
+        downloaded?;
+
+        let output_file_path = extracted?;
+
+        if let Some(expected) = expected {
+            let hash = hasher.finalize();
+
+            if hash != expected.hash {
+                fs::remove_file(&output_file_path).ok();
+
+                return Err(eyre::format_err!(
+                    "The file {} is '{}' but expect '{}'",
+                    expected.algorithm,
+                    hash,
+                    expected.hash
+                ));
+            }
+        }
+
+        Ok(())
+    };
+
+    let result = retry_async(
+        &cask.settings().get_retry_policy(),
+        downloader::is_transient,
+        download_once,
+    )
+    .await;
+
+    if result.is_err() {
+        fs::remove_dir_all(get_stream_dir(&prepared.tar_file_path)).ok();
+    }
+
+    result
+}
+
+// download the resource and verify the checksum
+async fn download_resource(
+    cask: &cask::Cask,
+    prepared: &PreparedInstall,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let download_target = &prepared.download_target;
+    let tar_file_path = &prepared.tar_file_path;
+
+    let is_offline = cask.settings().offline;
+
+    // the resource of rolling release (eg. nightly) may change without checksum
+    let is_cacheable = download_target.checksum.is_some()
+        || Version::parse(prepared.version.trim_start_matches('v')).is_ok();
+
+    if (is_cacheable || is_offline)
+        && cache::restore(
+            cask,
+            &download_target.url,
+            download_target.checksum.as_deref(),
+            tar_file_path,
+        )?
+    {
+        info!("{}", t!("install-cached", url = download_target.url));
+        return Ok(());
+    }
+
+    if is_offline {
+        return Err(eyre::format_err!(
+            "The resource '{}' is not in cache, it can not be downloaded in offline mode",
+            &download_target.url
+        ));
+    }
+
+    let config = cask.config();
+
+    // download from the mirror first, the checksum mismatch is retried from origin
+    let download_url = config.rewrite_url(&download_target.url);
+
+    debug!("download the resource from {}", download_url);
+
+    if prepared.is_streamable {
+        match download_stream(cask, &download_url, prepared, is_verbose).await {
+            Ok(()) => {
+                prepared.is_streamed.store(true, Ordering::SeqCst);
+                return Ok(());
+            }
+            // eg. the symlink points to the file that has been passed
+            Err(e) => warn!(
+                "Extract while downloading fail: {}, download the archive instead",
+                e
+            ),
+        }
+    }
+
+    download_source(cask, &download_url, tar_file_path, is_verbose).await?;
+
+    if let Some(checksum) = &download_target.checksum {
+        let expected = checksum::parse(checksum)?;
+
+        let mut hash = checksum::file_hash(tar_file_path, expected.algorithm)?;
+
+        if hash != expected.hash {
+            // the artifact may be stale or truncated, purge it and retry from origin once
+            warn!(
+                "The file {} is '{}' but expect '{}', retry downloading from {}",
+                expected.algorithm, hash, expected.hash, &download_target.url
+            );
+
+            fs::remove_file(tar_file_path)?;
+
+            download_source(cask, &download_target.url, tar_file_path, is_verbose).await?;
+
+            hash = checksum::file_hash(tar_file_path, expected.algorithm)?;
+        }
+
+        if hash != expected.hash {
+            fs::remove_file(tar_file_path)?;
+            return Err(CaskError::Integrity(format!(
+                "The file {} is '{}' but expect '{}'",
+                expected.algorithm, hash, expected.hash
+            ))
+            .into());
+        }
+    }
+
+    cache::store(cask, &download_target.url, tar_file_path)?;
+
+    Ok(())
+}
+
+// the environment variables of hook. the package folder is the folder of version,
+// and the bin folder is the folder in $PATH, it is the output folder for the untracked install
+fn get_hook_envs(
+    cask: &cask::Cask,
+    package_formula: &formula::Formula,
+    version: &str,
+    output_dir: &Path,
+    link_dir: Option<&Path>,
+    download_file: Option<&Path>,
+) -> HashMap<String, String> {
+    let package_dir = output_dir.parent().unwrap_or(output_dir);
+
+    let bin_dir = if package_dir.starts_with(cask.formula_dir()) {
+        link_dir
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| get_link_dir(cask, &package_formula.package.name))
+    } else {
+        output_dir.to_path_buf()
+    };
+
+    hooker::get_envs(
+        &cask.shared_root_dir(),
+        &package_formula.package.name,
+        version,
+        package_dir,
+        &bin_dir,
+        download_file,
+    )
+}
+
+// extract the executable and record the package.
+// the files are staged first, then they are put into place together, the previous files are restored on failure
+fn finish(
+    cask: &cask::Cask,
+    prepared: PreparedInstall,
+    options: &InstallOption<'_>,
+) -> Result<Installed, Report> {
+    let PreparedInstall {
+        formula: package_formula,
+        version: download_version,
+        requirement,
+        aliases,
+        download_target,
+        tar_file_path,
+        output_dir,
+        hook_cwd,
+        hook_policy,
+        temp_dir,
+        is_streamed,
+        is_installed,
+        ..
+    } = prepared;
+
+    let hook_cwd = &hook_cwd;
+
+    let executable_name = get_executable_name(&package_formula);
+
+    // the version that is replaced by this install, the 'postupgrade' hook runs if it is another version
+    let previous_version = match temp_dir {
+        Some(_) => None,
+        None => receipt::read(&cask.package_dir(&package_formula.package.name))
+            .ok()
+            .flatten()
+            .map(|r| r.version),
+    };
+
+    // the archive of executable resource is removed once it is installed
+    let checksum = download_target
+        .checksum
+        .clone()
+        .or_else(|| file_sha256(&tar_file_path).ok());
+
+    // the auxiliary files are put into the parent of binary folder. eg. ~/.local/share for ~/.local/bin
+    let prefix_dir = output_dir.parent().unwrap_or(&output_dir);
+
+    let work_dir = tar_file_path
+        .parent()
+        .ok_or_else(|| eyre::format_err!("can not get parent of '{}'", tar_file_path.display()))?;
+
+    let staging_dir = work_dir.join(".staging");
+
+    let stage = || -> Result<(PathBuf, Vec<String>), Report> {
+        // the staging folder has the same layout as the prefix folder
+        let staged_output_dir = match output_dir.file_name() {
+            Some(name) => staging_dir.join(name),
+            None => staging_dir.join("bin"),
+        };
+
+        fs::create_dir_all(&staged_output_dir)?;
+
+        let staged_file_path = if is_streamed.load(Ordering::SeqCst) {
+            let stream_dir = get_stream_dir(&tar_file_path);
+            let new_bin_path = staged_output_dir.join(&executable_name);
+
+            debug!("use the executable that is extracted while downloading");
+
+            // the temp folder may be located in another file system
+            fs::copy(stream_dir.join(&executable_name), &new_bin_path)?;
+            fs::remove_dir_all(&stream_dir)?;
+
+            new_bin_path
+        } else if download_target.executable {
+            let new_bin_path = staged_output_dir.join(&executable_name);
+
+            debug!("the resource is the executable, copy it as it is");
+
+            // the resource is linked to the cache, copy it so that the cache is never modified.
+            // and the temp folder may be located in another file system
+            fs::copy(
+                extractor::long_path(&tar_file_path),
+                extractor::long_path(&new_bin_path),
+            )?;
+
+            new_bin_path
+        } else if let Some(inner_path) = &download_target.inner_path {
+            debug!(
+                "extract '{}' from the archive '{}' in the resource",
+                download_target.path, inner_path
+            );
+
+            extractor::extract_nested(
+                &tar_file_path,
+                &staged_output_dir,
+                &executable_name,
+                download_target.path.as_str(),
+                inner_path,
+            )?
+        } else {
+            debug!("extract '{}' from the resource", download_target.path);
+
+            extractor::extract(
+                &tar_file_path,
+                &staged_output_dir,
+                &executable_name,
+                download_target.path.as_str(),
+            )?
+        };
+
+        let resource_files = install_resource_files(
+            &package_formula,
+            &download_version,
+            &download_target,
+            &tar_file_path,
+            &staging_dir,
+        )?;
+
+        // Make sure it's a executable, the entry of zip may have no unix mode
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            let mode = fs::metadata(&staged_file_path)?.permissions().mode();
+
+            fs::set_permissions(
+                &staged_file_path,
+                fs::Permissions::from_mode((mode & 0o7777) | 0o755),
+            )?;
+        }
+
+        Ok((staged_file_path, resource_files))
+    };
+
+    // the hook runs right before the files are put into place, after all packages are resolved and downloaded
+    if let Some(hook) = &package_formula.hook {
+        if !hook_cwd.exists() {
+            fs::create_dir_all(hook_cwd)?;
+        }
+
+        let renderer_context = &package_formula.ger_renderer_context(&download_version);
+
+        let envs = get_hook_envs(
+            cask,
+            &package_formula,
+            &download_version,
+            &output_dir,
+            options.link_dir,
+            None,
+        );
+
+        hook.run(
+            "preinstall",
+            hook_cwd,
+            renderer_context,
+            envs,
+            hook_policy,
+            options.verbose,
+        )?;
+    }
+
+    if staging_dir.exists() {
+        fs::remove_dir_all(extractor::long_path(&staging_dir))?;
+    }
+
+    let output_file_path = output_dir.join(&executable_name);
+
+    let package_dir = cask.package_dir(&package_formula.package.name);
+
+    // the files of installed version are kept, they are recorded in its formula file
+    let (staged_file_path, resource_files) = if is_installed {
+        info!(
+            "{}",
+            t!(
+                "install-switched",
+                name = package_formula.package.name,
+                version = download_version
+            )
+        );
+
+        let files = formula::new(&prefix_dir.join("Cask.toml"), "")?
+            .cask
+            .and_then(|c| c.files)
+            .unwrap_or_default();
+
+        (None, files)
+    } else {
+        let staged = stage();
+
+        if staged.is_err() {
+            fs::remove_dir_all(extractor::long_path(&staging_dir)).ok();
+        }
+
+        let (staged_file_path, resource_files) = staged?;
+
+        (Some(staged_file_path), resource_files)
+    };
+
+    let symlink_file = options
+        .link_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| get_link_dir(cask, &package_formula.package.name))
+        .join(&package_formula.package.bin);
+
+    // the bin folder is shared by all packages
+    let lock = match temp_dir {
+        Some(_) => None,
+        None => Some(cask.lock()?),
+    };
+
+    let mut transaction = transaction::new(&work_dir.join(".backup"))?;
+
+    let mut apply = || -> Result<(), Report> {
+        if let Some(staged_file_path) = &staged_file_path {
+            transaction.replace(
+                &extractor::long_path(staged_file_path),
+                &extractor::long_path(&output_file_path),
+            )?;
+
+            for file in &resource_files {
+                transaction.replace(
+                    &extractor::long_path(&staging_dir.join(file)),
+                    &extractor::long_path(&prefix_dir.join(file)),
+                )?;
+            }
+        }
+
+        // the untracked executable is not recorded
+        if temp_dir.is_some() {
+            return Ok(());
+        }
+
+        // remove the files of previous layout, which installs the only version into the package folder
+        let legacy_file_path = package_dir.join("bin").join(&executable_name);
+
+        if legacy_file_path.exists() {
+            transaction.backup(&legacy_file_path)?;
+
+            if let Ok(previous_formula) = formula::new(&package_dir.join("Cask.toml"), "") {
+                for file in previous_formula
+                    .cask
+                    .and_then(|c| c.files)
+                    .unwrap_or_default()
+                {
+                    transaction.backup(&package_dir.join(file))?;
+                }
+            }
+        }
+
+        // init Cask information in the Cask.toml of version
+        let version_file_path = &prefix_dir.join("Cask.toml");
+
+        transaction.backup(version_file_path)?;
+
+        let mut formula_file = File::create(version_file_path)?;
+
+        // the values are serialized by toml, so that the quotes in them do not break the file
+        let mut cask_info = toml::Table::new();
+        cask_info.insert(
+            "name".to_string(),
+            package_formula.package.name.clone().into(),
+        );
+        cask_info.insert("created_at".to_string(), get_iso8601().into());
+        cask_info.insert("version".to_string(), download_version.clone().into());
+        cask_info.insert(
+            "repository".to_string(),
+            package_formula.repository.clone().into(),
+        );
+
+        if let Some(channel) = options.channel {
+            cask_info.insert("channel".to_string(), channel.into());
+        }
+
+        if let Some(git_ref) = options.git_ref {
+            cask_info.insert("ref".to_string(), git_ref.into());
+        }
+
+        if let Some(requirement) = &requirement {
+            cask_info.insert("requirement".to_string(), requirement.clone().into());
+        }
+
+        if !resource_files.is_empty() {
+            cask_info.insert("files".to_string(), resource_files.clone().into());
+        }
+
+        if !package_formula.source.is_empty() {
+            cask_info.insert("source".to_string(), package_formula.source.clone().into());
+        }
+
+        if !aliases.is_empty() {
+            cask_info.insert("aliases".to_string(), aliases.clone().into());
+        }
+
+        let mut cask_content = toml::Table::new();
+        cask_content.insert("cask".to_string(), cask_info.into());
+
+        formula_file.write_all(
+            format!(
+                "# The file is generated by Cask. DO NOT MODIFY IT.\n{}\n",
+                toml::to_string(&cask_content)?
+            )
+            .as_bytes(),
+        )?;
+        formula_file.write_all(package_formula.get_toml_content()?.as_bytes())?;
+
+        // record the installed files
+        if !is_installed {
+            transaction.backup(&receipt::path(prefix_dir))?;
+
+            receipt::write(
+                prefix_dir,
+                &receipt::Receipt {
+                    name: package_formula.package.name.clone(),
+                    version: download_version.clone(),
+                    url: download_target.url.clone(),
+                    checksum: checksum.clone(),
+                    created_at: get_iso8601(),
+                    bin: vec![output_file_path.clone()],
+                    symlinks: symlink::get_link_files(&symlink_file),
+                    files: resource_files
+                        .iter()
+                        .map(|file| prefix_dir.join(file))
+                        .collect(),
+                    link: None,
+                    link_dir: None,
+                },
+            )?;
+        }
+
+        activate(
+            cask,
+            &package_formula.package.name,
+            &package_formula.package.bin,
+            prefix_dir,
+            options.link_dir,
+            options.force,
+            &mut transaction,
+        )
+    };
+
+    let result = apply();
+
+    fs::remove_dir_all(extractor::long_path(&staging_dir)).ok();
+
+    match result {
+        Ok(()) => transaction.commit()?,
+        Err(e) => {
+            transaction.rollback()?;
+            return Err(e);
+        }
+    }
+
+    // the index is updated while the bin folder is locked, so that it is not overwritten by another install
+    if temp_dir.is_none() {
+        index::refresh(cask)?;
+    }
+
+    drop(lock);
+
+    if let Some(hook) = &package_formula.hook {
+        let renderer_context = &package_formula.ger_renderer_context(&download_version);
+
+        let envs = get_hook_envs(
+            cask,
+            &package_formula,
+            &download_version,
+            &output_dir,
+            options.link_dir,
+            Some(&tar_file_path),
+        );
+
+        hook.run(
+            "postinstall",
+            hook_cwd,
+            renderer_context,
+            envs.clone(),
+            hook_policy,
+            options.verbose,
+        )?;
+
+        if previous_version.is_some_and(|v| v != download_version) {
+            hook.run(
+                "postupgrade",
+                hook_cwd,
+                renderer_context,
+                envs,
+                hook_policy,
+                options.verbose,
+            )?;
+        }
+    }
+
+    // the resource is linked to the cache, it is not needed any more
+    if download_target.executable && temp_dir.is_none() && !is_installed {
+        fs::remove_file(&tar_file_path)?;
+    }
+
+    if temp_dir.is_some() {
+        info!(
+            "{}",
+            t!(
+                "install-installed-to",
+                name = package_formula.package.name,
+                version = download_version,
+                path = output_file_path.display()
+            )
+        );
+
+        info!("{}", t!("install-untracked"));
+
+        return Ok(Installed {
+            name: package_formula.package.name.clone(),
+            version: download_version,
+            bin: package_formula.package.bin.clone(),
+            bin_dir: options.bin_dir.map(Path::to_path_buf).unwrap_or_default(),
+        });
+    }
+
+    info!(
+        "{}",
+        t!(
+            "install-installed",
+            name = package_formula.package.name,
+            version = download_version
+        )
+    );
+
+    info!(
+        "{}",
+        t!("install-try-help", bin = package_formula.package.bin)
+    );
+
+    Ok(Installed {
+        name: package_formula.package.name.clone(),
+        version: download_version,
+        bin: package_formula.package.bin.clone(),
+        bin_dir: get_link_dir(cask, &package_formula.package.name),
+    })
+}
+
+// the folder that the executable of package is linked into, the folder of `--link-dir` is recorded in the receipt.
+// it is the bin folder of Cask if the package is not installed with `--link-dir`
+pub fn get_link_dir(cask: &cask::Cask, package_name: &str) -> PathBuf {
+    receipt::read(&cask.package_dir(package_name))
+        .ok()
+        .flatten()
+        .and_then(|r| r.link_dir)
+        .unwrap_or_else(|| cask.bin_dir())
+}
+
+// make the installed version active, the executable is linked into the bin folder of Cask or the link folder.
+// the recorded link folder is kept if it is not specified, so that it is kept when the package is upgraded or switched.
+// the files in the new link folder that are not created by Cask are replaced only if it is forced.
+// the formula file and receipt of version are copied into the package folder, the receipt records how it is linked
+pub fn activate(
+    cask: &cask::Cask,
+    package_name: &str,
+    bin: &str,
+    installed_dir: &Path,
+    link_dir: Option<&Path>,
+    force: bool,
+    transaction: &mut transaction::Transaction,
+) -> Result<(), Report> {
+    let package_dir = cask.package_dir(package_name);
+
+    #[cfg(target_family = "unix")]
+    let executable_name = bin.to_string();
+    #[cfg(target_family = "windows")]
+    let executable_name = format!("{}.exe", bin);
+
+    let previous_link_dir = get_link_dir(cask, package_name);
+    let link_dir = link_dir
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| previous_link_dir.clone());
+
+    // the links in the previous folder are removed if the package is moved to another folder
+    if previous_link_dir != link_dir {
+        for link_file in symlink::get_link_files(&previous_link_dir.join(bin)) {
+            transaction.backup(&link_file)?;
+        }
+    }
+
+    fs::create_dir_all(&link_dir)?;
+
+    let symlink_file = link_dir.join(bin);
+
+    // the folder is shared with other programs. eg. /usr/local/bin
+    if !force && link_dir != previous_link_dir && link_dir != cask.bin_dir() {
+        let executables = cask
+            .list_installed_versions(package_name)?
+            .iter()
+            .map(|version| {
+                cask.package_installed_dir(package_name, version)
+                    .join("bin")
+                    .join(&executable_name)
+            })
+            .collect::<Vec<PathBuf>>();
+
+        for link_file in symlink::get_link_files(&symlink_file) {
+            if !symlink::is_owned(&link_file, &cask.formula_dir(), &executables) {
+                return Err(eyre::format_err!(
+                    "The file '{}' is not created by Cask. Try run with '--force' if you want to replace it.",
+                    link_file.display()
+                ));
+            }
+        }
+    }
+
+    for link_file in symlink::get_link_files(&symlink_file) {
+        transaction.backup(&link_file)?;
+    }
+
+    let link = symlink::symlink(
+        &installed_dir.join("bin").join(executable_name),
+        &symlink_file,
+        package_name,
+        cask.config().get_link_strategy(),
+    )?;
+
+    for file_path in [
+        installed_dir.join("Cask.toml"),
+        receipt::path(installed_dir),
+    ] {
+        let active_file_path = package_dir.join(file_path.file_name().unwrap_or_default());
+
+        transaction.backup(&active_file_path)?;
+
+        fs::copy(&file_path, &active_file_path)?;
+    }
+
+    // the bin folder of Cask is not recorded, so that it follows the config
+    let custom_link_dir = Some(link_dir.as_path()).filter(|dir| *dir != cask.bin_dir());
+
+    receipt::write_link(&package_dir, link, custom_link_dir)?;
+
+    Ok(())
+}
+
+// extract the auxiliary files of resource into the prefix folder, return the installed files relative to it
+fn install_resource_files(
+    package_formula: &formula::Formula,
+    version: &str,
+    download_target: &formula::DownloadTarget,
+    tar_file_path: &Path,
+    prefix_dir: &Path,
+) -> Result<Vec<String>, Report> {
+    let files = package_formula.get_resource_files(version)?;
+
+    if files.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let staging_dir = tar_file_path
+        .parent()
+        .ok_or_else(|| eyre::format_err!("can not get parent of '{}'", tar_file_path.display()))?
+        .join(".files");
+
+    let install = || -> Result<Vec<String>, Report> {
+        let mut installed_files = vec![];
+
+        for file in &files {
+            let archive_path = Path::new(&file.path);
+
+            let filename = archive_path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| eyre::format_err!("invalid resource file '{}'", file.path))?;
+            let folder = archive_path
+                .parent()
+                .and_then(|s| s.to_str())
+                .filter(|s| !s.is_empty())
+                .unwrap_or("/");
+
+            let extracted_file_path = match &download_target.inner_path {
+                Some(inner_path) => extractor::extract_nested(
+                    tar_file_path,
+                    &staging_dir,
+                    filename,
+                    folder,
+                    inner_path,
+                )?,
+                None => extractor::extract(tar_file_path, &staging_dir, filename, folder)?,
+            };
+
+            let dest_file_path = extractor::long_path(&prefix_dir.join(&file.dest));
+            let extracted_file_path = extractor::long_path(&extracted_file_path);
+
+            if let Some(parent) = dest_file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            if dest_file_path.exists() {
+                fs::remove_file(&dest_file_path)?;
+            }
+
+            // the staging folder may be located in another file system
+            fs::copy(&extracted_file_path, &dest_file_path)?;
+            fs::remove_file(&extracted_file_path)?;
+
+            installed_files.push(file.dest.clone());
+        }
+
+        Ok(installed_files)
+    };
+
+    let result = install();
+
+    fs::remove_dir_all(extractor::long_path(&staging_dir)).ok();
+
+    result
+}
+
+pub async fn install(
+    cask: &cask::Cask,
+    package_name: &str,
+    version: Option<&str>,
+    options: &InstallOption<'_>,
+) -> Result<Installed, Report> {
+    let prepared = prepare(cask, package_name, version, options).await?;
+
+    download(cask, &prepared, options.verbose).await?;
+
+    finish(cask, prepared, options)
+}
+
+// install the packages, the resources are downloaded concurrently
+pub async fn install_many(
+    cask: &cask::Cask,
+    packages: &[(&str, Option<&str>)],
+    options: &InstallOption<'_>,
+    jobs: usize,
+) -> Result<Vec<Installed>, Report> {
+    if options.git_ref.is_some() {
+        return Err(eyre::format_err!(
+            "The option '--ref' can not be used when installing multiple packages"
+        ));
+    }
+
+    let mut prepared_list: Vec<PreparedInstall> = vec![];
+
+    for (package_name, version) in packages {
+        prepared_list.push(prepare(cask, package_name, *version, options).await?);
+    }
+
+    // the overall progress is drawn above the progress bar of each package
+    let overall = downloader::new_overall_bar(
+        prepared_list.len() as u64,
+        format!("Downloading {} packages", prepared_list.len()),
+    );
+
+    stream::iter(prepared_list.iter().map(|prepared| async {
+        download(cask, prepared, options.verbose).await?;
+
+        overall.inc(1);
+
+        Ok::<(), Report>(())
+    }))
+    .buffer_unordered(jobs.max(1))
+    .try_collect::<Vec<()>>()
+    .await?;
+
+    overall.finish_with_message(format!("Downloaded {} packages", prepared_list.len()));
+
+    prepared_list
+        .into_iter()
+        .map(|prepared| finish(cask, prepared, options))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::{
+        cask,
+        install::{get_link_dir, parse_install_args},
+        receipt, symlink,
+    };
+
+    #[test]
+    fn test_parse_install_args() {
+        assert_eq!(
+            parse_install_args(&["github.com/axetroy/dvm", "1.2.0"]),
+            vec![("github.com/axetroy/dvm", Some("1.2.0"))]
+        );
+        assert_eq!(
+            parse_install_args(&["github.com/axetroy/dvm", "github.com/axetroy/gpm.rs@v0.1.0"]),
+            vec![
+                ("github.com/axetroy/dvm", None),
+                ("github.com/axetroy/gpm.rs", Some("v0.1.0"))
+            ]
+        );
+        assert_eq!(
+            parse_install_args(&["https://user@example.com/foo/bar.git"]),
+            vec![("https://user@example.com/foo/bar.git", None)]
+        );
+        assert_eq!(
+            parse_install_args(&[
+                "git@example.com:tool.git",
+                "git@github.com:foo/bar.git@1.0.0"
+            ]),
+            vec![
+                ("git@example.com:tool.git", None),
+                ("git@github.com:foo/bar.git", Some("1.0.0"))
+            ]
+        );
+        assert_eq!(
+            parse_install_args(&["github.com/axetroy/dvm", "^1.2"]),
+            vec![("github.com/axetroy/dvm", Some("^1.2"))]
+        );
+        assert_eq!(
+            parse_install_args(&[
+                "github.com/axetroy/dvm@~1.2.0",
+                "github.com/axetroy/gpm.rs@>=0.1"
+            ]),
+            vec![
+                ("github.com/axetroy/dvm", Some("~1.2.0")),
+                ("github.com/axetroy/gpm.rs", Some(">=0.1"))
+            ]
+        );
+    }
+    #[test]
+    fn test_get_link_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+        let package_dir = c.package_dir("github.com/axetroy/dvm");
+
+        // the package is not installed
+        assert_eq!(get_link_dir(&c, "github.com/axetroy/dvm"), c.bin_dir());
+
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            receipt::path(&package_dir),
+            r#"{"version":"1.0.0","url":"","checksum":null,"created_at":"","bin":[],"symlinks":[],"files":[]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(get_link_dir(&c, "github.com/axetroy/dvm"), c.bin_dir());
+
+        receipt::write_link(
+            &package_dir,
+            symlink::Strategy::Symlink,
+            Some(Path::new("/usr/local/bin")),
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_link_dir(&c, "github.com/axetroy/dvm"),
+            Path::new("/usr/local/bin")
+        );
+    }
+}
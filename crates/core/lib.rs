@@ -0,0 +1,82 @@
+#![deny(warnings)]
+
+//! The engine of Cask, the general distributed binary package manager.
+//!
+//! It parses the formula `Cask.toml`, resolves the version from the tags of repository
+//! or the release channel, and downloads, extracts and installs the package into the root of Cask.
+//! The command line `cask` is a thin wrapper of it.
+//!
+//! - [`cask`]: the root of Cask, eg. `~/.cask`, and the folders of installed packages
+//! - [`formula`]: fetch the formula of package and resolve the version and download URL
+//! - [`provider`]: the sources of formula, the third-party providers are registered by [`provider::register`]
+//! - [`registry`]: the index of build-in formula, the packages are searched and resolved by the short name
+//! - [`tap`]: the formula repositories besides the build-in formula, they are searched by the priority
+//! - [`install`]: install the package, the versions are switched with [`install::activate`]
+//! - [`config`]: the mirrors, proxy, tokens and timeouts of `config.toml`
+//! - [`settings`]: the settings of running Cask, they are resolved once from the flags, environment variables and config
+//!
+//! Nothing is printed to the terminal. The messages are emitted by [`tracing`],
+//! the progress of download is reported to the handler of [`downloader::set_progress_handler`],
+//! and the hooks that require the confirmation are asked by the handler of [`hooker::set_confirm`].
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use cask_core::{cask, config, install, settings};
+//!
+//! # async fn run() -> Result<(), eyre::Report> {
+//! let cask = cask::new(Path::new("/opt/cask"));
+//! let config = config::load(&cask)?;
+//! let settings = settings::resolve(&settings::Flags::default(), &settings::vars(), &config)?;
+//! let cask = cask.with_config(config).with_settings(settings);
+//!
+//! cask.init()?;
+//!
+//! let options = install::InstallOption {
+//!     force: false,
+//!     prerelease: false,
+//!     verbose: false,
+//!     bin_dir: None,
+//!     link_dir: None,
+//!     channel: None,
+//!     git_ref: None,
+//!     keep_archive: false,
+//!     formula: None,
+//! };
+//!
+//! let installed = install::install(&cask, "github.com/axetroy/dvm", Some("1.x"), &options).await?;
+//!
+//! println!("{} {} in {}", installed.name, installed.version, installed.bin_dir.display());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cache;
+pub mod cask;
+pub mod checksum;
+pub mod config;
+pub mod crates;
+pub mod error;
+pub mod forge;
+pub mod formula;
+pub mod hooker;
+pub mod i18n;
+pub mod index;
+pub mod install;
+pub mod lock;
+pub mod migration;
+pub mod project;
+pub mod provider;
+pub mod receipt;
+pub mod registry;
+pub mod release;
+pub mod retry;
+pub mod settings;
+pub mod signature;
+pub mod source;
+pub mod symlink;
+pub mod tap;
+pub mod transaction;
+pub mod util;
+
+pub use downloader;
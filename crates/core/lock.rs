@@ -0,0 +1,107 @@
+#![deny(warnings)]
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use eyre::Report;
+use tracing::info;
+
+// the locks that are held by the current process, the lock of the same file blocks each other even in one process
+static HELD_LOCKS: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+
+// the advisory lock of file, it is released when it goes out of scope or the process exits
+pub struct Lock {
+    path: PathBuf,
+    _file: File,
+}
+
+// acquire the lock, wait for another cask process to release it
+pub fn acquire(path: &Path, name: &str) -> Result<Lock, Report> {
+    {
+        let mut held_locks = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+
+        if held_locks.iter().any(|p| p == path) {
+            return Err(eyre::format_err!(
+                "The {} is being operated by the current process",
+                name
+            ));
+        }
+
+        held_locks.push(path.to_path_buf());
+    }
+
+    let result = lock_file(path, name);
+
+    if result.is_err() {
+        release(path);
+    }
+
+    result.map(|file| Lock {
+        path: path.to_path_buf(),
+        _file: file,
+    })
+}
+
+fn lock_file(path: &Path, name: &str) -> Result<File, Report> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            info!(
+                "Another cask process is running on the {}, waiting for it to finish...",
+                name
+            );
+
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    Ok(file)
+}
+
+fn release(path: &Path) {
+    let mut held_locks = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+
+    held_locks.retain(|p| p != path);
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        release(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock;
+
+    #[test]
+    fn test_acquire() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locks").join("foo.lock");
+
+        let l = lock::acquire(&path, "package 'foo'").unwrap();
+
+        assert!(path.exists());
+
+        // the lock can not be acquired twice in the same process
+        assert!(lock::acquire(&path, "package 'foo'").is_err());
+
+        drop(l);
+
+        lock::acquire(&path, "package 'foo'").unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+#![deny(warnings)]
+
+use crate::{cask, formula, install, lock, receipt, symlink, transaction};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+use tracing::info;
+
+// the version of storage layout, it is increased once the layout is changed
+pub const LAYOUT_VERSION: u32 = 1;
+
+// the migration upgrades the storage layout from the previous version
+struct Migration {
+    version: u32,                                   // the layout version after migration
+    description: &'static str,                      // what is changed
+    migrate: fn(&cask::Cask) -> Result<(), Report>, // upgrade the layout in place
+}
+
+const MIGRATIONS: [Migration; 1] = [Migration {
+    version: 1,
+    description: "install the packages into the folders of version",
+    migrate: migrate_versions_dir,
+}];
+
+// the file that records the layout version, the layout without it is the first version
+fn layout_file(cask: &cask::Cask) -> PathBuf {
+    cask.root_dir().join("layout")
+}
+
+// get the layout version of the cask root
+pub fn get_layout_version(cask: &cask::Cask) -> u32 {
+    fs::read_to_string(layout_file(cask))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+// whether the layout is older than the current version of Cask
+pub fn is_outdated(cask: &cask::Cask) -> bool {
+    get_layout_version(cask) < LAYOUT_VERSION
+}
+
+// upgrade the layout to the current version, return the number of applied migrations
+pub fn migrate(cask: &cask::Cask) -> Result<usize, Report> {
+    let _migration_lock = lock::acquire(&cask.lock_dir().join("migration.lock"), "migration")?;
+
+    // the layout may be upgraded by another cask process while waiting for the lock
+    let current = get_layout_version(cask);
+
+    let mut applied = 0;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        info!(
+            "Migrating the storage to layout {}: {}",
+            migration.version, migration.description
+        );
+
+        (migration.migrate)(cask)?;
+
+        fs::write(layout_file(cask), migration.version.to_string())?;
+
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+// mark the new cask root as the current layout, there is nothing to migrate
+pub fn init(cask: &cask::Cask) -> Result<(), Report> {
+    if !layout_file(cask).exists() && is_empty_dir(&cask.formula_dir())? {
+        fs::write(layout_file(cask), LAYOUT_VERSION.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn is_empty_dir(dir: &Path) -> Result<bool, Report> {
+    if !dir.exists() {
+        return Ok(true);
+    }
+
+    Ok(fs::read_dir(dir)?.next().is_none())
+}
+
+// layout 0 -> 1: the executable is moved from 'bin' into 'versions/<version>/bin' with the auxiliary files,
+// and the receipt is written for it
+fn migrate_versions_dir(cask: &cask::Cask) -> Result<(), Report> {
+    for entry in fs::read_dir(cask.formula_dir())? {
+        let entry = entry?;
+        let package_dir = entry.path();
+
+        if !package_dir.is_dir() {
+            continue;
+        }
+
+        let _package_lock = lock::acquire(
+            &cask.package_lock_file(&entry.file_name().to_string_lossy()),
+            &format!("package folder '{}'", package_dir.display()),
+        )?;
+
+        let package_formula = match formula::new(&package_dir.join("Cask.toml"), "") {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+
+        let cask_info = match &package_formula.cask {
+            Some(c) => c,
+            None => continue,
+        };
+
+        #[cfg(target_family = "unix")]
+        let executable_name = package_formula.package.bin.clone();
+        #[cfg(target_family = "windows")]
+        let executable_name = format!("{}.exe", &package_formula.package.bin);
+
+        let legacy_file_path = package_dir.join("bin").join(&executable_name);
+
+        // it is installed into the folder of version already
+        if !legacy_file_path.exists() {
+            continue;
+        }
+
+        let name = &package_formula.package.name;
+        let installed_dir = cask.package_installed_dir(name, &cask_info.version);
+        let output_file_path = installed_dir.join("bin").join(&executable_name);
+        let files = cask_info.files.clone().unwrap_or_default();
+
+        let _lock = cask.lock()?;
+
+        let mut transaction = transaction::new(&cask.package_version_dir(name).join(".backup"))?;
+
+        let mut apply = || -> Result<(), Report> {
+            transaction.replace(&legacy_file_path, &output_file_path)?;
+
+            for file in &files {
+                if package_dir.join(file).exists() {
+                    transaction.replace(&package_dir.join(file), &installed_dir.join(file))?;
+                }
+            }
+
+            let version_file_path = installed_dir.join("Cask.toml");
+
+            transaction.backup(&version_file_path)?;
+
+            fs::copy(package_dir.join("Cask.toml"), &version_file_path)?;
+
+            // the resolved URL is not recorded by the old version, it is rendered from the formula again
+            let url = package_formula
+                .get_current_download_url(&cask_info.version, cask_info.channel.as_deref())
+                .map(|target| target.url)
+                .unwrap_or_default();
+
+            transaction.backup(&receipt::path(&installed_dir))?;
+
+            receipt::write(
+                &installed_dir,
+                &receipt::Receipt {
+                    name: cask_info.name.clone(),
+                    version: cask_info.version.clone(),
+                    url,
+                    checksum: None,
+                    created_at: cask_info.created_at.clone(),
+                    bin: vec![output_file_path.clone()],
+                    symlinks: symlink::get_link_files(
+                        &cask.bin_dir().join(&package_formula.package.bin),
+                    ),
+                    files: files.iter().map(|file| installed_dir.join(file)).collect(),
+                    link: None,
+                    link_dir: None,
+                },
+            )?;
+
+            install::activate(
+                cask,
+                name,
+                &package_formula.package.bin,
+                &installed_dir,
+                None,
+                false,
+                &mut transaction,
+            )
+        };
+
+        match apply() {
+            Ok(()) => transaction.commit()?,
+            Err(e) => {
+                transaction.rollback()?;
+                return Err(e);
+            }
+        }
+
+        fs::remove_dir(package_dir.join("bin")).ok();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{cask, migration, receipt};
+
+    #[test]
+    fn test_migrate_versions_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+
+        let package_dir = c.package_dir("github.com/axetroy/foo");
+
+        fs::create_dir_all(package_dir.join("bin")).unwrap();
+        fs::create_dir_all(package_dir.join("share")).unwrap();
+        fs::write(
+            package_dir
+                .join("bin")
+                .join(if cfg!(windows) { "foo.exe" } else { "foo" }),
+            "foo",
+        )
+        .unwrap();
+        fs::write(package_dir.join("share").join("foo.1"), "man").unwrap();
+        fs::write(
+            package_dir.join("Cask.toml"),
+            r#"
+[cask]
+name = "github.com/axetroy/foo"
+created_at = "2022-01-01T00:00:00"
+version = "1.0.0"
+repository = "https://github.com/axetroy/foo"
+files = ["share/foo.1"]
+
+[package]
+name = "github.com/axetroy/foo"
+bin = "foo"
+repository = "https://github.com/axetroy/foo"
+description = "foo"
+
+[linux]
+x86_64 = "https://github.com/axetroy/foo/releases/download/v{version}/foo_linux_amd64.tar.gz"
+"#,
+        )
+        .unwrap();
+
+        assert!(migration::is_outdated(&c));
+
+        assert_eq!(migration::migrate(&c).unwrap(), 1);
+
+        assert!(!migration::is_outdated(&c));
+
+        let installed_dir = c.package_installed_dir("github.com/axetroy/foo", "1.0.0");
+
+        assert!(!package_dir.join("bin").exists());
+        assert!(!package_dir.join("share").join("foo.1").exists());
+        assert!(installed_dir.join("share").join("foo.1").exists());
+        assert!(installed_dir.join("Cask.toml").exists());
+        assert!(c.bin_dir().join("foo").exists());
+
+        let r = receipt::read(&package_dir).unwrap().unwrap();
+
+        assert_eq!(r.version, "1.0.0");
+        assert_eq!(r.files, vec![installed_dir.join("share/foo.1")]);
+
+        // nothing to migrate
+        assert_eq!(migration::migrate(&c).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_init() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+        migration::init(&c).unwrap();
+
+        assert!(!migration::is_outdated(&c));
+    }
+}
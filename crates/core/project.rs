@@ -0,0 +1,55 @@
+#![deny(warnings)]
+
+use std::path::{Path, PathBuf};
+
+// the folder of project-local packages, it is located in the root of project
+pub const LOCAL_DIR_NAME: &str = ".cask";
+
+// find the nearest project-local root from the folder up to the file system root.
+// the global root is skipped, eg. '$HOME/.cask' is found in the sub-folder of '$HOME'
+pub fn find_local_root(cwd: &Path, global_root: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(LOCAL_DIR_NAME))
+        .find(|root| root.is_dir() && root != global_root)
+}
+
+// the project-local root, it is created in the current folder if it is not found
+pub fn get_local_root(cwd: &Path, global_root: &Path) -> PathBuf {
+    find_local_root(cwd, global_root).unwrap_or_else(|| cwd.join(LOCAL_DIR_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::project;
+
+    #[test]
+    fn test_find_local_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("home");
+        let project_dir = home.join("project");
+        let sub_dir = project_dir.join("src").join("foo");
+        let global_root = home.join(".cask");
+
+        fs::create_dir_all(&sub_dir).unwrap();
+        fs::create_dir_all(&global_root).unwrap();
+
+        assert_eq!(project::find_local_root(&sub_dir, &global_root), None);
+        assert_eq!(
+            project::get_local_root(&sub_dir, &global_root),
+            sub_dir.join(".cask")
+        );
+
+        fs::create_dir_all(project_dir.join(".cask")).unwrap();
+
+        assert_eq!(
+            project::find_local_root(&sub_dir, &global_root),
+            Some(project_dir.join(".cask"))
+        );
+        assert_eq!(
+            project::get_local_root(&sub_dir, &global_root),
+            project_dir.join(".cask")
+        );
+    }
+}
@@ -0,0 +1,380 @@
+#![deny(warnings)]
+
+use crate::{
+    cask, crates,
+    error::CaskError,
+    formula::{self, Formula},
+    registry,
+    retry::retry_blocking,
+    util,
+};
+
+use std::{
+    path::{Component, Path},
+    sync::{Arc, RwLock},
+};
+
+use eyre::Report;
+use futures::{future::BoxFuture, FutureExt};
+use git::GitError;
+use tracing::info;
+use url::Url;
+
+// the options of fetching the formula
+pub struct FetchOption<'a> {
+    pub git_ref: Option<&'a str>, // the branch, tag or commit of the formula repository
+    pub temp: bool,               // fetch into the temp folder instead of the package folder
+    pub verbose: bool,            // print verbose information
+}
+
+// the provider acquires the formula of package, eg. from the git repository, the build-in formula or the registry.
+// the providers are tried in order, the first one that returns the formula wins
+pub trait SourceProvider: Send + Sync {
+    // the name of provider in the logs, eg. 'git'
+    fn name(&self) -> &str;
+
+    // fetch the formula, it is None if the package is not provided by it
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>>;
+}
+
+// the providers registered by the program that embeds Cask, they are tried before the build-in providers
+static PROVIDERS: RwLock<Vec<Arc<dyn SourceProvider>>> = RwLock::new(vec![]);
+
+// register the provider, eg. the formulas of the private registry
+pub fn register(provider: Arc<dyn SourceProvider>) {
+    PROVIDERS
+        .write()
+        .expect("lock the providers fail")
+        .push(provider);
+}
+
+// the registered providers, then the build-in ones
+pub fn list() -> Vec<Arc<dyn SourceProvider>> {
+    let mut providers = PROVIDERS.read().expect("lock the providers fail").clone();
+
+    providers.extend([
+        Arc::new(LocalPathProvider) as Arc<dyn SourceProvider>,
+        Arc::new(RegistryProvider),
+        Arc::new(CacheProvider),
+        Arc::new(CratesProvider),
+        Arc::new(BuildInProvider),
+        Arc::new(GitProvider),
+    ]);
+
+    providers
+}
+
+// the formula file or the folder that contains it, eg. './Cask.toml' or '/path/to/project'
+pub struct LocalPathProvider;
+
+impl SourceProvider for LocalPathProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        _cask: &'a cask::Cask,
+        package_name: &'a str,
+        _option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            let path = Path::new(package_name);
+
+            // the package name like 'github.com/axetroy/dvm' is not a path, even if the folder exists
+            let is_path = path.is_absolute()
+                || matches!(
+                    path.components().next(),
+                    Some(Component::CurDir | Component::ParentDir)
+                );
+
+            if !is_path || !path.exists() {
+                return Ok(None);
+            }
+
+            let cask_file_path = if path.is_dir() {
+                formula::find_formula_file(path).ok_or_else(|| {
+                    eyre::format_err!("can not found the formula file in '{}'", path.display())
+                })?
+            } else {
+                path.to_path_buf()
+            };
+
+            formula::new(&cask_file_path, "").map(Some)
+        }
+        .boxed()
+    }
+}
+
+// the short name in the index of build-in formula, eg. 'ripgrep' -> 'github.com/BurntSushi/ripgrep'.
+// the formula of full name is fetched by the other providers, the index is read locally so it works in offline mode
+pub struct RegistryProvider;
+
+impl SourceProvider for RegistryProvider {
+    fn name(&self) -> &str {
+        "registry"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            if !registry::is_short_name(package_name) {
+                return Ok(None);
+            }
+
+            let entries = registry::load(cask)?;
+
+            let entry = match registry::resolve(&entries, package_name)? {
+                Some(entry) => entry,
+                None => return Ok(None),
+            };
+
+            info!("'{}' is resolved to '{}'", package_name, entry.name);
+
+            formula::fetch(
+                cask,
+                &entry.name,
+                option.git_ref,
+                option.temp,
+                option.verbose,
+            )
+            .await
+            .map(Some)
+        }
+        .boxed()
+    }
+}
+
+// the build-in formula or the formula repository cloned before, it is used in offline mode only
+pub struct CacheProvider;
+
+impl SourceProvider for CacheProvider {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        _option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            if !cask.settings().offline {
+                return Ok(None);
+            }
+
+            formula::fetch_from_cache(cask, package_name).map(Some)
+        }
+        .boxed()
+    }
+}
+
+// the formula of crate is generated from its binstall metadata, eg. 'crates.io/cargo-binstall'
+pub struct CratesProvider;
+
+impl SourceProvider for CratesProvider {
+    fn name(&self) -> &str {
+        "crates"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            match package_name.strip_prefix(&format!("{}/", crates::HOST)) {
+                Some(crate_name) => crates::fetch(cask, crate_name, option.verbose)
+                    .await
+                    .map(Some),
+                None => Ok(None),
+            }
+        }
+        .boxed()
+    }
+}
+
+// the formula in the build-in formula repository and the taps, eg. '~/.cask/build-in/github.com/axetroy/dvm'
+pub struct BuildInProvider;
+
+impl SourceProvider for BuildInProvider {
+    fn name(&self) -> &str {
+        "build-in"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            // the build-in formula does not have the history of its repository
+            if option.git_ref.is_some() || is_repository_url(package_name) {
+                return Ok(None);
+            }
+
+            formula::find_package_in_taps(cask, package_name)
+        }
+        .boxed()
+    }
+}
+
+// the formula in the git repository of package, eg. 'https://github.com/axetroy/dvm.git'
+pub struct GitProvider;
+
+impl SourceProvider for GitProvider {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn fetch<'a>(
+        &'a self,
+        cask: &'a cask::Cask,
+        package_name: &'a str,
+        option: &'a FetchOption<'a>,
+    ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+        async move {
+            // the short name is not the address of repository, it is resolved by the index of build-in formula
+            if registry::is_short_name(package_name) {
+                return Ok(None);
+            }
+
+            let is_url = is_repository_url(package_name);
+
+            let git_url = match Url::parse(package_name) {
+                _ if util::is_ssh_url(package_name) => package_name.to_string(),
+                Ok(u) if u.scheme() == "http" || u.scheme() == "https" => u.to_string(),
+                Ok(u) => {
+                    return Err(eyre::format_err!(
+                        "Not support the protocol '{}' of package address.",
+                        u.scheme()
+                    ))
+                }
+                Err(_) => formula::get_formula_git_url(package_name),
+            };
+
+            let config = cask.config();
+            let repo = cask.settings().get_git_repository(config, &git_url)?;
+            let is_repo_exist =
+                retry_blocking(&cask.settings().get_retry_policy(), GitError::is_transient, move || {
+                    repo.is_exist()
+                })
+                .await?;
+
+            if !is_repo_exist {
+                let msg = if is_url {
+                    format!(
+                        "The package '{}' does not exist, or it is a private repository without credentials!",
+                        package_name
+                    )
+                } else {
+                    format!(
+                        "can not found package {}, or it is a private repository without credentials",
+                        package_name
+                    )
+                };
+
+                return Err(CaskError::PackageNotFound(msg).into());
+            }
+
+            formula::fetch_with_git_url(
+                cask,
+                package_name,
+                &git_url,
+                option.git_ref,
+                option.temp,
+                option.verbose,
+            )
+            .await
+            .map(Some)
+        }
+        .boxed()
+    }
+}
+
+// the package is the URL of repository, eg. 'https://github.com/axetroy/dvm' or 'git@github.com:axetroy/dvm.git'
+fn is_repository_url(package_name: &str) -> bool {
+    util::is_ssh_url(package_name) || Url::parse(package_name).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use eyre::Report;
+    use futures::{future::BoxFuture, FutureExt};
+
+    use crate::{
+        cask,
+        formula::{self, Formula},
+        provider::{self, FetchOption, SourceProvider},
+    };
+
+    // the formulas of the private registry are the fixtures
+    struct FixtureProvider;
+
+    impl SourceProvider for FixtureProvider {
+        fn name(&self) -> &str {
+            "fixture"
+        }
+
+        fn fetch<'a>(
+            &'a self,
+            _cask: &'a cask::Cask,
+            package_name: &'a str,
+            _option: &'a FetchOption<'a>,
+        ) -> BoxFuture<'a, Result<Option<Formula>, Report>> {
+            async move {
+                if package_name != "registry.example.com/gpm" {
+                    return Ok(None);
+                }
+
+                formula::new(
+                    &std::env::current_dir()?
+                        .join("fixtures")
+                        .join("config")
+                        .join("default_Cask.toml"),
+                    "",
+                )
+                .map(Some)
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_by_provider() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+
+        provider::register(Arc::new(FixtureProvider));
+
+        let f = formula::fetch(&c, "registry.example.com/gpm", None, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(f.package.name, "github.com/axetroy/gpm.rs");
+
+        // the formula file of the local path
+        let f = formula::fetch(&c, "./fixtures/config/simple_Cask.toml", None, true, false)
+            .await
+            .unwrap();
+
+        assert_eq!(f.package.name, "github.com/axetroy/gpm.rs");
+        assert_eq!(f.repository, "");
+    }
+}
@@ -0,0 +1,166 @@
+#![deny(warnings)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::symlink;
+
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+
+// the file name of receipt in the package folder
+const RECEIPT_FILE_NAME: &str = "receipt.json";
+
+// the files that are written by an install, so that they can be removed, verified or compared later
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Receipt {
+    #[serde(default)]
+    pub name: String, // the canonical name of package, it may be installed by the alias. it is empty for the old receipt
+    pub version: String,          // the installed version
+    pub url: String,              // the resolved URL of resource
+    pub checksum: Option<String>, // the SHA256 of resource, it is unknown if the archive is not kept
+    pub created_at: String,       // the time of install
+    pub bin: Vec<PathBuf>,        // the executables
+    pub symlinks: Vec<PathBuf>,   // the links in the bin folder of Cask
+    pub files: Vec<PathBuf>,      // the auxiliary files of resource
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<symlink::Strategy>, // how the executable is linked when it is activated, it is unknown for the old receipt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_dir: Option<PathBuf>, // the folder of `--link-dir` that the executable is linked into, it is the bin folder of Cask if not set
+}
+
+// the receipt file of package
+pub fn path(package_dir: &Path) -> PathBuf {
+    package_dir.join(RECEIPT_FILE_NAME)
+}
+
+// write the receipt into the package folder, the receipt of previous install is replaced
+pub fn write(package_dir: &Path, receipt: &Receipt) -> Result<(), Report> {
+    fs::write(path(package_dir), serde_json::to_string_pretty(receipt)?)?;
+
+    Ok(())
+}
+
+// read the receipt of package, the package installed by the old version of Cask does not have it
+pub fn read(package_dir: &Path) -> Result<Option<Receipt>, Report> {
+    let content = match fs::read_to_string(path(package_dir)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let receipt = serde_json::from_str(&content).map_err(|e| {
+        eyre::format_err!(
+            "can not parse receipt '{}': {}",
+            path(package_dir).display(),
+            e
+        )
+    })?;
+
+    Ok(Some(receipt))
+}
+
+// record how and where the executable of active version is linked, the symlink may fall back to the hard link or the copy
+pub fn write_link(
+    package_dir: &Path,
+    link: symlink::Strategy,
+    link_dir: Option<&Path>,
+) -> Result<(), Report> {
+    if let Some(mut receipt) = read(package_dir)? {
+        receipt.link = Some(link);
+        receipt.link_dir = link_dir.map(Path::to_path_buf);
+
+        write(package_dir, &receipt)?;
+    }
+
+    Ok(())
+}
+
+impl Receipt {
+    // all the files that are recorded
+    pub fn all_files(&self) -> Vec<&PathBuf> {
+        self.bin
+            .iter()
+            .chain(self.symlinks.iter())
+            .chain(self.files.iter())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::{receipt, symlink};
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(receipt::read(dir.path()).unwrap().is_none());
+
+        let r = receipt::Receipt {
+            name: "github.com/axetroy/foo".to_string(),
+            version: "1.0.0".to_string(),
+            url: "https://example.com/foo_linux_amd64.tar.gz".to_string(),
+            checksum: Some("abc".to_string()),
+            created_at: "2022-01-01T00:00:00".to_string(),
+            bin: vec![PathBuf::from("/cask/formula/foo/bin/foo")],
+            symlinks: vec![PathBuf::from("/cask/bin/foo")],
+            files: vec![PathBuf::from("/cask/formula/foo/share/man/foo.1")],
+            link: None,
+            link_dir: None,
+        };
+
+        receipt::write(dir.path(), &r).unwrap();
+
+        let saved = receipt::read(dir.path()).unwrap().unwrap();
+
+        assert_eq!(saved, r);
+        assert_eq!(
+            saved.all_files(),
+            vec![
+                &PathBuf::from("/cask/formula/foo/bin/foo"),
+                &PathBuf::from("/cask/bin/foo"),
+                &PathBuf::from("/cask/formula/foo/share/man/foo.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_link() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // the receipt is written by the old version of Cask
+        std::fs::write(
+            dir.path().join("receipt.json"),
+            r#"{"version":"1.0.0","url":"","checksum":null,"created_at":"","bin":[],"symlinks":[],"files":[]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(receipt::read(dir.path()).unwrap().unwrap().link, None);
+
+        receipt::write_link(
+            dir.path(),
+            symlink::Strategy::Copy,
+            Some(Path::new("/usr/local/bin")),
+        )
+        .unwrap();
+
+        let saved = receipt::read(dir.path()).unwrap().unwrap();
+
+        assert_eq!(saved.link, Some(symlink::Strategy::Copy));
+        assert_eq!(saved.link_dir, Some(PathBuf::from("/usr/local/bin")));
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::fs::write(dir.path().join("receipt.json"), "{").unwrap();
+
+        assert!(receipt::read(dir.path()).is_err());
+    }
+}
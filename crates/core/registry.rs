@@ -0,0 +1,318 @@
+#![deny(warnings)]
+
+use crate::{cask, formula, tap};
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+// the index of the build-in formula repository, it is synced by 'cask remote sync'
+const INDEX_FILE_NAME: &str = "index.toml";
+
+// the metadata of package in the index, so that the packages are searched without fetching their repositories.
+// the unknown fields are ignored, so that the index can be extended for the newer version of Cask
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String, // the full name of package. eg. github.com/BurntSushi/ripgrep
+    #[serde(default)]
+    pub bin: String, // the executable name. eg. rg
+    #[serde(default)]
+    pub aliases: Vec<String>, // the short names besides the last part of name. eg. ['ripgrep']
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub popularity: u64, // eg. the stars of repository, the popular package is listed first
+}
+
+#[derive(Deserialize)]
+struct Index {
+    #[serde(default)]
+    package: Vec<Entry>,
+}
+
+// the index file in the build-in formula repository. eg. ~/.cask/build-in/index.toml
+pub fn path(cask: &cask::Cask) -> PathBuf {
+    cask.build_in_formula_dir().join(INDEX_FILE_NAME)
+}
+
+// the name without the host and owner is a short name. eg. 'ripgrep', but not './ripgrep' or 'crates:ripgrep'
+pub fn is_short_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\', ':', '@'])
+        && !Path::new(name).is_absolute()
+}
+
+// the entries of the build-in formula and the taps, the package of the source of higher priority is kept.
+// the entries are collected from the formula files if the index of source does not exist.
+// it is empty if the build-in formula has not been synced
+pub fn load(cask: &cask::Cask) -> Result<Vec<Entry>, Report> {
+    let config = cask.config();
+
+    let mut names = HashSet::new();
+    let mut entries = vec![];
+
+    for source in tap::list(cask, config)? {
+        for entry in load_source(&source.dir)? {
+            if names.insert(entry.name.clone()) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn load_source(dir: &Path) -> Result<Vec<Entry>, Report> {
+    let index_file_path = dir.join(INDEX_FILE_NAME);
+
+    if index_file_path.exists() {
+        let content = fs::read_to_string(&index_file_path)?;
+
+        let index: Index = toml::from_str(&content)
+            .map_err(|e| eyre::format_err!("parse '{}' fail: {}", index_file_path.display(), e))?;
+
+        return Ok(index.package);
+    }
+
+    let mut entries = vec![];
+
+    if dir.exists() {
+        collect_formula(dir, &mut entries)?;
+    }
+
+    Ok(entries)
+}
+
+fn collect_formula(dir: &Path, entries: &mut Vec<Entry>) -> Result<(), Report> {
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let p = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if p.is_dir() {
+            if !file_name.starts_with('.') {
+                collect_formula(&p, entries)?;
+            }
+        } else if formula::FORMULA_FILE_NAMES.contains(&file_name.as_str()) {
+            match formula::new(&p, "") {
+                Ok(f) => entries.push(Entry {
+                    name: f.package.name,
+                    bin: f.package.bin,
+                    aliases: f.package.aliases.unwrap_or_default(),
+                    description: f.package.description,
+                    popularity: 0,
+                }),
+                Err(e) => debug!("skip the invalid formula '{}': {}", p.display(), e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Entry {
+    // the last part of name, the executable name and the aliases
+    fn short_names(&self) -> impl Iterator<Item = &str> {
+        self.name
+            .rsplit('/')
+            .next()
+            .into_iter()
+            .chain([self.bin.as_str()])
+            .chain(self.aliases.iter().map(|a| a.as_str()))
+            .filter(|n| !n.is_empty())
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        self.short_names().any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+// resolve the short name to the package. eg. 'ripgrep' or 'rg' -> 'github.com/BurntSushi/ripgrep'.
+// the short name that matches several packages must be replaced by the full name
+pub fn resolve<'a>(entries: &'a [Entry], name: &str) -> Result<Option<&'a Entry>, Report> {
+    let candidates = entries
+        .iter()
+        .filter(|e| e.is_named(name))
+        .collect::<Vec<&Entry>>();
+
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [entry] => Ok(Some(entry)),
+        _ => Err(eyre::format_err!(
+            "'{}' matches several packages: {}. Use the full name instead",
+            name,
+            candidates
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )),
+    }
+}
+
+// the packages that match the keyword, the package whose short name is the keyword is listed first,
+// then the name and the description. the popular package is listed first in each group
+pub fn search<'a>(entries: &'a [Entry], keyword: &str) -> Vec<&'a Entry> {
+    let keyword = keyword.trim().to_lowercase();
+
+    let mut matched = entries
+        .iter()
+        .filter_map(|e| {
+            let rank = if e.is_named(&keyword) {
+                0
+            } else if e.name.to_lowercase().contains(&keyword)
+                || e.short_names().any(|n| n.to_lowercase().contains(&keyword))
+            {
+                1
+            } else if e.description.to_lowercase().contains(&keyword) {
+                2
+            } else {
+                return None;
+            };
+
+            Some((rank, e))
+        })
+        .collect::<Vec<(u8, &Entry)>>();
+
+    matched.sort_by_key(|(rank, e)| (*rank, Reverse(e.popularity), e.name.clone()));
+
+    matched.into_iter().map(|(_, e)| e).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{cask, config, registry};
+
+    #[test]
+    fn test_is_short_name() {
+        assert!(registry::is_short_name("ripgrep"));
+        assert!(registry::is_short_name("rg"));
+        assert!(!registry::is_short_name(""));
+        assert!(!registry::is_short_name("github.com/BurntSushi/ripgrep"));
+        assert!(!registry::is_short_name("./Cask.toml"));
+        assert!(!registry::is_short_name("gitlab:group/project"));
+        assert!(!registry::is_short_name("git@github.com:foo/bar.git"));
+    }
+
+    #[test]
+    fn test_load_and_search() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        // the build-in formula has not been synced
+        assert!(registry::load(&c).unwrap().is_empty());
+
+        fs::create_dir_all(c.build_in_formula_dir()).unwrap();
+        fs::write(
+            registry::path(&c),
+            r#"
+[[package]]
+name = "github.com/BurntSushi/ripgrep"
+bin = "rg"
+aliases = ["ripgrep"]
+description = "Recursively search directories for a regex pattern"
+popularity = 45000
+
+[[package]]
+name = "github.com/sharkdp/fd"
+bin = "fd"
+description = "A simple, fast and user-friendly alternative to find"
+popularity = 33000
+
+[[package]]
+name = "github.com/foo/rg"
+bin = "rg-foo"
+description = "Another grep"
+updated_at = "2024-01-01"
+"#,
+        )
+        .unwrap();
+
+        let entries = registry::load(&c).unwrap();
+
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(
+            registry::resolve(&entries, "RipGrep")
+                .unwrap()
+                .map(|e| e.name.as_str()),
+            Some("github.com/BurntSushi/ripgrep")
+        );
+        assert_eq!(
+            registry::resolve(&entries, "fd")
+                .unwrap()
+                .map(|e| e.name.as_str()),
+            Some("github.com/sharkdp/fd")
+        );
+        assert!(registry::resolve(&entries, "bat").unwrap().is_none());
+        // both the executable of ripgrep and the repository of foo are named 'rg'
+        assert!(registry::resolve(&entries, "rg").is_err());
+
+        assert_eq!(
+            registry::search(&entries, "grep")
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["github.com/BurntSushi/ripgrep", "github.com/foo/rg"]
+        );
+        assert_eq!(
+            registry::search(&entries, "find")
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["github.com/sharkdp/fd"]
+        );
+
+        // the package of tap is merged, the build-in formula is kept for the same package
+        let tap_dir = root.path().join("corp");
+
+        fs::create_dir_all(&tap_dir).unwrap();
+        fs::write(
+            tap_dir.join("index.toml"),
+            r#"
+[[package]]
+name = "github.com/sharkdp/fd"
+description = "The fork of company"
+
+[[package]]
+name = "git.corp.example/tools/deploy"
+bin = "deploy"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            c.config_file(),
+            format!(
+                "[[taps]]\nname = \"corp\"\npath = {:?}\n",
+                tap_dir.display().to_string()
+            ),
+        )
+        .unwrap();
+
+        let c = cask::new(root.path()).with_config(config::load(&c).unwrap());
+        let entries = registry::load(&c).unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            registry::resolve(&entries, "fd")
+                .unwrap()
+                .map(|e| e.description.as_str()),
+            Some("A simple, fast and user-friendly alternative to find")
+        );
+        assert_eq!(
+            registry::resolve(&entries, "deploy")
+                .unwrap()
+                .map(|e| e.name.as_str()),
+            Some("git.corp.example/tools/deploy")
+        );
+    }
+}
@@ -0,0 +1,346 @@
+#![deny(warnings)]
+
+use crate::{
+    cache, cask,
+    config::Config,
+    forge::{self, Forge},
+    formula::Formula,
+};
+
+use eyre::Report;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::{debug, trace};
+
+// the asset of GitHub/GitLab/Gitea release or Bitbucket downloads
+#[derive(Debug, PartialEq)]
+pub struct ReleaseAsset {
+    pub name: String, // the file name of asset
+    pub url: String,  // the download url of asset
+}
+
+#[derive(Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubRelease {
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssets {
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLink {
+    href: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLinks {
+    #[serde(rename = "self")]
+    self_link: BitbucketLink,
+}
+
+#[derive(Deserialize)]
+struct BitbucketDownload {
+    name: String,
+    links: BitbucketLinks,
+}
+
+#[derive(Deserialize)]
+struct BitbucketDownloads {
+    values: Vec<BitbucketDownload>,
+}
+
+// the API URL of the release of tag, return None if the repository is not on a known code hosting service.
+// the API of the self-hosted instance or mirror is used if the base is provided. eg. https://git.corp.example/api/v3
+fn get_release_api_url(
+    repository: &str,
+    api_base: Option<&str>,
+    tag: &str,
+) -> Option<(Forge, String)> {
+    // the API of the repository that is cloned over SSH is still served over HTTPS
+    let mut repo = forge::parse(repository)?;
+
+    if let Some(base) = api_base {
+        repo.api = base.trim_end_matches('/').to_string();
+    }
+
+    Some((repo.forge, repo.release_api_url(tag)))
+}
+
+fn parse_assets(forge: &Forge, content: &str) -> Result<Vec<ReleaseAsset>, Report> {
+    let assets = match forge {
+        // the release of Gitea is compatible with GitHub
+        Forge::GitHub | Forge::Gitea => serde_json::from_str::<GitHubRelease>(content)?
+            .assets
+            .into_iter()
+            .map(|a| ReleaseAsset {
+                name: a.name,
+                url: a.browser_download_url,
+            })
+            .collect(),
+        Forge::GitLab => serde_json::from_str::<GitLabRelease>(content)?
+            .assets
+            .links
+            .into_iter()
+            .map(|l| ReleaseAsset {
+                name: l.name,
+                url: l.direct_asset_url.unwrap_or(l.url),
+            })
+            .collect(),
+        Forge::Bitbucket => serde_json::from_str::<BitbucketDownloads>(content)?
+            .values
+            .into_iter()
+            .map(|d| ReleaseAsset {
+                name: d.name,
+                url: d.links.self_link.href,
+            })
+            .collect(),
+    };
+
+    Ok(assets)
+}
+
+// find the only one asset that matches the pattern
+fn match_asset(assets: Vec<ReleaseAsset>, pattern: &Regex) -> Result<ReleaseAsset, Report> {
+    let names = assets
+        .iter()
+        .map(|a| a.name.clone())
+        .collect::<Vec<String>>();
+
+    let mut matched = assets
+        .into_iter()
+        .filter(|a| {
+            let is_match = pattern.is_match(&a.name);
+
+            trace!("the asset '{}' matches '{}': {}", a.name, pattern, is_match);
+
+            is_match
+        })
+        .collect::<Vec<ReleaseAsset>>();
+
+    debug!(
+        "{} of {} assets match the pattern '{}'",
+        matched.len(),
+        names.len(),
+        pattern
+    );
+
+    match matched.len() {
+        0 => Err(eyre::format_err!(
+            "there is no asset matches the pattern '{}', the available assets: {}",
+            pattern,
+            names.join(", ")
+        )),
+        1 => {
+            let asset = matched.remove(0);
+
+            debug!("pick the asset '{}': {}", asset.name, asset.url);
+
+            Ok(asset)
+        }
+        _ => Err(eyre::format_err!(
+            "there are multiple assets match the pattern '{}': {}",
+            pattern,
+            matched
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )),
+    }
+}
+
+// resolve the asset of the version from the release API of GitHub/GitLab/Gitea or the downloads of Bitbucket
+pub async fn resolve_asset(
+    cask: &cask::Cask,
+    config: &Config,
+    package_formula: &Formula,
+    version: &str,
+    pattern: &str,
+    is_verbose: bool,
+) -> Result<ReleaseAsset, Report> {
+    let re = Regex::new(pattern)
+        .map_err(|e| eyre::format_err!("invalid asset_pattern '{}': {}", pattern, e))?;
+
+    let repository = &package_formula.package.repository;
+
+    for tag in package_formula.get_version_tags(cask, version).await? {
+        let api_base = config.get_api_base(repository);
+
+        let (provider, api_url) = get_release_api_url(repository, api_base.as_deref(), &tag)
+            .ok_or_else(|| {
+            eyre::format_err!(
+                "the release assets are only supported for GitHub/GitLab/Bitbucket/Gitea repository, but got '{}'",
+                repository
+            )
+        })?;
+
+        let api_url = config.rewrite_url(&api_url);
+        let option = cask
+            .settings()
+            .get_download_option(config, &api_url, is_verbose);
+
+        debug!("fetch the release of tag '{}' from {}", tag, api_url);
+
+        let content = cache::fetch_text(cask, &api_url, &option, cask.settings().cache_ttl).await?;
+
+        // try the next tag if the release does not exist
+        if let Some(content) = content {
+            let assets = parse_assets(&provider, &content)
+                .map_err(|e| eyre::format_err!("parse the release of '{}' fail: {}", tag, e))?;
+
+            return match_asset(assets, &re).map_err(|e| {
+                eyre::format_err!("resolve the asset of release '{}' fail: {}", tag, e)
+            });
+        }
+    }
+
+    Err(eyre::format_err!(
+        "can not find the release of version '{}' in '{}'",
+        version,
+        repository
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::{
+        forge::Forge,
+        release::{get_release_api_url, match_asset, parse_assets, ReleaseAsset},
+    };
+
+    #[test]
+    fn test_get_release_api_url() {
+        assert_eq!(
+            get_release_api_url("https://github.com/axetroy/dvm.git", None, "v1.0.0"),
+            Some((
+                Forge::GitHub,
+                "https://api.github.com/repos/axetroy/dvm/releases/tags/v1.0.0".to_string()
+            ))
+        );
+        assert_eq!(
+            get_release_api_url("https://gitlab.com/group/sub/tool", None, "v1.0.0"),
+            Some((
+                Forge::GitLab,
+                "https://gitlab.com/api/v4/projects/group%2Fsub%2Ftool/releases/v1.0.0".to_string()
+            ))
+        );
+        assert_eq!(
+            get_release_api_url("git@github.com:axetroy/dvm.git", None, "v1.0.0"),
+            Some((
+                Forge::GitHub,
+                "https://api.github.com/repos/axetroy/dvm/releases/tags/v1.0.0".to_string()
+            ))
+        );
+        assert_eq!(
+            get_release_api_url("https://codeberg.org/foo/bar", None, "v1.0.0"),
+            Some((
+                Forge::Gitea,
+                "https://codeberg.org/api/v1/repos/foo/bar/releases/tags/v1.0.0".to_string()
+            ))
+        );
+        assert_eq!(
+            get_release_api_url(
+                "https://github.com/axetroy/dvm",
+                Some("https://git.corp.example/api/v3/"),
+                "v1.0.0"
+            ),
+            Some((
+                Forge::GitHub,
+                "https://git.corp.example/api/v3/repos/axetroy/dvm/releases/tags/v1.0.0"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            get_release_api_url("https://example.com/foo/bar", None, "v1.0.0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_and_match_assets() {
+        let content = r#"{
+            "tag_name": "v1.0.0",
+            "assets": [
+                { "name": "tool_linux_amd64.tar.gz", "browser_download_url": "https://github.com/foo/tool/releases/download/v1.0.0/tool_linux_amd64.tar.gz" },
+                { "name": "tool_linux_amd64.tar.gz.sha256", "browser_download_url": "https://github.com/foo/tool/releases/download/v1.0.0/tool_linux_amd64.tar.gz.sha256" },
+                { "name": "tool_darwin_arm64.tar.gz", "browser_download_url": "https://github.com/foo/tool/releases/download/v1.0.0/tool_darwin_arm64.tar.gz" }
+            ]
+        }"#;
+
+        let assets = parse_assets(&Forge::GitHub, content).unwrap();
+
+        assert_eq!(assets.len(), 3);
+
+        assert_eq!(
+            match_asset(
+                assets,
+                &Regex::new(r"linux_(amd64|x86_64)\.tar\.gz$").unwrap()
+            )
+            .unwrap(),
+            ReleaseAsset {
+                name: "tool_linux_amd64.tar.gz".to_string(),
+                url: "https://github.com/foo/tool/releases/download/v1.0.0/tool_linux_amd64.tar.gz"
+                    .to_string()
+            }
+        );
+
+        let assets = parse_assets(&Forge::GitHub, content).unwrap();
+
+        assert!(match_asset(assets, &Regex::new("linux").unwrap()).is_err());
+
+        let content = r#"{
+            "assets": {
+                "links": [
+                    { "name": "tool_linux_amd64.tar.gz", "url": "https://gitlab.com/foo/tool/-/package_files/1/download", "direct_asset_url": "https://gitlab.com/foo/tool/-/releases/v1.0.0/downloads/tool_linux_amd64.tar.gz" }
+                ]
+            }
+        }"#;
+
+        let assets = parse_assets(&Forge::GitLab, content).unwrap();
+
+        assert_eq!(
+            assets[0].url,
+            "https://gitlab.com/foo/tool/-/releases/v1.0.0/downloads/tool_linux_amd64.tar.gz"
+        );
+
+        let content = r#"{
+            "pagelen": 100,
+            "values": [
+                { "name": "tool_linux_amd64.tar.gz", "links": { "self": { "href": "https://api.bitbucket.org/2.0/repositories/foo/tool/downloads/tool_linux_amd64.tar.gz" } } }
+            ]
+        }"#;
+
+        let assets = parse_assets(&Forge::Bitbucket, content).unwrap();
+
+        assert_eq!(
+            assets[0],
+            ReleaseAsset {
+                name: "tool_linux_amd64.tar.gz".to_string(),
+                url: "https://api.bitbucket.org/2.0/repositories/foo/tool/downloads/tool_linux_amd64.tar.gz"
+                    .to_string()
+            }
+        );
+    }
+}
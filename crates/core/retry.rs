@@ -0,0 +1,243 @@
+#![deny(warnings)]
+
+use std::{
+    fmt::Display,
+    future::Future,
+    panic,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::warn;
+
+pub struct RetryPolicy {
+    pub attempts: u32,        // the max attempts, include the first one
+    pub base_delay: Duration, // the delay before the second attempt, it doubles for each attempt
+}
+
+impl RetryPolicy {
+    // the policy of `attempts`, the attempts are resolved by the settings. see `Settings::get_retry_policy`
+    pub fn new(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts: attempts.max(1),
+            base_delay: Duration::from_millis(500),
+        }
+    }
+
+    // the exponential backoff with jitter, `attempt` starts from 1
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.base_delay * 2u32.pow(attempt.saturating_sub(1).min(10));
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+
+        let jitter_millis = self.base_delay.as_millis() as u64 * (nanos % 1000) as u64 / 1000;
+
+        backoff + Duration::from_millis(jitter_millis)
+    }
+}
+
+fn report_retry<E: Display>(policy: &RetryPolicy, attempt: u32, err: &E, delay: Duration) {
+    warn!(
+        "{}, retry in {:.1}s ({}/{})",
+        err,
+        delay.as_secs_f32(),
+        attempt,
+        policy.attempts - 1
+    );
+}
+
+// run the operation until it succeed or the error is not transient
+pub fn retry<T, E, F, P>(policy: &RetryPolicy, is_transient: P, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Result<T, E>,
+    P: Fn(&E) -> bool,
+{
+    let mut attempt = 1;
+
+    loop {
+        match operation() {
+            Err(e) if attempt < policy.attempts && is_transient(&e) => {
+                let delay = policy.delay(attempt);
+
+                report_retry(policy, attempt, &e, delay);
+
+                thread::sleep(delay);
+
+                attempt += 1;
+            }
+            r => return r,
+        }
+    }
+}
+
+// the async version of `retry`
+pub async fn retry_async<T, E, F, Fut, P>(
+    policy: &RetryPolicy,
+    is_transient: P,
+    mut operation: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Err(e) if attempt < policy.attempts && is_transient(&e) => {
+                let delay = policy.delay(attempt);
+
+                report_retry(policy, attempt, &e, delay);
+
+                tokio::time::sleep(delay).await;
+
+                attempt += 1;
+            }
+            r => return r,
+        }
+    }
+}
+
+// run the blocking operation in the thread pool of blocking tasks, so that the workers of runtime are not stalled.
+// eg. the git command that is run while installing multiple packages at the same time
+pub async fn retry_blocking<T, E, F, P>(
+    policy: &RetryPolicy,
+    is_transient: P,
+    operation: F,
+) -> Result<T, E>
+where
+    T: Send + 'static,
+    E: Display + Send + 'static,
+    F: Fn() -> Result<T, E> + Send + Sync + 'static,
+    P: Fn(&E) -> bool,
+{
+    let operation = Arc::new(operation);
+
+    retry_async(policy, is_transient, || {
+        let operation = operation.clone();
+
+        async move {
+            tokio::task::spawn_blocking(move || operation())
+                .await
+                .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()))
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use crate::retry::{retry, retry_blocking, RetryPolicy};
+
+    #[test]
+    fn test_retry_transient_error() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+
+        let mut count = 0;
+
+        let r: Result<(), String> = retry(
+            &policy,
+            |_| true,
+            || {
+                count += 1;
+                Err("fail".to_string())
+            },
+        );
+
+        assert!(r.is_err());
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+
+        let mut count = 0;
+
+        let r: Result<i32, String> = retry(
+            &policy,
+            |_| true,
+            || {
+                count += 1;
+                if count < 2 {
+                    Err("fail".to_string())
+                } else {
+                    Ok(count)
+                }
+            },
+        );
+
+        assert_eq!(r.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_retry_permanent_error() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+
+        let mut count = 0;
+
+        let r: Result<(), String> = retry(
+            &policy,
+            |_| false,
+            || {
+                count += 1;
+                Err("not found".to_string())
+            },
+        );
+
+        assert!(r.is_err());
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_blocking() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+
+        let count = Arc::new(AtomicU32::new(0));
+        let counter = count.clone();
+
+        let r: Result<u32, String> = retry_blocking(
+            &policy,
+            |_| true,
+            move || {
+                let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                if n < 2 {
+                    Err("fail".to_string())
+                } else {
+                    Ok(n)
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(r.unwrap(), 2);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
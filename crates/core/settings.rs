@@ -0,0 +1,603 @@
+#![deny(warnings)]
+
+use crate::{config::Config, hooker, retry::RetryPolicy, util};
+
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use downloader::DownloadOption;
+use eyre::Report;
+
+// the environment variable to specify the root of Cask, `--root` takes precedence over it
+pub const ENV_HOME: &str = "CASK_HOME";
+
+// the environment variable to disable the network access, the cached data is used instead
+pub const ENV_OFFLINE: &str = "CASK_OFFLINE";
+
+// the environment variable to answer yes to the prompts
+pub const ENV_YES: &str = "CASK_YES";
+
+// the environment variable to skip the hooks of formula
+pub const ENV_NO_HOOKS: &str = "CASK_NO_HOOKS";
+
+// the environment variable to print the plain status lines instead of the progress bars
+pub const ENV_NO_PROGRESS: &str = "CASK_NO_PROGRESS";
+
+// the environment variable of GitHub Actions mode.
+// see https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions
+pub const ENV_GHA: &str = "CASK_GHA";
+
+// the environment variables of timeout in seconds
+pub const ENV_CONNECT_TIMEOUT: &str = "CASK_CONNECT_TIMEOUT";
+pub const ENV_TIMEOUT: &str = "CASK_TIMEOUT";
+
+// the environment variable of extra CA certificates file
+pub const ENV_CA_FILE: &str = "CASK_CA_FILE";
+
+// the environment variable of download rate limit
+pub const ENV_LIMIT_RATE: &str = "CASK_LIMIT_RATE";
+
+// the environment variable to disable the certificate verification
+pub const ENV_INSECURE: &str = "CASK_INSECURE";
+
+// the environment variable of the time to live in seconds of cached versions and API responses
+pub const ENV_CACHE_TTL: &str = "CASK_CACHE_TTL";
+
+// the environment variable of the seconds between the automatic updates of formula sources
+pub const ENV_INDEX_TTL: &str = "CASK_INDEX_TTL";
+
+// the environment variable of proxy, it takes precedence over `HTTP_PROXY` as well
+pub const ENV_PROXY: &str = "CASK_PROXY";
+
+// the environment variable of the max number of concurrent downloads
+pub const ENV_JOBS: &str = "CASK_JOBS";
+
+// the environment variable of the folder that the executables are linked into
+pub const ENV_BIN_DIR: &str = "CASK_BIN_DIR";
+
+// the environment variable of the git binary. eg. the portable git that is not in $PATH
+pub const ENV_GIT_BIN: &str = "CASK_GIT_BIN";
+
+// the environment variable of the max attempts of network operation
+pub const ENV_RETRY_ATTEMPTS: &str = "CASK_RETRY_ATTEMPTS";
+
+// the proxy of environment that is respected by the downloader and git
+const PROXY_ENVS: [&str; 6] = [
+    "HTTP_PROXY",
+    "http_proxy",
+    "HTTPS_PROXY",
+    "https_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+// the default time to live in seconds of cached versions and API responses
+const DEFAULT_CACHE_TTL: u64 = 300;
+
+// the default seconds between the automatic updates of formula sources
+const DEFAULT_INDEX_TTL: u64 = 86400;
+
+// the default timeout in seconds of downloading
+const DEFAULT_CONNECT_TIMEOUT: u64 = 30;
+const DEFAULT_READ_TIMEOUT: u64 = 60;
+
+// the default max number of concurrent downloads when installing multiple packages
+const DEFAULT_JOBS: usize = 4;
+
+// the default max attempts of network operation, include the first one
+const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+
+// the flags of command line, they take precedence over the environment variables and the config
+#[derive(Debug, Default, Clone)]
+pub struct Flags {
+    pub home: Option<PathBuf>,        // --root
+    pub offline: bool,                // --offline
+    pub insecure: bool,               // --insecure
+    pub yes: bool,                    // --yes
+    pub no_hooks: bool,               // --no-hooks
+    pub no_progress: bool,            // --no-progress
+    pub gha: bool,                    // --gha
+    pub jobs: Option<usize>,          // --jobs of install
+    pub limit_rate: Option<String>,   // --limit-rate
+    pub connect_timeout: Option<u64>, // --connect-timeout
+    pub timeout: Option<u64>,         // --timeout
+}
+
+// the settings of running Cask, they are resolved once from the flags, environment variables, config and defaults
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub offline: bool,                 // use the cached data instead of network
+    pub insecure: bool,                // do not verify the certificate of server
+    pub yes: bool,                     // answer yes to the prompts
+    pub no_hooks: bool,                // skip the hooks of formula
+    pub no_progress: bool,             // print the plain status lines instead of the progress bars
+    pub gha: bool,                     // print the workflow commands of GitHub Actions
+    pub jobs: usize,                   // the max number of concurrent downloads
+    pub bin_dir: Option<PathBuf>, // the folder that the executables are linked into, it is relative to the root
+    pub git_bin: Option<PathBuf>, // the git binary, the git command in $PATH is used if not set
+    pub proxy: Option<String>, // the proxy of downloading and git, the proxy of environment is used if not set
+    pub ca_file: Option<PathBuf>, // the extra CA certificates file in PEM format
+    pub limit_rate: Option<u64>, // the max bytes per second of downloading, no limit if not set
+    pub connect_timeout: Duration, // the timeout of connecting to the server
+    pub read_timeout: Duration, // the timeout of waiting for the response data
+    pub git_timeout: Option<Duration>, // the timeout of git command, the default timeout of each command is used if not set
+    pub cache_ttl: Duration,           // the time to live of cached versions and API responses
+    pub index_ttl: Duration, // the time between the automatic updates of formula sources, zero to disable
+    pub retry_attempts: u32, // the max attempts of network operation, include the first one
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        resolve(&Flags::default(), &HashMap::new(), &Config::default())
+            .expect("the default settings are invalid")
+    }
+}
+
+// the environment variables of process, the ones that are not valid unicode are ignored
+pub fn vars() -> HashMap<String, String> {
+    env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+// the non-empty environment variable
+fn var<'a>(vars: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    vars.get(key)
+        .map(|v| v.as_str())
+        .filter(|v| !v.trim().is_empty())
+}
+
+fn parse_var<T: FromStr>(vars: &HashMap<String, String>, key: &str) -> Option<T> {
+    var(vars, key).and_then(|v| v.trim().parse::<T>().ok())
+}
+
+// the flag is on, otherwise the environment variable decides. eg. CASK_YES=0 turns off the config
+fn flag_or_var(flag: bool, vars: &HashMap<String, String>, key: &str) -> Option<bool> {
+    if flag {
+        return Some(true);
+    }
+
+    var(vars, key).map(util::is_set)
+}
+
+// the root of Cask, it is '$HOME/.cask' by default
+pub fn get_home(flags: &Flags, vars: &HashMap<String, String>) -> PathBuf {
+    let root = flags
+        .home
+        .clone()
+        .filter(|root| !root.as_os_str().is_empty())
+        .or_else(|| var(vars, ENV_HOME).map(PathBuf::from))
+        .unwrap_or_else(|| {
+            dirs::home_dir()
+                .expect("can not get home dir")
+                .join(".cask")
+        });
+
+    // the links in the bin folder point to the absolute path
+    if root.is_relative() {
+        env::current_dir()
+            .expect("can not get current dir")
+            .join(root)
+    } else {
+        root
+    }
+}
+
+// resolve the settings, the flag takes precedence over the environment variable, and then the config
+pub fn resolve(
+    flags: &Flags,
+    vars: &HashMap<String, String>,
+    config: &Config,
+) -> Result<Settings, Report> {
+    let seconds = |flag: Option<u64>, key: &str, seconds: Option<u64>| {
+        flag.or_else(|| parse_var(vars, key))
+            .or(seconds)
+            .filter(|s| *s > 0)
+            .map(Duration::from_secs)
+    };
+
+    let ttl = |key: &str, seconds: Option<u64>, default: u64| {
+        Duration::from_secs(parse_var(vars, key).or(seconds).unwrap_or(default))
+    };
+
+    let path = |key: &str, path: &Option<String>| {
+        var(vars, key)
+            .map(|p| p.to_string())
+            .or_else(|| path.clone())
+            .filter(|p| !p.trim().is_empty())
+            .map(PathBuf::from)
+    };
+
+    // `CASK_PROXY` is for Cask only, so it overrides the proxy of environment, but the config does not
+    let proxy = match var(vars, ENV_PROXY) {
+        Some(proxy) => Some(proxy.to_string()),
+        None if PROXY_ENVS.iter().any(|key| vars.contains_key(*key)) => None,
+        None => config.proxy.clone().filter(|p| !p.is_empty()),
+    };
+
+    let limit_rate = match flags
+        .limit_rate
+        .as_deref()
+        .or_else(|| var(vars, ENV_LIMIT_RATE))
+        .or(config.limit_rate.as_deref())
+    {
+        Some(rate) if !rate.trim().is_empty() => Some(
+            util::parse_size(rate)
+                .map_err(|e| eyre::format_err!("invalid download rate limit: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    Ok(Settings {
+        offline: flag_or_var(flags.offline, vars, ENV_OFFLINE).unwrap_or(false),
+        insecure: flag_or_var(flags.insecure, vars, ENV_INSECURE).unwrap_or(false),
+        yes: flag_or_var(flags.yes, vars, ENV_YES)
+            .or(config.defaults.yes)
+            .unwrap_or(false),
+        no_hooks: flag_or_var(flags.no_hooks, vars, ENV_NO_HOOKS).unwrap_or(false),
+        no_progress: flag_or_var(flags.no_progress, vars, ENV_NO_PROGRESS)
+            .or(config.progress.map(|p| !p))
+            .unwrap_or(false),
+        gha: flag_or_var(flags.gha, vars, ENV_GHA).unwrap_or(false),
+        jobs: flags
+            .jobs
+            .or_else(|| parse_var(vars, ENV_JOBS))
+            .or(config.defaults.jobs)
+            .unwrap_or(DEFAULT_JOBS)
+            .max(1),
+        bin_dir: path(ENV_BIN_DIR, &config.bin_dir),
+        git_bin: path(ENV_GIT_BIN, &config.git_bin),
+        proxy,
+        ca_file: path(ENV_CA_FILE, &config.ca_file),
+        limit_rate: limit_rate.filter(|b| *b > 0),
+        connect_timeout: seconds(
+            flags.connect_timeout,
+            ENV_CONNECT_TIMEOUT,
+            config.connect_timeout,
+        )
+        .unwrap_or(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT)),
+        read_timeout: seconds(flags.timeout, ENV_TIMEOUT, config.timeout)
+            .unwrap_or(Duration::from_secs(DEFAULT_READ_TIMEOUT)),
+        git_timeout: seconds(flags.timeout, ENV_TIMEOUT, config.timeout),
+        cache_ttl: ttl(ENV_CACHE_TTL, config.cache_ttl, DEFAULT_CACHE_TTL),
+        index_ttl: ttl(ENV_INDEX_TTL, config.index_ttl, DEFAULT_INDEX_TTL),
+        retry_attempts: parse_var(vars, ENV_RETRY_ATTEMPTS)
+            .unwrap_or(DEFAULT_RETRY_ATTEMPTS)
+            .max(1),
+    })
+}
+
+impl Settings {
+    // whether the user can answer the prompts
+    pub fn is_interactive(&self) -> bool {
+        !self.yes && util::is_interactive()
+    }
+
+    // the relative folder is resolved from the root of Cask, so that it does not depend on where cask runs
+    pub fn get_bin_dir(&self, root: &Path) -> Option<PathBuf> {
+        self.bin_dir.as_ref().map(|dir| root.join(dir))
+    }
+
+    // the policy of running hooks, `--no-hooks` takes precedence over the config.
+    // the hooks are confirmed for the package that is installed for the first time by default
+    pub fn get_hook_policy(&self, config: &Config, is_first_install: bool) -> hooker::Policy {
+        if self.no_hooks {
+            return hooker::Policy::Skip;
+        }
+
+        match config.hooks {
+            Some(hooker::Policy::Confirm) if self.yes => hooker::Policy::Run,
+            Some(policy) => policy,
+            None if is_first_install && self.is_interactive() => hooker::Policy::Confirm,
+            None => hooker::Policy::Run,
+        }
+    }
+
+    pub fn get_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.retry_attempts)
+    }
+
+    // the option for downloading the URL
+    pub fn get_download_option(&self, config: &Config, url: &str, verbose: bool) -> DownloadOption {
+        DownloadOption {
+            verbose,
+            headers: config.get_auth_headers(url),
+            connect_timeout: Some(self.connect_timeout),
+            read_timeout: Some(self.read_timeout),
+            ca_file: self.ca_file.clone(),
+            insecure: self.insecure,
+            limit_rate: self.limit_rate,
+            proxy: self.proxy.clone(),
+        }
+    }
+
+    // the config entries of git for the proxy and TLS, they are passed to the git process only.
+    // see https://git-scm.com/docs/git-config#Documentation/git-config.txt-httpproxy
+    fn get_git_config(&self) -> Vec<(String, String)> {
+        let mut entries = vec![];
+
+        if let Some(proxy) = &self.proxy {
+            entries.push(("http.proxy".to_string(), proxy.clone()));
+        }
+
+        if let Some(ca_file) = &self.ca_file {
+            entries.push(("http.sslCAInfo".to_string(), ca_file.display().to_string()));
+        }
+
+        if self.insecure {
+            entries.push(("http.sslVerify".to_string(), "false".to_string()));
+        }
+
+        entries
+    }
+
+    // the git repository of URL, it is cloned from the mirror if mapped
+    pub fn get_git_repository(
+        &self,
+        config: &Config,
+        url: &str,
+    ) -> Result<git::Repository, git::GitError> {
+        let mut entries = config.get_git_config();
+
+        entries.extend(self.get_git_config());
+
+        Ok(git::new(&config.rewrite_git_url(url))?
+            .with_timeout(self.git_timeout)
+            .with_bin(self.git_bin.clone())
+            .with_config(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use crate::{
+        config::Config,
+        hooker,
+        settings::{
+            resolve, Flags, Settings, ENV_BIN_DIR, ENV_CACHE_TTL, ENV_CONNECT_TIMEOUT, ENV_GIT_BIN,
+            ENV_INDEX_TTL, ENV_JOBS, ENV_LIMIT_RATE, ENV_NO_HOOKS, ENV_NO_PROGRESS, ENV_PROXY,
+            ENV_TIMEOUT, ENV_YES,
+        },
+    };
+
+    fn parse(content: &str) -> Config {
+        toml::from_str::<Config>(content).unwrap()
+    }
+
+    fn vars(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn settings(content: &str, entries: &[(&str, &str)]) -> Settings {
+        resolve(&Flags::default(), &vars(entries), &parse(content)).unwrap()
+    }
+
+    #[test]
+    fn test_get_timeout() {
+        let s = settings("", &[]);
+
+        assert_eq!(s.connect_timeout, Duration::from_secs(30));
+        assert_eq!(s.read_timeout, Duration::from_secs(60));
+        assert_eq!(s.git_timeout, None);
+
+        let s = settings("connect_timeout = 5\ntimeout = 120", &[]);
+
+        assert_eq!(s.connect_timeout, Duration::from_secs(5));
+        assert_eq!(s.read_timeout, Duration::from_secs(120));
+        assert_eq!(s.git_timeout, Some(Duration::from_secs(120)));
+
+        let s = settings(
+            "connect_timeout = 5\ntimeout = 120",
+            &[(ENV_CONNECT_TIMEOUT, "1"), (ENV_TIMEOUT, "10")],
+        );
+
+        assert_eq!(s.connect_timeout, Duration::from_secs(1));
+        assert_eq!(s.read_timeout, Duration::from_secs(10));
+
+        // the flag takes precedence over the environment variable
+        let flags = Flags {
+            timeout: Some(20),
+            ..Default::default()
+        };
+        let s = resolve(&flags, &vars(&[(ENV_TIMEOUT, "10")]), &parse("")).unwrap();
+
+        assert_eq!(s.read_timeout, Duration::from_secs(20));
+        assert_eq!(s.git_timeout, Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_get_cache_ttl() {
+        assert_eq!(settings("", &[]).cache_ttl, Duration::from_secs(300));
+        assert_eq!(settings("cache_ttl = 0", &[]).cache_ttl, Duration::ZERO);
+        assert_eq!(
+            settings("cache_ttl = 0", &[(ENV_CACHE_TTL, "60")]).cache_ttl,
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn test_get_limit_rate() {
+        assert_eq!(settings("", &[]).limit_rate, None);
+        assert_eq!(
+            settings(r#"limit_rate = "2M""#, &[]).limit_rate,
+            Some(2 * 1024 * 1024)
+        );
+        assert_eq!(
+            settings(r#"limit_rate = "2M""#, &[(ENV_LIMIT_RATE, "512K")]).limit_rate,
+            Some(512 * 1024)
+        );
+
+        assert!(resolve(
+            &Flags::default(),
+            &vars(&[]),
+            &parse(r#"limit_rate = "2X""#)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_hook_policy() {
+        let config = parse("");
+
+        // the upgrade is not confirmed
+        assert_eq!(
+            settings("", &[]).get_hook_policy(&config, false),
+            hooker::Policy::Run
+        );
+
+        let config = parse(r#"hooks = "skip""#);
+
+        assert_eq!(
+            settings("", &[]).get_hook_policy(&config, true),
+            hooker::Policy::Skip
+        );
+
+        // `--yes` answers the confirmation
+        let config = parse(r#"hooks = "confirm""#);
+
+        assert_eq!(
+            settings("", &[(ENV_YES, "1")]).get_hook_policy(&config, false),
+            hooker::Policy::Run
+        );
+
+        // `--no-hooks` takes precedence over the config
+        let config = parse(r#"hooks = "run""#);
+
+        assert_eq!(
+            settings("", &[(ENV_NO_HOOKS, "1")]).get_hook_policy(&config, false),
+            hooker::Policy::Skip
+        );
+    }
+
+    #[test]
+    fn test_get_jobs_and_yes() {
+        let s = settings("", &[]);
+
+        assert_eq!(s.jobs, 4);
+        assert!(!s.yes);
+
+        let content = "[defaults]\njobs = 8\nyes = true";
+
+        let s = settings(content, &[]);
+
+        assert_eq!(s.jobs, 8);
+        assert!(s.yes);
+
+        let s = settings(content, &[(ENV_JOBS, "2"), (ENV_YES, "0")]);
+
+        assert_eq!(s.jobs, 2);
+        assert!(!s.yes);
+
+        let flags = Flags {
+            jobs: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve(&flags, &vars(&[(ENV_JOBS, "2")]), &parse(content))
+                .unwrap()
+                .jobs,
+            1
+        );
+    }
+
+    #[test]
+    fn test_get_git_config() {
+        assert!(settings("", &[]).get_git_config().is_empty());
+
+        let content = "proxy = \"http://127.0.0.1:7890\"\nca_file = \"/etc/ca.pem\"";
+        let flags = Flags {
+            insecure: true,
+            ..Default::default()
+        };
+        let s = resolve(&flags, &vars(&[]), &parse(content)).unwrap();
+
+        assert_eq!(
+            s.get_git_config(),
+            vec![
+                (
+                    "http.proxy".to_string(),
+                    "http://127.0.0.1:7890".to_string()
+                ),
+                ("http.sslCAInfo".to_string(), "/etc/ca.pem".to_string()),
+                ("http.sslVerify".to_string(), "false".to_string()),
+            ]
+        );
+
+        let option = s.get_download_option(&parse(content), "https://example.com", false);
+
+        assert_eq!(option.proxy, Some("http://127.0.0.1:7890".to_string()));
+        assert!(option.insecure);
+    }
+
+    #[test]
+    fn test_get_overrides() {
+        let root = Path::new("/home/axetroy/.cask");
+
+        let s = settings("", &[]);
+
+        assert_eq!(s.proxy, None);
+        assert_eq!(s.get_bin_dir(root), None);
+        assert_eq!(s.git_bin, None);
+        assert!(!s.no_progress);
+        assert_eq!(s.index_ttl, Duration::from_secs(86400));
+
+        let content = r#"
+proxy = "http://127.0.0.1:7890"
+bin_dir = "bin"
+git_bin = "/opt/git/bin/git"
+progress = false
+index_ttl = 0
+"#;
+
+        let s = settings(content, &[]);
+
+        assert_eq!(s.proxy, Some("http://127.0.0.1:7890".to_string()));
+        // the relative folder is resolved from the root of Cask
+        assert_eq!(
+            s.get_bin_dir(root),
+            Some(PathBuf::from("/home/axetroy/.cask/bin"))
+        );
+        assert_eq!(s.git_bin, Some(PathBuf::from("/opt/git/bin/git")));
+        assert!(s.no_progress);
+        assert!(s.index_ttl.is_zero());
+
+        // the proxy of environment takes precedence over the config
+        assert_eq!(
+            settings(content, &[("HTTPS_PROXY", "http://127.0.0.1:8080")]).proxy,
+            None
+        );
+
+        let s = settings(
+            content,
+            &[
+                (ENV_PROXY, "http://127.0.0.1:1080"),
+                (ENV_BIN_DIR, "/usr/local/bin"),
+                (ENV_GIT_BIN, "/usr/bin/git"),
+                (ENV_NO_PROGRESS, "0"),
+                (ENV_INDEX_TTL, "3600"),
+                ("HTTPS_PROXY", "http://127.0.0.1:8080"),
+            ],
+        );
+
+        assert_eq!(s.proxy, Some("http://127.0.0.1:1080".to_string()));
+        assert_eq!(s.get_bin_dir(root), Some(PathBuf::from("/usr/local/bin")));
+        assert_eq!(s.git_bin, Some(PathBuf::from("/usr/bin/git")));
+        assert!(!s.no_progress);
+        assert_eq!(s.index_ttl, Duration::from_secs(3600));
+    }
+}
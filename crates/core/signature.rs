@@ -0,0 +1,211 @@
+#![deny(warnings)]
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use blake2::{Blake2b512, Digest};
+use eyre::Report;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+// verify the signature of file with the public key, the key can be a minisign or GPG public key
+pub fn verify(file: &Path, signature_file: &Path, public_key: &str) -> Result<(), Report> {
+    if public_key.contains("BEGIN PGP PUBLIC KEY BLOCK") {
+        verify_gpg(file, signature_file, public_key)
+    } else {
+        verify_minisign(file, &fs::read_to_string(signature_file)?, public_key)
+    }
+}
+
+// the base64 line of minisign file, the comment lines are ignored
+fn get_minisign_line<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    lines
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty() && !l.starts_with("untrusted comment:"))
+}
+
+// verify the minisign signature. see https://jedisct1.github.io/minisign/#signature-format
+fn verify_minisign(file: &Path, signature: &str, public_key: &str) -> Result<(), Report> {
+    let err =
+        |msg: &str| eyre::format_err!("Verify the signature of '{}' fail: {}", file.display(), msg);
+
+    let public_key = get_minisign_line(&mut public_key.lines())
+        .and_then(|line| BASE64.decode(line).ok())
+        .filter(|key| key.len() == 42 && &key[0..2] == b"Ed")
+        .ok_or_else(|| err("invalid minisign public key"))?;
+
+    let mut lines = signature.lines();
+
+    let sig = get_minisign_line(&mut lines)
+        .and_then(|line| BASE64.decode(line).ok())
+        .filter(|sig| sig.len() == 74)
+        .ok_or_else(|| err("invalid minisign signature"))?;
+
+    let trusted_comment = lines
+        .next()
+        .and_then(|line| {
+            line.trim_end_matches('\r')
+                .strip_prefix("trusted comment: ")
+        })
+        .ok_or_else(|| err("the trusted comment is missing in signature"))?;
+
+    let global_sig = lines
+        .next()
+        .and_then(|line| BASE64.decode(line.trim()).ok())
+        .ok_or_else(|| err("the global signature is missing in signature"))?;
+
+    if sig[2..10] != public_key[2..10] {
+        return Err(err("the signature is not signed by the public key"));
+    }
+
+    let key = UnparsedPublicKey::new(&ED25519, &public_key[10..]);
+
+    // the file is hashed with BLAKE2b-512 before signing if the algorithm is 'ED'
+    let message = match &sig[0..2] {
+        b"Ed" => fs::read(file)?,
+        b"ED" => blake2b_512_file(file)?,
+        _ => return Err(err("unsupported signature algorithm")),
+    };
+
+    key.verify(&message, &sig[10..])
+        .map_err(|_| err("the signature does not match the file"))?;
+
+    let mut global_message = sig[10..].to_vec();
+    global_message.extend_from_slice(trusted_comment.as_bytes());
+
+    key.verify(&global_message, &global_sig)
+        .map_err(|_| err("the trusted comment has been tampered"))?;
+
+    Ok(())
+}
+
+// verify the detached GPG signature in a temporary keyring, so that the keyring of user is not touched
+fn verify_gpg(file: &Path, signature_file: &Path, public_key: &str) -> Result<(), Report> {
+    let home = tempfile::Builder::new().prefix("cask_gpg_").tempdir()?;
+
+    // gpg warns if the homedir can be accessed by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::prelude::PermissionsExt;
+
+        fs::set_permissions(home.path(), fs::Permissions::from_mode(0o700))?;
+    }
+
+    let gpg = |args: &[&str]| {
+        Command::new("gpg")
+            .arg("--batch")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| eyre::format_err!("Run gpg fail, make sure gpg is installed: {}", e))
+    };
+
+    let mut child = gpg(&["--import"])?;
+    child
+        .stdin
+        .take()
+        .expect("can not get stdin of gpg")
+        .write_all(public_key.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(eyre::format_err!(
+            "Import the GPG public key fail: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let output = gpg(&[
+        "--verify",
+        &signature_file.display().to_string(),
+        &file.display().to_string(),
+    ])?
+    .wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(eyre::format_err!(
+            "Verify the signature of '{}' fail: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+fn blake2b_512_file(file: &Path) -> Result<Vec<u8>, Report> {
+    let mut f = File::open(file)?;
+    let mut hasher = Blake2b512::new();
+
+    io::copy(&mut f, &mut hasher)?;
+
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::signature::{blake2b_512_file, verify_minisign};
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_blake2b_512() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+
+        assert_eq!(
+            to_hex(&blake2b_512_file(file.path()).unwrap()),
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        );
+
+        file.write_all(b"abc").unwrap();
+
+        assert_eq!(
+            to_hex(&blake2b_512_file(file.path()).unwrap()),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn test_verify_minisign() {
+        let public_key = "untrusted comment: minisign public key\nRWQBI0VniavN7ymsy847t9nr13JajW09PzIE5Yjrnvn2kLhjm2fyuMWD\n";
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"hello cask\n").unwrap();
+
+        // pre-hashed signature
+        let signature = "untrusted comment: signature from minisign secret key\nRUQBI0VniavN7wEazElVtly5lnEgAbmcxkyWQGH2UKF9pJT0RmniKwPFpbwJI4gg4eZJxyDlU6O6G0o68R1Vdam8gIT8pEJY7gc=\ntrusted comment: timestamp:1700000000\tfile:data.txt\nXM+59J8fYfWdRVkS2M62VU3P9K3MvfR6YB69UYEKdb41EJEkAfqWMcOz5bSkpTpmDsgPVlRlHMfE/s7RwMXaDA==\n";
+
+        verify_minisign(file.path(), signature, public_key).unwrap();
+
+        // legacy signature
+        let signature = "untrusted comment: signature from minisign secret key\nRWQBI0VniavN7zMKvX7nJksZsWMX7mWeQCwpp50DynK4y4kgNYdMEb0W0PpjpFZ/O5RM6CvQat6yz091Lmoe5zsILJBgmzaF6gQ=\ntrusted comment: timestamp:1700000000\tfile:data.txt\nLHKw4R2BwKgcubBLckPkt9771q2BfZyRt32c0YLFL3fNea05cnbM+HA8GQxe1pHNJNwXeVAQN9Vflmn6ivQaCw==\n";
+
+        verify_minisign(file.path(), signature, public_key).unwrap();
+
+        // the trusted comment is tampered
+        assert!(verify_minisign(
+            file.path(),
+            &signature.replace("timestamp:1700000000", "timestamp:1700000001"),
+            public_key
+        )
+        .is_err());
+
+        // the file is tampered
+        file.write_all(b"!").unwrap();
+
+        assert!(verify_minisign(file.path(), signature, public_key).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+// #![deny(warnings)]
+
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fmt, fs,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+// how the executable of package is linked into the bin folder of Cask
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    Symlink,  // the symbolic link, it requires the Developer Mode or administrator on Windows
+    Hardlink, // the hard link, it requires the executable in the same volume
+    Copy,     // the copy of executable, it is updated when the version is activated
+    Shim, // the launchers that run the executable, like Scoop does. it is the default on Windows
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Strategy::Symlink => "symlink",
+            Strategy::Hardlink => "hardlink",
+            Strategy::Copy => "copy",
+            Strategy::Shim => "shim",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        if cfg!(windows) {
+            Strategy::Shim
+        } else {
+            Strategy::Symlink
+        }
+    }
+}
+
+// the file name of package may contain the dot, eg. 'foo.bar', the extension is appended instead of replaced
+fn with_suffix(dest: &Path, extension: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", dest.display(), extension))
+}
+
+// link the executable into the bin folder of Cask, it returns the strategy that is used.
+// the symlink falls back to the hard link and then the copy, eg. on FAT/exFAT or for the restricted account of Windows
+pub fn symlink(
+    src: &Path,
+    dest: &Path,
+    package_name: &str,
+    strategy: Strategy,
+) -> Result<Strategy, Report> {
+    for file in get_link_files(dest) {
+        // the broken symlink does not exist, but it should be removed as well
+        if fs::symlink_metadata(&file).is_ok() {
+            fs::remove_file(&file)?;
+        }
+    }
+
+    if strategy == Strategy::Shim {
+        // the shell script works in Git Bash, Cygwin and WSL, it is the only launcher on Unix
+        write_script(
+            dest,
+            include_str!("./script/exe.sh").replace("\r\n", "\n"),
+            src,
+            package_name,
+        )?;
+
+        if cfg!(windows) {
+            // the batch script for cmd and PowerShell
+            write_script(
+                &with_suffix(dest, "cmd"),
+                include_str!("./script/exe.cmd").to_string(),
+                src,
+                package_name,
+            )?;
+
+            // the executable for the programs that can not run the batch script
+            write_shim(src, dest)?;
+        }
+
+        return Ok(strategy);
+    }
+
+    // the executable in the bin folder must have the extension on Windows
+    let link_file = if cfg!(windows) {
+        with_suffix(dest, "exe")
+    } else {
+        dest.to_path_buf()
+    };
+
+    let mut errors = vec![];
+
+    for s in [Strategy::Symlink, Strategy::Hardlink, Strategy::Copy]
+        .into_iter()
+        .skip_while(|s| *s != strategy)
+    {
+        let result = match s {
+            Strategy::Symlink => create_symlink(src, &link_file),
+            Strategy::Hardlink => fs::hard_link(src, &link_file),
+            _ => fs::copy(src, &link_file).map(|_| ()),
+        };
+
+        match result {
+            Ok(()) => {
+                if !errors.is_empty() {
+                    warn!(
+                        "can not {} '{}', the {} is used instead. {}",
+                        strategy,
+                        link_file.display(),
+                        s,
+                        errors.join(", ")
+                    );
+                }
+
+                return Ok(s);
+            }
+            Err(e) => errors.push(format!("{}: {}", s, e)),
+        }
+    }
+
+    Err(eyre::format_err!(
+        "link '{}' to '{}' fail. {}",
+        src.display(),
+        link_file.display(),
+        errors.join(", ")
+    ))
+}
+
+fn create_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(src, dest);
+
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(src, dest);
+}
+
+fn write_script(
+    file_path: &Path,
+    template: String,
+    src: &Path,
+    package_name: &str,
+) -> Result<(), Report> {
+    let script = template
+        .replace("{filepath}", &src.display().to_string())
+        .replace("{package}", package_name);
+
+    let mut file = File::create(file_path)?;
+
+    file.write_all(script.as_bytes())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(file_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
+
+// the shim executable is shipped next to Cask, it runs the executable that is recorded in the '.shim' file next to it.
+// it is hard linked, so that it does not take more space. the scripts still work if it is missing
+fn write_shim(src: &Path, dest: &Path) -> Result<(), Report> {
+    let shim = env::current_exe()?.with_file_name(shim::EXE_NAME);
+    let shim_exe = with_suffix(dest, "exe");
+
+    if !shim.is_file() {
+        warn!(
+            "the shim '{}' is not found, '{}' is not generated. reinstall Cask to fix it",
+            shim.display(),
+            shim_exe.display()
+        );
+        return Ok(());
+    }
+
+    if fs::hard_link(&shim, &shim_exe).is_err() {
+        // the bin folder is in another volume
+        fs::copy(&shim, &shim_exe)?;
+    }
+
+    fs::write(
+        with_suffix(dest, "shim"),
+        format!("path = \"{}\"\n", src.display()),
+    )?;
+
+    Ok(())
+}
+
+// whether the link file is created by Cask, it is true if the file does not exist.
+// the symlink, the scripts and the shim point into the root folder, the copy is the same as one of the executables
+pub fn is_owned(link_file: &Path, root: &Path, executables: &[PathBuf]) -> bool {
+    let metadata = match fs::symlink_metadata(link_file) {
+        Ok(metadata) => metadata,
+        Err(_) => return true,
+    };
+
+    if metadata.file_type().is_symlink() {
+        // the relative target is resolved from the folder of link
+        return fs::read_link(link_file)
+            .map(|target| {
+                link_file
+                    .parent()
+                    .unwrap_or(link_file)
+                    .join(target)
+                    .starts_with(root)
+            })
+            .unwrap_or(false);
+    }
+
+    if shim::read(link_file)
+        .map(|target| target.starts_with(root))
+        .unwrap_or(false)
+    {
+        return true;
+    }
+
+    let content = match fs::read(link_file) {
+        Ok(content) => content,
+        Err(_) => return false,
+    };
+
+    // the scripts contain the path of executable
+    if metadata.len() <= 64 * 1024
+        && String::from_utf8_lossy(&content).contains(&root.display().to_string())
+    {
+        return true;
+    }
+
+    executables
+        .iter()
+        .any(|executable| fs::read(executable).map(|c| c == content).unwrap_or(false))
+}
+
+// the files that may be created by symlink, the legacy batch script '.bat' is included.
+// a shell script, a batch script and a shim executable are generated on Windows
+pub fn get_link_files(dest: &Path) -> Vec<PathBuf> {
+    if cfg!(unix) {
+        vec![dest.to_path_buf()]
+    } else {
+        vec![
+            with_suffix(dest, "bat"),
+            with_suffix(dest, "cmd"),
+            with_suffix(dest, "exe"),
+            with_suffix(dest, "shim"),
+            dest.to_path_buf(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::symlink;
+    use std::{env, fs};
+
+    #[test]
+    fn test_symlink() {
+        let cwd = env::current_dir().unwrap();
+
+        let src = cwd
+            .join("fixtures")
+            .join("symlink")
+            .join("src")
+            .join("test");
+
+        let dest = cwd
+            .join("fixtures")
+            .join("symlink")
+            .join("dest")
+            .join("test");
+
+        symlink::symlink(
+            &src,
+            &dest,
+            "github.com/axetroy/test",
+            symlink::Strategy::default(),
+        )
+        .unwrap();
+
+        if cfg!(unix) {
+            assert!(&dest.is_symlink());
+
+            let d = fs::read_link(&dest).unwrap();
+
+            assert_eq!(format!("{}", d.display()), format!("{}", src.display()));
+        } else {
+            assert!(dest.is_file());
+
+            let shell_content = fs::read_to_string(&dest).unwrap();
+
+            assert!(shell_content
+                .contains(format!(r#"# package: {}"#, "github.com/axetroy/test").as_str()));
+            assert!(shell_content.contains(format!(r#"# filepath: {}"#, src.display()).as_str()));
+            assert!(shell_content.contains(format!(r#"xbin="{}""#, src.display()).as_str()));
+
+            let cmd = dest.parent().unwrap().join("test.cmd");
+
+            assert!(&cmd.is_file());
+
+            let cmd_content = fs::read_to_string(&cmd).unwrap();
+
+            assert!(cmd_content
+                .contains(format!(r#":: package: {}"#, "github.com/axetroy/test").as_str()));
+            assert!(cmd_content.contains(format!(r#":: filepath: {}"#, src.display()).as_str()));
+            assert!(cmd_content.contains(format!(r#""{}" %*"#, src.display()).as_str()));
+
+            // the shim executable is not built next to the test
+            let exe = dest.parent().unwrap().join("test.exe");
+
+            if exe.is_file() {
+                assert_eq!(shim::read(&exe), Some(src));
+            }
+        }
+    }
+
+    #[test]
+    fn test_shim() {
+        let cwd = env::current_dir().unwrap();
+
+        let src = cwd
+            .join("fixtures")
+            .join("symlink")
+            .join("src")
+            .join("test");
+
+        let dest = cwd
+            .join("fixtures")
+            .join("symlink")
+            .join("dest")
+            .join("test_shim");
+
+        symlink::symlink(
+            &src,
+            &dest,
+            "github.com/axetroy/test",
+            symlink::Strategy::Shim,
+        )
+        .unwrap();
+
+        assert!(!dest.is_symlink());
+
+        let shell_content = fs::read_to_string(&dest).unwrap();
+
+        assert!(shell_content.contains(format!(r#"xbin="{}""#, src.display()).as_str()));
+
+        if cfg!(unix) {
+            // the shim is replaced by the symlink
+            symlink::symlink(
+                &src,
+                &dest,
+                "github.com/axetroy/test",
+                symlink::Strategy::Symlink,
+            )
+            .unwrap();
+
+            assert!(dest.is_symlink());
+        }
+    }
+
+    #[test]
+    fn test_hardlink_and_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("foo_1.0.0");
+        let dest = dir.path().join("foo");
+
+        fs::write(&src, "#!/bin/sh").unwrap();
+
+        for strategy in [symlink::Strategy::Hardlink, symlink::Strategy::Copy] {
+            let link_file = symlink::get_link_files(&dest)
+                .into_iter()
+                .find(|f| cfg!(unix) || f.extension().map(|e| e == "exe").unwrap_or(false))
+                .unwrap();
+
+            assert_eq!(
+                symlink::symlink(&src, &dest, "github.com/axetroy/foo", strategy).unwrap(),
+                strategy
+            );
+
+            assert!(!link_file.is_symlink());
+            assert_eq!(fs::read_to_string(&link_file).unwrap(), "#!/bin/sh");
+        }
+    }
+
+    #[test]
+    fn test_is_owned() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("formula");
+        let bin_dir = dir.path().join("bin");
+        let src = root.join("foo").join("versions/1.0.0/bin/foo");
+
+        fs::create_dir_all(src.parent().unwrap()).unwrap();
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(&src, "#!/bin/sh").unwrap();
+
+        let executables = vec![src.clone()];
+
+        // the file does not exist
+        assert!(symlink::is_owned(&bin_dir.join("foo"), &root, &executables));
+
+        for strategy in [
+            symlink::Strategy::Symlink,
+            symlink::Strategy::Copy,
+            symlink::Strategy::Shim,
+        ] {
+            let dest = bin_dir.join("foo");
+
+            symlink::symlink(&src, &dest, "github.com/axetroy/foo", strategy).unwrap();
+
+            for link_file in symlink::get_link_files(&dest) {
+                assert!(symlink::is_owned(&link_file, &root, &executables));
+            }
+        }
+
+        // the file of other program
+        fs::write(bin_dir.join("bar"), "#!/bin/sh\necho bar").unwrap();
+
+        assert!(!symlink::is_owned(
+            &bin_dir.join("bar"),
+            &root,
+            &executables
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/usr/bin/env", bin_dir.join("env")).unwrap();
+
+            assert!(!symlink::is_owned(
+                &bin_dir.join("env"),
+                &root,
+                &executables
+            ));
+        }
+    }
+}
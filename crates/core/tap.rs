@@ -0,0 +1,277 @@
+#![deny(warnings)]
+
+use crate::{cask, config, formula, util};
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use eyre::Report;
+
+// the name of build-in formula source
+pub const BUILD_IN: &str = "build-in";
+
+// the repository of build-in formula
+pub const BUILD_IN_URL: &str = "https://github.com/cask-pkg/cask-core";
+
+// the root of formula files, the formula of package is in the folder of its name.
+// eg. '~/.cask/taps/corp/github.com/axetroy/dvm/Cask.toml'
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub name: String,        // the build-in formula or the name of tap
+    pub dir: PathBuf,        // the folder of formula files
+    pub url: Option<String>, // the git repository that is synced, it is None for the local folder
+}
+
+// the name of tap is the folder name, eg. 'corp'
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != BUILD_IN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+// the formula sources in the order of searching, the tap of higher priority is searched first.
+// the priority of build-in formula is 0, it is searched before the taps of the same priority
+pub fn list(cask: &cask::Cask, config: &config::Config) -> Result<Vec<Source>, Report> {
+    let mut names = HashSet::new();
+
+    let mut sources = vec![(
+        0,
+        Source {
+            name: BUILD_IN.to_string(),
+            dir: cask.build_in_formula_dir(),
+            url: Some(BUILD_IN_URL.to_string()),
+        },
+    )];
+
+    for tap in &config.taps {
+        if !is_valid_name(&tap.name) || !names.insert(tap.name.as_str()) {
+            return Err(eyre::format_err!(
+                "invalid tap '{}', the name must be unique and consist of letters, digits, '-', '_' and '.'",
+                tap.name
+            ));
+        }
+
+        let source = match (&tap.url, &tap.path) {
+            (Some(url), None) => Source {
+                name: tap.name.clone(),
+                dir: cask.taps_dir().join(&tap.name),
+                url: Some(url.clone()),
+            },
+            (None, Some(path)) => Source {
+                name: tap.name.clone(),
+                dir: PathBuf::from(path),
+                url: None,
+            },
+            _ => {
+                return Err(eyre::format_err!(
+                    "invalid tap '{}', either 'url' or 'path' is required",
+                    tap.name
+                ))
+            }
+        };
+
+        sources.push((tap.priority, source));
+    }
+
+    // the sort is stable, so the order of config is kept
+    sources.sort_by_key(|(priority, _)| Reverse(*priority));
+
+    Ok(sources.into_iter().map(|(_, source)| source).collect())
+}
+
+// the number of formula files that are changed by the update of source
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Changes {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+// the file that records the time of the last update of formula sources, it is in the folder of taps
+// whose names can not start with the dot
+pub fn updated_file(cask: &cask::Cask) -> PathBuf {
+    cask.taps_dir().join(".updated")
+}
+
+// the seconds since the epoch are recorded instead of touching the file,
+// so that it does not depend on the modified time of file system
+pub fn mark_updated(cask: &cask::Cask) -> Result<(), Report> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    fs::create_dir_all(cask.taps_dir())?;
+    fs::write(updated_file(cask), now.to_string())?;
+
+    Ok(())
+}
+
+// whether the formula sources are not updated within the ttl, they are outdated if they have never been updated
+pub fn is_outdated(cask: &cask::Cask, ttl: Duration) -> bool {
+    fs::read_to_string(updated_file(cask))
+        .ok()
+        .and_then(|content| content.trim().parse::<u64>().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .and_then(|time| time.elapsed().ok())
+        .map(|elapsed| elapsed >= ttl)
+        .unwrap_or(true)
+}
+
+// the checksums of formula files in the folder of source, the key is the relative path of formula file.
+// it is empty if the source has not been synced
+pub fn snapshot(dir: &Path) -> Result<HashMap<PathBuf, String>, Report> {
+    let mut files = HashMap::new();
+
+    if dir.exists() {
+        collect_formula(dir, dir, &mut files)?;
+    }
+
+    Ok(files)
+}
+
+fn collect_formula(
+    root: &Path,
+    dir: &Path,
+    files: &mut HashMap<PathBuf, String>,
+) -> Result<(), Report> {
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let p = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if p.is_dir() {
+            // skip the '.git' folder
+            if !file_name.starts_with('.') {
+                collect_formula(root, &p, files)?;
+            }
+        } else if formula::FORMULA_FILE_NAMES.contains(&file_name.as_str()) {
+            files.insert(p.strip_prefix(root)?.to_path_buf(), util::file_sha256(&p)?);
+        }
+    }
+
+    Ok(())
+}
+
+// compare the snapshots of source before and after the update
+pub fn diff(before: &HashMap<PathBuf, String>, after: &HashMap<PathBuf, String>) -> Changes {
+    let mut changes = Changes::default();
+
+    for (file, checksum) in after {
+        match before.get(file) {
+            None => changes.added += 1,
+            Some(previous) if previous != checksum => changes.changed += 1,
+            _ => {}
+        }
+    }
+
+    changes.removed = before.keys().filter(|f| !after.contains_key(*f)).count();
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, time::Duration};
+
+    use crate::{cask, config::Config, tap};
+
+    #[test]
+    fn test_list() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        let config: Config = toml::from_str(
+            r#"
+[[taps]]
+name = "personal"
+path = "/home/axetroy/cask-formula"
+priority = -1
+
+[[taps]]
+name = "corp"
+url = "https://git.corp.example/tools/cask-formula.git"
+priority = 10
+
+[[taps]]
+name = "team"
+url = "https://git.corp.example/team/cask-formula.git"
+"#,
+        )
+        .unwrap();
+
+        let sources = tap::list(&c, &config).unwrap();
+
+        assert_eq!(
+            sources
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["corp", "build-in", "team", "personal"]
+        );
+        assert_eq!(sources[0].dir, c.taps_dir().join("corp"));
+        assert_eq!(sources[3].dir, PathBuf::from("/home/axetroy/cask-formula"));
+        assert_eq!(sources[3].url, None);
+
+        for taps in [
+            "[[taps]]\nname = \"corp\"",
+            "[[taps]]\nname = \"build-in\"\npath = \"/tmp\"",
+            "[[taps]]\nname = \"../corp\"\npath = \"/tmp\"",
+            "[[taps]]\nname = \"corp\"\npath = \"/tmp\"\n[[taps]]\nname = \"corp\"\npath = \"/opt\"",
+        ] {
+            let config: Config = toml::from_str(taps).unwrap();
+
+            assert!(tap::list(&c, &config).is_err(), "{}", taps);
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        assert!(tap::is_outdated(&c, Duration::from_secs(3600)));
+
+        tap::mark_updated(&c).unwrap();
+
+        assert!(!tap::is_outdated(&c, Duration::from_secs(3600)));
+        assert!(tap::is_outdated(&c, Duration::from_secs(0)));
+
+        let dir = root.path().join("formula");
+
+        assert!(tap::snapshot(&dir).unwrap().is_empty());
+
+        for name in ["foo", "bar", "baz"] {
+            fs::create_dir_all(dir.join("github.com").join(name)).unwrap();
+            fs::write(dir.join("github.com").join(name).join("Cask.toml"), name).unwrap();
+        }
+
+        // the files of git are not formula
+        fs::create_dir_all(dir.join(".git").join("foo")).unwrap();
+        fs::write(dir.join(".git").join("foo").join("Cask.toml"), "").unwrap();
+
+        let before = tap::snapshot(&dir).unwrap();
+
+        assert_eq!(before.len(), 3);
+
+        fs::write(dir.join("github.com/foo/Cask.toml"), "foo 2.0").unwrap();
+        fs::remove_dir_all(dir.join("github.com/bar")).unwrap();
+        fs::create_dir_all(dir.join("github.com/qux")).unwrap();
+        fs::write(dir.join("github.com/qux/Cask.toml"), "qux").unwrap();
+        fs::create_dir_all(dir.join("github.com/quux")).unwrap();
+        fs::write(dir.join("github.com/quux/Cask.toml"), "quux").unwrap();
+
+        assert_eq!(
+            tap::diff(&before, &tap::snapshot(&dir).unwrap()),
+            tap::Changes {
+                added: 2,
+                changed: 1,
+                removed: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,159 @@
+#![deny(warnings)]
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+
+// the files are put into place together, the replaced files are restored if any step fails
+pub struct Transaction {
+    backup_dir: PathBuf, // the folder that the replaced files are moved to
+    changes: Vec<(PathBuf, Option<PathBuf>)>, // the changed files and their backups
+}
+
+pub fn new(backup_dir: &Path) -> Result<Transaction, Report> {
+    if backup_dir.exists() {
+        fs::remove_dir_all(backup_dir)?;
+    }
+
+    fs::create_dir_all(backup_dir)?;
+
+    Ok(Transaction {
+        backup_dir: backup_dir.to_path_buf(),
+        changes: vec![],
+    })
+}
+
+// move the file or symlink, it is copied if the destination is located in another file system
+fn move_file(src: &Path, dest: &Path) -> io::Result<()> {
+    if fs::rename(src, dest).is_ok() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    if fs::symlink_metadata(src)?.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(src)?, dest)?;
+
+        return fs::remove_file(src);
+    }
+
+    fs::copy(src, dest)?;
+    fs::remove_file(src)
+}
+
+impl Transaction {
+    // move the existing file away before it is written or removed, it is restored on rollback
+    pub fn backup(&mut self, file: &Path) -> Result<(), Report> {
+        // the broken symlink does not exist, but it should be moved as well
+        let backup = if fs::symlink_metadata(file).is_ok() {
+            let backup = self.backup_dir.join(self.changes.len().to_string());
+
+            move_file(file, &backup)?;
+
+            Some(backup)
+        } else {
+            None
+        };
+
+        self.changes.push((file.to_path_buf(), backup));
+
+        Ok(())
+    }
+
+    // move the staged file into place, the existing file is backed up
+    pub fn replace(&mut self, staged_file: &Path, file: &Path) -> Result<(), Report> {
+        self.backup(file)?;
+
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        move_file(staged_file, file)?;
+
+        Ok(())
+    }
+
+    // keep the changes and remove the backups
+    pub fn commit(self) -> Result<(), Report> {
+        fs::remove_dir_all(&self.backup_dir)?;
+
+        Ok(())
+    }
+
+    // restore the backups in reverse order, the new files are removed
+    pub fn rollback(self) -> Result<(), Report> {
+        for (file, backup) in self.changes.iter().rev() {
+            if fs::symlink_metadata(file).is_ok() {
+                fs::remove_file(file)?;
+            }
+
+            if let Some(backup) = backup {
+                move_file(backup, file)?;
+            }
+        }
+
+        fs::remove_dir_all(&self.backup_dir)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::transaction;
+
+    #[test]
+    fn test_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let staged_file = dir.path().join("staged");
+        let file = dir.path().join("bin").join("foo");
+        let backup_dir = dir.path().join(".backup");
+
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "old").unwrap();
+        fs::write(&staged_file, "new").unwrap();
+
+        let mut t = transaction::new(&backup_dir).unwrap();
+
+        t.replace(&staged_file, &file).unwrap();
+        t.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert!(!staged_file.exists());
+        assert!(!backup_dir.exists());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let staged_file = dir.path().join("staged");
+        let file = dir.path().join("foo");
+        let new_file = dir.path().join("bar");
+        let removed_file = dir.path().join("baz");
+        let backup_dir = dir.path().join(".backup");
+
+        fs::write(&file, "old").unwrap();
+        fs::write(&staged_file, "new").unwrap();
+        fs::write(&removed_file, "removed").unwrap();
+
+        let mut t = transaction::new(&backup_dir).unwrap();
+
+        t.replace(&staged_file, &file).unwrap();
+        t.backup(&new_file).unwrap();
+        fs::write(&new_file, "new").unwrap();
+        t.backup(&removed_file).unwrap();
+
+        assert!(!removed_file.exists());
+
+        t.rollback().unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&removed_file).unwrap(), "removed");
+        assert!(!new_file.exists());
+        assert!(!backup_dir.exists());
+    }
+}
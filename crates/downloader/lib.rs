@@ -1,12 +1,93 @@
 #![deny(warnings)]
 
+mod progress;
 mod rustls;
+mod throttle;
 
 use core::result::Result;
-use std::path::Path;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use eyre::Report;
 
-pub async fn download(url: &str, filepath: &Path) -> Result<(), Report> {
-    rustls::download(url, filepath).await
+pub use progress::{
+    is_hidden, new_overall_bar, set_color, set_plain, set_progress_handler, suspend, ProgressEvent,
+    ProgressHandler,
+};
+
+// the error that may be recovered by retrying, eg. timeout or 5xx response
+#[derive(Debug)]
+pub(crate) struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+// whether the download error may be recovered by retrying
+pub fn is_transient(err: &Report) -> bool {
+    err.downcast_ref::<TransientError>().is_some()
+}
+
+#[derive(Default)]
+pub struct DownloadOption {
+    pub verbose: bool,                     // include the response diagnostics in error
+    pub headers: Vec<(String, String)>,    // the extra request headers. eg. Authorization
+    pub connect_timeout: Option<Duration>, // the timeout of connecting to the server
+    pub read_timeout: Option<Duration>,    // the timeout of waiting for the response data
+    pub ca_file: Option<PathBuf>, // the extra CA certificates in PEM format. eg. the CA of enterprise proxy
+    pub insecure: bool,           // do not verify the certificate of server
+    pub limit_rate: Option<u64>,  // the max bytes per second of all downloads
+    pub proxy: Option<String>, // the proxy of requests, the proxy of environment is used if not set
+}
+
+// the validators of response for the conditional request
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,          // the ETag header of response
+    pub last_modified: Option<String>, // the Last-Modified header of response
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FetchResult {
+    Modified(String, Validators), // the content and its validators
+    NotModified,                  // the content is not changed since the validators
+    NotFound,                     // the resource does not exist
+}
+
+// fetch the text content of URL, eg. the response of API. return None if the resource does not exist
+pub async fn fetch_text(url: &str, options: &DownloadOption) -> Result<Option<String>, Report> {
+    match rustls::fetch_text(url, options, None).await? {
+        FetchResult::Modified(text, _) => Ok(Some(text)),
+        _ => Ok(None),
+    }
+}
+
+// fetch the text content of URL with the validators of the cached content
+pub async fn fetch_text_conditional(
+    url: &str,
+    options: &DownloadOption,
+    validators: &Validators,
+) -> Result<FetchResult, Report> {
+    rustls::fetch_text(url, options, Some(validators)).await
+}
+
+// download the resource to the file
+pub async fn download(url: &str, filepath: &Path, options: &DownloadOption) -> Result<(), Report> {
+    rustls::download(url, filepath, options).await
+}
+
+// download the resource and pass the chunks to the callback, return the downloaded size
+pub async fn download_stream<F: FnMut(&[u8]) -> Result<(), Report>>(
+    url: &str,
+    options: &DownloadOption,
+    on_chunk: F,
+) -> Result<u64, Report> {
+    rustls::download_stream(url, options, on_chunk).await
 }
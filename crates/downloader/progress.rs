@@ -0,0 +1,168 @@
+#![deny(warnings)]
+
+use std::{sync::OnceLock, time::Duration};
+
+use console::Term;
+use eyre::Report;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use tracing::info;
+
+// the progress bars of the concurrent downloads are drawn together, so that they do not overwrite each other
+static MULTI_PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+fn multi_progress() -> &'static MultiProgress {
+    MULTI_PROGRESS.get_or_init(MultiProgress::new)
+}
+
+// the progress of download, it is reported to the handler of the program that embeds the downloader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressEvent<'a> {
+    Start {
+        url: &'a str,
+        total_size: Option<u64>, // it is unknown if the response is chunked
+    },
+    Update {
+        url: &'a str,
+        downloaded: u64,
+        total_size: Option<u64>,
+    },
+    Finish {
+        url: &'a str,
+        downloaded: u64,
+    },
+}
+
+pub type ProgressHandler = dyn Fn(&ProgressEvent) + Send + Sync;
+
+static PROGRESS_HANDLER: OnceLock<Box<ProgressHandler>> = OnceLock::new();
+
+// report the progress of downloads to the handler instead of drawing the progress bars
+pub fn set_progress_handler(handler: Box<ProgressHandler>) {
+    PROGRESS_HANDLER.set(handler).ok();
+    set_plain();
+}
+
+fn report(event: ProgressEvent) {
+    if let Some(handler) = PROGRESS_HANDLER.get() {
+        handler(&event);
+    }
+}
+
+// force the colors and progress bars on or off, they are detected by the terminal and NO_COLOR by default.
+// the progress bars are drawn even if stderr is not a terminal if enabled, eg. the output panel of IDE
+pub fn set_color(enabled: bool) {
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+
+    let target = if enabled {
+        ProgressDrawTarget::term_like_with_hz(Box::new(Term::buffered_stderr()), 20)
+    } else {
+        ProgressDrawTarget::hidden()
+    };
+
+    multi_progress().set_draw_target(target);
+}
+
+// print the plain status lines instead of the progress bars, eg. the logs of CI are read later
+pub fn set_plain() {
+    multi_progress().set_draw_target(ProgressDrawTarget::hidden());
+}
+
+// add the progress bar below the others
+pub(crate) fn add(pb: ProgressBar) -> ProgressBar {
+    multi_progress().add(pb)
+}
+
+// whether the progress bars are drawn, they are hidden if stderr is not a terminal
+pub fn is_hidden() -> bool {
+    multi_progress().is_hidden()
+}
+
+// run the function with the progress bars cleared, eg. print the log line above them
+pub fn suspend<F: FnOnce() -> R, R>(f: F) -> R {
+    multi_progress().suspend(f)
+}
+
+// the overall progress of the tasks above their progress bars, eg. the downloads of multiple packages
+pub fn new_overall_bar(total: u64, message: String) -> ProgressBar {
+    let pb = multi_progress().insert(0, ProgressBar::new(total));
+
+    if let Ok(style) = ProgressStyle::default_bar().template("{msg} [{pos}/{len}]") {
+        pb.set_style(style);
+    }
+
+    pb.set_message(message);
+
+    pb
+}
+
+// the progress of a download, it is a spinner if the total size is unknown.
+// the status is printed line by line if the progress bar is hidden, eg. in CI
+pub(crate) struct Download<'a> {
+    url: &'a str,
+    total_size: Option<u64>,
+    pb: ProgressBar,
+}
+
+impl<'a> Download<'a> {
+    pub(crate) fn new(url: &'a str, total_size: Option<u64>) -> Result<Download<'a>, Report> {
+        if is_hidden() {
+            match total_size {
+                Some(total_size) => info!("Downloading {} ({})", url, HumanBytes(total_size)),
+                None => info!("Downloading {}", url),
+            }
+        }
+
+        let pb = match total_size {
+            Some(total_size) => {
+                let progress_template = "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})";
+                let pb = add(ProgressBar::new(total_size));
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .progress_chars("#>-")
+                        .template(progress_template)?,
+                );
+                pb
+            }
+            None => {
+                let progress_template =
+                    "{msg}\n{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})";
+                let pb = add(ProgressBar::new_spinner());
+                pb.set_style(ProgressStyle::default_spinner().template(progress_template)?);
+                pb.enable_steady_tick(Duration::from_millis(100));
+                pb
+            }
+        };
+        pb.set_message(format!("Downloading {}", url));
+
+        report(ProgressEvent::Start { url, total_size });
+
+        Ok(Download {
+            url,
+            total_size,
+            pb,
+        })
+    }
+
+    pub(crate) fn set_position(&self, downloaded: u64) {
+        self.pb.set_position(downloaded);
+
+        report(ProgressEvent::Update {
+            url: self.url,
+            downloaded,
+            total_size: self.total_size,
+        });
+    }
+
+    // the finished progress bar is replaced by the line, so that the others keep in place
+    pub(crate) fn finish(self, downloaded: u64) {
+        self.pb.finish_and_clear();
+
+        info!("Downloaded {} ({})", self.url, HumanBytes(downloaded));
+
+        report(ProgressEvent::Finish {
+            url: self.url,
+            downloaded,
+        });
+    }
+}
@@ -1,69 +1,493 @@
 #![deny(warnings)]
 
 use core::result::Result;
-use std::{cmp::min, fs, fs::File, io::Write, path::Path};
+use std::{
+    cmp::min,
+    fs,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use eyre::Report;
+
+use crate::{
+    progress, throttle::throttle, DownloadOption, FetchResult, TransientError, Validators,
+};
 use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use reqwest::{
+    header::{
+        CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+    },
+    Certificate, Client, Proxy, Response, StatusCode,
+};
+
+// the user agent of request
+const USER_AGENT: &str = "cask";
+
+// the max length of response body that print in diagnostics
+const DIAGNOSTIC_BODY_LIMIT: usize = 512;
+
+async fn describe_response(url: &str, res: Response, is_verbose: bool) -> String {
+    let mut lines = vec![format!(
+        "Download {} fail with http code {}",
+        url,
+        res.status()
+    )];
+
+    if res.url().as_str() != url {
+        lines.push(format!("Final URL: {}", res.url()));
+    }
+
+    if is_verbose {
+        for (name, value) in res.headers() {
+            let name = name.as_str();
+
+            if name.starts_with("x-ratelimit-") || name == "retry-after" || name == "content-type" {
+                lines.push(format!("{}: {}", name, value.to_str().unwrap_or_default()));
+            }
+        }
+    }
+
+    // the body usually tells the reason. eg. the error message of API
+    if let Ok(body) = res.bytes().await {
+        let len = min(body.len(), DIAGNOSTIC_BODY_LIMIT);
+        let snippet = String::from_utf8_lossy(&body[..len]);
+
+        if !snippet.trim().is_empty() {
+            lines.push(format!("Response body: {}", snippet.trim()));
+        }
+    }
+
+    lines.join("\n")
+}
+
+// the error of unexpected response status, the server error may be recovered by retrying
+fn status_error(status: StatusCode, msg: String) -> Report {
+    if status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+    {
+        return TransientError(msg).into();
+    }
+
+    eyre::format_err!("{}", msg)
+}
+
+// the partial file that keeps the downloaded bytes for resuming
+fn get_part_filepath(filepath: &Path) -> PathBuf {
+    let mut filename = filepath.file_name().unwrap_or_default().to_os_string();
+    filename.push(".part");
+
+    filepath.with_file_name(filename)
+}
+
+// the validator of the partial file, the download is resumed only if the resource is not changed since then
+fn get_validator_filepath(filepath: &Path) -> PathBuf {
+    let mut filename = filepath.file_name().unwrap_or_default().to_os_string();
+    filename.push(".part.validator");
+
+    filepath.with_file_name(filename)
+}
+
+// the strong ETag or the Last-Modified of response, the weak ETag can not be used by If-Range.
+// see https://httpwg.org/specs/rfc9110.html#field.if-range
+fn get_validator(res: &Response) -> Option<String> {
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+}
+
+// the client with the timeout and TLS settings of option
+fn build_client(options: &DownloadOption) -> Result<Client, Report> {
+    // some API requires the user agent. eg. GitHub API
+    let mut builder = Client::builder().user_agent(USER_AGENT);
+
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+
+    if let Some(timeout) = options.read_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+
+    if let Some(ca_file) = &options.ca_file {
+        let pem = fs::read(ca_file)
+            .map_err(|e| eyre::format_err!("read CA file '{}' fail: {}", ca_file.display(), e))?;
+
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| eyre::format_err!("parse CA file '{}' fail: {}", ca_file.display(), e))?;
+
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if options.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    if let Some(proxy) = &options.proxy {
+        let proxy =
+            Proxy::all(proxy).map_err(|e| eyre::format_err!("invalid proxy '{}': {}", proxy, e))?;
+
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+// fetch the text content of URL, the request is conditional if the validators are provided
+pub(crate) async fn fetch_text(
+    url: &str,
+    options: &DownloadOption,
+    validators: Option<&Validators>,
+) -> Result<FetchResult, Report> {
+    let client = build_client(options)?;
+
+    let mut req = client.get(url);
+
+    for (name, value) in &options.headers {
+        req = req.header(name, value);
+    }
+
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &validators.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let res = req
+        .send()
+        .await
+        .map_err(|e| TransientError(format!("Fetch {} fail: {}", url, e)))?;
+
+    let status = res.status();
+
+    if status == StatusCode::NOT_FOUND {
+        return Ok(FetchResult::NotFound);
+    }
+
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(FetchResult::NotModified);
+    }
+
+    if !status.is_success() {
+        let msg = describe_response(url, res, options.verbose).await;
+
+        return Err(status_error(status, msg));
+    }
+
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+
+    let text = res
+        .text()
+        .await
+        .map_err(|e| TransientError(format!("Fetch {} fail: {}", url, e)))?;
+
+    Ok(FetchResult::Modified(text, validators))
+}
+
+pub(crate) async fn download(
+    url: &str,
+    filepath: &Path,
+    options: &DownloadOption,
+) -> Result<(), Report> {
+    let client = &build_client(options)?;
+
+    let part_filepath = get_part_filepath(filepath);
+    let validator_filepath = get_validator_filepath(filepath);
+
+    // the partial file without validator can not be resumed, it may be of another version of resource
+    let validator = fs::read_to_string(&validator_filepath)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+
+    let mut downloaded: u64 = match (fs::metadata(&part_filepath), &validator) {
+        (Ok(meta), Some(_)) if meta.is_file() => meta.len(),
+        _ => 0,
+    };
+
+    let mut req = client.get(url);
+
+    for (name, value) in &options.headers {
+        req = req.header(name, value);
+    }
+
+    if let (true, Some(validator)) = (downloaded > 0, &validator) {
+        // the server responds the whole resource if it has been changed
+        req = req
+            .header(RANGE, format!("bytes={}-", downloaded))
+            .header(IF_RANGE, validator);
+    }
+
+    let res = req
+        .send()
+        .await
+        .map_err(|e| TransientError(format!("Download {} fail: {}", url, e)))?;
+
+    if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // the partial file is stale, download from scratch
+        fs::remove_file(&part_filepath)?;
+        fs::remove_file(&validator_filepath).ok();
+
+        return Box::pin(download(url, filepath, options)).await;
+    }
+
+    // the range of response must start from the end of partial file. eg. 'bytes 100-199/200'
+    let is_resumed = res.status() == StatusCode::PARTIAL_CONTENT
+        && res
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with(&format!("bytes {}-", downloaded)));
+
+    if res.status() == StatusCode::PARTIAL_CONTENT && !is_resumed {
+        fs::remove_file(&part_filepath)?;
+        fs::remove_file(&validator_filepath).ok();
+
+        return Err(TransientError(format!(
+            "Download {} fail, the range of response does not match the partial file",
+            url
+        ))
+        .into());
+    }
 
-pub(crate) async fn download(url: &str, filepath: &Path) -> Result<(), Report> {
-    let client = &Client::new();
+    if res.status() != StatusCode::OK && !is_resumed {
+        let status = res.status();
+        let msg = describe_response(url, res, options.verbose).await;
 
-    let res = client.get(url).send().await?;
+        return Err(status_error(status, msg));
+    }
 
-    if res.status() != 200 {
-        return Err(eyre::format_err!(
-            "Download {} fail with http code {}",
-            &url,
-            res.status()
-        ));
+    if !is_resumed {
+        // the server does not support range request, or the resource has been changed
+        downloaded = 0;
+
+        // the validator of the new partial file, it can not be resumed if the server does not provide one
+        match get_validator(&res) {
+            Some(validator) => fs::write(&validator_filepath, validator)?,
+            None => {
+                if validator_filepath.exists() {
+                    fs::remove_file(&validator_filepath)?;
+                }
+            }
+        }
     }
 
-    let total_size = res
-        .content_length()
-        .ok_or_else(|| eyre::format_err!("Failed to get content length from {}", &url))?;
+    // the content length is unknown if the response is chunked or streamed
+    let total_size = res.content_length().map(|len| downloaded + len);
+
+    let pb = progress::Download::new(url, total_size)?;
+    pb.set_position(downloaded);
+
+    let mut dest = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(is_resumed)
+        .truncate(!is_resumed)
+        .open(&part_filepath)?;
+
+    let mut stream = res.bytes_stream();
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| {
+            TransientError(format!(
+                "Error while downloading {} after {} bytes: {}",
+                url, downloaded, e
+            ))
+        })?;
+
+        dest.write_all(&chunk).map_err(|e| {
+            eyre::format_err!("Error while write file '{}': {}", filepath.display(), e)
+        })?;
 
-    let progress_template = "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})";
-    let pb = ProgressBar::new(total_size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .progress_chars("#>-")
-            .template(progress_template)?,
-    );
-    pb.set_message(format!("Downloading {}", url));
+        downloaded += chunk.len() as u64;
+
+        if let Some(total_size) = total_size {
+            downloaded = min(downloaded, total_size);
+        }
+
+        pb.set_position(downloaded);
+
+        if let Some(rate) = options.limit_rate {
+            throttle(chunk.len() as u64, rate).await;
+        }
+    }
+
+    pb.finish(downloaded);
+
+    drop(dest);
+
+    // keep the partial file for resuming if the download is incomplete
+    let part_size = fs::metadata(&part_filepath)?.len();
+
+    if let Some(total_size) = total_size {
+        if part_size != total_size {
+            return Err(TransientError(format!(
+                "Download {} incomplete, expect {} bytes but got {} bytes",
+                url, total_size, part_size
+            ))
+            .into());
+        }
+    }
 
     if filepath.exists() {
         fs::remove_file(filepath)?;
     }
 
-    let mut dest = File::create(filepath)?;
+    fs::rename(&part_filepath, filepath)?;
+
+    fs::remove_file(&validator_filepath).ok();
+
+    Ok(())
+}
+
+// download the resource and pass the chunks to the callback, the download can not be resumed
+pub(crate) async fn download_stream<F: FnMut(&[u8]) -> Result<(), Report>>(
+    url: &str,
+    options: &DownloadOption,
+    mut on_chunk: F,
+) -> Result<u64, Report> {
+    let client = build_client(options)?;
+
+    let mut req = client.get(url);
+
+    for (name, value) in &options.headers {
+        req = req.header(name, value);
+    }
+
+    let res = req
+        .send()
+        .await
+        .map_err(|e| TransientError(format!("Download {} fail: {}", url, e)))?;
+
+    let status = res.status();
+
+    if status != StatusCode::OK {
+        let msg = describe_response(url, res, options.verbose).await;
+
+        return Err(status_error(status, msg));
+    }
+
+    let total_size = res.content_length();
+
+    let pb = progress::Download::new(url, total_size)?;
 
     let mut downloaded: u64 = 0;
+
     let mut stream = res.bytes_stream();
 
     while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|_| eyre::format_err!("Error while downloading file"))?;
+        let chunk = item.map_err(|e| {
+            TransientError(format!(
+                "Error while downloading {} after {} bytes: {}",
+                url, downloaded, e
+            ))
+        })?;
 
-        dest.write_all(&chunk)
-            .map_err(|_| eyre::format_err!("Error while write file"))?;
+        on_chunk(&chunk)?;
 
-        downloaded = min(downloaded + (chunk.len() as u64), total_size);
+        downloaded += chunk.len() as u64;
 
         pb.set_position(downloaded);
+
+        if let Some(rate) = options.limit_rate {
+            throttle(chunk.len() as u64, rate).await;
+        }
     }
 
-    pb.finish();
+    pb.finish(downloaded);
 
-    Ok(())
+    if let Some(total_size) = total_size {
+        if downloaded != total_size {
+            return Err(TransientError(format!(
+                "Download {} incomplete, expect {} bytes but got {} bytes",
+                url, total_size, downloaded
+            ))
+            .into());
+        }
+    }
+
+    Ok(downloaded)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{env, fs};
+    use std::{
+        env, fs,
+        io::{Read, Write},
+        net::TcpListener,
+        path::Path,
+        sync::mpsc,
+        thread,
+    };
+
+    use crate::{
+        download, download_stream,
+        rustls::{get_part_filepath, get_validator_filepath},
+        DownloadOption,
+    };
+
+    // serve the raw HTTP response once, return the URL of server and the request it receives
+    fn serve_request(response: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let mut request = vec![];
+
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            // the request is ignored if the receiver is dropped
+            tx.send(String::from_utf8_lossy(&request).to_lowercase())
+                .ok();
+
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        (format!("http://{}/file", addr), rx)
+    }
 
-    use crate::download;
+    fn serve_once(response: &'static str) -> String {
+        serve_request(response).0
+    }
+
+    #[test]
+    fn test_get_part_filepath() {
+        assert_eq!(
+            get_part_filepath(Path::new("/tmp/1.0.0.tar.gz")),
+            Path::new("/tmp/1.0.0.tar.gz.part")
+        );
+    }
 
     #[tokio::test]
 
@@ -75,7 +499,9 @@ mod tests {
 
         let dest = cwd.join("cask_darwin_amd64.tar.gz");
 
-        download(url, &dest).await.unwrap();
+        download(url, &dest, &DownloadOption::default())
+            .await
+            .unwrap();
 
         assert!(dest.exists());
 
@@ -87,6 +513,119 @@ mod tests {
         fs::remove_file(&dest).unwrap();
     }
 
+    #[tokio::test]
+    async fn test_download_without_content_length() {
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file");
+
+        download(&url, &dest, &DownloadOption::default())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_download_resume() {
+        let (url, request) = serve_request(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-10/11\r\nContent-Length: 6\r\nConnection: close\r\n\r\n world",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file");
+
+        fs::write(get_part_filepath(&dest), "hello").unwrap();
+        fs::write(get_validator_filepath(&dest), "\"v1\"").unwrap();
+
+        download(&url, &dest, &DownloadOption::default())
+            .await
+            .unwrap();
+
+        let request = request.recv().unwrap();
+
+        assert!(request.contains("range: bytes=5-\r\n"));
+        assert!(request.contains("if-range: \"v1\"\r\n"));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello world");
+        assert!(!get_validator_filepath(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_restart() {
+        // the resource has been changed since the partial file is downloaded
+        let (url, _) = serve_request(
+            "HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file");
+
+        fs::write(get_part_filepath(&dest), "stale").unwrap();
+        fs::write(get_validator_filepath(&dest), "\"v1\"").unwrap();
+
+        download(&url, &dest, &DownloadOption::default())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello world");
+
+        // the partial file without validator is never resumed
+        let (url, request) = serve_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        );
+
+        fs::remove_file(&dest).unwrap();
+        fs::write(get_part_filepath(&dest), "stale").unwrap();
+
+        download(&url, &dest, &DownloadOption::default())
+            .await
+            .unwrap();
+
+        assert!(!request.recv().unwrap().contains("range:"));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_download_stream() {
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        );
+
+        let mut content = vec![];
+
+        let size = download_stream(&url, &DownloadOption::default(), |chunk| {
+            content.extend_from_slice(chunk);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(size, 11);
+        assert_eq!(content, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_download_not_found() {
+        let url = serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nConnection: close\r\n\r\nnot found",
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("file");
+
+        let err = download(&url, &dest, &DownloadOption::default())
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("404"));
+        assert!(err.contains("Response body: not found"));
+        assert!(!dest.exists());
+    }
+
     #[tokio::test]
 
     async fn test_download_invalid_url() {
@@ -96,7 +635,7 @@ mod tests {
 
         let dest = cwd.join("cask_darwin_amd64.tar.gz");
 
-        let r = download(url, &dest).await;
+        let r = download(url, &dest, &DownloadOption::default()).await;
 
         assert!(r.is_err())
     }
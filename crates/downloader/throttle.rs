@@ -0,0 +1,37 @@
+#![deny(warnings)]
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// the time when the next chunk is allowed to be received.
+// it is shared by all downloads, so that the concurrent downloads do not exceed the limit in total
+static NEXT_SLOT: Mutex<Option<Instant>> = Mutex::new(None);
+
+// the time to wait for receiving the bytes with the rate limit in bytes per second
+fn reserve(bytes: u64, rate: u64) -> Duration {
+    let now = Instant::now();
+    let cost = Duration::from_secs_f64(bytes as f64 / rate.max(1) as f64);
+
+    let mut next_slot = NEXT_SLOT.lock().unwrap();
+
+    // the idle time is not accumulated, otherwise it bursts after idle
+    let start = match *next_slot {
+        Some(slot) if slot > now => slot,
+        _ => now,
+    };
+
+    *next_slot = Some(start + cost);
+
+    (start + cost).saturating_duration_since(now)
+}
+
+// sleep until the received bytes fit in the rate limit
+pub(crate) async fn throttle(bytes: u64, rate: u64) {
+    let wait = reserve(bytes, rate);
+
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
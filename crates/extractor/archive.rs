@@ -5,48 +5,125 @@ use regex::Regex;
 use std::{io::Read, path::Path};
 
 use eyre::Report;
-use tar::Archive;
+use tar::{Archive, EntryType};
 
-pub(crate) fn extract<R: Read>(
-    reader: R,
+// the max depth of symlink chain in the tarball. eg. tool -> tool-1.2.3 -> bin/tool
+pub(crate) const MAX_SYMLINK_DEPTH: usize = 8;
+
+// normalize the path in archive to the absolute path. eg. './bin/../tool' -> '/tool'
+pub(crate) fn normalize_path(path: &str) -> String {
+    let mut components: Vec<&str> = vec![];
+
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            c => components.push(c),
+        }
+    }
+
+    format!("/{}", components.join("/"))
+}
+
+// resolve the target of link in archive. the relative target is based on the folder of link
+pub(crate) fn resolve_link(link_path: &str, target: &str) -> String {
+    if target.starts_with('/') {
+        return normalize_path(target);
+    }
+
+    let parent = link_path.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+
+    normalize_path(&format!("{}/{}", parent, target))
+}
+
+// the tarball is opened again if the symlink points to the file that has been passed
+pub(crate) fn extract<R: Read, F: Fn() -> Result<R, Report>>(
+    open: F,
     filename: &str,
     folder: &str,
     dest: &Path,
 ) -> Result<(), Report> {
-    let mut archive = Archive::new(reader);
-    archive.set_unpack_xattrs(true);
-    archive.set_overwrite(true);
-    archive.set_preserve_permissions(true);
-    archive.set_preserve_mtime(true);
+    let mut target_file_path = normalize_path(&format!("{}/{}", folder, filename));
+
+    let re = Regex::new(r"^GNUSparseFile\.\d+/").unwrap();
 
-    let files = archive.entries()?.flatten();
+    let mut visited: Vec<String> = vec![];
 
-    let target_file_path = format!("{}/{}", folder, filename).replace("//", "/");
+    while visited.len() <= MAX_SYMLINK_DEPTH {
+        let mut archive = Archive::new(open()?);
+        archive.set_unpack_xattrs(true);
+        archive.set_overwrite(true);
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_mtime(true);
 
-    for mut entry in files {
-        let file_path = entry.path()?;
+        let files = archive.entries()?.flatten();
 
-        let re = Regex::new(r"^GNUSparseFile\.\d+/").unwrap();
+        let mut is_redirected = false;
 
-        // GNUSparseFile.0/gpm
-        // ./gpm
-        // /gpm
-        let relative_path = format!("{}", file_path.display());
+        for mut entry in files {
+            let file_path = entry.path()?;
 
-        let absolute_path = format!(
-            "/{}",
-            re.replace_all(
-                relative_path
-                    .trim_start_matches("./")
-                    .trim_start_matches('/'),
-                ""
-            )
-        );
+            // GNUSparseFile.0/gpm
+            // ./gpm
+            // /gpm
+            let relative_path = format!("{}", file_path.display());
+
+            let absolute_path = normalize_path(
+                &re.replace_all(
+                    relative_path
+                        .trim_start_matches("./")
+                        .trim_start_matches('/'),
+                    "",
+                ),
+            );
+
+            if target_file_path != absolute_path {
+                continue;
+            }
+
+            let entry_type = entry.header().entry_type();
+
+            // follow the link to the real file. the hard link is relative to the root of tarball
+            if entry_type == EntryType::Symlink || entry_type == EntryType::Link {
+                let link_name = entry.link_name()?.ok_or_else(|| {
+                    eyre::format_err!("the link '{}' has no target in tarball", absolute_path)
+                })?;
+
+                let link_name = format!("{}", link_name.display());
+
+                visited.push(target_file_path);
+
+                target_file_path = if entry_type == EntryType::Symlink {
+                    resolve_link(&absolute_path, &link_name)
+                } else {
+                    normalize_path(&link_name)
+                };
+
+                if visited.contains(&target_file_path) {
+                    return Err(eyre::format_err!(
+                        "the symlink '{}' is circular in tarball",
+                        absolute_path
+                    ));
+                }
+
+                is_redirected = true;
+
+                continue;
+            }
+
+            if std::fs::symlink_metadata(dest).is_ok() {
+                std::fs::remove_file(dest)?;
+            }
 
-        if target_file_path == absolute_path {
             entry.unpack(dest)?;
             return Ok(());
         }
+
+        if !is_redirected {
+            break;
+        }
     }
 
     Err(eyre::format_err!(
@@ -0,0 +1,177 @@
+#![deny(warnings)]
+
+use core::result::Result;
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use bzip2_rs::DecoderReader;
+use eyre::Report;
+use libflate::gzip::Decoder as GzDecoder;
+
+use crate::{archive, decompress};
+
+// the .deb package is an ar archive, the files are in the member 'data.tar.*'.
+// see https://manpages.debian.org/unstable/dpkg-dev/deb.5.en.html
+pub(crate) fn extract(
+    src_filepath: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+) -> Result<PathBuf, Report> {
+    let output_file_path = dest_dir.join(filename);
+
+    let mut file = BufReader::new(File::open(src_filepath)?);
+
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)?;
+
+    if &magic != b"!<arch>\n" {
+        return Err(eyre::format_err!("it is not a deb package"));
+    }
+
+    loop {
+        let mut header = [0u8; 60];
+
+        match file.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        // the GNU ar terminates the name with '/'
+        let name = String::from_utf8_lossy(&header[0..16])
+            .trim_end()
+            .trim_end_matches('/')
+            .to_string();
+
+        let size = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| eyre::format_err!("invalid size of member '{}': {}", name, e))?;
+
+        if !name.starts_with("data.tar") {
+            // the member is padded to even size
+            io::copy(&mut (&mut file).take(size + size % 2), &mut io::sink())?;
+            continue;
+        }
+
+        // the member is read again if the symlink points to the file that has been passed
+        let offset = file.stream_position()?;
+
+        let open_member = || -> Result<io::Take<BufReader<File>>, Report> {
+            let mut file = BufReader::new(File::open(src_filepath)?);
+            file.seek(SeekFrom::Start(offset))?;
+
+            Ok(file.take(size))
+        };
+
+        match name.as_str() {
+            "data.tar" => archive::extract(open_member, filename, folder, &output_file_path)?,
+            "data.tar.gz" => archive::extract(
+                || Ok(GzDecoder::new(open_member()?)?),
+                filename,
+                folder,
+                &output_file_path,
+            )?,
+            "data.tar.bz2" => archive::extract(
+                || Ok(DecoderReader::new(open_member()?)),
+                filename,
+                folder,
+                &output_file_path,
+            )?,
+            "data.tar.zst" => archive::extract(
+                || decompress::zstd(open_member()?),
+                filename,
+                folder,
+                &output_file_path,
+            )?,
+            "data.tar.xz" => archive::extract(
+                || decompress::xz(open_member()?),
+                filename,
+                folder,
+                &output_file_path,
+            )?,
+            _ => {
+                return Err(eyre::format_err!(
+                    "not support the member '{}' of deb package",
+                    name
+                ))
+            }
+        }
+
+        return Ok(output_file_path);
+    }
+
+    Err(eyre::format_err!(
+        "can not found the member 'data.tar' in deb package"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::extract;
+
+    #[test]
+    fn test_extract_deb_00() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("deb");
+
+        let tar_file_path = extractor_dir.join("00.deb");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let meta = fs::metadata(&extracted_file_path).unwrap();
+
+        assert_eq!(meta.len(), 2);
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_deb_01() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("deb");
+
+        let tar_file_path = extractor_dir.join("01.deb");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_deb_02() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("deb");
+
+        let tar_file_path = extractor_dir.join("02.deb");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(&extracted_file_path).ok();
+
+        let r = extract(&tar_file_path, &dest_dir, "00.txt", "/");
+
+        assert!(r.is_err());
+    }
+}
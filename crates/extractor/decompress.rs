@@ -0,0 +1,167 @@
+#![deny(warnings)]
+
+use core::result::Result;
+use std::{
+    io::{self, BufReader, Read},
+    sync::RwLock,
+    time::Instant,
+};
+
+use eyre::Report;
+use liblzma::{
+    read::XzDecoder,
+    stream::{MtStreamBuilder, Stream, CONCATENATED},
+};
+use tracing::debug;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+// the options of decompressing the xz and zstd streams, they are shared by all extractions of the process
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DecompressOption {
+    pub memory_limit: Option<u64>, // the max memory of decoder, the archive that needs more is refused
+    pub threads: Option<u32>, // the threads of decoding the multi-block xz stream, it is decoded in the current thread if None
+}
+
+static OPTION: RwLock<DecompressOption> = RwLock::new(DecompressOption {
+    memory_limit: None,
+    threads: None,
+});
+
+pub fn set_decompress_option(option: DecompressOption) {
+    if let Ok(mut o) = OPTION.write() {
+        *o = option;
+    }
+}
+
+fn get_option() -> DecompressOption {
+    OPTION.read().map(|o| *o).unwrap_or_default()
+}
+
+// count the bytes that are decompressed, the throughput is logged when the decoder is dropped
+pub(crate) struct Metered<R> {
+    inner: R,
+    format: &'static str, // the format of compressed stream. eg. xz
+    bytes: u64,           // the bytes that are decompressed
+    start: Instant,       // the time of creating the decoder
+}
+
+impl<R> Metered<R> {
+    fn new(inner: R, format: &'static str) -> Self {
+        Metered {
+            inner,
+            format,
+            bytes: 0,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<R: Read> Read for Metered<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        self.bytes += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl<R> Drop for Metered<R> {
+    fn drop(&mut self) {
+        if self.bytes == 0 {
+            return;
+        }
+
+        let elapsed = self.start.elapsed();
+
+        debug!(
+            "decompressed {} bytes of {} in {:.2?}, {:.2} MiB/s",
+            self.bytes,
+            self.format,
+            elapsed,
+            self.bytes as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64().max(f64::EPSILON)
+        );
+    }
+}
+
+// the decoder of .xz, the concatenated streams are decoded if it runs in the current thread.
+// the multi-threaded decoder of liblzma stops at the end of the first stream,
+// and it only decodes in parallel if the sizes of blocks are stored. eg. compressed by 'xz -T0'
+pub(crate) fn xz<R: Read>(reader: R) -> Result<Metered<XzDecoder<R>>, Report> {
+    let option = get_option();
+    let memory_limit = option.memory_limit.unwrap_or(u64::MAX);
+
+    let stream = match option.threads.filter(|t| *t > 1) {
+        Some(threads) => MtStreamBuilder::new()
+            .threads(threads)
+            .memlimit_threading(memory_limit)
+            .memlimit_stop(memory_limit)
+            .decoder()?,
+        None => Stream::new_stream_decoder(memory_limit, CONCATENATED)?,
+    };
+
+    Ok(Metered::new(XzDecoder::new_stream(reader, stream), "xz"))
+}
+
+// the decoder of legacy .lzma
+pub(crate) fn lzma<R: Read>(reader: R) -> Result<Metered<XzDecoder<R>>, Report> {
+    let stream = Stream::new_lzma_decoder(get_option().memory_limit.unwrap_or(u64::MAX))?;
+
+    Ok(Metered::new(XzDecoder::new_stream(reader, stream), "lzma"))
+}
+
+// the decoder of .zst, the memory of zstd decoder is mostly the window of frame.
+// so the frame whose window is larger than the limit is refused
+pub(crate) fn zstd<R: Read>(
+    reader: R,
+) -> Result<Metered<ZstdDecoder<'static, BufReader<R>>>, Report> {
+    let mut decoder = ZstdDecoder::new(reader)?;
+
+    if let Some(limit) = get_option().memory_limit {
+        decoder.window_log_max(window_log(limit))?;
+    }
+
+    Ok(Metered::new(decoder, "zstd"))
+}
+
+// the max window log within the memory limit.
+// see https://github.com/facebook/zstd/blob/dev/lib/zstd.h
+fn window_log(memory_limit: u64) -> u32 {
+    let max = if cfg!(target_pointer_width = "64") {
+        31
+    } else {
+        30
+    };
+
+    (63 - memory_limit.max(1).leading_zeros()).clamp(10, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Read};
+
+    use crate::decompress;
+
+    #[test]
+    fn test_window_log() {
+        assert_eq!(decompress::window_log(0), 10);
+        assert_eq!(decompress::window_log(64 * 1024 * 1024), 26);
+        assert_eq!(decompress::window_log(100 * 1024 * 1024), 26);
+        assert_eq!(decompress::window_log(u64::MAX), 31);
+    }
+
+    #[test]
+    fn test_metered() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("txz");
+
+        let mut decoder =
+            decompress::xz(fs::File::open(extractor_dir.join("00.tar.xz")).unwrap()).unwrap();
+
+        let mut tarball = vec![];
+
+        decoder.read_to_end(&mut tarball).unwrap();
+
+        assert_eq!(decoder.bytes, tarball.len() as u64);
+        assert!(tarball.len() > 512);
+    }
+}
@@ -0,0 +1,99 @@
+#![deny(warnings)]
+
+use core::result::Result;
+use std::path::{Path, PathBuf};
+
+use eyre::Report;
+
+// the disk image is mounted by 'hdiutil', then copy the file out of it.
+// the CLI of .app bundle can be extracted with the folder like '/Foo.app/Contents/MacOS'
+#[cfg(target_os = "macos")]
+pub(crate) fn extract(
+    src_filepath: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+) -> Result<PathBuf, Report> {
+    use std::{fs, process::Command};
+
+    let output_file_path = dest_dir.join(filename);
+
+    let mount_point = std::env::temp_dir().join(format!("cask-dmg-{}", std::process::id()));
+
+    fs::create_dir_all(&mount_point)?;
+
+    let output = Command::new("hdiutil")
+        .arg("attach")
+        .arg("-nobrowse")
+        .arg("-readonly")
+        .arg("-noautoopen")
+        .arg("-mountpoint")
+        .arg(&mount_point)
+        .arg(src_filepath)
+        .output()?;
+
+    if !output.status.success() {
+        fs::remove_dir(&mount_point).ok();
+
+        return Err(eyre::format_err!(
+            "mount '{}' fail: {}",
+            src_filepath.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let target_file_path = mount_point
+        .join(folder.trim_start_matches('/'))
+        .join(filename);
+
+    let result = if target_file_path.is_file() {
+        fs::copy(&target_file_path, &output_file_path)
+            .map(|_| output_file_path)
+            .map_err(|e| e.into())
+    } else {
+        Err(eyre::format_err!(
+            "can not found file '{}' in the '{}' of disk image",
+            &filename,
+            folder
+        ))
+    };
+
+    // always detach the disk image, even if the copy fail
+    Command::new("hdiutil")
+        .arg("detach")
+        .arg(&mount_point)
+        .arg("-force")
+        .output()
+        .ok();
+
+    fs::remove_dir(&mount_point).ok();
+
+    result
+}
+
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn extract(
+    _src_filepath: &Path,
+    _dest_dir: &Path,
+    _filename: &str,
+    _folder: &str,
+) -> Result<PathBuf, Report> {
+    Err(eyre::format_err!("the .dmg is only supported on macOS"))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_extract_dmg_not_support() {
+        use std::env;
+
+        use crate::extract;
+
+        let extractor_dir = env::current_dir().unwrap().join("fixtures");
+
+        let r = extract(&extractor_dir.join("00.dmg"), &extractor_dir, "00.txt", "/");
+
+        assert!(r.is_err());
+    }
+}
@@ -1,14 +1,26 @@
 #![deny(warnings)]
 
 mod archive;
+mod deb;
+mod decompress;
+mod dmg;
+mod gz;
+mod path;
+mod rpm;
+mod sevenz;
 mod tar;
 mod tbz2;
 mod tgz;
+mod txz;
+mod tzst;
 mod zip;
+mod zst;
 
 use core::result::Result;
 use std::{
-    fs, io,
+    cell::RefCell,
+    fs,
+    io::{self, Read},
     path::{Path, PathBuf},
 };
 
@@ -16,6 +28,9 @@ use eyre::Report;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub use decompress::{set_decompress_option, DecompressOption};
+pub use path::{long_path, sanitize_file_name, short_path};
+
 #[derive(Error, Debug)]
 pub enum ExtractorError {
     #[error("can not create folder '{folder:?}': {source:?}")]
@@ -36,9 +51,18 @@ pub enum ExtractorError {
 pub enum Extension {
     TarGz,
     Tgz,
+    Gz,
     TarBiz2,
+    Tbz2,
+    TarXz,
+    TarZst,
+    Zst,
     Tar,
     Zip,
+    SevenZip,
+    Deb,
+    Rpm,
+    Dmg,
 }
 
 impl Extension {
@@ -46,9 +70,18 @@ impl Extension {
         match self {
             Extension::TarGz => ".tar.gz",
             Extension::Tgz => ".tgz",
+            Extension::Gz => ".gz",
             Extension::TarBiz2 => ".tar.bz2",
+            Extension::Tbz2 => ".tbz2",
+            Extension::TarXz => ".tar.xz",
+            Extension::TarZst => ".tar.zst",
+            Extension::Zst => ".zst",
             Extension::Tar => ".tar",
             Extension::Zip => ".zip",
+            Extension::SevenZip => ".7z",
+            Extension::Deb => ".deb",
+            Extension::Rpm => ".rpm",
+            Extension::Dmg => ".dmg",
         }
     }
 }
@@ -59,11 +92,15 @@ pub fn extract(
     filename: &str,
     folder: &str,
 ) -> Result<PathBuf, ExtractorError> {
+    // extract with the long path on Windows, the returned path is the short one
+    let tarball = &long_path(tarball);
+    let dest_dir = &long_path(dest_dir);
+
     let tar_file_name = tarball.file_name().unwrap().to_str().unwrap();
 
     let ensure_extract_file_exist = |s: &Path| {
         if s.exists() && s.is_file() {
-            Ok(s.to_owned())
+            Ok(short_path(s))
         } else {
             Err(ExtractorError::FindNotFoundInTarball {
                 filename: filename.to_string(),
@@ -92,11 +129,33 @@ pub fn extract(
             Ok(p) => ensure_extract_file_exist(&p),
             Err(e) => handle_extract_error(e),
         }
-    } else if tar_file_name.ends_with(Extension::TarBiz2.as_str()) {
+    } else if tar_file_name.ends_with(Extension::Gz.as_str()) {
+        match gz::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::TarBiz2.as_str())
+        || tar_file_name.ends_with(Extension::Tbz2.as_str())
+    {
         match tbz2::extract(tarball, dest_dir, filename, folder) {
             Ok(p) => ensure_extract_file_exist(&p),
             Err(e) => handle_extract_error(e),
         }
+    } else if tar_file_name.ends_with(Extension::TarXz.as_str()) {
+        match txz::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::TarZst.as_str()) {
+        match tzst::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::Zst.as_str()) {
+        match zst::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
     } else if tar_file_name.ends_with(Extension::Tar.as_str()) {
         match tar::extract(tarball, dest_dir, filename, folder) {
             Ok(p) => ensure_extract_file_exist(&p),
@@ -107,9 +166,207 @@ pub fn extract(
             Ok(p) => ensure_extract_file_exist(&p),
             Err(e) => handle_extract_error(e),
         }
+    } else if tar_file_name.ends_with(Extension::SevenZip.as_str()) {
+        match sevenz::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::Deb.as_str()) {
+        match deb::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::Rpm.as_str()) {
+        match rpm::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
+    } else if tar_file_name.ends_with(Extension::Dmg.as_str()) {
+        match dmg::extract(tarball, dest_dir, filename, folder) {
+            Ok(p) => ensure_extract_file_exist(&p),
+            Err(e) => handle_extract_error(e),
+        }
     } else {
         Err(ExtractorError::NotSupportExtension {
             filename: tar_file_name.to_string(),
         })
     }
 }
+
+// whether the file can be extracted from the stream of tarball. eg. while downloading
+pub fn is_streamable(ext: &str) -> bool {
+    [
+        Extension::TarGz,
+        Extension::Tgz,
+        Extension::TarBiz2,
+        Extension::Tbz2,
+        Extension::TarZst,
+        Extension::Tar,
+    ]
+    .iter()
+    .any(|e| e.as_str() == ext)
+}
+
+// extract the file from the stream of tarball, the stream can not be read again.
+// so it fails if the symlink points to the file that has been passed
+pub fn extract_stream<R: Read>(
+    reader: R,
+    ext: &str,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+) -> Result<PathBuf, ExtractorError> {
+    let dest_dir = &long_path(dest_dir);
+
+    let handle_extract_error = |e: Report| ExtractorError::ExtractFail {
+        filename: filename.to_string(),
+        path: folder.to_string(),
+        msg: format!("{}", e),
+    };
+
+    fs::create_dir_all(dest_dir).map_err(|e| ExtractorError::CreateFolderFail {
+        folder: format!("{}", dest_dir.display()),
+        source: e,
+    })?;
+
+    let output_file_path = dest_dir.join(filename);
+
+    let reader = RefCell::new(Some(reader));
+
+    let open = || {
+        reader
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| eyre::format_err!("the stream of tarball can not be read again"))
+    };
+
+    let result = if ext == Extension::TarGz.as_str() || ext == Extension::Tgz.as_str() {
+        archive::extract(
+            || Ok(libflate::gzip::Decoder::new(open()?)?),
+            filename,
+            folder,
+            &output_file_path,
+        )
+    } else if ext == Extension::TarBiz2.as_str() || ext == Extension::Tbz2.as_str() {
+        archive::extract(
+            || Ok(bzip2_rs::DecoderReader::new(open()?)),
+            filename,
+            folder,
+            &output_file_path,
+        )
+    } else if ext == Extension::TarZst.as_str() {
+        archive::extract(
+            || decompress::zstd(open()?),
+            filename,
+            folder,
+            &output_file_path,
+        )
+    } else if ext == Extension::Tar.as_str() {
+        archive::extract(open, filename, folder, &output_file_path)
+    } else {
+        return Err(ExtractorError::NotSupportExtension {
+            filename: ext.to_string(),
+        });
+    };
+
+    result.map_err(handle_extract_error)?;
+
+    if output_file_path.is_file() {
+        Ok(short_path(&output_file_path))
+    } else {
+        Err(ExtractorError::FindNotFoundInTarball {
+            filename: filename.to_string(),
+            path: folder.to_string(),
+        })
+    }
+}
+
+// extract the file from the inner archive of tarball. eg. the zip file contains a tar.gz
+// the inner archive is extracted to a temporary folder and removed after
+pub fn extract_nested(
+    tarball: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+    inner_path: &str,
+) -> Result<PathBuf, ExtractorError> {
+    let inner_path = Path::new(inner_path);
+
+    let inner_filename = inner_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let inner_folder = inner_path
+        .parent()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("/");
+
+    let inner_dir = dest_dir.join(".inner");
+
+    let result = extract(tarball, &inner_dir, inner_filename, inner_folder)
+        .and_then(|inner_tarball| extract(&inner_tarball, dest_dir, filename, folder));
+
+    fs::remove_dir_all(long_path(&inner_dir)).ok();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{extract_nested, extract_stream, is_streamable};
+
+    #[test]
+    fn test_extract_nested() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("nested");
+
+        let tar_file_path = extractor_dir.join("00.zip");
+
+        let dest_dir = extractor_dir.clone();
+
+        let extracted_file_path = extract_nested(
+            &tar_file_path,
+            &dest_dir,
+            "00.txt",
+            "/bin",
+            "/dist/00.tar.gz",
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        assert!(!extractor_dir.join(".inner").exists());
+
+        fs::remove_file(extracted_file_path).ok();
+
+        assert!(extract_nested(&tar_file_path, &dest_dir, "00.txt", "/bin", "/00.tar.gz").is_err());
+    }
+
+    #[test]
+    fn test_extract_stream() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("tgz");
+
+        let dest_dir = extractor_dir.join("stream");
+
+        let reader = fs::File::open(extractor_dir.join("01.tgz")).unwrap();
+
+        let extracted_file_path =
+            extract_stream(reader, ".tgz", &dest_dir, "01.txt", "/sub-folder").unwrap();
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "01");
+
+        // the symlink points to the file that has been passed
+        let reader = fs::File::open(extractor_dir.join("03.tgz")).unwrap();
+
+        assert!(extract_stream(reader, ".tgz", &dest_dir, "tool", "/tool-1.2.3/bin").is_err());
+
+        assert!(is_streamable(".tar.gz"));
+        assert!(!is_streamable(".zip"));
+
+        fs::remove_dir_all(dest_dir).ok();
+    }
+}
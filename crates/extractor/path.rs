@@ -0,0 +1,116 @@
+#![deny(warnings)]
+
+use std::path::{Path, PathBuf};
+
+// the device names are reserved on Windows, even with a extension. eg. 'aux.txt'
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// make the file name valid on Windows. eg. 'aux.tar.gz' -> 'aux_.tar.gz', 'a:b' -> 'a_b'
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // the trailing dot and space are removed by Windows
+    while name.ends_with('.') || name.ends_with(' ') {
+        name.pop();
+        name.push('_');
+    }
+
+    let stem_len = name.find('.').unwrap_or(name.len());
+
+    if RESERVED_NAMES.contains(&name[..stem_len].trim_end().to_uppercase().as_str()) {
+        name.insert(stem_len, '_');
+    }
+
+    name
+}
+
+// the path with '\\?\' prefix on Windows, so that the path can be longer than 260 characters
+// and the reserved names in the archive can be written
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let p = path.to_string_lossy().replace('/', "\\");
+
+        if path.is_absolute() && !p.starts_with(r"\\?\") {
+            return match p.strip_prefix(r"\\") {
+                Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+                None => PathBuf::from(format!(r"\\?\{}", p)),
+            };
+        }
+    }
+
+    path.to_path_buf()
+}
+
+// remove the '\\?\' prefix, the path is used by the scripts that do not support it
+pub fn short_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let p = path.to_string_lossy();
+
+        if let Some(unc) = p.strip_prefix(r"\\?\UNC\") {
+            return PathBuf::from(format!(r"\\{}", unc));
+        }
+
+        if let Some(p) = p.strip_prefix(r"\\?\") {
+            return PathBuf::from(p);
+        }
+    }
+
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::path::{long_path, sanitize_file_name, short_path};
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("tool.exe"), "tool.exe");
+        assert_eq!(sanitize_file_name("aux"), "aux_");
+        assert_eq!(sanitize_file_name("con.exe"), "con_.exe");
+        assert_eq!(sanitize_file_name("LPT1.tar.gz"), "LPT1_.tar.gz");
+        assert_eq!(sanitize_file_name("console.exe"), "console.exe");
+        assert_eq!(sanitize_file_name("a:b*c"), "a_b_c");
+        assert_eq!(sanitize_file_name("tool."), "tool_");
+    }
+
+    #[test]
+    fn test_long_path() {
+        #[cfg(not(windows))]
+        {
+            let p = Path::new("/usr/local/bin/tool");
+
+            assert_eq!(long_path(p), p);
+            assert_eq!(short_path(p), p);
+        }
+
+        #[cfg(windows)]
+        {
+            let p = Path::new(r"C:\Users\foo\.cask\bin\tool.exe");
+
+            assert_eq!(
+                long_path(p),
+                Path::new(r"\\?\C:\Users\foo\.cask\bin\tool.exe")
+            );
+            assert_eq!(short_path(&long_path(p)), p);
+            assert_eq!(
+                long_path(Path::new(r"\\server\share\tool.exe")),
+                Path::new(r"\\?\UNC\server\share\tool.exe")
+            );
+            assert_eq!(long_path(Path::new("tool.exe")), Path::new("tool.exe"));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+#![deny(warnings)]
+
+use core::result::Result;
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use bzip2_rs::DecoderReader;
+use eyre::Report;
+use libflate::gzip::Decoder as GzDecoder;
+
+use crate::decompress;
+
+// skip the header structure, return the size of it
+fn skip_header<R: Read>(reader: &mut R) -> Result<u64, Report> {
+    let mut intro = [0u8; 16];
+    reader.read_exact(&mut intro)?;
+
+    if intro[0..3] != [0x8e, 0xad, 0xe8] {
+        return Err(eyre::format_err!("invalid header of rpm package"));
+    }
+
+    let index_count = u32::from_be_bytes(intro[8..12].try_into()?) as u64;
+    let store_size = u32::from_be_bytes(intro[12..16].try_into()?) as u64;
+    let size = index_count * 16 + store_size;
+
+    io::copy(&mut reader.take(size), &mut io::sink())?;
+
+    Ok(16 + size)
+}
+
+// the payload is a compressed cpio archive, detect the compressor by the magic
+fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>, Report> {
+    let magic = reader.fill_buf()?.to_vec();
+
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(GzDecoder::new(reader)?))
+    } else if magic.starts_with(b"BZh") {
+        Ok(Box::new(DecoderReader::new(reader)))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Ok(Box::new(decompress::zstd(reader)?))
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Ok(Box::new(decompress::xz(reader)?))
+    } else if magic.starts_with(&[0x5d, 0x00, 0x00]) {
+        Ok(Box::new(decompress::lzma(reader)?))
+    } else if magic.starts_with(b"07070") {
+        Ok(Box::new(reader))
+    } else {
+        Err(eyre::format_err!(
+            "not support the payload compressor of rpm package"
+        ))
+    }
+}
+
+fn skip<R: Read>(reader: &mut R, size: u64) -> Result<(), Report> {
+    io::copy(&mut reader.take(size), &mut io::sink())?;
+    Ok(())
+}
+
+// the .rpm package is lead + signature + header + payload.
+// see https://rpm-software-management.github.io/rpm/manual/format.html
+pub(crate) fn extract(
+    src_filepath: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+) -> Result<PathBuf, Report> {
+    let output_file_path = dest_dir.join(filename);
+
+    let mut file = BufReader::new(File::open(src_filepath)?);
+
+    let mut lead = [0u8; 96];
+    file.read_exact(&mut lead)?;
+
+    if lead[0..4] != [0xed, 0xab, 0xee, 0xdb] {
+        return Err(eyre::format_err!("it is not a rpm package"));
+    }
+
+    // the signature is padded to 8 bytes
+    let signature_size = skip_header(&mut file)?;
+    skip(&mut file, (8 - signature_size % 8) % 8)?;
+
+    skip_header(&mut file)?;
+
+    let mut payload = decompress(file)?;
+
+    let target_file_path = format!("{}/{}", folder, filename).replace("//", "/");
+
+    // the cpio archive in 'newc' format, the header and data are padded to 4 bytes
+    loop {
+        let mut header = [0u8; 110];
+        payload.read_exact(&mut header)?;
+
+        if &header[0..6] != b"070701" && &header[0..6] != b"070702" {
+            return Err(eyre::format_err!(
+                "not support the cpio format of rpm package"
+            ));
+        }
+
+        let field = |index: usize| -> Result<u64, Report> {
+            let start = 6 + index * 8;
+            let value = std::str::from_utf8(&header[start..start + 8])?;
+
+            Ok(u64::from_str_radix(value, 16)?)
+        };
+
+        let mode = field(1)?;
+        let file_size = field(6)?;
+        let name_size = field(11)?;
+
+        let mut name = vec![0u8; name_size as usize];
+        payload.read_exact(&mut name)?;
+        skip(&mut payload, (4 - (110 + name_size) % 4) % 4)?;
+
+        let name = String::from_utf8_lossy(&name)
+            .trim_end_matches('\0')
+            .to_string();
+
+        if name == "TRAILER!!!" {
+            break;
+        }
+
+        let absolute_path = format!("/{}", name.trim_start_matches("./").trim_start_matches('/'));
+
+        // only the regular file
+        if absolute_path != target_file_path || mode & 0o170000 != 0o100000 {
+            skip(&mut payload, file_size + (4 - file_size % 4) % 4)?;
+            continue;
+        }
+
+        let mut output_file = File::create(&output_file_path)?;
+
+        if let Err(e) = io::copy(&mut (&mut payload).take(file_size), &mut output_file) {
+            fs::remove_file(&output_file_path).ok();
+            return Err(e.into());
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            let permissions = (mode & 0o7777) as u32;
+
+            if permissions != 0 {
+                fs::set_permissions(&output_file_path, fs::Permissions::from_mode(permissions))?;
+            }
+        };
+
+        return Ok(output_file_path);
+    }
+
+    Err(eyre::format_err!(
+        "can not found file '{}' in the '{}' of rpm package",
+        &filename,
+        folder
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::extract;
+
+    #[test]
+    fn test_extract_rpm_00() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("rpm");
+
+        let tar_file_path = extractor_dir.join("00.rpm");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let meta = fs::metadata(&extracted_file_path).unwrap();
+
+        assert_eq!(meta.len(), 2);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+        };
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_rpm_01() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("rpm");
+
+        let tar_file_path = extractor_dir.join("01.rpm");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_rpm_02() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("rpm");
+
+        let tar_file_path = extractor_dir.join("02.rpm");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/usr/bin").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(&extracted_file_path).ok();
+
+        let r = extract(&tar_file_path, &dest_dir, "00.txt", "/usr");
+
+        assert!(r.is_err());
+    }
+}
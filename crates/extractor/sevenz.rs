@@ -0,0 +1,219 @@
+#![deny(warnings)]
+
+use core::result::Result;
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+use sevenz_rust::{Password, SevenZReader};
+
+pub(crate) fn extract(
+    src_filepath: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    folder: &str,
+) -> Result<PathBuf, Report> {
+    let output_file_path = dest_dir.join(filename);
+
+    let mut archive = SevenZReader::open(src_filepath, Password::empty())
+        .map_err(|e| eyre::format_err!("open '{}' fail: {}", src_filepath.display(), e))?;
+
+    let target_file_path = format!("{}/{}", folder, filename).replace("//", "/");
+
+    // the windows attributes of the extracted file, it is None if the file is not found
+    let mut extracted: Option<Option<u32>> = None;
+
+    // the entries of solid archive share the same stream, so the skipped entries must be read through.
+    // the CRC of entry is verified by the reader when it is read to the end
+    archive
+        .for_each_entries(|entry, reader| {
+            let absolute_path = format!(
+                "/{}",
+                entry
+                    .name()
+                    .replace('\\', "/")
+                    .trim_start_matches("./")
+                    .trim_start_matches('/')
+            );
+
+            if target_file_path != absolute_path || entry.is_directory() {
+                io::copy(reader, &mut io::sink())?;
+                return Ok(true);
+            }
+
+            io::copy(reader, &mut File::create(&output_file_path)?)?;
+
+            extracted = Some(
+                entry
+                    .has_windows_attributes
+                    .then_some(entry.windows_attributes()),
+            );
+
+            Ok(false)
+        })
+        .map_err(|e| eyre::format_err!("extract '{}' fail: {}", src_filepath.display(), e))?;
+
+    let attributes = extracted.ok_or_else(|| {
+        eyre::format_err!(
+            "can not found file '{}' in the '{}' of tarball",
+            &filename,
+            folder
+        )
+    })?;
+
+    // the high 16 bits are the unix permissions if the 0x8000 bit is set
+    #[cfg(unix)]
+    {
+        use std::{fs, os::unix::prelude::PermissionsExt};
+
+        if let Some(attributes) = attributes.filter(|a| a & 0x8000 != 0) {
+            let mode = (attributes >> 16) & 0o7777;
+
+            if mode != 0 {
+                fs::set_permissions(&output_file_path, fs::Permissions::from_mode(mode))?;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let _ = attributes;
+
+    Ok(output_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::extract;
+
+    #[test]
+    fn test_extract_7z_00() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("7z");
+
+        let tar_file_path = extractor_dir.join("00.7z");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/").unwrap();
+
+        let meta = fs::metadata(&extracted_file_path).unwrap();
+
+        assert_eq!(meta.len(), 2);
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_7z_01() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("7z");
+
+        let tar_file_path = extractor_dir.join("01.7z");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path =
+            extract(&tar_file_path, &dest_dir, "01.txt", "/sub-folder").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "01");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            let meta = fs::metadata(&extracted_file_path).unwrap();
+
+            assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+        }
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_7z_02() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("7z");
+
+        let tar_file_path = extractor_dir.join("02.7z");
+
+        let dest_dir = extractor_dir;
+
+        let r = extract(&tar_file_path, &dest_dir, "not_exist", "/");
+
+        assert!(r.is_err());
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/").unwrap();
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_7z_03() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("7z");
+
+        let tar_file_path = extractor_dir.join("03.7z");
+
+        let dest_dir = extractor_dir;
+
+        // the executable is compressed with BCJ and LZMA2
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "03.exe", "/bin").unwrap();
+
+        let content = fs::read(&extracted_file_path).unwrap();
+
+        // the CALL/JMP instructions are converted by BCJ
+        let expected = (0..512u32)
+            .flat_map(|k| {
+                [
+                    0x55,
+                    0xE8,
+                    (k * 7) as u8,
+                    (k >> 8) as u8,
+                    0x00,
+                    0x00,
+                    0xE9,
+                    k as u8,
+                    0x01,
+                    0xFF,
+                    0xFF,
+                ]
+            })
+            .collect::<Vec<u8>>();
+
+        assert_eq!(content, expected);
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+
+    #[test]
+    fn test_extract_7z_truncated() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("7z");
+
+        let archive = fs::read(extractor_dir.join("03.7z")).unwrap();
+
+        let tar_file_path = extractor_dir.join("truncated.7z");
+
+        let dest_dir = extractor_dir.join("truncated");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // the broken archive is an error instead of a panic
+        for size in [8, 32, archive.len() / 2, archive.len() - 1] {
+            fs::write(&tar_file_path, &archive[..size]).unwrap();
+
+            assert!(extract(&tar_file_path, &dest_dir, "03.exe", "/bin").is_err());
+        }
+
+        fs::remove_file(tar_file_path).ok();
+        fs::remove_dir_all(dest_dir).ok();
+    }
+}
@@ -21,7 +21,7 @@ pub(crate) fn extract(
     let output_file_path = dest_dir.join(filename);
 
     archive::extract(
-        DecoderReader::new(File::open(src_filepath)?),
+        || Ok(DecoderReader::new(File::open(src_filepath)?)),
         filename,
         folder,
         &output_file_path,
@@ -91,4 +91,21 @@ mod tests {
 
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_extract_tbz2_03() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("tbz2");
+
+        let tar_file_path = extractor_dir.join("03.tbz2");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
 }
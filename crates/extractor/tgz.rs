@@ -20,7 +20,7 @@ pub(crate) fn extract(
     let output_file_path = dest_dir.join(filename);
 
     archive::extract(
-        GzDecoder::new(File::open(src_filepath)?)?,
+        || Ok(GzDecoder::new(File::open(src_filepath)?)?),
         filename,
         folder,
         &output_file_path,
@@ -90,4 +90,41 @@ mod tests {
 
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_extract_tgz_03() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("tgz");
+
+        let tar_file_path = extractor_dir.join("03.tgz");
+
+        let dest_dir = extractor_dir.join("03");
+
+        // the symlink points to the file that has been passed
+        let extracted_file_path =
+            extract(&tar_file_path, &dest_dir, "tool", "/tool-1.2.3/bin").unwrap();
+
+        let meta = fs::symlink_metadata(&extracted_file_path).unwrap();
+
+        assert!(meta.is_file());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+        };
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "03");
+
+        let extracted_file_path =
+            extract(&tar_file_path, &dest_dir, "tool-hard", "/tool-1.2.3/bin").unwrap();
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "03");
+
+        let r = extract(&tar_file_path, &dest_dir, "loop", "/tool-1.2.3/bin");
+
+        assert!(r.is_err());
+
+        fs::remove_dir_all(dest_dir).ok();
+    }
 }
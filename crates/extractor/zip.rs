@@ -2,12 +2,15 @@
 
 use core::result::Result;
 use std::{
-    fs, io,
+    fs,
+    io::{self, Read},
     path::{Path, PathBuf},
 };
 
 use eyre::Report;
 
+use crate::archive;
+
 pub(crate) fn extract(
     src_filepath: &Path,
     dest_dir: &Path,
@@ -19,33 +22,66 @@ pub(crate) fn extract(
     let tar_file = fs::File::open(src_filepath)?;
     let mut archive = zip::ZipArchive::new(tar_file)?;
 
-    let target_file_path = format!("{}/{}", folder, filename).replace("//", "/");
+    let mut target_file_path = archive::normalize_path(&format!("{}/{}", folder, filename));
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
+    let mut visited: Vec<String> = vec![];
 
-        if file.is_dir() {
-            continue;
-        }
+    'outer: while visited.len() <= archive::MAX_SYMLINK_DEPTH {
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+
+            if file.is_dir() {
+                continue;
+            }
+
+            let absolute_path = archive::normalize_path(file.name());
+
+            if target_file_path != absolute_path {
+                continue;
+            }
+
+            let mode = file.unix_mode();
+
+            // the content of symlink is the target path
+            if mode.map(|m| m & 0o170000 == 0o120000).unwrap_or(false) {
+                let mut link_name = String::new();
+                file.read_to_string(&mut link_name)?;
+
+                visited.push(target_file_path);
 
-        let absolute_path = format!("/{}", file.name());
+                target_file_path = archive::resolve_link(&absolute_path, &link_name);
+
+                if visited.contains(&target_file_path) {
+                    return Err(eyre::format_err!(
+                        "the symlink '{}' is circular in tarball",
+                        absolute_path
+                    ));
+                }
+
+                continue 'outer;
+            }
+
+            if fs::symlink_metadata(&output_file_path).is_ok() {
+                fs::remove_file(&output_file_path)?;
+            }
 
-        if target_file_path == absolute_path {
             let mut output_file = fs::File::create(&output_file_path)?;
             io::copy(&mut file, &mut output_file)?;
 
-            // Get and Set permissions
+            // Get and Set permissions, the entry without unix mode is made executable by installer
             #[cfg(unix)]
             {
                 use std::os::unix::prelude::PermissionsExt;
 
-                if let Some(mode) = file.unix_mode() {
+                if let Some(mode) = mode.map(|m| m & 0o7777).filter(|m| *m != 0) {
                     fs::set_permissions(&output_file_path, fs::Permissions::from_mode(mode))?;
                 };
             };
 
             return Ok(output_file_path);
         }
+
+        break;
     }
 
     Err(eyre::format_err!(
@@ -116,4 +152,37 @@ mod tests {
 
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_extract_zip_03() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("zip");
+
+        let tar_file_path = extractor_dir.join("03.zip");
+
+        let dest_dir = extractor_dir.join("03");
+
+        let extracted_file_path =
+            extract(&tar_file_path, &dest_dir, "tool", "/tool-1.2.3/bin").unwrap();
+
+        let meta = fs::symlink_metadata(&extracted_file_path).unwrap();
+
+        assert!(meta.is_file());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::prelude::PermissionsExt;
+
+            assert_eq!(meta.permissions().mode() & 0o777, 0o755);
+        };
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "03");
+
+        // the entry without unix mode
+        let extracted_file_path =
+            extract(&tar_file_path, &dest_dir, "plain", "/tool-1.2.3/bin").unwrap();
+
+        assert_eq!(fs::read_to_string(&extracted_file_path).unwrap(), "03");
+
+        fs::remove_dir_all(dest_dir).ok();
+    }
 }
@@ -0,0 +1,57 @@
+#![deny(warnings)]
+
+use core::result::Result;
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+
+use crate::decompress;
+
+// the compressed file is the binary itself, not a tarball
+pub(crate) fn extract(
+    src_filepath: &Path,
+    dest_dir: &Path,
+    filename: &str,
+    _folder: &str,
+) -> Result<PathBuf, Report> {
+    let output_file_path = dest_dir.join(filename);
+
+    let mut decoder = decompress::zstd(File::open(src_filepath)?)?;
+    let mut output_file = File::create(&output_file_path)?;
+
+    if let Err(e) = io::copy(&mut decoder, &mut output_file) {
+        fs::remove_file(&output_file_path).ok();
+        return Err(e.into());
+    }
+
+    Ok(output_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::extract;
+
+    #[test]
+    fn test_extract_zst_00() {
+        let extractor_dir = env::current_dir().unwrap().join("fixtures").join("zst");
+
+        let tar_file_path = extractor_dir.join("00.txt.zst");
+
+        let dest_dir = extractor_dir;
+
+        let extracted_file_path = extract(&tar_file_path, &dest_dir, "00.txt", "/").unwrap();
+
+        let content = fs::read_to_string(&extracted_file_path).unwrap();
+
+        assert_eq!(content, "00");
+
+        fs::remove_file(extracted_file_path).ok();
+    }
+}
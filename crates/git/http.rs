@@ -0,0 +1,739 @@
+#![deny(warnings)]
+
+// the native transport of git over HTTP(S), so that the repository can be accessed without the git binary.
+// the protocol, pack and checkout are handled by gitoxide, see https://github.com/GitoxideLabs/gitoxide
+
+use std::{
+    error::Error as StdError,
+    fs, io, panic,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use gix::{
+    bstr::ByteSlice,
+    clone::{self, PrepareFetch},
+    create,
+    objs::tree::EntryKind,
+    open,
+    progress::Discard,
+    protocol::{
+        handshake::{self, Ref},
+        transport::client::{self, http},
+    },
+    remote::{
+        self,
+        fetch::{Shallow, Tags},
+        ref_map, Direction,
+    },
+    ThreadSafeRepository,
+};
+use reqwest::{header, StatusCode};
+use tracing::debug;
+
+use crate::{is_commit_like, GitError};
+
+// the file that records the commit of the folder which is cloned natively
+const HEAD_FILE: &str = ".cask-head";
+
+// the reference of remote repository. eg. 'refs/tags/v1.0.0'
+#[derive(Debug, PartialEq)]
+pub(crate) struct RemoteRef {
+    pub hash: String, // the object id that the reference points to
+    pub name: String, // the full name of reference
+}
+
+// whether the URL can be accessed by the native transport.
+// the certificates and proxy can not be customized for the HTTP client of gitoxide,
+// so the git command is used instead if they are set by the config entries.
+// see https://git-scm.com/docs/git-config#Documentation/git-config.txt-httpsslCAInfo
+pub(crate) fn is_supported(url: &str, config: &[(String, String)]) -> bool {
+    let url = url.to_ascii_lowercase();
+
+    let is_custom = |keys: &[&str]| {
+        config
+            .iter()
+            .any(|(key, _)| keys.contains(&key.to_ascii_lowercase().as_str()))
+    };
+
+    if is_custom(&["http.proxy"]) {
+        return false;
+    }
+
+    url.starts_with("http://")
+        || (url.starts_with("https://") && !is_custom(&["http.sslcainfo", "http.sslverify"]))
+}
+
+fn status_error(url: &str, status: StatusCode) -> GitError {
+    let url = url.to_string();
+    let message = format!("the server responded {}", status);
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            GitError::AuthenticationError { url, message }
+        }
+        StatusCode::NOT_FOUND => GitError::RemoteRepositoryNotExists { url },
+        StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => {
+            GitError::NetworkError { url, message }
+        }
+        s if s.is_server_error() => GitError::NetworkError { url, message },
+        _ => GitError::ProtocolError { url, message },
+    }
+}
+
+// classify the error of gitoxide by its sources, the unknown error is reported as protocol error,
+// so that it can be retried with the git command
+fn map_error(url: &str, e: &(dyn StdError + 'static)) -> GitError {
+    let mut message = e.to_string();
+    let mut source = Some(e);
+
+    while let Some(s) = source {
+        if let Some(e) = s.downcast_ref::<handshake::Error>() {
+            if matches!(
+                e,
+                handshake::Error::Credentials(_)
+                    | handshake::Error::EmptyCredentials
+                    | handshake::Error::InvalidCredentials { .. }
+            ) {
+                return GitError::AuthenticationError {
+                    url: url.to_string(),
+                    message,
+                };
+            }
+        }
+
+        if let Some(e) = s.downcast_ref::<io::Error>() {
+            // eg. 'Received HTTP status 404'
+            let status = e
+                .to_string()
+                .strip_prefix("Received HTTP status ")
+                .and_then(|code| code.trim().parse::<u16>().ok())
+                .and_then(|code| StatusCode::from_u16(code).ok());
+
+            if let Some(status) = status {
+                return status_error(url, status);
+            }
+
+            // the failure of sending request is converted to the plain message by the transport
+            let is_network = matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::UnexpectedEof
+            ) || e.to_string().starts_with("error sending request");
+
+            if is_network {
+                return GitError::NetworkError {
+                    url: url.to_string(),
+                    message,
+                };
+            }
+        }
+
+        source = s.source();
+
+        if let Some(s) = source {
+            message = format!("{}: {}", message, s);
+        }
+    }
+
+    GitError::ProtocolError {
+        url: url.to_string(),
+        message,
+    }
+}
+
+// the reference that is not found is not retried with the git command
+fn fetch_error(url: &str, e: clone::fetch::Error) -> GitError {
+    match e {
+        clone::fetch::Error::RefNameMissing { wanted } => GitError::ReferenceNotExists {
+            url: url.to_string(),
+            reference: wanted.as_ref().as_bstr().to_string(),
+        },
+        e => map_error(url, &e),
+    }
+}
+
+// the blocking client can not be dropped in the async runtime, so the request is sent in a new thread
+fn spawn<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, GitError> + Send + 'static,
+) -> Result<T, GitError> {
+    thread::spawn(f)
+        .join()
+        .unwrap_or_else(|e| panic::resume_unwind(e))
+}
+
+// the extra headers of the URL from the config entries. eg. the token of private repository
+// see https://git-scm.com/docs/git-config#Documentation/git-config.txt-httpextraHeader
+fn get_extra_headers(url: &str, config: &[(String, String)]) -> Vec<(String, String)> {
+    config
+        .iter()
+        .filter_map(|(key, value)| {
+            // http.extraHeader or http.<url>.extraHeader
+            let lower = key.to_ascii_lowercase();
+            let prefix = match lower.as_str() {
+                "http.extraheader" => "",
+                _ => {
+                    let url = lower.strip_prefix("http.")?.strip_suffix(".extraheader")?;
+
+                    &key[5..5 + url.len()]
+                }
+            };
+
+            if !url.starts_with(prefix) {
+                return None;
+            }
+
+            let (name, value) = value.split_once(':')?;
+
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+// the options of HTTP transport, the config of user is not read so that it behaves the same on every machine.
+// the headers and timeout are applied to every request, eg. the advertisement and the pack
+fn transport_options(url: &str, config: &[(String, String)], timeout: Duration) -> http::Options {
+    let mut headers = header::HeaderMap::new();
+
+    for (name, value) in get_extra_headers(url, config) {
+        if let (Ok(name), Ok(value)) = (
+            header::HeaderName::from_bytes(name.as_bytes()),
+            header::HeaderValue::from_str(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+
+    let backend = http::reqwest::Options {
+        configure_request: Some(Box::new(move |request| {
+            request.headers_mut().extend(headers.clone());
+            *request.timeout_mut() = Some(timeout);
+
+            Ok(())
+        })),
+    };
+
+    http::Options {
+        connect_timeout: Some(timeout),
+        backend: Some(Arc::new(Mutex::new(backend))),
+        ..Default::default()
+    }
+}
+
+// the credentials are never asked, the git command is tried instead with the credential helpers of user.
+// the signature of credentials function is defined by gitoxide
+#[allow(clippy::result_large_err)]
+fn configure_connection<T: client::Transport>(
+    connection: &mut remote::Connection<'_, '_, T>,
+    options: http::Options,
+) {
+    connection
+        .set_credentials(|_| Ok(None))
+        .set_transport_options(Box::new(options));
+}
+
+fn to_remote_refs(refs: &[Ref]) -> Vec<RemoteRef> {
+    let mut remote_refs = vec![];
+
+    for r in refs {
+        let (name, id, peeled) = match r {
+            Ref::Direct {
+                full_ref_name,
+                object,
+            } => (full_ref_name, object, None),
+            Ref::Peeled {
+                full_ref_name,
+                tag,
+                object,
+            } => (full_ref_name, tag, Some(object)),
+            Ref::Symbolic {
+                full_ref_name,
+                tag: Some(tag),
+                object,
+                ..
+            } => (full_ref_name, tag, Some(object)),
+            Ref::Symbolic {
+                full_ref_name,
+                tag: None,
+                object,
+                ..
+            } => (full_ref_name, object, None),
+            // the HEAD of empty repository
+            Ref::Unborn { .. } => continue,
+        };
+
+        let name = name.to_str_lossy().to_string();
+
+        remote_refs.push(RemoteRef {
+            hash: id.to_string(),
+            name: name.clone(),
+        });
+
+        // the annotated tag is peeled as same as the advertisement of git
+        if let Some(peeled) = peeled {
+            remote_refs.push(RemoteRef {
+                hash: peeled.to_string(),
+                name: format!("{}^{{}}", name),
+            });
+        }
+    }
+
+    remote_refs
+}
+
+// list the references of remote repository, same as `git ls-remote`
+pub(crate) fn ls_remote(
+    url: &str,
+    config: &[(String, String)],
+    timeout: Duration,
+) -> Result<Vec<RemoteRef>, GitError> {
+    let url = url.to_string();
+    let options = transport_options(&url, config, timeout);
+
+    spawn(move || {
+        debug!("list the refs of {}", url);
+
+        // the remote can only be connected from a repository, a temporary one is created to hold nothing
+        let dir = tempfile::tempdir().map_err(|e| GitError::IO { source: e })?;
+
+        let repo = ThreadSafeRepository::init_opts(
+            dir.path(),
+            create::Kind::Bare,
+            create::Options::default(),
+            open::Options::isolated(),
+        )
+        .map_err(|e| map_error(&url, &e))?
+        .to_thread_local();
+
+        let remote = repo
+            .remote_at(url.as_str())
+            .map_err(|e| map_error(&url, &e))?
+            .with_fetch_tags(Tags::None);
+
+        let mut connection = remote
+            .connect(Direction::Fetch)
+            .map_err(|e| map_error(&url, &e))?;
+
+        configure_connection(&mut connection, options);
+
+        let (ref_map, _) = connection
+            .ref_map(
+                Discard,
+                ref_map::Options {
+                    prefix_from_spec_as_filter_on_remote: false,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| map_error(&url, &e))?;
+
+        Ok(to_remote_refs(&ref_map.remote_refs))
+    })
+}
+
+// resolve the branch, tag or commit to the reference that it is fetched by. the tag is peeled to the commit it points to
+fn resolve_reference<'a>(
+    url: &str,
+    refs: &'a [RemoteRef],
+    reference: &str,
+) -> Result<&'a RemoteRef, GitError> {
+    let names = [
+        reference.to_string(),
+        format!("refs/heads/{}", reference),
+        format!("refs/tags/{}^{{}}", reference),
+        format!("refs/tags/{}", reference),
+    ];
+
+    for name in &names {
+        if let Some(r) = refs.iter().find(|r| &r.name == name) {
+            return Ok(r);
+        }
+    }
+
+    // the commit that is not advertised can not be fetched by name
+    if reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+        if let Some(r) = refs.iter().find(|r| r.hash == reference) {
+            return Ok(r);
+        }
+    }
+
+    // the abbreviated commit is resolved by the git command with the whole history
+    if is_commit_like(reference) {
+        return Err(GitError::ProtocolError {
+            url: url.to_string(),
+            message: format!("can not fetch the commit '{}' directly", reference),
+        });
+    }
+
+    Err(GitError::ReferenceNotExists {
+        url: url.to_string(),
+        reference: reference.to_string(),
+    })
+}
+
+// the commit that the default branch or the reference points to, None if the repository is empty
+fn get_head<'a>(
+    url: &str,
+    refs: &'a [RemoteRef],
+    reference: Option<&str>,
+) -> Result<Option<&'a str>, GitError> {
+    match reference {
+        Some(reference) => resolve_reference(url, refs, reference).map(|r| Some(r.hash.as_str())),
+        None => Ok(refs
+            .iter()
+            .find(|r| r.name == "HEAD")
+            .or_else(|| refs.iter().find(|r| r.name.starts_with("refs/heads/")))
+            .map(|r| r.hash.as_str())),
+    }
+}
+
+// write the files of sparse paths from the commit of HEAD, the other files are not checked out
+fn sparse_checkout(
+    url: &str,
+    repo: &gix::Repository,
+    paths: &[String],
+    dest: &Path,
+) -> Result<(), GitError> {
+    let tree = repo
+        .head_commit()
+        .map_err(|e| map_error(url, &e))?
+        .tree()
+        .map_err(|e| map_error(url, &e))?;
+
+    for path in paths {
+        let path = path.trim_start_matches('/');
+
+        let entry = match tree
+            .lookup_entry_by_path(path)
+            .map_err(|e| map_error(url, &e))?
+        {
+            Some(entry) => entry,
+            None => continue,
+        };
+
+        let kind = entry.mode().kind();
+
+        if !matches!(
+            kind,
+            EntryKind::Blob | EntryKind::BlobExecutable | EntryKind::Link
+        ) {
+            continue;
+        }
+
+        let object = entry.object().map_err(|e| map_error(url, &e))?;
+        let file_path = dest.join(path);
+
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| GitError::IO { source: e })?;
+        }
+
+        #[cfg(unix)]
+        if kind == EntryKind::Link {
+            let target = object.data.to_path_lossy();
+
+            std::os::unix::fs::symlink(&*target, &file_path)
+                .map_err(|e| GitError::IO { source: e })?;
+
+            continue;
+        }
+
+        fs::write(&file_path, &object.data).map_err(|e| GitError::IO { source: e })?;
+
+        #[cfg(unix)]
+        if kind == EntryKind::BlobExecutable {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&file_path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| GitError::IO { source: e })?;
+        }
+    }
+
+    Ok(())
+}
+
+// clone the default branch or the reference of repository into the folder, only the files are kept without '.git'.
+// only the files of sparse paths are written, the blobs are fetched all because gitoxide does not filter the pack
+pub(crate) fn clone(
+    url: &str,
+    config: &[(String, String)],
+    dest: &Path,
+    depth: Option<i32>,
+    sparse: Option<Vec<String>>,
+    reference: Option<&str>,
+    timeout: Duration,
+) -> Result<(), GitError> {
+    // the commit is fetched by the reference that points to it
+    let reference = match reference {
+        Some(commit) if is_commit_like(commit) => {
+            let refs = ls_remote(url, config, timeout)?;
+            let r = resolve_reference(url, &refs, commit)?;
+
+            Some(r.name.trim_end_matches("^{}").to_string())
+        }
+        r => r.map(|r| r.to_string()),
+    };
+
+    let url = url.to_string();
+    let options = transport_options(&url, config, timeout);
+    let dest = dest.to_path_buf();
+
+    spawn(move || {
+        debug!("clone {} into '{}'", url, dest.display());
+
+        let interrupt = AtomicBool::new(false);
+
+        let result = (|| {
+            let mut prepare = PrepareFetch::new(
+                url.as_str(),
+                &dest,
+                create::Kind::WithWorktree,
+                create::Options::default(),
+                open::Options::isolated(),
+            )
+            .map_err(|e| map_error(&url, &e))?
+            // only the HEAD and the reference are fetched, same as `git clone --single-branch --no-tags`
+            .configure_remote(|remote| {
+                let mut remote = remote.with_fetch_tags(Tags::None);
+
+                remote.replace_refspecs(None::<&str>, Direction::Fetch)?;
+
+                Ok(remote)
+            })
+            .configure_connection(move |connection| {
+                configure_connection(connection, options.clone());
+
+                Ok(())
+            })
+            .with_ref_name(reference.as_deref())
+            .map_err(|e| map_error(&url, &e))?;
+
+            if let Some(depth) = depth.and_then(|d| u32::try_from(d).ok()?.try_into().ok()) {
+                prepare = prepare.with_shallow(Shallow::DepthAtRemote(depth));
+            }
+
+            let repo = match &sparse {
+                Some(paths) => {
+                    let (repo, _) = prepare
+                        .fetch_only(Discard, &interrupt)
+                        .map_err(|e| fetch_error(&url, e))?;
+
+                    // the empty repository is cloned as an empty folder
+                    if repo.head_id().is_ok() {
+                        sparse_checkout(&url, &repo, paths, &dest)?;
+                    }
+
+                    repo
+                }
+                None => {
+                    let (mut checkout, _) = prepare
+                        .fetch_then_checkout(Discard, &interrupt)
+                        .map_err(|e| fetch_error(&url, e))?;
+
+                    let (repo, _) = checkout
+                        .main_worktree(Discard, &interrupt)
+                        .map_err(|e| map_error(&url, &e))?;
+
+                    repo
+                }
+            };
+
+            let head = repo.head_id().ok().map(|id| id.to_string());
+
+            drop(repo);
+
+            // the history is not kept, the folder is cloned again if it is outdated
+            fs::remove_dir_all(dest.join(".git")).map_err(|e| GitError::IO { source: e })?;
+
+            // the commit is recorded so that the folder is not cloned again if it is up to date
+            if let Some(head) = head {
+                fs::write(dest.join(HEAD_FILE), head).map_err(|e| GitError::IO { source: e })?;
+            }
+
+            Ok(())
+        })();
+
+        // remove the incomplete repository so that it can be cloned again
+        if result.is_err() && dest.exists() {
+            fs::remove_dir_all(&dest).ok();
+        }
+
+        result
+    })
+}
+
+// whether the folder that is cloned natively is at the latest commit of the default branch or the reference
+pub(crate) fn is_up_to_date(
+    url: &str,
+    config: &[(String, String)],
+    dest: &Path,
+    reference: Option<&str>,
+    timeout: Duration,
+) -> Result<bool, GitError> {
+    let current = match fs::read_to_string(dest.join(HEAD_FILE)) {
+        Ok(commit) => commit.trim().to_string(),
+        Err(_) => return Ok(false),
+    };
+
+    let refs = ls_remote(url, config, timeout)?;
+
+    Ok(get_head(url, &refs, reference)? == Some(current.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, io};
+
+    use crate::{
+        http::{
+            get_extra_headers, get_head, is_supported, map_error, resolve_reference, RemoteRef,
+        },
+        GitError,
+    };
+
+    const HEAD: &str = "2aedc1ce2a2046ab0f24b7c573ed6c21ea82f75a";
+
+    #[test]
+    fn test_is_supported() {
+        let url = "https://github.com/axetroy/cask.rs.git";
+        let entry = |key: &str, value: &str| vec![(key.to_string(), value.to_string())];
+
+        assert!(is_supported(url, &[]));
+        assert!(!is_supported("git@github.com:axetroy/cask.rs.git", &[]));
+
+        // the git command is used for the custom certificates and proxy
+        assert!(!is_supported(url, &entry("http.sslVerify", "false")));
+        assert!(!is_supported(url, &entry("http.sslCAInfo", "/etc/ca.pem")));
+        assert!(is_supported(
+            "http://example.com/a.git",
+            &entry("http.sslVerify", "false")
+        ));
+        assert!(!is_supported(
+            "http://example.com/a.git",
+            &entry("http.proxy", "http://127.0.0.1:7890")
+        ));
+    }
+
+    #[test]
+    fn test_resolve_reference() {
+        let tag = "1111111111111111111111111111111111111111";
+        let branch = "2222222222222222222222222222222222222222";
+        let other = "3333333333333333333333333333333333333333";
+
+        let refs = [
+            (HEAD, "HEAD"),
+            (branch, "refs/heads/dev"),
+            (tag, "refs/tags/v1.0.0"),
+            (HEAD, "refs/tags/v1.0.0^{}"),
+        ]
+        .iter()
+        .map(|(hash, name)| RemoteRef {
+            hash: hash.to_string(),
+            name: name.to_string(),
+        })
+        .collect::<Vec<RemoteRef>>();
+
+        let resolve = |r: &str| {
+            resolve_reference("https://example.com/a.git", &refs, r).map(|r| r.name.as_str())
+        };
+
+        assert_eq!(resolve("dev").unwrap(), "refs/heads/dev");
+        assert_eq!(resolve("refs/heads/dev").unwrap(), "refs/heads/dev");
+        // the annotated tag is peeled to the commit
+        assert_eq!(resolve("v1.0.0").unwrap(), "refs/tags/v1.0.0^{}");
+        assert_eq!(resolve(branch).unwrap(), "refs/heads/dev");
+
+        assert!(matches!(
+            resolve("not-exist"),
+            Err(GitError::ReferenceNotExists { .. })
+        ));
+        assert!(matches!(
+            resolve(other),
+            Err(GitError::ProtocolError { .. })
+        ));
+        assert!(matches!(
+            resolve("2aedc1c"),
+            Err(GitError::ProtocolError { .. })
+        ));
+
+        let head = |r: Option<&str>| get_head("https://example.com/a.git", &refs, r).unwrap();
+
+        assert_eq!(head(None), Some(HEAD));
+        assert_eq!(head(Some("v1.0.0")), Some(HEAD));
+        assert_eq!(head(Some("dev")), Some(branch));
+        assert_eq!(
+            get_head("https://example.com/a.git", &[], None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_extra_headers() {
+        let config = [
+            ("http.extraHeader", "X-Foo: foo"),
+            (
+                "http.https://github.com/.extraHeader",
+                "Authorization: Basic abc",
+            ),
+            (
+                "http.https://gitlab.com/.extraHeader",
+                "Authorization: Basic xyz",
+            ),
+            ("user.name", "axetroy"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<(String, String)>>();
+
+        assert_eq!(
+            get_extra_headers("https://github.com/axetroy/dvm.git", &config),
+            vec![
+                ("X-Foo".to_string(), "foo".to_string()),
+                ("Authorization".to_string(), "Basic abc".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_error() {
+        let url = "https://example.com/a.git";
+
+        let error = |kind: io::ErrorKind, message: &str| -> Box<dyn Error> {
+            Box::new(io::Error::new(kind, message.to_string()))
+        };
+
+        assert!(matches!(
+            map_error(
+                url,
+                &*error(io::ErrorKind::Other, "Received HTTP status 404")
+            ),
+            GitError::RemoteRepositoryNotExists { .. }
+        ));
+        assert!(matches!(
+            map_error(
+                url,
+                &*error(io::ErrorKind::Other, "Received HTTP status 403")
+            ),
+            GitError::AuthenticationError { .. }
+        ));
+        assert!(matches!(
+            map_error(
+                url,
+                &*error(io::ErrorKind::ConnectionAborted, "Received HTTP status 502")
+            ),
+            GitError::NetworkError { .. }
+        ));
+        assert!(matches!(
+            map_error(url, &*error(io::ErrorKind::TimedOut, "operation timed out")),
+            GitError::NetworkError { .. }
+        ));
+        assert!(matches!(
+            map_error(url, &*error(io::ErrorKind::Other, "the pack is malformed")),
+            GitError::ProtocolError { .. }
+        ));
+    }
+}
@@ -1,15 +1,20 @@
 #![deny(warnings)]
 
+mod http;
+
 use core::result::Result;
 use std::{
-    io,
-    path::Path,
-    process::{Command as ChildProcess, Stdio},
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command as ChildProcess, Stdio},
+    thread,
     time::Duration,
 };
 
 use semver::Version;
 use thiserror::Error;
+use tracing::debug;
 use wait_timeout::ChildExt;
 
 #[derive(Error, Debug)]
@@ -28,6 +33,74 @@ pub enum GitError {
     RemoteRepositoryNotExists { url: String },
     #[error("can not get tag from output: {row:?})")]
     ParseTagError { row: String },
+    #[error("network error while accessing {url:?}: {message}")]
+    NetworkError { url: String, message: String },
+    #[error("authentication failed while accessing {url:?}: {message}")]
+    AuthenticationError { url: String, message: String },
+    #[error("unsupported response while accessing {url:?}: {message}")]
+    ProtocolError { url: String, message: String },
+    #[error("can not found the branch, tag or commit {reference:?} in {url:?}")]
+    ReferenceNotExists { url: String, reference: String },
+}
+
+impl GitError {
+    // whether the error may be recovered by retrying
+    pub fn is_transient(&self) -> bool {
+        matches!(self, GitError::NetworkError { .. })
+    }
+
+    // whether the git command may succeed when the native transport fails. eg. with the credential helper
+    fn is_fallback(&self) -> bool {
+        matches!(
+            self,
+            GitError::AuthenticationError { .. } | GitError::ProtocolError { .. }
+        )
+    }
+}
+
+// the environment variable to always use the git command instead of the native transport if it is '0'
+pub const ENV_NATIVE: &str = "CASK_GIT_NATIVE";
+
+// the messages of git stderr that indicate a network failure
+const NETWORK_ERROR_PATTERNS: [&str; 9] = [
+    "Could not resolve host",
+    "Failed to connect",
+    "Connection timed out",
+    "Connection reset",
+    "Connection refused",
+    "Operation timed out",
+    "The requested URL returned error: 5",
+    "RPC failed",
+    "early EOF",
+];
+
+fn get_network_error(stderr: &str) -> Option<String> {
+    stderr
+        .lines()
+        .find(|line| NETWORK_ERROR_PATTERNS.iter().any(|p| line.contains(p)))
+        .map(|line| line.trim().to_string())
+}
+
+// the messages of git stderr that indicate the credentials are missing or rejected
+const AUTHENTICATION_ERROR_PATTERNS: [&str; 7] = [
+    "could not read Username",
+    "could not read Password",
+    "terminal prompts disabled",
+    "Authentication failed",
+    "Access denied",
+    "Permission denied (publickey",
+    "The requested URL returned error: 40",
+];
+
+fn get_authentication_error(stderr: &str) -> Option<String> {
+    stderr
+        .lines()
+        .find(|line| {
+            AUTHENTICATION_ERROR_PATTERNS
+                .iter()
+                .any(|p| line.contains(p))
+        })
+        .map(|line| line.trim().to_string())
 }
 
 #[derive(Debug)]
@@ -48,13 +121,70 @@ impl PartialEq for GitTag {
     }
 }
 
+// the default timeout of cloning repository
+const CLONE_TIMEOUT: Duration = Duration::from_secs(300);
+
+// the default timeout of querying remote repository
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// wait for the child process, the process will be killed if it does not exit in time
+fn wait_child(child: &mut Child, timeout: Duration) -> Result<Option<i32>, GitError> {
+    match child
+        .wait_timeout(timeout)
+        .map_err(|e| GitError::IO { source: e })?
+    {
+        Some(status) => Ok(Some(status.code().unwrap_or(1))),
+        None => {
+            // child hasn't exited yet
+            child.kill().map_err(|e| GitError::IO { source: e })?;
+            child.wait().map_err(|e| GitError::IO { source: e })?;
+
+            Ok(None)
+        }
+    }
+}
+
+// the ssh command never prompts for the password, the custom command of user is kept. eg. 'ssh -i ~/.ssh/deploy_key'
+fn get_ssh_command() -> String {
+    let command = env::var("GIT_SSH_COMMAND")
+        .ok()
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| "ssh".to_string());
+
+    format!("{} -o ControlMaster=no -o BatchMode=yes", command.trim())
+}
+
+// the config entries of the environment variables that defined by user.
+// see https://git-scm.com/docs/git-config#ENVIRONMENT
+fn get_env_config() -> Vec<(String, String)> {
+    let count: usize = env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+
+    (0..count)
+        .filter_map(|i| {
+            let key = env::var(format!("GIT_CONFIG_KEY_{}", i)).ok()?;
+            let value = env::var(format!("GIT_CONFIG_VALUE_{}", i)).ok()?;
+
+            Some((key, value))
+        })
+        .collect()
+}
+
 pub struct Repository {
     remote: String,
+    timeout: Option<Duration>, // kill the git process if it does not exit in time
+    bin: Option<PathBuf>,      // the git binary, it is the git in $PATH by default
+    config: Vec<(String, String)>, // the config entries of git. eg. the token of private repository
 }
 
 pub fn new(url: &str) -> Result<Repository, GitError> {
     let r = Repository {
         remote: url.to_string(),
+        timeout: None,
+        bin: None,
+        config: vec![],
     };
 
     Ok(r)
@@ -68,9 +198,88 @@ pub struct CloneOption {
     pub single_branch: Option<bool>,
     pub dissociate: Option<bool>,
     pub filter: Option<String>,
+    pub sparse: Option<Vec<String>>, // only check out the paths, the other blobs are not fetched. eg. Cask.toml
+    pub reference: Option<String>, // check out the branch, tag or commit instead of the default branch
+}
+
+// whether the reference looks like a commit, it may be abbreviated. eg. '2aedc1c'
+fn is_commit_like(reference: &str) -> bool {
+    (4..=40).contains(&reference.len()) && reference.chars().all(|c| c.is_ascii_hexdigit())
 }
 
 impl Repository {
+    // override the default timeout of git command
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // override the git binary. eg. the portable git that is not in $PATH
+    pub fn with_bin(mut self, bin: Option<PathBuf>) -> Self {
+        self.bin = bin;
+        self
+    }
+
+    // set the config entries of git, they are passed to the git process only and never to the environment of Cask.
+    // eg. ('http.https://github.com/.extraHeader', 'Authorization: Basic ...')
+    pub fn with_config(mut self, config: Vec<(String, String)>) -> Self {
+        self.config = config;
+        self
+    }
+
+    // the config entries of user are kept, the entries of repository are appended to them
+    fn get_config(&self) -> Vec<(String, String)> {
+        let mut config = get_env_config();
+
+        config.extend(self.config.iter().cloned());
+
+        config
+    }
+
+    fn command(&self) -> ChildProcess {
+        let mut command = ChildProcess::new(self.bin.as_deref().unwrap_or(Path::new("git")));
+
+        let config = self.get_config();
+
+        for (i, (key, value)) in config.iter().enumerate() {
+            command
+                .env(format!("GIT_CONFIG_KEY_{}", i), key)
+                .env(format!("GIT_CONFIG_VALUE_{}", i), value);
+        }
+
+        if !config.is_empty() {
+            command.env("GIT_CONFIG_COUNT", config.len().to_string());
+        }
+
+        command
+    }
+
+    // the HTTP(S) repository is accessed natively, so that the git binary is not required
+    fn is_native(&self) -> bool {
+        let is_disabled = matches!(env::var(ENV_NATIVE).as_deref(), Ok("0") | Ok("false"));
+
+        !is_disabled && http::is_supported(&self.remote, &self.get_config())
+    }
+
+    // try the git command if the native transport fails, the native error is kept if git is not installed
+    fn fallback<T>(
+        &self,
+        err: GitError,
+        f: impl FnOnce() -> Result<T, GitError>,
+    ) -> Result<T, GitError> {
+        debug!(
+            "the native transport fails, fallback to git command: {}",
+            err
+        );
+
+        match f() {
+            Err(GitError::CommandError { source }) if source.kind() == io::ErrorKind::NotFound => {
+                Err(err)
+            }
+            r => r,
+        }
+    }
+
     pub fn clone(&self, dest: &Path, options: CloneOption) -> Result<(), GitError> {
         if dest.exists() {
             return Err(GitError::RepositoryExist {
@@ -78,9 +287,35 @@ impl Repository {
             });
         }
 
+        if !self.is_native() {
+            return self.clone_with_command(dest, options);
+        }
+
+        let timeout = self.timeout.unwrap_or(CLONE_TIMEOUT);
+
+        match http::clone(
+            &self.remote,
+            &self.get_config(),
+            dest,
+            options.depth,
+            options.sparse.clone(),
+            options.reference.as_deref(),
+            timeout,
+        ) {
+            Err(e) if e.is_fallback() => {
+                self.fallback(e, || self.clone_with_command(dest, options))
+            }
+            r => r,
+        }
+    }
+
+    fn clone_with_command(&self, dest: &Path, options: CloneOption) -> Result<(), GitError> {
         let mut args: Vec<String> = vec![];
 
-        if let Some(depth) = options.depth {
+        // the commit can not be cloned directly, it is checked out from the whole history
+        let commit = options.reference.as_deref().filter(|r| is_commit_like(r));
+
+        if let Some(depth) = options.depth.filter(|_| commit.is_none()) {
             args.push(format!("--depth={}", depth))
         }
 
@@ -108,25 +343,43 @@ impl Repository {
             }
         }
 
-        if let Some(single_branch) = options.single_branch {
+        if let Some(single_branch) = options.single_branch.filter(|_| commit.is_none()) {
             if single_branch {
                 args.push("--single-branch".to_string())
             }
         }
 
-        if let Some(filter) = options.filter {
-            args.push(format!("--filter={}", filter))
+        match &options.reference {
+            Some(branch) if commit.is_none() => args.push(format!("--branch={}", branch)),
+            _ => {}
+        }
+
+        // the blobs of sparse paths are fetched on checkout
+        if options.sparse.is_some() {
+            args.push("--no-checkout".to_string());
+            args.push("--filter=blob:none".to_string());
+        } else {
+            if commit.is_some() {
+                args.push("--no-checkout".to_string());
+            }
+
+            if let Some(filter) = options.filter {
+                args.push(format!("--filter={}", filter))
+            }
         }
 
-        let mut stderr = io::stderr();
+        debug!(
+            "run git clone {} {} {}",
+            self.remote,
+            args.join(" "),
+            dest.display()
+        );
 
-        let mut child = ChildProcess::new("git")
+        let mut child = self
+            .command()
             .env("GIT_TERMINAL_PROMPT", "0")
             .env("GCM_INTERACTIVE", "never")
-            .env(
-                "GIT_SSH_COMMAND",
-                "ssh -o ControlMaster=no -o BatchMode=yes",
-            )
+            .env("GIT_SSH_COMMAND", get_ssh_command())
             .stdin(Stdio::null())
             .stderr(Stdio::piped())
             .stdout(Stdio::piped())
@@ -137,35 +390,286 @@ impl Repository {
             .spawn()
             .map_err(|e| GitError::CommandError { source: e })?;
 
-        if let Some(verbose) = options.verbose {
+        let verbose = options.verbose == Some(true);
+
+        // the output is printed while cloning in verbose mode
+        let mut child_stdout = child.stdout.take().unwrap();
+        let printer = thread::spawn(move || {
             if verbose {
-                io::copy(&mut child.stdout.take().unwrap(), &mut stderr)
-                    .map_err(|e| GitError::CommandError { source: e })?;
-                io::copy(&mut child.stderr.take().unwrap(), &mut stderr)
-                    .map_err(|e| GitError::CommandError { source: e })?;
+                io::copy(&mut child_stdout, &mut io::stderr()).ok();
+            } else {
+                io::copy(&mut child_stdout, &mut io::sink()).ok();
             }
+        });
+
+        // the stderr is captured to classify the error, even it is printed in verbose mode
+        let mut child_stderr = child.stderr.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut output = vec![];
+            let mut buf = [0; 4096];
+
+            while let Ok(n) = child_stderr.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+
+                if verbose {
+                    io::stderr().write_all(&buf[..n]).ok();
+                }
+
+                output.extend_from_slice(&buf[..n]);
+            }
+
+            String::from_utf8_lossy(&output).to_string()
+        });
+
+        let state = wait_child(&mut child, self.timeout.unwrap_or(CLONE_TIMEOUT))?;
+
+        printer.join().expect("can not read data from stdout");
+
+        let output = reader.join().expect("can not read data from stderr");
+
+        if state == Some(0) {
+            let result = match (&options.sparse, commit) {
+                (None, None) => Ok(()),
+                (sparse, commit) => {
+                    self.checkout(dest, commit.unwrap_or("HEAD"), sparse.as_deref())
+                }
+            };
+
+            if result.is_err() && dest.exists() {
+                fs::remove_dir_all(dest).map_err(|e| GitError::IO { source: e })?;
+            }
+
+            return result;
         }
 
-        let timeout = Duration::from_secs(300); // 5min
+        // remove the incomplete repository so that it can be cloned again
+        if dest.exists() {
+            fs::remove_dir_all(dest).map_err(|e| GitError::IO { source: e })?;
+        }
 
-        let state = match child
-            .wait_timeout(timeout)
-            .map_err(|e| GitError::IO { source: e })?
-        {
-            Some(status) => status.code(),
-            None => {
-                // child hasn't exited yet
-                child.kill().map_err(|e| GitError::IO { source: e })?;
-                child.wait().map_err(|e| GitError::IO { source: e })?.code()
+        // eg. 'Remote branch foo not found in upstream origin'
+        if output.contains("not found in upstream") {
+            return Err(self.reference_error(options.reference.as_deref().unwrap_or_default()));
+        }
+
+        self.get_exit_error(state, &output)
+    }
+
+    // bring the repository that is cloned before to the latest commit of the default branch or the reference.
+    // it is cloned if it does not exist, or cloned again if it can not be updated incrementally
+    pub fn update(&self, dest: &Path, options: CloneOption) -> Result<(), GitError> {
+        if !dest.exists() {
+            return self.clone(dest, options);
+        }
+
+        // the repository that is cloned by the git command is fetched incrementally
+        let result = if self.is_native() && !dest.join(".git").is_dir() {
+            // the native transport does not keep the history, the folder is cloned again only if it is outdated
+            match http::is_up_to_date(
+                &self.remote,
+                &self.get_config(),
+                dest,
+                options.reference.as_deref(),
+                self.timeout.unwrap_or(REMOTE_TIMEOUT),
+            ) {
+                Ok(true) => return Ok(()),
+                Ok(false) => Err(GitError::RepositoryExist {
+                    path: format!("{}", dest.display()),
+                }),
+                Err(e) if e.is_fallback() => {
+                    self.fallback(e, || self.fetch_with_command(dest, &options))
+                }
+                Err(e) => Err(e),
             }
+        } else {
+            self.fetch_with_command(dest, &options)
         };
 
-        let exit_code = state.unwrap_or(1);
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if e.is_transient() => Err(e),
+            Err(_) => {
+                fs::remove_dir_all(dest).map_err(|e| GitError::IO { source: e })?;
+
+                self.clone(dest, options)
+            }
+        }
+    }
+
+    // fetch the latest commit into the repository that is cloned by the git command, then reset the files to it
+    fn fetch_with_command(&self, dir: &Path, options: &CloneOption) -> Result<(), GitError> {
+        if !dir.join(".git").is_dir() {
+            return Err(GitError::RepositoryExist {
+                path: format!("{}", dir.display()),
+            });
+        }
+
+        let mut args: Vec<String> = vec!["fetch".to_string()];
+
+        if let Some(depth) = options.depth {
+            args.push(format!("--depth={}", depth))
+        }
+
+        if options.quiet == Some(true) {
+            args.push("--quiet".to_string())
+        }
 
-        if exit_code == 0 {
+        if let Some(filter) = &options.filter {
+            args.push(format!("--filter={}", filter))
+        }
+
+        args.push("origin".to_string());
+        args.push(
+            options
+                .reference
+                .clone()
+                .unwrap_or_else(|| "HEAD".to_string()),
+        );
+
+        self.run_in(dir, &args)?;
+
+        self.run_in(dir, &["reset", "--hard", "--quiet", "FETCH_HEAD"])?;
+
+        // the files that are created by the hooks are removed as well
+        self.run_in(dir, &["clean", "-d", "--force", "--quiet"])
+    }
+
+    // run the git command in the repository
+    fn run_in<S: AsRef<std::ffi::OsStr>>(&self, dir: &Path, args: &[S]) -> Result<(), GitError> {
+        debug!(
+            "run git {} in '{}'",
+            args.iter()
+                .map(|a| a.as_ref().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            dir.display()
+        );
+
+        let mut child = self
+            .command()
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .env("GCM_INTERACTIVE", "never")
+            .env("GIT_SSH_COMMAND", get_ssh_command())
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::null())
+            .args(args)
+            .spawn()
+            .map_err(|e| GitError::CommandError { source: e })?;
+
+        let state = wait_child(&mut child, self.timeout.unwrap_or(CLONE_TIMEOUT))?;
+
+        if state == Some(0) {
             return Ok(());
         }
 
+        let mut output = String::new();
+
+        if let Some(mut child_stderr) = child.stderr.take() {
+            child_stderr.read_to_string(&mut output).ok();
+        }
+
+        self.get_exit_error(state, &output)
+    }
+
+    fn reference_error(&self, reference: &str) -> GitError {
+        GitError::ReferenceNotExists {
+            url: self.remote.to_string(),
+            reference: reference.to_string(),
+        }
+    }
+
+    // check out the commit of the repository that is cloned without checkout, only the sparse paths are written if specified
+    fn checkout(
+        &self,
+        dir: &Path,
+        commit: &str,
+        sparse: Option<&[String]>,
+    ) -> Result<(), GitError> {
+        if let Some(paths) = sparse {
+            let patterns = paths
+                .iter()
+                .map(|p| format!("/{}\n", p.trim_start_matches('/')))
+                .collect::<String>();
+
+            fs::write(
+                dir.join(".git").join("info").join("sparse-checkout"),
+                patterns,
+            )
+            .map_err(|e| GitError::IO { source: e })?;
+        }
+
+        let sparse_checkout = format!("core.sparseCheckout={}", sparse.is_some());
+
+        debug!(
+            "run git -c {} read-tree -mu {} in '{}'",
+            sparse_checkout,
+            commit,
+            dir.display()
+        );
+
+        let mut child = self
+            .command()
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .env("GCM_INTERACTIVE", "never")
+            .env("GIT_SSH_COMMAND", get_ssh_command())
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::null())
+            .args(["-c", &sparse_checkout, "read-tree", "-mu", commit])
+            .spawn()
+            .map_err(|e| GitError::CommandError { source: e })?;
+
+        let state = wait_child(&mut child, self.timeout.unwrap_or(CLONE_TIMEOUT))?;
+
+        if state == Some(0) {
+            return Ok(());
+        }
+
+        let mut output = String::new();
+
+        if let Some(mut child_stderr) = child.stderr.take() {
+            child_stderr.read_to_string(&mut output).ok();
+        }
+
+        // eg. 'Not a valid object name 2aedc1c'
+        if output.contains("Not a valid object name") {
+            return Err(self.reference_error(commit));
+        }
+
+        self.get_exit_error(state, &output)
+    }
+
+    fn get_exit_error<T>(&self, state: Option<i32>, stderr: &str) -> Result<T, GitError> {
+        let exit_code = match state {
+            Some(code) => code,
+            None => {
+                return Err(GitError::NetworkError {
+                    url: self.remote.to_string(),
+                    message: "timeout".to_string(),
+                })
+            }
+        };
+
+        if let Some(message) = get_network_error(stderr) {
+            return Err(GitError::NetworkError {
+                url: self.remote.to_string(),
+                message,
+            });
+        }
+
+        // the private repository is reported as authentication failure instead of not exist
+        if let Some(message) = get_authentication_error(stderr) {
+            return Err(GitError::AuthenticationError {
+                url: self.remote.to_string(),
+                message,
+            });
+        }
+
         if exit_code == 128 {
             return Err(GitError::RemoteRepositoryNotExists {
                 url: self.remote.to_string(),
@@ -176,15 +680,37 @@ impl Repository {
     }
 
     pub fn is_exist(&self) -> Result<bool, GitError> {
-        let mut child = ChildProcess::new("git")
+        if !self.is_native() {
+            return self.is_exist_with_command();
+        }
+
+        let result = match http::ls_remote(
+            &self.remote,
+            &self.get_config(),
+            self.timeout.unwrap_or(REMOTE_TIMEOUT),
+        ) {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_fallback() => self.fallback(e, || self.is_exist_with_command()),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Err(GitError::RemoteRepositoryNotExists { .. })
+            | Err(GitError::AuthenticationError { .. }) => Ok(false),
+            r => r,
+        }
+    }
+
+    fn is_exist_with_command(&self) -> Result<bool, GitError> {
+        debug!("run git ls-remote {}", self.remote);
+
+        let mut child = self
+            .command()
             .env("GIT_TERMINAL_PROMPT", "0")
             .env("GCM_INTERACTIVE", "never")
-            .env(
-                "GIT_SSH_COMMAND",
-                "ssh -o ControlMaster=no -o BatchMode=yes",
-            )
+            .env("GIT_SSH_COMMAND", get_ssh_command())
             .stdin(Stdio::null())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .stdout(Stdio::null())
             .arg("ls-remote")
             .arg("-h")
@@ -192,45 +718,62 @@ impl Repository {
             .spawn()
             .map_err(|e| GitError::CommandError { source: e })?;
 
-        let timeout = Duration::from_secs(30);
-
-        let state = match child
-            .wait_timeout(timeout)
-            .map_err(|e| GitError::IO { source: e })?
-        {
-            Some(status) => status.code(),
-            None => {
-                // child hasn't exited yet
-                child.kill().map_err(|e| GitError::IO { source: e })?;
-                child.wait().map_err(|e| GitError::IO { source: e })?.code()
-            }
-        };
-
-        let exit_code = state.unwrap_or(1);
+        let state = wait_child(&mut child, self.timeout.unwrap_or(REMOTE_TIMEOUT))?;
 
-        if exit_code == 0 {
+        if state == Some(0) {
             return Ok(true);
         }
 
-        if exit_code == 128 {
-            return Ok(false);
+        let mut output = String::new();
+
+        if let Some(mut child_stderr) = child.stderr.take() {
+            child_stderr.read_to_string(&mut output).ok();
         }
 
-        Err(GitError::CommandExitError { code: exit_code })
+        // GitHub asks for the credentials if the repository does not exist
+        match self.get_exit_error(state, &output) {
+            Err(GitError::RemoteRepositoryNotExists { .. })
+            | Err(GitError::AuthenticationError { .. }) => Ok(false),
+            r => r,
+        }
     }
 
     pub fn tags(&self) -> Result<Vec<GitTag>, GitError> {
+        if !self.is_native() {
+            return self.tags_with_command();
+        }
+
+        match http::ls_remote(
+            &self.remote,
+            &self.get_config(),
+            self.timeout.unwrap_or(REMOTE_TIMEOUT),
+        ) {
+            Ok(refs) => Ok(refs
+                .into_iter()
+                .filter_map(|r| {
+                    r.name.strip_prefix("refs/tags/").map(|tag| GitTag {
+                        hash: r.hash.clone(),
+                        tag: tag.to_string(),
+                    })
+                })
+                .collect()),
+            Err(e) if e.is_fallback() => self.fallback(e, || self.tags_with_command()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn tags_with_command(&self) -> Result<Vec<GitTag>, GitError> {
         let mut tags: Vec<GitTag> = vec![];
 
-        let child = ChildProcess::new("git")
+        debug!("run git ls-remote -t {}", self.remote);
+
+        let mut child = self
+            .command()
             .env("GIT_TERMINAL_PROMPT", "0")
             .env("GCM_INTERACTIVE", "never")
-            .env(
-                "GIT_SSH_COMMAND",
-                "ssh -o ControlMaster=no -o BatchMode=yes",
-            )
+            .env("GIT_SSH_COMMAND", get_ssh_command())
             .stdin(Stdio::null())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .stdout(Stdio::piped())
             .arg("ls-remote")
             .arg("-t")
@@ -238,23 +781,31 @@ impl Repository {
             .spawn()
             .map_err(|e| GitError::CommandError { source: e })?;
 
-        let output = child
-            .wait_with_output()
+        // read stdout in the background so that the child will not be blocked by a full pipe
+        let mut child_stdout = child.stdout.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut buf = vec![];
+            child_stdout.read_to_end(&mut buf).map(|_| buf)
+        });
+
+        let state = wait_child(&mut child, self.timeout.unwrap_or(REMOTE_TIMEOUT))?;
+
+        let output = reader
+            .join()
+            .expect("can not read data from stdout")
             .map_err(|e| GitError::CommandError { source: e })?;
 
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
+        if state != Some(0) {
+            let mut stderr = String::new();
 
-            if exit_code == 128 {
-                return Err(GitError::RemoteRepositoryNotExists {
-                    url: self.remote.to_string(),
-                });
+            if let Some(mut child_stderr) = child.stderr.take() {
+                child_stderr.read_to_string(&mut stderr).ok();
             }
 
-            return Err(GitError::CommandExitError { code: exit_code });
+            return self.get_exit_error(state, &stderr);
         }
 
-        let stdout = String::from_utf8(output.stdout).expect("can not read data from stdout");
+        let stdout = String::from_utf8(output).expect("can not read data from stdout");
 
         for line in stdout.lines().map(|f| f.to_string()) {
             let mut inter = line.split_whitespace();
@@ -278,7 +829,8 @@ impl Repository {
         Ok(tags)
     }
 
-    pub fn versions(&self) -> Result<Vec<String>, GitError> {
+    // get the versions from tags, the pre-release versions are ignored unless `include_prerelease` is true
+    pub fn versions(&self, include_prerelease: bool) -> Result<Vec<String>, GitError> {
         let mut versions: Vec<semver::Version> = vec![];
         let tags = self.tags()?;
 
@@ -289,13 +841,15 @@ impl Repository {
             if let Ok(v) = Version::parse(version) {
                 // ignore unstable version
                 // eg. 2.5.2-test
-                if v.pre.is_empty() {
+                if include_prerelease || v.pre.is_empty() {
                     versions.push(v);
                 }
             };
         }
 
         versions.sort_by(|a, b| b.cmp(a));
+        // the tags 'v1.0.0' and '1.0.0' are the same version
+        versions.dedup();
 
         let versions_str: Vec<String> = versions.into_iter().map(|v| v.to_string()).collect();
 
@@ -303,6 +857,64 @@ impl Repository {
     }
 }
 
+#[cfg(test)]
+mod tests_network_error {
+    use crate::get_network_error;
+
+    #[test]
+    fn test_get_network_error() {
+        assert_eq!(
+            get_network_error("Cloning into 'foo'...\nfatal: unable to access 'https://github.com/foo/bar/': Could not resolve host: github.com\n"),
+            Some("fatal: unable to access 'https://github.com/foo/bar/': Could not resolve host: github.com".to_string())
+        );
+        assert_eq!(get_network_error("remote: Repository not found.\n"), None);
+    }
+
+    #[test]
+    fn test_get_authentication_error() {
+        use crate::get_authentication_error;
+
+        assert_eq!(
+            get_authentication_error("fatal: could not read Username for 'https://git.example.com': terminal prompts disabled\n"),
+            Some("fatal: could not read Username for 'https://git.example.com': terminal prompts disabled".to_string())
+        );
+        assert_eq!(
+            get_authentication_error("git@git.example.com: Permission denied (publickey).\nfatal: Could not read from remote repository.\n"),
+            Some("git@git.example.com: Permission denied (publickey).".to_string())
+        );
+        assert_eq!(
+            get_authentication_error("remote: Repository not found.\n"),
+            None
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_config {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_with_config() {
+        let repo = new("https://github.com/axetroy/gpm.rs.git")
+            .unwrap()
+            .with_config(vec![(
+                "http.https://github.com/.extraHeader".to_string(),
+                "Authorization: Basic eHh4".to_string(),
+            )]);
+
+        let command = repo.command();
+
+        // the config is passed to the git process only
+        assert!(command.get_envs().any(|(key, value)| {
+            key.to_string_lossy().starts_with("GIT_CONFIG_VALUE_")
+                && value.map(|v| v.to_string_lossy() == "Authorization: Basic eHh4") == Some(true)
+        }));
+        assert!(env::vars().all(|(_, value)| !value.contains("eHh4")));
+    }
+}
+
 #[cfg(test)]
 mod tests_clone {
     use std::{env, fs, path::Path};
@@ -327,6 +939,8 @@ mod tests_clone {
                 single_branch: Some(true),
                 dissociate: Some(true),
                 filter: Some("tree:0".to_string()),
+                sparse: None,
+                reference: None,
             },
         )
         .unwrap();
@@ -350,6 +964,8 @@ mod tests_clone {
                 single_branch: Some(true),
                 dissociate: Some(true),
                 filter: Some("tree:0".to_string()),
+                sparse: None,
+                reference: None,
             },
         );
 
@@ -367,6 +983,83 @@ mod tests_clone {
             })
         }
     }
+
+    #[test]
+    fn test_clone_with_command_if_network_error_in_verbose() {
+        let repo = new("https://cask.invalid/axetroy/not_exist.git").unwrap();
+
+        let dest_dir = env::temp_dir().join("cask_test_verbose_network_error");
+
+        let r = repo.clone_with_command(
+            &dest_dir,
+            CloneOption {
+                depth: Some(1),
+                quiet: None,
+                verbose: Some(true),
+                progress: None,
+                single_branch: None,
+                dissociate: None,
+                filter: None,
+                sparse: None,
+                reference: None,
+            },
+        );
+
+        fs::remove_dir_all(dest_dir).ok();
+
+        // the stderr printed in verbose mode is still used to classify the error
+        assert!(matches!(r, Err(GitError::NetworkError { .. })));
+    }
+}
+
+#[cfg(test)]
+mod tests_update {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn option() -> CloneOption {
+        CloneOption {
+            depth: Some(1),
+            quiet: Some(true),
+            verbose: Some(false),
+            progress: Some(false),
+            single_branch: Some(true),
+            dissociate: Some(true),
+            filter: None,
+            sparse: None,
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let repo = new("https://github.com/axetroy/gpm.rs.git").unwrap();
+
+        let dest_dir = env::temp_dir().join("cask_test_update");
+
+        fs::remove_dir_all(&dest_dir).ok();
+
+        // clone if it does not exist
+        repo.update(&dest_dir, option()).unwrap();
+
+        assert!(dest_dir.join("README.md").exists());
+
+        // the removed file is restored
+        fs::remove_file(dest_dir.join("README.md")).unwrap();
+        fs::remove_file(dest_dir.join(".cask-head")).ok();
+
+        repo.update(&dest_dir, option()).unwrap();
+
+        assert!(dest_dir.join("README.md").exists());
+
+        // it is kept if it is up to date
+        repo.update(&dest_dir, option()).unwrap();
+
+        assert!(dest_dir.join("README.md").exists());
+
+        fs::remove_dir_all(dest_dir).ok();
+    }
 }
 
 #[cfg(test)]
@@ -513,7 +1206,7 @@ mod tests_versions {
     fn test_versions() {
         let repo = new("https://github.com/axetroy/prune.v.git").unwrap();
 
-        let versions = repo.versions().unwrap();
+        let versions = repo.versions(false).unwrap();
 
         let expect: Vec<String> = vec![
             "0.2.14", "0.2.13", "0.2.12", "0.2.11", "0.2.10", "0.2.9", "0.2.8", "0.2.7", "0.2.6",
@@ -530,7 +1223,7 @@ mod tests_versions {
     fn test_get_versions_from_a_not_exist_repo() {
         let repo = new("https://github.com/axetroy/not_exist.git").unwrap();
 
-        let r1 = repo.versions();
+        let r1 = repo.versions(false);
 
         assert!(r1.is_err());
 
@@ -4,11 +4,33 @@ use eyre::Report;
 
 use std::{
     collections::HashMap,
-    io,
+    env,
+    io::{self, Read},
     path::Path,
-    process::{Command as ChildProcess, Stdio},
+    process::{Child, Command as ChildProcess, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
+// the credentials of Cask and git are never inherited by the commands. eg. the hooks of formula
+const SECRET_ENVS: [&str; 7] = [
+    "GITHUB_TOKEN",
+    "GITLAB_TOKEN",
+    "CASK_GITHUB_TOKEN",
+    "CASK_GITLAB_TOKEN",
+    "CASK_GIT_TOKENS",
+    "GIT_CONFIG_COUNT",
+    "GIT_CONFIG_PARAMETERS",
+];
+
+// the config entries of git environment variables. eg. GIT_CONFIG_KEY_0 and GIT_CONFIG_VALUE_0
+fn is_secret_env(key: &str) -> bool {
+    SECRET_ENVS.contains(&key)
+        || key.starts_with("GIT_CONFIG_KEY_")
+        || key.starts_with("GIT_CONFIG_VALUE_")
+}
+
 pub enum Output<'a> {
     Writer(&'a mut dyn io::Write), // write command output to writer
     Inherit,                       // inherit stdout/stderr from parent process
@@ -18,30 +40,139 @@ pub enum Output<'a> {
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Terminal {
     Cmd,
-    PowerShell,
+    PowerShell, // Windows PowerShell, it is shipped with Windows
+    Pwsh,       // PowerShell 7+, it is installed separately and runs on Unix as well
     Sh,
     Bash,
 }
 
+impl Terminal {
+    // the executable of terminal
+    pub fn program(&self) -> &'static str {
+        match self {
+            Terminal::Cmd => "cmd.exe",
+            Terminal::PowerShell => "powershell.exe",
+            Terminal::Pwsh => "pwsh",
+            Terminal::Sh => "sh",
+            Terminal::Bash => "bash",
+        }
+    }
+
+    // whether the terminal is installed, the executable is looked up in $PATH
+    pub fn is_available(&self) -> bool {
+        let program = self.program();
+
+        let paths = match env::var_os("PATH") {
+            Some(paths) => paths,
+            None => return false,
+        };
+
+        env::split_paths(&paths).any(|dir| {
+            dir.join(program).is_file()
+                || (cfg!(windows) && dir.join(format!("{}.exe", program)).is_file())
+        })
+    }
+}
+
+// kill the process and its children, the script may spawn the sub-processes.
+// the children can be found on Unix only if the process is the leader of its own group
+fn kill_tree(child: &mut Child, is_group_leader: bool) {
+    #[cfg(unix)]
+    if is_group_leader {
+        // the negative pid means the whole group
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    }
+
+    #[cfg(windows)]
+    let _ = is_group_leader;
+
+    #[cfg(windows)]
+    ChildProcess::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok();
+
+    child.kill().ok();
+}
+
+// the time to wait for the pipes to be closed after the process exits.
+// the descendants may keep the pipes open, eg. the daemon that is started by the script
+const PIPE_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// the output of pipe that is read in another thread, so that the process is never blocked by the full pipe
+struct Pipe {
+    buf: Arc<Mutex<Vec<u8>>>,
+    reader: thread::JoinHandle<()>,
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> Pipe {
+    let buf = Arc::new(Mutex::new(vec![]));
+    let output = buf.clone();
+
+    let reader = thread::spawn(move || {
+        let mut chunk = [0u8; 8192];
+
+        if let Some(mut r) = pipe {
+            while let Ok(n) = r.read(&mut chunk) {
+                if n == 0 {
+                    break;
+                }
+
+                if let Ok(mut output) = output.lock() {
+                    output.extend_from_slice(&chunk[..n]);
+                }
+            }
+        }
+    });
+
+    Pipe { buf, reader }
+}
+
+impl Pipe {
+    // the output that has been read until the pipe is closed or the deadline
+    fn take(self, deadline: Instant) -> Vec<u8> {
+        while !self.reader.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        self.buf.lock().map(|b| b.clone()).unwrap_or_default()
+    }
+}
+
 pub fn run_with(
     terminal: Terminal,
     cwd: &Path,
     command: &str,
     output: &mut Output,
     envs: HashMap<String, String>,
+) -> Result<(), Report> {
+    run_with_timeout(terminal, cwd, command, output, envs, None)
+}
+
+// run the command, it is killed with its children if it does not exit in time
+pub fn run_with_timeout(
+    terminal: Terminal,
+    cwd: &Path,
+    command: &str,
+    output: &mut Output,
+    envs: HashMap<String, String>,
+    timeout: Option<Duration>,
 ) -> Result<(), Report> {
     let commands: Vec<&str> = {
         match terminal {
-            Terminal::Cmd => vec!["cmd.exe", "--%", "/c"],
-            Terminal::PowerShell => vec![
-                "powershell.exe",
+            Terminal::Cmd => vec![terminal.program(), "--%", "/c"],
+            Terminal::PowerShell | Terminal::Pwsh => vec![
+                terminal.program(),
                 "-NoLogo",
                 "-NoProfile",
                 "-NonInteractive",
                 "-Command",
             ],
-            Terminal::Sh => vec!["sh", "-c"],
-            Terminal::Bash => vec!["bash", "-c"],
+            Terminal::Sh | Terminal::Bash => vec![terminal.program(), "-c"],
         }
     };
 
@@ -56,14 +187,45 @@ pub fn run_with(
 
     let mut ps = cmd.current_dir(cwd).args(args);
 
+    for (key, _) in env::vars_os() {
+        if key.to_str().is_some_and(is_secret_env) {
+            ps.env_remove(&key);
+        }
+    }
+
     ps.envs(envs);
 
+    // the command that inherits the terminal may read it, so it is kept in the foreground process group.
+    // otherwise it gets SIGTTIN when reading the terminal, and Ctrl+C does not reach it
+    let is_interactive = matches!(output, Output::Inherit);
+
     match &output {
         Output::Writer(_) => {
-            ps = ps.stdout(Stdio::piped()).stderr(Stdio::piped());
+            ps = ps
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
         }
-        Output::Inherit => ps = ps.stdout(Stdio::inherit()).stderr(Stdio::inherit()),
-        Output::None => ps = ps.stdout(Stdio::null()).stderr(Stdio::null()),
+        Output::Inherit => {
+            ps = ps
+                .stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit())
+        }
+        Output::None => {
+            ps = ps
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+        }
+    }
+
+    // put the non-interactive process into a new group, so that its children are killed together
+    #[cfg(unix)]
+    if !is_interactive {
+        use std::os::unix::process::CommandExt;
+
+        ps = ps.process_group(0);
     }
 
     let mut child = match ps.spawn() {
@@ -71,23 +233,47 @@ pub fn run_with(
         Err(e) => Err(eyre::format_err!("{}", e)),
     }?;
 
-    if let Output::Writer(r) = output {
-        io::copy(&mut child.stdout.take().unwrap(), r)?;
-        io::copy(&mut child.stderr.take().unwrap(), r)?;
-    };
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
 
-    match child.wait() {
-        Ok(state) => {
-            if state.success() {
-                Ok(())
-            } else {
-                Err(eyre::format_err!(
-                    "exit code: {}",
-                    state.code().unwrap_or(1),
-                ))
-            }
+    let deadline = timeout.map(|t| Instant::now() + t);
+
+    let result = loop {
+        match child.try_wait() {
+            Ok(Some(state)) => break Ok(state),
+            Ok(None) => {}
+            Err(e) => break Err(eyre::format_err!("{}", e)),
         }
-        Err(e) => Err(eyre::format_err!("{}", e)),
+
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            kill_tree(&mut child, !is_interactive);
+            child.wait().ok();
+
+            break Err(eyre::format_err!(
+                "timeout after {} seconds",
+                timeout.unwrap_or_default().as_secs()
+            ));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    if let Output::Writer(w) = output {
+        let pipe_deadline = Instant::now() + PIPE_CLOSE_TIMEOUT;
+
+        w.write_all(&stdout.take(pipe_deadline))?;
+        w.write_all(&stderr.take(pipe_deadline))?;
+    };
+
+    let state = result?;
+
+    if state.success() {
+        Ok(())
+    } else {
+        Err(eyre::format_err!(
+            "exit code: {}",
+            state.code().unwrap_or(1),
+        ))
     }
 }
 
@@ -111,7 +297,7 @@ mod tests {
     use std::collections::HashMap;
     use std::env;
 
-    use crate::{run, run_with, Output, Terminal};
+    use crate::{is_secret_env, run, run_with, run_with_timeout, Output, Terminal};
 
     #[test]
     fn test_echo() {
@@ -183,6 +369,92 @@ mod tests {
         assert_eq!(result, "hello powershell")
     }
 
+    #[test]
+    fn test_with_pwsh() {
+        // pwsh is optional
+        if !Terminal::Pwsh.is_available() {
+            return;
+        }
+
+        let cwd = env::current_dir().unwrap();
+
+        let mut buf = Vec::new();
+
+        run_with(
+            Terminal::Pwsh,
+            &cwd,
+            r#"Write-Output 'hello pwsh'"#,
+            &mut Output::Writer(&mut buf),
+            HashMap::from([]),
+        )
+        .unwrap();
+
+        let result = std::str::from_utf8(&buf).unwrap().trim();
+
+        assert_eq!(result, "hello pwsh")
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_with_timeout() {
+        let cwd = env::current_dir().unwrap();
+
+        let mut buf = Vec::new();
+
+        let start = std::time::Instant::now();
+
+        // the sub-process is killed as well, otherwise the output pipe is never closed
+        let r = run_with_timeout(
+            Terminal::Sh,
+            &cwd,
+            r#"echo 'start'; sleep 10 & sleep 10"#,
+            &mut Output::Writer(&mut buf),
+            HashMap::from([]),
+            Some(std::time::Duration::from_millis(500)),
+        );
+
+        assert!(r.unwrap_err().to_string().contains("timeout"));
+        assert!(start.elapsed().as_secs() < 5);
+        assert_eq!(std::str::from_utf8(&buf).unwrap().trim(), "start");
+
+        run_with_timeout(
+            Terminal::Sh,
+            &cwd,
+            r#"echo 'done'"#,
+            &mut Output::None,
+            HashMap::from([]),
+            Some(std::time::Duration::from_secs(10)),
+        )
+        .unwrap();
+
+        // the background process keeps the output pipe open after the script exits
+        let mut buf = Vec::new();
+        let start = std::time::Instant::now();
+
+        run_with_timeout(
+            Terminal::Sh,
+            &cwd,
+            r#"sleep 10 & echo 'daemon'"#,
+            &mut Output::Writer(&mut buf),
+            HashMap::from([]),
+            None,
+        )
+        .unwrap();
+
+        assert!(start.elapsed().as_secs() < 5);
+        assert_eq!(std::str::from_utf8(&buf).unwrap().trim(), "daemon");
+    }
+
+    #[test]
+    fn test_is_available() {
+        if cfg!(unix) {
+            assert!(Terminal::Sh.is_available());
+        } else {
+            assert!(Terminal::Cmd.is_available());
+            assert!(Terminal::PowerShell.is_available());
+        }
+    }
+
     #[cfg(target_family = "unix")]
     #[test]
     fn test_with_sh() {
@@ -276,4 +548,13 @@ mod tests {
 
         assert!(result.contains("hello world"))
     }
+
+    #[test]
+    fn test_is_secret_env() {
+        assert!(is_secret_env("GITHUB_TOKEN"));
+        assert!(is_secret_env("GIT_CONFIG_COUNT"));
+        assert!(is_secret_env("GIT_CONFIG_VALUE_0"));
+        assert!(!is_secret_env("GIT_CONFIG_GLOBAL"));
+        assert!(!is_secret_env("CASK_PACKAGE_NAME"));
+    }
 }
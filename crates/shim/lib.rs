@@ -0,0 +1,126 @@
+#![deny(warnings)]
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// the file name of shim executable, it is shipped next to Cask
+pub const EXE_NAME: &str = if cfg!(windows) {
+    "cask-shim.exe"
+} else {
+    "cask-shim"
+};
+
+// the executable that the shim runs, it is None if the executable is not a shim.
+// the '.shim' file is in the format of Scoop. eg. 'path = "C:\Users\Axetroy\.cask\...\dvm.exe"'
+pub fn read(shim_exe: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(shim_exe.with_extension("shim")).ok()?;
+
+    content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+
+        if key.trim() != "path" {
+            return None;
+        }
+
+        Some(PathBuf::from(value.trim().trim_matches('"')))
+    })
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn SetConsoleCtrlHandler(
+        handler: Option<unsafe extern "system" fn(u32) -> i32>,
+        add: i32,
+    ) -> i32;
+}
+
+// the Ctrl+C is handled by the executable, the shim exits after it.
+// the handler is not inherited by the executable, unlike ignoring the Ctrl+C
+#[cfg(windows)]
+fn ignore_ctrl_c() {
+    unsafe extern "system" fn handler(_ctrl_type: u32) -> i32 {
+        1
+    }
+
+    unsafe {
+        SetConsoleCtrlHandler(Some(handler), 1);
+    }
+}
+
+#[cfg(not(windows))]
+fn ignore_ctrl_c() {}
+
+// run the executable of the shim with the arguments, it returns the exit code of executable
+pub fn run<I: IntoIterator<Item = OsString>>(shim_exe: &Path, args: I) -> io::Result<i32> {
+    let target = read(shim_exe).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "can not found the executable of shim '{}'",
+                shim_exe.display()
+            ),
+        )
+    })?;
+
+    ignore_ctrl_c();
+
+    let status = Command::new(&target)
+        .args(args)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("run '{}' fail: {}", target.display(), e)))?;
+
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use crate::{read, run};
+
+    #[test]
+    fn test_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let shim_exe = dir.path().join("dvm.exe");
+
+        fs::write(
+            dir.path().join("dvm.shim"),
+            "path = \"C:\\Users\\Axetroy\\.cask\\bin\\dvm.exe\"\nargs = --help\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read(&shim_exe),
+            Some(PathBuf::from("C:\\Users\\Axetroy\\.cask\\bin\\dvm.exe"))
+        );
+        assert_eq!(read(&dir.path().join("cask.exe")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let shim_exe = dir.path().join("sh.exe");
+
+        fs::write(dir.path().join("sh.shim"), "path = \"/bin/sh\"\n").unwrap();
+
+        assert_eq!(run(&shim_exe, ["-c".into(), "exit 3".into()]).unwrap(), 3);
+
+        // the error is returned instead of printed
+        let err = run(&dir.path().join("foo.exe"), []).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        fs::write(dir.path().join("sh.shim"), "path = \"/not/exist\"\n").unwrap();
+
+        assert!(run(&shim_exe, [])
+            .unwrap_err()
+            .to_string()
+            .contains("/not/exist"));
+    }
+}
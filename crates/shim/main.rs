@@ -0,0 +1,10 @@
+#![deny(warnings)]
+
+use std::{env, io, process};
+
+// the launcher of package executable on Windows, it is hard linked into the bin folder by the name of executable
+fn main() -> io::Result<()> {
+    let code = shim::run(&env::current_exe()?, env::args_os().skip(1))?;
+
+    process::exit(code)
+}
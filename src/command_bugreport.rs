@@ -0,0 +1,174 @@
+#![deny(warnings)]
+
+use crate::{cask, command_config, logger, util::redact_secrets};
+
+use std::{
+    env,
+    fs::{self, File},
+    path::Path,
+    process::Command,
+};
+
+use chrono::prelude::*;
+use eyre::Report;
+use libflate::gzip::Encoder as GzEncoder;
+use tracing::info;
+
+fn get_libc() -> &'static str {
+    if cfg!(target_env = "musl") {
+        "musl"
+    } else if cfg!(target_env = "gnu") {
+        "gnu"
+    } else if cfg!(target_env = "msvc") {
+        "msvc"
+    } else {
+        "unknown"
+    }
+}
+
+fn get_git_version(cask: &cask::Cask) -> String {
+    let git_bin = cask
+        .settings()
+        .git_bin
+        .clone()
+        .unwrap_or_else(|| "git".into());
+
+    match Command::new(git_bin).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        Ok(output) => format!("error: {}", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+// hide the user information and secrets from the report
+fn redact(content: &str) -> String {
+    let content = redact_secrets(content);
+
+    match dirs::home_dir() {
+        Some(home) => content.replace(&home.display().to_string(), "~"),
+        None => content,
+    }
+}
+
+fn append_file(
+    archive: &mut tar::Builder<GzEncoder<File>>,
+    name: &str,
+    content: &str,
+) -> Result<(), Report> {
+    let content = redact(content);
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    header.set_cksum();
+
+    archive.append_data(&mut header, name, content.as_bytes())?;
+
+    Ok(())
+}
+
+fn get_report(cask: &cask::Cask, last_failure: Option<&Path>) -> String {
+    let bin_in_path = cask.check_bin_path().is_ok();
+
+    let mut lines = vec![
+        format!("cask: {}", env!("CARGO_PKG_VERSION")),
+        format!("os: {}", env::consts::OS),
+        format!("arch: {}", env::consts::ARCH),
+        format!("family: {}", env::consts::FAMILY),
+        format!("libc: {}", get_libc()),
+        format!("root: {}", cask.root_dir().display()),
+        format!("git: {}", get_git_version(cask)),
+        format!("bin in $PATH: {}", bin_in_path),
+        format!("created at: {}", Local::now().to_rfc3339()),
+        format!(
+            "last failing run: {}",
+            last_failure
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "none".to_string())
+        ),
+        "".to_string(),
+        "packages:".to_string(),
+    ];
+
+    match cask.list_formula() {
+        Ok(formulas) => {
+            for f in formulas {
+                let (name, version) = f
+                    .cask
+                    .map(|c| (c.name, c.version))
+                    .unwrap_or_else(|| (f.package.name.clone(), "unknown".to_string()));
+
+                lines.push(format!("  {} {} ({})", name, version, f.package.bin));
+            }
+        }
+        Err(e) => lines.push(format!("  error: {}", e)),
+    }
+
+    lines.join("\n") + "\n"
+}
+
+pub async fn bugreport(cask: &cask::Cask, output: Option<&Path>) -> Result<(), Report> {
+    let output_file_path = match output {
+        Some(p) => p.to_path_buf(),
+        None => env::current_dir()?.join(format!(
+            "cask-bugreport-{}.tar.gz",
+            Local::now().format("%Y%m%d%H%M%S")
+        )),
+    };
+
+    let encoder = GzEncoder::new(File::create(&output_file_path)?)?;
+
+    let mut archive = tar::Builder::new(encoder);
+
+    let last_failure = logger::find_last_failure(cask)?;
+
+    append_file(
+        &mut archive,
+        "report.txt",
+        &get_report(cask, last_failure.as_deref()),
+    )?;
+
+    // the config affects the downloading and the git operations, the tokens are hidden
+    if cask.config_file().exists() {
+        append_file(
+            &mut archive,
+            "config.toml",
+            &command_config::read_redacted(cask).unwrap_or_else(|e| format!("# error: {}", e)),
+        )?;
+    }
+
+    // the log of the last failing run tells what happened
+    if let Some(filepath) = &last_failure {
+        if let Some(filename) = filepath.file_name() {
+            append_file(
+                &mut archive,
+                &format!("logs/{}", filename.to_string_lossy()),
+                &fs::read_to_string(filepath)?,
+            )?;
+        }
+    }
+
+    // the installed formulas help to reproduce the issue
+    for f in cask.list_formula()? {
+        append_file(
+            &mut archive,
+            &format!("formula/{}.toml", f.package.bin),
+            &f.file_content,
+        )?;
+    }
+
+    archive.into_inner()?.finish().into_result()?;
+
+    info!(
+        "The bug report has been written to '{}', attach it to the issue at {}/issues",
+        output_file_path.display(),
+        env!("CARGO_PKG_REPOSITORY")
+    );
+
+    info!("Please review the content before sharing it.");
+
+    Ok(())
+}
@@ -1,9 +1,12 @@
 #![deny(warnings)]
 
-use crate::{cask, command_install};
+use crate::{cask, formula, i18n::t, install, notify, output};
+use std::time::Instant;
+
 use eyre::Report;
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
 #[derive(Serialize, Deserialize, Debug)]
 struct PackageInfo {
@@ -11,33 +14,75 @@ struct PackageInfo {
     bin: String,
     current_version: String,
     latest_version: String,
+    channel: Option<String>,
+    git_ref: Option<String>,
+    requirement: Option<String>,
 }
 
 pub async fn check_updates(
     cask: &cask::Cask,
     is_check_only: bool,
+    is_force: bool,
+    is_print_as_json: bool,
     is_verbose: bool,
 ) -> Result<(), Report> {
+    let start = Instant::now();
     let mut packages: Vec<PackageInfo> = vec![];
 
     let package_list = match cask.list_formula() {
         Ok(list) => list,
         Err(e) => {
-            eprintln!("Error listing formulas: {}", e);
+            error!("list formulas fail: {}", e);
             return Err(e);
         }
     };
 
     for package in package_list {
-        eprintln!("Checking {} for update...", package.package.name);
+        info!(
+            "{}",
+            t!("check-updates-checking", name = package.package.name)
+        );
+
+        if let Some(msg) = package.get_deprecation_message() {
+            warn!("{}", msg);
+        }
+
+        let channel = package.cask.as_ref().and_then(|c| c.channel.clone());
+        let requirement = package.cask.as_ref().and_then(|c| c.requirement.clone());
 
-        let latest_version_op = match package.get_latest_version() {
+        let latest_version_result = match &channel {
+            Some(name) => match package.get_channel(name) {
+                Ok(c) if c.version.is_some() => {
+                    info!(
+                        "{}",
+                        t!(
+                            "check-updates-skip-rolling",
+                            name = package.package.name,
+                            channel = name
+                        )
+                    );
+                    continue;
+                }
+                Ok(_) => package
+                    .get_channel_versions(cask, name)
+                    .await
+                    .map(|versions| versions.first().cloned()),
+                Err(e) => Err(e),
+            },
+            // the package is upgraded within the version requirement. eg. ^1.2
+            None => match &requirement {
+                Some(req) => package
+                    .get_versions(cask, formula::is_prerelease_spec(req))
+                    .await
+                    .and_then(|versions| formula::resolve_version(&versions, req)),
+                None => package.get_latest_version(cask, false).await,
+            },
+        };
+
+        let latest_version_op = match latest_version_result {
             Ok(ver) => ver,
             Err(e) => {
-                eprintln!(
-                    "Error getting latest version for {}: {}",
-                    package.package.name, e
-                );
+                error!("get latest version of {} fail: {}", package.package.name, e);
                 continue;
             }
         };
@@ -51,7 +96,7 @@ pub async fn check_updates(
         let cask_info = match package.cask {
             Some(info) => info,
             None => {
-                eprintln!(
+                warn!(
                     "No cask info available for package {}",
                     package.package.name
                 );
@@ -62,8 +107,8 @@ pub async fn check_updates(
         let current = match Version::parse(&cask_info.version) {
             Ok(ver) => ver,
             Err(e) => {
-                eprintln!(
-                    "Error parsing current version for {}: {}",
+                error!(
+                    "parse current version of {} fail: {}",
                     package.package.name, e
                 );
                 continue;
@@ -73,8 +118,8 @@ pub async fn check_updates(
         let latest = match Version::parse(&latest_version_str) {
             Ok(ver) => ver,
             Err(e) => {
-                eprintln!(
-                    "Error parsing latest version for {}: {}",
+                error!(
+                    "parse latest version of {} fail: {}",
                     package.package.name, e
                 );
                 continue;
@@ -87,36 +132,87 @@ pub async fn check_updates(
                 bin: package.package.bin,
                 current_version: cask_info.version,
                 latest_version: latest_version_str,
+                channel,
+                git_ref: cask_info.git_ref,
+                requirement,
             });
         }
     }
 
+    // the outdated packages are printed before they are updated
+    if is_print_as_json {
+        output::print_json(&packages)?;
+    }
+
+    let mut updated = 0;
+    let mut failed = 0;
+    let is_updating = !is_check_only && !packages.is_empty();
+
     for package in packages {
-        eprintln!(
-            "{}@{} got an update to {}",
-            package.name, package.current_version, package.latest_version
+        info!(
+            "{}",
+            t!(
+                "check-updates-found",
+                name = package.name,
+                current = package.current_version,
+                latest = package.latest_version
+            )
         );
 
         if !is_check_only {
-            if let Err(e) = command_install::install(
+            match install::install(
                 cask,
                 &package.name,
-                Some(&package.latest_version),
-                is_verbose,
+                Some(
+                    package
+                        .requirement
+                        .as_deref()
+                        .unwrap_or(&package.latest_version),
+                ),
+                &install::InstallOption {
+                    force: is_force,
+                    prerelease: false,
+                    verbose: is_verbose,
+                    bin_dir: None,
+                    link_dir: None,
+                    channel: package.channel.as_deref(),
+                    git_ref: package.git_ref.as_deref(),
+                    keep_archive: false,
+                    formula: None,
+                },
             )
             .await
             {
-                if e.to_string().contains("404 Not Found") {
-                    eprintln!(
-                        "Warning: Package {}'s distribute files not found. Skipping...",
-                        package.name
-                    );
-                } else {
-                    eprintln!("Error installing package {}: {}", package.name, e);
+                Ok(_) => updated += 1,
+                Err(e) => {
+                    failed += 1;
+
+                    if e.to_string().contains("404 Not Found") {
+                        warn!(
+                            "Package {}'s distribute files not found. Skipping...",
+                            package.name
+                        );
+                    } else {
+                        error!("install package {} fail: {}", package.name, e);
+                    }
                 }
             }
         }
     }
 
+    if is_updating {
+        let message = if failed > 0 {
+            t!(
+                "check-updates-summary-failed",
+                count = updated,
+                failed = failed
+            )
+        } else {
+            t!("check-updates-summary", count = updated)
+        };
+
+        notify::notify(cask, start.elapsed(), &message);
+    }
+
     Ok(())
 }
@@ -1,10 +1,11 @@
 #![deny(warnings)]
 
-use crate::{cask, formula};
+use crate::{cask, formula, lock};
 
 use std::{fs, io::ErrorKind, path::PathBuf};
 
 use eyre::Report;
+use tracing::info;
 
 pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
     // clear formula dir
@@ -12,7 +13,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
 
     let dir = fs::read_dir(formula_dir)?;
 
-    let clean_log = |p: PathBuf| eprintln!("Remove {}", p.display());
+    let clean_log = |p: PathBuf| info!("Remove {}", p.display());
 
     for entry in dir {
         let file = entry?;
@@ -24,12 +25,24 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
             continue;
         }
 
+        // wait for the package that is being installed
+        let _package_lock = lock::acquire(
+            &cask.package_lock_file(&file.file_name().to_string_lossy()),
+            &format!("package folder '{}'", path.display()),
+        )?;
+
         // clear version
         {
             if version_dir.exists() {
                 for download_resource in fs::read_dir(version_dir)? {
                     let resource_file_path = download_resource?;
-                    fs::remove_file(&resource_file_path.path())?;
+
+                    // the staging and backup folders are left if the install was interrupted
+                    if resource_file_path.path().is_dir() {
+                        fs::remove_dir_all(resource_file_path.path())?;
+                    } else {
+                        fs::remove_file(resource_file_path.path())?;
+                    }
 
                     clean_log(resource_file_path.path());
                 }
@@ -51,8 +64,8 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
         #[cfg(windows)]
         let bin_name = f.package.bin.clone() + "exe";
 
-        // clear bin of formula
-        {
+        // clear bin of formula, the versions are installed into their own folder now
+        if bin_dir.exists() {
             for bin_entry in fs::read_dir(bin_dir)? {
                 let entry = bin_entry?;
                 let path = entry.path();
@@ -70,7 +83,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
                                 // if symlink is point to the binary file, then remove it
                                 if p.as_os_str().to_string_lossy() == path_str {
                                     if let Ok(()) = fs::remove_file(&symlink) {
-                                        eprintln!(
+                                        info!(
                                             "The symlink file '{}' has been removed",
                                             symlink.display()
                                         );
@@ -81,7 +94,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
                                 if err.kind() == ErrorKind::NotFound {
                                     // try to remove and ignore error
                                     if let Ok(()) = fs::remove_file(&symlink) {
-                                        eprintln!(
+                                        info!(
                                             "The broken symlink file '{}' has been removed",
                                             symlink.display()
                                         );
@@ -90,9 +103,9 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
                             }
                         };
                     } else if symlink.is_file() {
-                        // shell script
+                        // shell script, the hard link or the copy of executable is not text
                         {
-                            let file_content = fs::read_to_string(&symlink)?;
+                            let file_content = fs::read_to_string(&symlink).unwrap_or_default();
 
                             if file_content.contains(&path_str) {
                                 if let Ok(()) = fs::remove_file(&symlink) {
@@ -118,7 +131,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
 
                                 if file_content.contains(&path_str) {
                                     if let Ok(()) = fs::remove_file(&bat_file_path) {
-                                        eprintln!(
+                                        info!(
                                             "The batch script '{}' has been removed",
                                             bat_file_path.display()
                                         );
@@ -127,7 +140,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
                             }
                         }
                     } else if let Ok(()) = fs::remove_file(&symlink) {
-                        eprintln!("The unknown file '{}' has been removed", symlink.display());
+                        info!("The unknown file '{}' has been removed", symlink.display());
                     }
                 }
             }
@@ -137,6 +150,8 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
     // remove broken symlink
     #[cfg(unix)]
     {
+        let _lock = cask.lock()?;
+
         let bin_dir = cask.bin_dir();
 
         let dir = fs::read_dir(bin_dir)?;
@@ -151,7 +166,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
                         if err.kind() == ErrorKind::NotFound {
                             // try to remove and ignore error
                             if let Ok(()) = fs::remove_file(&file) {
-                                eprintln!("broken symlink '{}' has been removed", file.display());
+                                info!("broken symlink '{}' has been removed", file.display());
                             }
                         }
                     }
@@ -160,7 +175,7 @@ pub async fn clean(cask: &cask::Cask) -> Result<(), Report> {
         }
     }
 
-    eprintln!("clear!");
+    info!("clear!");
 
     Ok(())
 }
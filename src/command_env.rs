@@ -0,0 +1,29 @@
+#![deny(warnings)]
+
+use crate::{cask, wizard};
+
+use eyre::Report;
+use tracing::info;
+
+pub async fn env(cask: &cask::Cask, is_apply: bool) -> Result<(), Report> {
+    // print the lines for the current terminal, eg. eval "$(cask env)"
+    if !is_apply {
+        print!(
+            "{}",
+            wizard::get_env_content(&cask.root_dir(), &cask.bin_dir())
+        );
+
+        return Ok(());
+    }
+
+    match wizard::apply_path(&cask.root_dir(), &cask.bin_dir())? {
+        Some(target) => info!(
+            "'{}' has been added to $PATH in {}, restart your terminal to make it work",
+            cask.bin_dir().display(),
+            target
+        ),
+        None => info!("'{}' is in $PATH already", cask.bin_dir().display()),
+    }
+
+    Ok(())
+}
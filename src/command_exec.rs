@@ -0,0 +1,38 @@
+#![deny(warnings)]
+
+use crate::{cask, settings};
+
+use std::{env, process};
+
+use eyre::Report;
+
+// run the command with the project-local packages, the bin folder is put in front of $PATH
+pub async fn exec(cask: &cask::Cask, command: &str, args: &[&str]) -> Result<i32, Report> {
+    let bin_dir = cask.bin_dir();
+
+    if !bin_dir.is_dir() {
+        return Err(eyre::format_err!(
+            "can not found the project-local packages in '{}', try run 'cask install --local' first",
+            cask.root_dir().display()
+        ));
+    }
+
+    let paths = env::var_os("PATH").unwrap_or_default();
+
+    let path = env::join_paths(
+        [bin_dir]
+            .into_iter()
+            .chain(env::split_paths(&paths).filter(|p| p != &cask.bin_dir())),
+    )?;
+
+    let status = process::Command::new(command)
+        .args(args)
+        .env("PATH", path)
+        // the `cask` in the command uses the same root
+        .env(settings::ENV_HOME, cask.shared_root_dir())
+        .status()
+        .map_err(|e| eyre::format_err!("run '{}' fail: {}", command, e))?;
+
+    // the process is terminated by signal
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,194 @@
+#![deny(warnings)]
+
+use crate::cask;
+
+use std::{fs, path::Path};
+
+use eyre::Report;
+use serde_json::json;
+use tracing::info;
+
+// the id of devcontainer feature, it is the folder name of feature as well
+const FEATURE_ID: &str = "cask-packages";
+
+struct ExportPackage {
+    name: String,
+    bin: String,
+    version: String,
+}
+
+const INSTALL_CASK: &str =
+    "curl -fsSL https://raw.githubusercontent.com/cask-pkg/cask.rs/main/install.sh | bash";
+
+// the packages are linked into the system bin folder without tracking, the image does not need the Cask root
+fn install_package(name: &str, version: &str) -> String {
+    format!(
+        "cask install {} {} --bin-dir /usr/local/bin --no-track",
+        name, version
+    )
+}
+
+// the option of devcontainer feature is passed to the install script as the environment variable.
+// eg. 'prettier-cli' -> 'PRETTIER_CLI'
+fn option_env(option: &str) -> String {
+    option
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn to_dockerfile(packages: &[ExportPackage]) -> String {
+    let mut lines = vec![
+        "# The snippet is generated by Cask".to_string(),
+        format!("RUN {}", INSTALL_CASK),
+    ];
+
+    for package in packages {
+        lines.push(format!(
+            "RUN {}",
+            install_package(&package.name, &package.version)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+// the install script of devcontainer feature, the versions can be overridden by the options of feature
+fn to_install_script(packages: &[ExportPackage]) -> String {
+    let mut lines = vec![
+        "#!/usr/bin/env bash".to_string(),
+        "# The script is generated by Cask".to_string(),
+        "set -e".to_string(),
+        "".to_string(),
+        INSTALL_CASK.to_string(),
+        "".to_string(),
+    ];
+
+    for package in packages {
+        lines.push(install_package(
+            &package.name,
+            &format!("\"${{{}:-{}}}\"", option_env(&package.bin), package.version),
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn to_devcontainer(packages: &[ExportPackage]) -> Result<String, Report> {
+    let mut options = serde_json::Map::new();
+
+    for package in packages {
+        options.insert(
+            package.bin.clone(),
+            json!({
+                "type": "string",
+                "default": package.version,
+                "description": format!("The version of {}", package.name),
+            }),
+        );
+    }
+
+    let feature = json!({
+        "id": FEATURE_ID,
+        "version": "1.0.0",
+        "name": "Cask packages",
+        "description": "The packages installed by Cask",
+        "options": options,
+    });
+
+    Ok(serde_json::to_string_pretty(&feature)?)
+}
+
+// the devcontainer feature is a folder of the metadata and the install script
+fn write_devcontainer(packages: &[ExportPackage], output: &Path) -> Result<(), Report> {
+    fs::create_dir_all(output)?;
+    fs::write(
+        output.join("devcontainer-feature.json"),
+        to_devcontainer(packages)?,
+    )?;
+
+    let install_script_path = output.join("install.sh");
+
+    fs::write(&install_script_path, to_install_script(packages))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::prelude::PermissionsExt;
+
+        fs::set_permissions(&install_script_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    info!(
+        "The devcontainer feature has been written to '{}'",
+        output.display()
+    );
+
+    Ok(())
+}
+
+pub async fn export(cask: &cask::Cask, format: &str, output: Option<&Path>) -> Result<(), Report> {
+    let mut packages: Vec<ExportPackage> = vec![];
+
+    for package in cask.list_formula()? {
+        let cask_info = package.cask.ok_or_else(|| {
+            eyre::format_err!(
+                "can not parse cask property of package '{}'",
+                package.package.name
+            )
+        })?;
+
+        packages.push(ExportPackage {
+            name: cask_info.name,
+            bin: package.package.bin,
+            version: cask_info.version,
+        });
+    }
+
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match format {
+        "dockerfile" => println!("{}", to_dockerfile(&packages)),
+        "devcontainer" => {
+            write_devcontainer(&packages, output.unwrap_or_else(|| Path::new(FEATURE_ID)))?
+        }
+        _ => {
+            return Err(eyre::format_err!(
+                "not support the export format '{}'",
+                format
+            ))
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_export::{self, ExportPackage};
+
+    #[test]
+    fn test_to_install_script() {
+        let packages = vec![ExportPackage {
+            name: "github.com/axetroy/prettier-cli".to_string(),
+            bin: "prettier-cli".to_string(),
+            version: "1.2.0".to_string(),
+        }];
+
+        assert_eq!(
+            command_export::to_install_script(&packages).lines().last(),
+            Some(
+                r#"cask install github.com/axetroy/prettier-cli "${PRETTIER_CLI:-1.2.0}" --bin-dir /usr/local/bin --no-track"#
+            )
+        );
+        assert_eq!(
+            command_export::to_dockerfile(&packages).lines().last(),
+            Some("RUN cask install github.com/axetroy/prettier-cli 1.2.0 --bin-dir /usr/local/bin --no-track")
+        );
+    }
+}
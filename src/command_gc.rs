@@ -0,0 +1,385 @@
+#![deny(warnings)]
+
+use crate::{cache, cask, formula, i18n::t, lock, receipt, util};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use eyre::Report;
+use tracing::{info, warn};
+
+// the file or folder that is not used anymore
+#[derive(Debug, PartialEq)]
+struct Garbage {
+    path: PathBuf,
+    reason: String,
+}
+
+// the disk usage of file or folder, the symlink is not followed
+fn get_size(path: &Path) -> u64 {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return 0,
+    };
+
+    if meta.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| get_size(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0)
+    } else {
+        meta.len()
+    }
+}
+
+fn remove(path: &Path) -> Result<(), Report> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+// the files in the bin folder of Cask that point into the folder, the scripts are generated instead of symlink on Windows
+fn find_links_into(cask: &cask::Cask, dir: &Path) -> Result<Vec<PathBuf>, Report> {
+    let bin_dir = cask.bin_dir();
+
+    if !bin_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut links = vec![];
+
+    for entry in fs::read_dir(bin_dir)? {
+        let path = entry?.path();
+
+        let is_link_into = match fs::read_link(&path) {
+            Ok(target) => target.starts_with(dir),
+            Err(_) => fs::read_to_string(&path)
+                .map(|content| content.contains(&dir.display().to_string()))
+                .unwrap_or(false),
+        };
+
+        if is_link_into {
+            // the shim executable is a copy of Cask, it runs the executable of '.shim' file next to it
+            let shim_exe = path.with_extension("exe");
+
+            if path.extension().map(|e| e == "shim").unwrap_or(false) && shim_exe.exists() {
+                links.push(shim_exe);
+            }
+
+            links.push(path);
+        }
+    }
+
+    Ok(links)
+}
+
+// the modified time of the receipt is the time of install
+fn get_install_age(installed_dir: &Path) -> Duration {
+    fs::metadata(receipt::path(installed_dir))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.elapsed().ok())
+        .unwrap_or_default()
+}
+
+// collect the garbage of package.
+// the newest `keep` versions except the active one are kept, the others are removed once they are older than `older_than`
+fn collect_package(
+    cask: &cask::Cask,
+    package_dir: &Path,
+    keep: usize,
+    older_than: Duration,
+) -> Result<Vec<Garbage>, Report> {
+    // the receipt that can not be read is not garbage, the package may be in use
+    let active = receipt::read(package_dir)?;
+    let cask_file_path = package_dir.join("Cask.toml");
+
+    // the install was interrupted before it completes, or the package was removed by hand
+    let active = match active {
+        Some(active) if cask_file_path.exists() => active,
+        _ => {
+            let mut garbage = vec![Garbage {
+                path: package_dir.to_path_buf(),
+                reason: "the receipt of package is missing".to_string(),
+            }];
+
+            for link in find_links_into(cask, package_dir)? {
+                garbage.push(Garbage {
+                    path: link,
+                    reason: "the package is removed".to_string(),
+                });
+            }
+
+            return Ok(garbage);
+        }
+    };
+
+    let mut garbage = vec![];
+
+    // the downloads, the staging and backup folders are left if the install was interrupted
+    let version_dir = package_dir.join("version");
+
+    if version_dir.is_dir() {
+        for entry in fs::read_dir(version_dir)? {
+            garbage.push(Garbage {
+                path: entry?.path(),
+                reason: "download artifact".to_string(),
+            });
+        }
+    }
+
+    let package_formula = formula::new(&cask_file_path, "")?;
+    let name = &package_formula.package.name;
+
+    let versions_dir = cask.package_versions_dir(name);
+
+    if versions_dir.is_dir() {
+        for entry in fs::read_dir(versions_dir)? {
+            let path = entry?.path();
+
+            if !receipt::path(&path).exists() {
+                garbage.push(Garbage {
+                    path,
+                    reason: "the version is not installed completely".to_string(),
+                });
+            }
+        }
+    }
+
+    let inactive_versions = cask
+        .list_installed_versions(name)?
+        .into_iter()
+        .filter(|v| *v != active.version);
+
+    for version in inactive_versions.skip(keep) {
+        let installed_dir = cask.package_installed_dir(name, &version);
+
+        if get_install_age(&installed_dir) >= older_than {
+            garbage.push(Garbage {
+                path: installed_dir,
+                reason: format!("the version {} is not in use", version),
+            });
+        }
+    }
+
+    Ok(garbage)
+}
+
+pub async fn gc(
+    cask: &cask::Cask,
+    keep: usize,
+    older_than: Duration,
+    is_dry_run: bool,
+) -> Result<(), Report> {
+    let mut count = 0;
+    let mut total_size = 0;
+
+    let mut clear = |garbage: Vec<Garbage>| -> Result<(), Report> {
+        for g in garbage {
+            let size = get_size(&g.path);
+
+            if is_dry_run {
+                info!(
+                    "Would remove '{}' ({}): {}",
+                    g.path.display(),
+                    util::format_size(size),
+                    g.reason
+                );
+            } else {
+                remove(&g.path)?;
+                info!(
+                    "Removed '{}' ({}): {}",
+                    g.path.display(),
+                    util::format_size(size),
+                    g.reason
+                );
+            }
+
+            count += 1;
+            total_size += size;
+        }
+
+        Ok(())
+    };
+
+    for entry in fs::read_dir(cask.formula_dir())? {
+        let entry = entry?;
+        let package_dir = entry.path();
+
+        if !package_dir.is_dir() {
+            continue;
+        }
+
+        // wait for the package that is being installed
+        let _package_lock = lock::acquire(
+            &cask.package_lock_file(&entry.file_name().to_string_lossy()),
+            &format!("package folder '{}'", package_dir.display()),
+        )?;
+
+        let garbage = match collect_package(cask, &package_dir, keep, older_than) {
+            Ok(garbage) => garbage,
+            Err(e) => {
+                warn!("Skip '{}': {}", package_dir.display(), e);
+                continue;
+            }
+        };
+
+        // the links in the bin folder may be removed
+        let _lock = cask.lock()?;
+
+        clear(garbage)?;
+    }
+
+    clear(
+        cache::list_orphaned_files(cask)?
+            .into_iter()
+            .map(|path| Garbage {
+                path,
+                reason: "the cache is not referenced".to_string(),
+            })
+            .collect(),
+    )?;
+
+    if is_dry_run {
+        info!(
+            "{}",
+            t!(
+                "gc-would-remove",
+                count = count,
+                size = util::format_size(total_size)
+            )
+        );
+    } else {
+        info!(
+            "{}",
+            t!(
+                "gc-removed",
+                count = count,
+                size = util::format_size(total_size)
+            )
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, time::Duration};
+
+    use crate::{cask, command_gc, receipt};
+
+    fn install(installed_dir: &Path, version: &str) {
+        fs::create_dir_all(installed_dir.join("bin")).unwrap();
+
+        receipt::write(
+            installed_dir,
+            &receipt::Receipt {
+                name: "".to_string(),
+                version: version.to_string(),
+                url: "".to_string(),
+                checksum: None,
+                created_at: "2022-01-01T00:00:00".to_string(),
+                bin: vec![],
+                symlinks: vec![],
+                files: vec![],
+                link: None,
+                link_dir: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_collect_package() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+
+        c.init().unwrap();
+
+        let name = "github.com/axetroy/foo";
+        let package_dir = c.package_dir(name);
+
+        for version in ["1.0.0", "2.0.0", "3.0.0"] {
+            install(&c.package_installed_dir(name, version), version);
+        }
+
+        fs::create_dir_all(c.package_installed_dir(name, "4.0.0")).unwrap();
+        fs::create_dir_all(c.package_version_dir(name).join(".staging")).unwrap();
+
+        // the package without receipt is removed entirely
+        assert_eq!(
+            command_gc::collect_package(&c, &package_dir, 0, Duration::ZERO).unwrap(),
+            vec![command_gc::Garbage {
+                path: package_dir.clone(),
+                reason: "the receipt of package is missing".to_string(),
+            }]
+        );
+
+        install(&package_dir, "2.0.0");
+        fs::write(
+            package_dir.join("Cask.toml"),
+            r#"
+[package]
+name = "github.com/axetroy/foo"
+bin = "foo"
+repository = "https://github.com/axetroy/foo"
+description = "foo"
+"#,
+        )
+        .unwrap();
+
+        let paths = |keep: usize, older_than: Duration| {
+            command_gc::collect_package(&c, &package_dir, keep, older_than)
+                .unwrap()
+                .into_iter()
+                .map(|g| g.path)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(0, Duration::ZERO),
+            vec![
+                c.package_version_dir(name).join(".staging"),
+                c.package_installed_dir(name, "4.0.0"),
+                c.package_installed_dir(name, "3.0.0"),
+                c.package_installed_dir(name, "1.0.0"),
+            ]
+        );
+
+        // the newest inactive version is kept
+        assert_eq!(
+            paths(1, Duration::ZERO),
+            vec![
+                c.package_version_dir(name).join(".staging"),
+                c.package_installed_dir(name, "4.0.0"),
+                c.package_installed_dir(name, "1.0.0"),
+            ]
+        );
+
+        // the versions are installed just now
+        assert_eq!(
+            paths(0, Duration::from_secs(3600)),
+            vec![
+                c.package_version_dir(name).join(".staging"),
+                c.package_installed_dir(name, "4.0.0"),
+            ]
+        );
+
+        // the package is skipped if the receipt is broken
+        fs::write(receipt::path(&package_dir), "{").unwrap();
+
+        assert!(command_gc::collect_package(&c, &package_dir, 0, Duration::ZERO).is_err());
+    }
+}
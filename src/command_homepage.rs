@@ -1,24 +1,21 @@
 #![deny(warnings)]
 
-use crate::cask;
+use crate::{cask, error::CaskError, util};
 
 use eyre::Report;
 
 pub async fn homepage(cask: &cask::Cask, package_name: &str) -> Result<(), Report> {
-    let packages = cask.list_formula()?;
-
-    let package_formula = packages
-        .iter()
-        .find(|p| p.package.name == package_name)
-        .or_else(|| packages.iter().find(|p| p.package.bin == package_name))
-        .ok_or_else(|| {
-            eyre::format_err!("can not found the installed package '{}'", package_name)
-        })?;
+    let package_formula = cask.find_formula(package_name)?.ok_or_else(|| {
+        CaskError::NotInstalled(format!(
+            "can not found the installed package '{}'",
+            package_name
+        ))
+    })?;
 
     if let Some(homepage) = &package_formula.package.homepage {
         opener::open(homepage)?;
     } else {
-        opener::open(package_formula.package.repository.clone())?;
+        opener::open(util::to_https_url(&package_formula.package.repository))?;
     }
 
     Ok(())
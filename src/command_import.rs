@@ -0,0 +1,358 @@
+#![deny(warnings)]
+
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{cask, command_remote_sync, gha, install};
+
+use eyre::Report;
+use regex::Regex;
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+// the package manager that the packages are imported from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Source {
+    Brewfile,     // the Brewfile of Homebrew
+    Scoop,        // the output of 'scoop export'
+    Winget,       // the output of 'winget export'
+    ToolVersions, // the '.tool-versions' of asdf and mise
+}
+
+impl Source {
+    // the mapping from the app of package manager to the package of Cask, it is curated in the build-in formula.
+    // eg. ripgrep = "github.com/BurntSushi/ripgrep"
+    fn mapping_file(&self) -> &'static str {
+        match self {
+            Source::Brewfile => "brew.toml",
+            Source::Scoop => "scoop.toml",
+            Source::Winget => "winget.toml",
+            Source::ToolVersions => "tool-versions.toml",
+        }
+    }
+
+    // the apps with the pinned version, the version is not pinned by the package managers except asdf and mise
+    fn parse(&self, content: &str) -> Result<Vec<(String, Option<String>)>, Report> {
+        let unpinned = |apps: Vec<String>| apps.into_iter().map(|app| (app, None)).collect();
+
+        match self {
+            Source::Brewfile => Ok(unpinned(parse_brewfile(content))),
+            Source::Scoop => parse_scoop(content).map(unpinned),
+            Source::Winget => parse_winget(content).map(unpinned),
+            Source::ToolVersions => Ok(parse_tool_versions(content)),
+        }
+    }
+}
+
+// the formulas in Brewfile, eg. `brew "jq"` or `brew 'mysql@5.7', restart_service: true`.
+// the taps, casks and the apps of Mac App Store are not command line tools
+fn parse_brewfile(content: &str) -> Vec<String> {
+    let re = Regex::new(r#"^\s*brew\s*\(?\s*["']([^"']+)["']"#).unwrap();
+
+    content
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+// the apps of Scoop with the bucket, eg. 'main/git'.
+// the JSON is exported by the newer Scoop, and the lines like 'git (v:2.37.1) [main]' by the older one
+fn parse_scoop(content: &str) -> Result<Vec<String>, Report> {
+    let get_name = |name: &str, bucket: Option<&str>| match bucket {
+        Some(bucket) if !bucket.is_empty() => format!("{}/{}", bucket, name),
+        _ => name.to_string(),
+    };
+
+    if let Ok(manifest) = serde_json::from_str::<Value>(content) {
+        let apps = manifest["apps"]
+            .as_array()
+            .ok_or_else(|| eyre::format_err!("can not found the apps in the export of Scoop"))?;
+
+        return Ok(apps
+            .iter()
+            .filter_map(|app| Some(get_name(app["Name"].as_str()?, app["Source"].as_str())))
+            .collect());
+    }
+
+    // the flags such as '*global*' are between the version and the bucket
+    let re = Regex::new(r"^\s*(\S+)\s+\(v:[^)]*\)(?:\s+\*\w+\*)*(?:\s+\[([^\]]+)\])?").unwrap();
+
+    Ok(content
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| get_name(&caps[1], caps.get(2).map(|m| m.as_str())))
+        .collect())
+}
+
+// the package identifiers of winget, eg. 'BurntSushi.ripgrep.MSVC'
+fn parse_winget(content: &str) -> Result<Vec<String>, Report> {
+    let manifest = serde_json::from_str::<Value>(content)?;
+
+    let sources = manifest["Sources"]
+        .as_array()
+        .ok_or_else(|| eyre::format_err!("can not found the sources in the export of winget"))?;
+
+    Ok(sources
+        .iter()
+        .filter_map(|source| source["Packages"].as_array())
+        .flatten()
+        .filter_map(|package| package["PackageIdentifier"].as_str())
+        .map(|id| id.to_string())
+        .collect())
+}
+
+// the tools of '.tool-versions' with the first version, eg. 'nodejs 20.11.0 18.19.0 # comment'.
+// the 'latest' is the newest version, 'latest:1.2' is the newest version of 1.2.x,
+// the tool of 'system', 'path:' and 'ref:' is not managed by the version manager either
+fn parse_tool_versions(content: &str) -> Vec<(String, Option<String>)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace();
+            let name = fields.next()?.to_string();
+
+            match fields.next()? {
+                "system" => None,
+                version if version.starts_with("path:") || version.starts_with("ref:") => None,
+                "latest" => Some((name, None)),
+                version => Some((
+                    name,
+                    Some(version.trim_start_matches("latest:").to_string()),
+                )),
+            }
+        })
+        .collect()
+}
+
+fn load_mapping(cask: &cask::Cask, source: Source) -> Result<HashMap<String, String>, Report> {
+    let filepath = cask.build_in_formula_dir().join(source.mapping_file());
+
+    let content = fs::read_to_string(&filepath).map_err(|e| {
+        eyre::format_err!(
+            "can not read the mapping '{}': {}, try run 'cask remote sync'",
+            filepath.display(),
+            e
+        )
+    })?;
+
+    Ok(toml::from_str(&content)?)
+}
+
+// the app of tap or bucket is looked up by the full name first, then by the short name. eg. 'homebrew/core/jq' or 'main/jq'
+fn find_package<'a>(mapping: &'a HashMap<String, String>, app: &str) -> Option<&'a str> {
+    mapping
+        .get(app)
+        .or_else(|| mapping.get(app.rsplit('/').next().unwrap_or(app)))
+        .map(|s| s.as_str())
+}
+
+pub async fn import(
+    cask: &cask::Cask,
+    source: Source,
+    filepath: &Path,
+    options: &install::InstallOption<'_>,
+) -> Result<(), Report> {
+    let content = fs::read_to_string(filepath)
+        .map_err(|e| eyre::format_err!("can not read '{}': {}", filepath.display(), e))?;
+
+    let apps = source
+        .parse(&content)
+        .map_err(|e| eyre::format_err!("can not parse '{}': {}", filepath.display(), e))?;
+
+    if apps.is_empty() {
+        warn!("There is no package in '{}'", filepath.display());
+        return Ok(());
+    }
+
+    // the mapping is updated with the build-in formula, the local one is used in offline mode
+    if !cask.settings().offline {
+        command_remote_sync::sync(cask, options.verbose)?;
+    }
+
+    let mapping = load_mapping(cask, source)?;
+
+    let mut installed = 0;
+    let mut unmapped = vec![];
+    let mut failed = vec![];
+
+    for (app, version) in &apps {
+        let package_name = match find_package(&mapping, app) {
+            Some(package_name) => package_name,
+            None => {
+                unmapped.push(app.as_str());
+                continue;
+            }
+        };
+
+        let installed_version = cask
+            .find_formula(package_name)?
+            .and_then(|f| f.cask)
+            .map(|c| c.version);
+
+        // the installed package is switched to the pinned version if it is different
+        if installed_version.is_some() && (version.is_none() || *version == installed_version) {
+            info!(
+                "The package '{}' of '{}' has been installed, skip it",
+                package_name, app
+            );
+            continue;
+        }
+
+        // install what it can, the failure of a package does not stop the others
+        let _group = gha::group(&format!("Install {}", package_name));
+
+        match install::install(cask, package_name, version.as_deref(), options)
+            .await
+            .and_then(|package| gha::export(&[package]))
+        {
+            Ok(()) => installed += 1,
+            Err(e) => {
+                error!("install '{}' of '{}' fail: {}", package_name, app, e);
+                failed.push(package_name);
+            }
+        }
+    }
+
+    info!(
+        "Imported {} of {} packages from '{}'",
+        installed,
+        apps.len(),
+        filepath.display()
+    );
+
+    if !unmapped.is_empty() {
+        warn!("There is no cask equivalent of: {}", unmapped.join(", "));
+    }
+
+    if !failed.is_empty() {
+        return Err(eyre::format_err!(
+            "install the packages fail: {}",
+            failed.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::command_import::{
+        find_package, parse_brewfile, parse_scoop, parse_tool_versions, parse_winget,
+    };
+
+    #[test]
+    fn test_parse_brewfile() {
+        let content = r#"
+tap "homebrew/bundle"
+# brew "commented"
+brew "jq"
+brew 'mysql@5.7', restart_service: true
+brew("ripgrep")
+brew "axetroy/tap/dvm", args: ["HEAD"]
+cask "firefox"
+mas "Xcode", id: 497799835
+"#;
+
+        assert_eq!(
+            parse_brewfile(content),
+            vec!["jq", "mysql@5.7", "ripgrep", "axetroy/tap/dvm"]
+        );
+    }
+
+    #[test]
+    fn test_parse_scoop() {
+        let content = r#"{
+  "buckets": [{ "Name": "main", "Source": "https://github.com/ScoopInstaller/Main" }],
+  "apps": [
+    { "Name": "git", "Source": "main", "Version": "2.37.1", "Info": "" },
+    { "Name": "dvm", "Source": "", "Version": "1.0.0", "Info": "" }
+  ]
+}"#;
+
+        assert_eq!(parse_scoop(content).unwrap(), vec!["main/git", "dvm"]);
+
+        // the export of the older Scoop
+        let content = "git (v:2.37.1) [main]\njq (v:1.6) *global* [main]\ndvm (v:1.0.0)\n";
+
+        assert_eq!(
+            parse_scoop(content).unwrap(),
+            vec!["main/git", "main/jq", "dvm"]
+        );
+
+        assert!(parse_scoop("{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_winget() {
+        let content = r#"{
+  "$schema": "https://aka.ms/winget-packages.schema.2.0.json",
+  "Sources": [
+    {
+      "Packages": [
+        { "PackageIdentifier": "Git.Git" },
+        { "PackageIdentifier": "BurntSushi.ripgrep.MSVC" }
+      ],
+      "SourceDetails": { "Name": "winget", "Identifier": "Microsoft.Winget.Source_8wekyb3d8bbwe" }
+    }
+  ]
+}"#;
+
+        assert_eq!(
+            parse_winget(content).unwrap(),
+            vec!["Git.Git", "BurntSushi.ripgrep.MSVC"]
+        );
+
+        assert!(parse_winget("git").is_err());
+    }
+
+    #[test]
+    fn test_parse_tool_versions() {
+        let content = r#"
+# the tools of project
+nodejs 20.11.0 18.19.0
+ripgrep latest # the newest
+deno latest:1.40
+python system
+golang path:/usr/local/go
+ruby ref:v3.3.0
+terraform
+"#;
+
+        assert_eq!(
+            parse_tool_versions(content),
+            vec![
+                ("nodejs".to_string(), Some("20.11.0".to_string())),
+                ("ripgrep".to_string(), None),
+                ("deno".to_string(), Some("1.40".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_package() {
+        let mapping = HashMap::from([
+            ("jq".to_string(), "github.com/jqlang/jq".to_string()),
+            (
+                "axetroy/tap/dvm".to_string(),
+                "github.com/axetroy/dvm".to_string(),
+            ),
+        ]);
+
+        assert_eq!(find_package(&mapping, "jq"), Some("github.com/jqlang/jq"));
+        assert_eq!(
+            find_package(&mapping, "homebrew/core/jq"),
+            Some("github.com/jqlang/jq")
+        );
+        assert_eq!(
+            find_package(&mapping, "axetroy/tap/dvm"),
+            Some("github.com/axetroy/dvm")
+        );
+        assert_eq!(find_package(&mapping, "ripgrep"), None);
+    }
+}
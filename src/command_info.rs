@@ -1,86 +1,148 @@
 #![deny(warnings)]
 
-use crate::{cask, formula};
+use crate::{cask, error::CaskError, formula, output, receipt};
 
 use eyre::Report;
+use serde::Serialize;
+
+// the information of package, the fields are kept stable for the JSON output
+#[derive(Serialize, Debug)]
+struct PackageInfo {
+    name: String,
+    description: String,
+    repository: String,
+    source: Option<String>, // the provider or the tap that resolved the package. eg. build-in or git
+    installed: bool,
+    version: Option<String>,         // the version in use
+    location: Option<String>,        // the folder of installed package
+    installed_versions: Vec<String>, // the installed versions, the newest first
+    remote_versions: Vec<String>,    // the released versions, the newest first
+}
 
-pub async fn info(cask: &cask::Cask, package_name: &str) -> Result<(), Report> {
-    let packages = cask.list_formula()?;
+fn print_text(info: &PackageInfo) {
+    let mut lines = vec![info.description.clone(), format!("Package: {}", info.name)];
 
-    let package = packages
-        .iter()
-        .find(|p| p.package.name == package_name)
-        .or_else(|| packages.iter().find(|p| p.package.bin == package_name));
+    if let Some(version) = &info.version {
+        lines.push(format!("Version: {}", version));
+    }
 
-    if let Some(package_formula) = package {
-        let cask_info = &package_formula.cask.as_ref().ok_or_else(|| {
-            eyre::format_err!("can not parse cask property of file '{}'", package_name)
-        })?;
+    lines.push(format!("Repository: {}", info.repository));
 
-        let msg = format!(
-            r#"{}
-            Package: {}
-            Version: {}
-            Repository: {}
-            Location: {}
-            Installed: true
-            "#,
-            package_formula.package.description,
-            cask_info.name,
-            cask_info.version,
-            package_formula.package.repository,
-            package_formula
-                .filepath
-                .parent()
-                .ok_or_else(|| eyre::format_err!(
-                    "can not get parent folder of '{}'",
-                    package_formula.filepath.display()
-                ))?
-                .display()
-        )
-        .lines()
-        .map(|s| s.trim_start().to_owned())
-        .collect::<Vec<String>>()
-        .join("\n");
+    if let Some(source) = &info.source {
+        lines.push(format!("Source: {}", source));
+    }
 
-        print!("{}", msg);
+    if let Some(location) = &info.location {
+        lines.push(format!("Location: {}", location));
+    }
 
-        let remote_versions = &package_formula.get_versions()?;
+    lines.push(format!("Installed: {}", info.installed));
 
-        println!("Remote Versions:");
+    println!("{}", lines.join("\n"));
 
-        for v in remote_versions {
-            println!("{}", v);
+    if info.installed {
+        println!("Installed Versions:");
+
+        for v in &info.installed_versions {
+            if Some(v) == info.version.as_ref() {
+                println!("{} (in use)", v);
+            } else {
+                println!("{}", v);
+            }
         }
+    }
 
-        Ok(())
+    println!("Remote Versions:");
+
+    for v in &info.remote_versions {
+        println!("{}", v);
+    }
+}
+
+pub async fn info(
+    cask: &cask::Cask,
+    package_name: &str,
+    is_print_files: bool,
+    is_print_as_json: bool,
+) -> Result<(), Report> {
+    let package = cask.find_formula(package_name)?;
+
+    // print the installed files only, one file per line
+    if is_print_files {
+        let package_formula = package.ok_or_else(|| {
+            CaskError::NotInstalled(format!(
+                "can not found the installed package '{}'",
+                package_name
+            ))
+        })?;
+
+        let r =
+            receipt::read(&cask.package_dir(&package_formula.package.name))?.ok_or_else(|| {
+                eyre::format_err!(
+                    "The files of package '{}' are not recorded, try reinstall it",
+                    package_formula.package.name
+                )
+            })?;
+
+        if is_print_as_json {
+            return output::print_json(&r.all_files());
+        }
+
+        for file in r.all_files() {
+            println!("{}", file.display());
+        }
+
+        return Ok(());
+    }
+
+    let info = if let Some(package_formula) = package {
+        let cask_info = &package_formula.cask.as_ref().ok_or_else(|| {
+            eyre::format_err!("can not parse cask property of file '{}'", package_name)
+        })?;
+
+        let location = package_formula
+            .filepath
+            .parent()
+            .ok_or_else(|| {
+                eyre::format_err!(
+                    "can not get parent folder of '{}'",
+                    package_formula.filepath.display()
+                )
+            })?
+            .display()
+            .to_string();
+
+        PackageInfo {
+            name: cask_info.name.clone(),
+            description: package_formula.package.description.clone(),
+            repository: package_formula.package.repository.clone(),
+            source: cask_info.source.clone(),
+            installed: true,
+            version: Some(cask_info.version.clone()),
+            location: Some(location),
+            installed_versions: cask.list_installed_versions(&package_formula.package.name)?,
+            remote_versions: package_formula.get_versions(cask, false).await?,
+        }
     } else {
-        let package_formula = formula::fetch(cask, package_name, true, false)?;
-
-        let msg = format!(
-            r#"{}
-            Package: {}
-            Repository: {}
-            Installed: false
-            "#,
-            package_formula.package.description,
-            package_formula.package.name,
-            package_formula.package.repository
-        )
-        .lines()
-        .map(|s| s.trim_start().to_owned())
-        .collect::<Vec<String>>()
-        .join("\n");
-
-        print!("{}", msg);
-
-        let remote_versions = &package_formula.get_versions()?;
-
-        println!("Remote Versions:");
-
-        for v in remote_versions {
-            println!("{}", v);
+        let package_formula = formula::fetch(cask, package_name, None, true, false).await?;
+
+        PackageInfo {
+            name: package_formula.package.name.clone(),
+            description: package_formula.package.description.clone(),
+            repository: package_formula.package.repository.clone(),
+            source: Some(package_formula.source.clone()),
+            installed: false,
+            version: None,
+            location: None,
+            installed_versions: vec![],
+            remote_versions: package_formula.get_versions(cask, false).await?,
         }
+    };
+
+    if is_print_as_json {
+        output::print_json(&info)
+    } else {
+        print_text(&info);
 
         Ok(())
     }
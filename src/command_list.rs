@@ -1,6 +1,6 @@
 #![deny(warnings)]
 
-use crate::cask;
+use crate::{cask, index, output};
 
 use chrono::prelude::*;
 use eyre::Report;
@@ -18,28 +18,76 @@ struct PackageInfo {
     create_at: String,
 }
 
-pub async fn list(cask: &cask::Cask, is_print_as_json: bool) -> Result<(), Report> {
-    let mut packages: Vec<PackageInfo> = vec![];
+impl PackageInfo {
+    fn get_field(&self, field: &str) -> Option<&str> {
+        match field {
+            "name" => Some(&self.name),
+            "bin" => Some(&self.bin),
+            "version" => Some(&self.version),
+            "install_at" => Some(&self.install_at),
+            _ => None,
+        }
+    }
+}
+
+// render the line of package by the template, eg. '{name}\t{version}'.
+// the escapes '\t', '\n' and '\\' are supported so that the template can be passed in the shell
+fn render_format(template: &str, package: &PackageInfo) -> Result<String, Report> {
+    let mut line = String::new();
+    let mut chars = template.chars();
 
-    for package in cask.list_formula()? {
-        let cask_info = package.cask.ok_or_else(|| {
-            eyre::format_err!(
-                "can not parse cask property of package '{}'",
-                package.package.name
-            )
-        })?;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => line.push('\t'),
+                Some('n') => line.push('\n'),
+                Some(c) => line.push(c),
+                None => line.push('\\'),
+            },
+            '{' => {
+                let field = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+
+                let value = package.get_field(field.trim()).ok_or_else(|| {
+                    eyre::format_err!(
+                        "unknown field '{}' in the format, the fields are name, bin, version and install_at",
+                        field
+                    )
+                })?;
+
+                line.push_str(value);
+            }
+            c => line.push(c),
+        }
+    }
+
+    Ok(line)
+}
+
+pub async fn list(
+    cask: &cask::Cask,
+    is_print_as_json: bool,
+    format: Option<&str>,
+) -> Result<(), Report> {
+    if is_print_as_json && format.is_some() {
+        return Err(eyre::format_err!(
+            "The option '--format' can not be used with '--json'"
+        ));
+    }
+
+    let mut packages: Vec<PackageInfo> = vec![];
 
-        let create_at = DateTime::parse_from_str(&cask_info.created_at, "%+")
+    for entry in index::refresh(cask)? {
+        let create_at = DateTime::parse_from_str(&entry.created_at, "%+")
             .unwrap()
             .format("%Y-%m-%d %H:%M:%S")
             .to_string();
 
         packages.push(PackageInfo {
-            name: cask_info.name,
-            bin: package.package.bin,
-            version: cask_info.version,
+            name: entry.name,
+            bin: entry.bin,
+            version: entry.version,
             install_at: create_at,
-            create_at: cask_info.created_at,
+            create_at: entry.created_at,
         });
     }
 
@@ -50,14 +98,47 @@ pub async fn list(cask: &cask::Cask, is_print_as_json: bool) -> Result<(), Repor
         t2.cmp(&t1)
     });
 
-    let table = Table::new(&packages).with(Style::psql()).to_string();
-
     if is_print_as_json {
-        let serialized = serde_json::to_string(&packages).unwrap();
-        println!("{}", serialized);
-    } else {
-        print!("{}", table);
+        return output::print_json(&packages);
     }
 
+    if let Some(template) = format {
+        for package in &packages {
+            println!("{}", render_format(template, package)?);
+        }
+
+        return Ok(());
+    }
+
+    let table = Table::new(&packages).with(Style::psql()).to_string();
+
+    println!("{}", table);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::command_list::{render_format, PackageInfo};
+
+    #[test]
+    fn test_render_format() {
+        let package = PackageInfo {
+            name: "github.com/axetroy/dvm".to_string(),
+            bin: "dvm".to_string(),
+            version: "1.0.0".to_string(),
+            install_at: "2022-01-01 00:00:00".to_string(),
+            create_at: "2022-01-01T00:00:00+00:00".to_string(),
+        };
+
+        assert_eq!(
+            render_format("{name}\\t{ version }", &package).unwrap(),
+            "github.com/axetroy/dvm\t1.0.0"
+        );
+        assert_eq!(
+            render_format("{bin}@{version} ({install_at})\\\\", &package).unwrap(),
+            "dvm@1.0.0 (2022-01-01 00:00:00)\\"
+        );
+        assert!(render_format("{name}\t{size}", &package).is_err());
+    }
+}
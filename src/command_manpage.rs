@@ -0,0 +1,322 @@
+#![deny(warnings)]
+
+use crate::cask;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Arg, Command};
+use eyre::Report;
+use tracing::info;
+
+// escape the text for roff, the line that starts with the dot or quote is a request of roff
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// the name of value, eg. <PACKAGE> or [PATH]
+fn get_value_name(arg: &Arg) -> String {
+    let name = arg
+        .get_value_names()
+        .and_then(|names| names.first())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| arg.get_id().to_string());
+
+    let num_args = arg.get_num_args().unwrap_or_default();
+    let is_optional = !arg.is_required_set() && (arg.is_positional() || num_args.min_values() == 0);
+    let suffix = if num_args.max_values() > 1 { "..." } else { "" };
+
+    if is_optional {
+        format!("[\\fI{}\\fR]{}", escape(&name), suffix)
+    } else {
+        format!("<\\fI{}\\fR>{}", escape(&name), suffix)
+    }
+}
+
+fn render_arg(arg: &Arg) -> String {
+    let mut names = vec![];
+
+    if let Some(short) = arg.get_short() {
+        names.push(format!("\\fB\\-{}\\fR", short));
+    }
+
+    if let Some(long) = arg.get_long() {
+        names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+
+    let mut header = names.join(", ");
+
+    if arg.is_positional() {
+        header = get_value_name(arg);
+    } else if arg.get_action().takes_values() {
+        header = format!("{} {}", header, get_value_name(arg));
+    }
+
+    let mut help = arg
+        .get_long_help()
+        .or_else(|| arg.get_help())
+        .map(|help| help.to_string())
+        .unwrap_or_default();
+
+    let possible_values = arg
+        .get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<String>>();
+
+    // the flags have the default value 'false'
+    let default_values = arg
+        .get_default_values()
+        .iter()
+        .map(|v| v.to_string_lossy().to_string())
+        .filter(|v| v != "false")
+        .collect::<Vec<String>>();
+
+    if !possible_values.is_empty() {
+        help = format!("{} [possible values: {}]", help, possible_values.join(", "));
+    }
+
+    if !default_values.is_empty() {
+        help = format!("{} [default: {}]", help, default_values.join(", "));
+    }
+
+    format!(".TP\n{}\n{}", header, escape(help.trim()))
+}
+
+// the man page of command, the name is the path of command. eg. ['cask', 'remote', 'sync']
+fn render(cmd: &Command, names: &[&str], version: &str) -> String {
+    let page_name = names.join("-");
+
+    let mut lines = vec![
+        format!(
+            ".TH {} 1 \"\" \"{} {}\"",
+            escape(&page_name.to_uppercase()),
+            names[0],
+            version
+        ),
+        ".SH NAME".to_string(),
+        format!(
+            "{} \\- {}",
+            escape(&page_name),
+            escape(&cmd.get_about().map(|s| s.to_string()).unwrap_or_default())
+        ),
+    ];
+
+    let arguments = cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .collect::<Vec<&Arg>>();
+
+    let subcommands = cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .collect::<Vec<&Command>>();
+
+    // synopsis
+    {
+        let mut synopsis = vec![format!("\\fB{}\\fR", escape(&names.join(" ")))];
+
+        if arguments.iter().any(|arg| !arg.is_positional()) {
+            synopsis.push("[\\fIOPTIONS\\fR]".to_string());
+        }
+
+        for arg in arguments.iter().filter(|arg| arg.is_positional()) {
+            synopsis.push(get_value_name(arg));
+        }
+
+        if !subcommands.is_empty() {
+            synopsis.push("<\\fICOMMAND\\fR>".to_string());
+        }
+
+        lines.push(".SH SYNOPSIS".to_string());
+        lines.push(synopsis.join(" "));
+    }
+
+    if let Some(about) = cmd.get_long_about().or_else(|| cmd.get_about()) {
+        lines.push(".SH DESCRIPTION".to_string());
+        lines.push(escape(&about.to_string()).replace("\n\n", "\n.PP\n"));
+    }
+
+    let aliases = cmd.get_visible_aliases().collect::<Vec<&str>>();
+
+    if !aliases.is_empty() {
+        lines.push(".SH ALIASES".to_string());
+        lines.push(escape(&aliases.join(", ")));
+    }
+
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) =
+        arguments.into_iter().partition(|arg| arg.is_positional());
+
+    for (title, args) in [("ARGUMENTS", positionals), ("OPTIONS", options)] {
+        if !args.is_empty() {
+            lines.push(format!(".SH {}", title));
+
+            for arg in args {
+                lines.push(render_arg(arg));
+            }
+        }
+    }
+
+    if !subcommands.is_empty() {
+        lines.push(".SH COMMANDS".to_string());
+
+        for sub in subcommands {
+            lines.push(".TP".to_string());
+            lines.push(format!(
+                "\\fB{}\\-{}\\fR(1)",
+                escape(&page_name),
+                escape(sub.get_name())
+            ));
+            lines.push(escape(
+                &sub.get_about().map(|s| s.to_string()).unwrap_or_default(),
+            ));
+        }
+    }
+
+    if names.len() > 1 {
+        lines.push(".SH SEE ALSO".to_string());
+        lines.push(format!(
+            "\\fB{}\\fR(1)",
+            escape(&names[..names.len() - 1].join("-"))
+        ));
+    } else if let Some(author) = cmd.get_author() {
+        lines.push(".SH AUTHORS".to_string());
+        lines.push(escape(author));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// the man pages of the command and its subcommands. eg. 'cask.1', 'cask-install.1' and 'cask-remote-sync.1'
+fn render_pages(cmd: &Command, names: &[&str], version: &str) -> Vec<(String, String)> {
+    let mut pages = vec![(
+        format!("{}.1", names.join("-")),
+        render(cmd, names, version),
+    )];
+
+    for sub in cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+    {
+        let sub_names = names
+            .iter()
+            .copied()
+            .chain([sub.get_name()])
+            .collect::<Vec<&str>>();
+
+        pages.extend(render_pages(sub, &sub_names, version));
+    }
+
+    pages
+}
+
+// generate the man pages from the definition of command line.
+// they are put into the 'share/man' of Cask by default, so that 'man cask' works without setting $MANPATH
+pub async fn manpage(
+    cask: &cask::Cask,
+    app: &Command,
+    output: Option<&Path>,
+) -> Result<(), Report> {
+    let mut app = app.clone();
+
+    // the global arguments are propagated into the subcommands
+    app.build();
+
+    let dir = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| cask.man_dir())
+        .join("man1");
+
+    fs::create_dir_all(&dir)?;
+
+    let version = app.get_version().unwrap_or_default().to_string();
+
+    for (file_name, content) in render_pages(&app, &[app.get_name()], &version) {
+        fs::write(dir.join(file_name), content)?;
+    }
+
+    info!("The man pages have been generated in '{}'", dir.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Arg, ArgAction, Command};
+
+    use crate::command_manpage;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(command_manpage::escape("foo"), "foo");
+        assert_eq!(
+            command_manpage::escape("--bin-dir C:\\bin\n.cask"),
+            "\\-\\-bin\\-dir C:\\ebin\n\\&.cask"
+        );
+    }
+
+    #[test]
+    fn test_render_pages() {
+        let mut app = Command::new("cask")
+            .about("General distributed binary distribution package management")
+            .subcommand(
+                Command::new("install")
+                    .visible_alias("i")
+                    .about("Install package")
+                    .long_about("Install package.\n\nThe package is the repository URL.")
+                    .arg(Arg::new("PACKAGE").required(true).num_args(1..))
+                    .arg(
+                        Arg::new("jobs")
+                            .short('j')
+                            .long("jobs")
+                            .help("The max number of concurrent downloads")
+                            .default_value("4"),
+                    )
+                    .arg(
+                        Arg::new("pre")
+                            .long("pre")
+                            .help("Allow the pre-release")
+                            .action(ArgAction::SetTrue),
+                    ),
+            );
+
+        app.build();
+
+        let pages = command_manpage::render_pages(&app, &["cask"], "1.0.0");
+
+        assert_eq!(
+            pages
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["cask.1", "cask-install.1"]
+        );
+
+        let (_, install) = &pages[1];
+
+        assert!(install.starts_with(".TH CASK\\-INSTALL 1 \"\" \"cask 1.0.0\"\n"));
+        assert!(install.contains("\\fBcask install\\fR [\\fIOPTIONS\\fR] <\\fIPACKAGE\\fR>..."));
+        assert!(install.contains("Install package.\n.PP\nThe package is the repository URL."));
+        assert!(install.contains(
+            "\\fB\\-j\\fR, \\fB\\-\\-jobs\\fR <\\fIjobs\\fR>\nThe max number of concurrent downloads [default: 4]"
+        ));
+        assert!(install.contains("\\fB\\-\\-pre\\fR\nAllow the pre\\-release\n"));
+        assert!(pages[0]
+            .1
+            .contains("\\fBcask\\-install\\fR(1)\nInstall package"));
+    }
+}
@@ -0,0 +1,22 @@
+#![deny(warnings)]
+
+use crate::{cask, migration};
+
+use eyre::Report;
+use tracing::info;
+
+pub async fn migrate(cask: &cask::Cask) -> Result<(), Report> {
+    if migration::migrate(cask)? == 0 {
+        info!(
+            "The storage is up to date with layout {}",
+            migration::LAYOUT_VERSION
+        );
+    } else {
+        info!(
+            "The storage has been migrated to layout {}!",
+            migration::LAYOUT_VERSION
+        );
+    }
+
+    Ok(())
+}
@@ -1,39 +1,46 @@
 #![deny(warnings)]
 
-use crate::{cask, symlink};
-
-use std::fs;
+use crate::{cask, receipt, symlink};
 
 use eyre::Report;
 
 pub async fn relink(cask: &cask::Cask) -> Result<(), Report> {
+    let _lock = cask.lock()?;
+
     let list = cask.list_formula()?;
+    let strategy = cask.config().get_link_strategy();
 
     for package_formula in list {
-        let symlink_file = cask.bin_dir().join(&package_formula.package.bin);
-
         let package_dir = cask.package_dir(&package_formula.package.name);
 
+        let receipt = receipt::read(&package_dir)?;
+
+        // the package installed with `--link-dir` is linked into the recorded folder
+        let link_dir = receipt.as_ref().and_then(|r| r.link_dir.clone());
+        let symlink_file = link_dir
+            .clone()
+            .unwrap_or_else(|| cask.bin_dir())
+            .join(&package_formula.package.bin);
+
         #[cfg(target_family = "unix")]
         let executable_name = package_formula.package.bin.clone();
         #[cfg(target_family = "windows")]
         let executable_name = format!("{}.exe", &package_formula.package.bin);
 
-        let output_file_path = package_dir.join("bin").join(executable_name);
-
-        // unlink before symlink
-        {
-            fs::remove_file(&symlink_file).ok();
+        // the executable of active version is recorded in the receipt
+        let output_file_path = match receipt.and_then(|r| r.bin.first().cloned()) {
+            Some(file_path) => file_path,
+            None => package_dir.join("bin").join(executable_name),
+        };
 
-            #[cfg(target_family = "windows")]
-            fs::remove_file(format!("{}.bat", &symlink_file.display())).ok();
-        }
-
-        symlink::symlink(
+        let link = symlink::symlink(
             &output_file_path,
             &symlink_file,
             &package_formula.package.name,
+            strategy,
         )?;
+
+        receipt::write_link(&package_dir, link, link_dir.as_deref())?;
     }
 
     Ok(())
@@ -2,32 +2,72 @@
 
 use std::{fs, path::Path};
 
-use crate::{cask, command_remote_sync, formula};
+use crate::{cask, command_remote_sync, formula, output, tap};
 
 use eyre::Report;
+use serde::Serialize;
 
-fn print_formula(dir_path: &Path) -> Result<(), Report> {
+// the formula of build-in or taps, the fields are kept stable for the JSON output
+#[derive(Serialize, Debug)]
+struct FormulaInfo {
+    name: String,
+    bin: String,
+    description: String,
+    repository: String,
+    source: String, // the build-in formula or the name of tap
+}
+
+fn collect_formula(
+    source: &str,
+    dir_path: &Path,
+    list: &mut Vec<FormulaInfo>,
+) -> Result<(), Report> {
     let dir = fs::read_dir(dir_path)?;
 
     for entry in dir.into_iter().filter_map(|f| f.ok()) {
         let p = entry.path();
 
         if p.is_dir() {
-            print_formula(&p)?
-        } else if entry.file_name().to_str().unwrap() == "Cask.toml" {
+            collect_formula(source, &p, list)?
+        } else if formula::FORMULA_FILE_NAMES.contains(&entry.file_name().to_str().unwrap()) {
             let f = formula::new(&p, "")?;
 
-            println!("{}", f.package.name)
+            list.push(FormulaInfo {
+                name: f.package.name,
+                bin: f.package.bin,
+                description: f.package.description,
+                repository: f.package.repository,
+                source: source.to_string(),
+            });
         }
     }
 
     Ok(())
 }
 
-pub fn list(cask: &cask::Cask, is_verbose: bool) -> Result<(), Report> {
-    let mirror_dir = cask.build_in_formula_dir();
+pub fn list(cask: &cask::Cask, is_print_as_json: bool, is_verbose: bool) -> Result<(), Report> {
+    // list the local build-in formula in offline mode
+    if !cask.settings().offline {
+        command_remote_sync::sync(cask, is_verbose)?;
+    }
+
+    let config = cask.config();
+
+    let mut list = vec![];
+
+    for source in tap::list(cask, config)? {
+        if source.dir.exists() {
+            collect_formula(&source.name, &source.dir, &mut list)?;
+        }
+    }
+
+    if is_print_as_json {
+        return output::print_json(&list);
+    }
 
-    command_remote_sync::sync(cask, is_verbose)?;
+    for f in list {
+        println!("{}", f.name)
+    }
 
-    print_formula(&mirror_dir)
+    Ok(())
 }
@@ -1,70 +1,70 @@
 #![deny(warnings)]
 
-use std::{collections::HashMap, io};
-
-use crate::cask;
+use crate::{cask, retry::retry, tap};
 
 use eyre::Report;
+use git::GitError;
+use tracing::info;
 
+// sync the build-in formula and the taps of git repository, the taps of local folder are not synced
 pub fn sync(cask: &cask::Cask, is_verbose: bool) -> Result<(), Report> {
-    let mirror_dir = cask.build_in_formula_dir();
+    if cask.settings().offline {
+        return Err(eyre::format_err!(
+            "Can not sync build-in formula in offline mode"
+        ));
+    }
 
-    if mirror_dir.exists() {
-        eprintln!("Updating build-in formula...");
+    let config = cask.config();
 
-        if is_verbose {
-            let mut stderr = io::stderr();
-            let mut output = shell::Output::Writer(&mut stderr);
-            shell::run(&mirror_dir, "git fetch", &mut output, HashMap::from([]))?;
-            shell::run(
-                &mirror_dir,
-                "git checkout main",
-                &mut output,
-                HashMap::from([]),
-            )?;
-            shell::run(&mirror_dir, "git clean -df", &mut output, HashMap::from([]))?;
-            shell::run(
-                &mirror_dir,
-                "git pull --rebase",
-                &mut output,
-                HashMap::from([]),
-            )?;
-        } else {
-            let mut output = shell::Output::None;
-            shell::run(
-                &mirror_dir,
-                "git checkout ./",
-                &mut output,
-                HashMap::from([]),
-            )?;
-            shell::run(&mirror_dir, "git clean -df", &mut output, HashMap::from([]))?;
-            shell::run(
-                &mirror_dir,
-                "git pull --rebase",
-                &mut output,
-                HashMap::from([]),
-            )?;
-        };
-    } else {
-        eprintln!("Pulling build-in formula...");
+    for source in tap::list(cask, config)? {
+        if let Some(url) = &source.url {
+            sync_source(cask, &source, url, is_verbose)?;
+        }
+    }
 
-        let client = git::new("https://github.com/cask-pkg/cask-core")?;
+    tap::mark_updated(cask)
+}
 
-        client.clone(
-            &mirror_dir,
-            git::CloneOption {
-                depth: Some(1),
-                quiet: Some(true),
-                verbose: Some(is_verbose),
-                progress: Some(true),
-                single_branch: Some(true),
-                dissociate: Some(true),
-                filter: Some("tree:0".to_string()),
-            },
-        )?
+// clone the formula repository of source, or pull it if it has been cloned
+pub fn sync_source(
+    cask: &cask::Cask,
+    source: &tap::Source,
+    url: &str,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let mirror_dir = &source.dir;
+
+    if mirror_dir.exists() {
+        info!("Updating {} formula...", source.name);
+    } else {
+        info!("Pulling {} formula...", source.name);
     }
 
-    eprintln!("Sync remote build-in formula success");
+    // the git command runs with the token of config, the folder is reset to the latest commit of default branch
+    let client = cask.settings().get_git_repository(cask.config(), url)?;
+
+    retry(
+        &cask.settings().get_retry_policy(),
+        GitError::is_transient,
+        || {
+            client.update(
+                mirror_dir,
+                git::CloneOption {
+                    depth: Some(1),
+                    quiet: Some(true),
+                    verbose: Some(is_verbose),
+                    progress: Some(true),
+                    single_branch: Some(true),
+                    dissociate: Some(true),
+                    filter: Some("tree:0".to_string()),
+                    sparse: None,
+                    reference: None,
+                },
+            )
+        },
+    )?;
+
+    info!("Sync remote {} formula success", source.name);
 
     Ok(())
 }
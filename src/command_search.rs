@@ -0,0 +1,55 @@
+#![deny(warnings)]
+
+use crate::{cask, command_remote_sync, output, registry};
+
+use eyre::Report;
+use serde::Serialize;
+use tabled::{settings::Style, Table, Tabled};
+
+// the package in the search result, the fields are kept stable for the JSON output
+#[derive(Serialize, Debug, Tabled)]
+struct PackageInfo {
+    name: String,
+    bin: String,
+    popularity: u64,
+    description: String,
+}
+
+// search the packages in the index of build-in formula, so that the repositories of packages are not fetched.
+// the build-in formula is synced on the first search, run 'cask update-index' to update it
+pub async fn search(
+    cask: &cask::Cask,
+    keyword: &str,
+    is_print_as_json: bool,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    if !cask.build_in_formula_dir().exists() && !cask.settings().offline {
+        command_remote_sync::sync(cask, is_verbose)?;
+    }
+
+    let entries = registry::load(cask)?;
+
+    let packages = registry::search(&entries, keyword)
+        .into_iter()
+        .map(|e| PackageInfo {
+            name: e.name.clone(),
+            bin: e.bin.clone(),
+            popularity: e.popularity,
+            description: e.description.clone(),
+        })
+        .collect::<Vec<PackageInfo>>();
+
+    if is_print_as_json {
+        return output::print_json(&packages);
+    }
+
+    if packages.is_empty() {
+        return Err(eyre::format_err!("No package matches '{}'", keyword));
+    }
+
+    let table = Table::new(&packages).with(Style::psql()).to_string();
+
+    println!("{}", table);
+
+    Ok(())
+}
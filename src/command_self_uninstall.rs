@@ -5,6 +5,7 @@ use std::{env, error::Error, fs, path::PathBuf};
 use crate::cask;
 
 use eyre::Report;
+use tracing::{error, info};
 
 pub async fn self_uninstall(cask: &cask::Cask) -> Result<(), Report> {
     let root_dir = cask.root_dir();
@@ -14,7 +15,7 @@ pub async fn self_uninstall(cask: &cask::Cask) -> Result<(), Report> {
     fs::remove_dir_all(root_dir)?;
 
     fn when_delete_fail(_e: impl Error, filepath: PathBuf) -> Report {
-        eprintln!("self uninstall fail");
+        error!("self uninstall fail");
         eyre::format_err!("try delete file '{}' manually.", filepath.display())
     }
 
@@ -27,7 +28,7 @@ pub async fn self_uninstall(cask: &cask::Cask) -> Result<(), Report> {
         fs::remove_file(&exe_path).map_err(|e| when_delete_fail(e, exe_path))?;
     }
 
-    eprintln!("self uninstall success");
+    info!("self uninstall success");
 
     Ok(())
 }
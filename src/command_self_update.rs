@@ -2,14 +2,20 @@
 
 use std::env;
 use std::fs;
+use std::path::Path;
 
-use crate::cask;
+use crate::{
+    cask,
+    retry::{retry_async, retry_blocking},
+};
 
 use eyre::Report;
+use git::GitError;
 use semver::Version;
+use tracing::info;
 
-/// run the following command to show all build target
-/// rustc --print target-list
+// run the following command to show all build target
+// rustc --print target-list
 
 fn get_arch() -> String {
     #[cfg(target_arch = "arm")]
@@ -180,8 +186,16 @@ fn get_abi() -> Option<String> {
 }
 
 // get the latest version without 'v' prefix
-fn get_latest_release() -> Result<String, Report> {
-    let versions = git::new(env!("CARGO_PKG_REPOSITORY"))?.versions()?;
+async fn get_latest_release(cask: &cask::Cask) -> Result<String, Report> {
+    let repo = cask
+        .settings()
+        .get_git_repository(cask.config(), env!("CARGO_PKG_REPOSITORY"))?;
+    let versions = retry_blocking(
+        &cask.settings().get_retry_policy(),
+        GitError::is_transient,
+        move || repo.versions(false),
+    )
+    .await?;
 
     let err_can_not_found_release = eyre::format_err!("There is no one release of Cask");
 
@@ -194,8 +208,42 @@ fn get_latest_release() -> Result<String, Report> {
     Ok(latest_version.to_string())
 }
 
-pub async fn self_update(_cask: &cask::Cask) -> Result<(), Report> {
-    let latest_release = get_latest_release()?;
+// the running executable is renamed instead of overwritten, it can not be written on Windows
+fn replace_exe(
+    new_file: &Path,
+    current_file: &Path,
+    permissions: fs::Permissions,
+) -> Result<(), Report> {
+    let temp_file = env::temp_dir().join(format!(
+        "old_{}",
+        current_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    ));
+
+    fs::set_permissions(new_file, permissions)?;
+
+    if current_file.exists() {
+        fs::rename(current_file, &temp_file)?;
+    }
+
+    fs::rename(new_file, current_file)?;
+
+    // remove temp file
+    fs::remove_file(temp_file).ok();
+
+    Ok(())
+}
+
+pub async fn self_update(cask: &cask::Cask) -> Result<(), Report> {
+    if cask.settings().offline {
+        return Err(eyre::format_err!("Can not update Cask in offline mode"));
+    }
+
+    let config = cask.config();
+
+    let latest_release = get_latest_release(cask).await?;
 
     let latest_remote_version = Version::parse(&latest_release)
         .map_err(|e| eyre::format_err!("parse latest version '{}' fail: {}", &latest_release, e))?;
@@ -209,7 +257,7 @@ pub async fn self_update(_cask: &cask::Cask) -> Result<(), Report> {
     })?;
 
     if latest_remote_version <= current_version {
-        eprintln!("You are using the latest version of Cask");
+        info!("You are using the latest version of Cask");
         return Ok(());
     }
 
@@ -225,14 +273,23 @@ pub async fn self_update(_cask: &cask::Cask) -> Result<(), Report> {
 
     filename += ".tar.gz";
 
-    let resource_url = format!(
+    let resource_url = config.rewrite_url(&format!(
         "https://github.com/cask-pkg/cask.rs/releases/download/v{}/{}",
         &latest_release, filename,
-    );
+    ));
 
     let resource_file_path = env::temp_dir().join(format!("{}-{}", &latest_release, filename));
 
-    downloader::download(&resource_url, &resource_file_path).await?;
+    let download_option = cask
+        .settings()
+        .get_download_option(config, &resource_url, false);
+
+    retry_async(
+        &cask.settings().get_retry_policy(),
+        downloader::is_transient,
+        || downloader::download(&resource_url, &resource_file_path, &download_option),
+    )
+    .await?;
 
     #[cfg(unix)]
     let exe_name = env!("CARGO_BIN_NAME").to_string();
@@ -242,6 +299,11 @@ pub async fn self_update(_cask: &cask::Cask) -> Result<(), Report> {
     let binary_file_path =
         extractor::extract(&resource_file_path, &env::temp_dir(), &exe_name, "/")?;
 
+    // the shim of package executables is shipped next to Cask on Windows, it is missing in the old releases
+    #[cfg(windows)]
+    let shim_file_path =
+        extractor::extract(&resource_file_path, &env::temp_dir(), shim::EXE_NAME, "/").ok();
+
     // remove tarball file
     fs::remove_file(&resource_file_path).ok();
 
@@ -264,16 +326,18 @@ pub async fn self_update(_cask: &cask::Cask) -> Result<(), Report> {
         ));
     }
 
-    let temp_file = env::temp_dir().join(format!("old_{}", exe_name));
-
-    fs::set_permissions(&binary_file_path, permissions)?;
-    fs::rename(&current_bin_path, &temp_file)?;
-    fs::rename(binary_file_path, &current_bin_path)?;
+    #[cfg(windows)]
+    if let Some(shim_file_path) = shim_file_path {
+        replace_exe(
+            &shim_file_path,
+            &current_bin_path.with_file_name(shim::EXE_NAME),
+            permissions.clone(),
+        )?;
+    }
 
-    // remove temp file
-    fs::remove_file(temp_file).ok();
+    replace_exe(&binary_file_path, &current_bin_path, permissions)?;
 
-    eprintln!(
+    info!(
         "Update from '{}' to '{}' success!",
         env!("CARGO_PKG_VERSION"),
         &latest_release
@@ -1,43 +1,108 @@
 #![deny(warnings)]
 
-use crate::cask;
+use crate::{cask, error::CaskError, hooker, i18n::t, index, install, receipt, symlink};
 
 use std::fs;
 
 use eyre::Report;
+use tracing::{info, warn};
 
-pub async fn uninstall(cask: &cask::Cask, package_name: &str) -> Result<(), Report> {
-    let packages = cask.list_formula()?;
-
-    let package_formula = packages
-        .iter()
-        .find(|p| p.package.name == package_name)
-        .or_else(|| packages.iter().find(|p| p.package.bin == package_name))
-        .ok_or_else(|| {
-            eyre::format_err!("can not found the installed package '{}'", package_name)
-        })?;
-
-    // remove symlink file
-    if cfg!(unix) {
-        let symlink_file = cask.bin_dir().join(&package_formula.package.bin);
-        if symlink_file.exists() {
-            fs::remove_file(symlink_file).ok();
+pub async fn uninstall(
+    cask: &cask::Cask,
+    package_name: &str,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let package_formula = cask.find_formula(package_name)?.ok_or_else(|| {
+        CaskError::NotInstalled(format!(
+            "can not found the installed package '{}'",
+            package_name
+        ))
+    })?;
+
+    let package_lock = cask.lock_package(&package_formula.package.name)?;
+
+    let package_dir = cask.package_dir(&package_formula.package.name);
+
+    let version = package_formula
+        .cask
+        .as_ref()
+        .map(|c| c.version.clone())
+        .unwrap_or_default();
+
+    // the package installed with `--link-dir` is linked into the recorded folder
+    let link_dir = install::get_link_dir(cask, &package_formula.package.name);
+
+    // the hooks run in the repository, it does not exist if the formula was installed from file
+    let hook_cwd = match package_dir.join("repository") {
+        dir if dir.exists() => dir,
+        _ => package_dir.clone(),
+    };
+
+    let envs = hooker::get_envs(
+        &cask.shared_root_dir(),
+        &package_formula.package.name,
+        &version,
+        &cask.package_installed_dir(&package_formula.package.name, &version),
+        &link_dir,
+        None,
+    );
+
+    let hook_policy = cask.settings().get_hook_policy(cask.config(), false);
+
+    if let Some(hook) = &package_formula.hook {
+        hook.run(
+            "preuninstall",
+            &hook_cwd,
+            package_formula.ger_renderer_context(&version),
+            envs.clone(),
+            hook_policy,
+            is_verbose,
+        )?;
+    }
+
+    let lock = cask.lock()?;
+
+    // the hard link or the copy keeps working after the package is removed, so its failure is reported.
+    // eg. the executable is in use on Windows
+    let is_copied = matches!(
+        receipt::read(&package_dir)
+            .ok()
+            .flatten()
+            .and_then(|r| r.link),
+        Some(symlink::Strategy::Hardlink | symlink::Strategy::Copy)
+    );
+
+    // remove symlink file, and the launchers on Windows
+    for link_file in symlink::get_link_files(&link_dir.join(&package_formula.package.bin)) {
+        if let Err(e) = fs::remove_file(&link_file) {
+            if is_copied && link_file.exists() {
+                warn!("can not remove '{}': {}", link_file.display(), e);
+            }
         }
-    } else {
-        let bat_file_path = cask
-            .bin_dir()
-            .join(package_formula.package.bin.clone() + ".bat");
-        let bash_file_path = cask.bin_dir().join(&package_formula.package.bin);
-
-        fs::remove_file(bat_file_path).ok();
-        fs::remove_file(bash_file_path).ok();
     }
 
-    fs::remove_dir_all(cask.package_dir(&package_formula.package.name))?;
+    fs::remove_dir_all(&package_dir)?;
+
+    index::refresh(cask)?;
+
+    drop(lock);
+    drop(package_lock);
+
+    // the package folder is removed, so the hook runs in the root of Cask
+    if let Some(hook) = &package_formula.hook {
+        hook.run(
+            "postuninstall",
+            &cask.root_dir(),
+            package_formula.ger_renderer_context(&version),
+            envs,
+            hook_policy,
+            is_verbose,
+        )?;
+    }
 
-    eprintln!(
-        "The package '{}' has been uninstalled!",
-        package_formula.package.name
+    info!(
+        "{}",
+        t!("uninstall-uninstalled", name = package_formula.package.name)
     );
 
     Ok(())
@@ -1,25 +1,25 @@
 #![deny(warnings)]
 
-use crate::{cask, command_install, formula};
+use crate::{cask, error::CaskError, formula, i18n::t, install};
 
 use eyre::Report;
 use semver::Version;
+use tracing::{info, warn};
 
 pub async fn update(
     cask: &cask::Cask,
     package_name: &str,
     is_check_only: bool,
+    is_force: bool,
+    is_prerelease: bool,
     is_verbose: bool,
 ) -> Result<(), Report> {
-    let packages = cask.list_formula()?;
-
-    let package_formula = packages
-        .iter()
-        .find(|p| p.package.name == package_name)
-        .or_else(|| packages.iter().find(|p| p.package.bin == package_name))
-        .ok_or_else(|| {
-            eyre::format_err!("can not found the installed package '{}'", package_name)
-        })?;
+    let package_formula = cask.find_formula(package_name)?.ok_or_else(|| {
+        CaskError::NotInstalled(format!(
+            "can not found the installed package '{}'",
+            package_name
+        ))
+    })?;
 
     let cask_info = package_formula.cask.as_ref().ok_or_else(|| {
         eyre::format_err!(
@@ -28,52 +28,150 @@ pub async fn update(
         )
     })?;
 
-    let current = Version::parse(&cask_info.version)
-        .map_err(|e| eyre::format_err!("invalid semver version '{}': {}", &cask_info.version, e))?;
+    // keep the package on the channel that it was installed from
+    let channel = cask_info.channel.as_deref();
+
+    // keep the formula on the branch, tag or commit that it was installed from
+    let git_ref = cask_info.git_ref.as_deref();
 
-    let remote_formula = formula::fetch(cask, &package_formula.package.name, true, is_verbose)?;
+    // keep the package within the version requirement that it was installed with. eg. ^1.2
+    let requirement = cask_info.requirement.as_deref();
 
-    let remote_versions = remote_formula.get_versions()?;
+    let remote_formula = formula::fetch(
+        cask,
+        &package_formula.package.name,
+        git_ref,
+        true,
+        is_verbose,
+    )
+    .await?;
+
+    if let Some(msg) = remote_formula.get_deprecation_message() {
+        warn!("{}", msg);
+    }
 
-    let err_not_found_release = eyre::format_err!(
+    // the rolling release always points to the latest build
+    let is_rolling = match channel {
+        Some(name) => remote_formula.get_channel(name)?.version.is_some(),
+        None => false,
+    };
+
+    // the rolling release is not a semver version, so that it can not be matched by requirement
+    let requirement = requirement.filter(|_| !is_rolling);
+
+    let remote_versions = match channel {
+        Some(name) => remote_formula.get_channel_versions(cask, name).await?,
+        None => {
+            remote_formula
+                .get_versions(
+                    cask,
+                    is_prerelease
+                        || requirement
+                            .map(formula::is_prerelease_spec)
+                            .unwrap_or(false),
+                )
+                .await?
+        }
+    };
+
+    let err_not_found_release = CaskError::VersionNotFound(format!(
         "can not found any version on '{}' remote",
         &package_formula.package.name
-    );
+    ));
 
     if remote_versions.is_empty() {
-        return Err(err_not_found_release);
+        return Err(err_not_found_release.into());
     }
 
-    let latest_str = &remote_versions.first().ok_or(err_not_found_release)?;
-
-    let latest = Version::parse(latest_str)
-        .map_err(|e| eyre::format_err!("invalid semver version '{}': {}", latest_str, e))?;
+    let latest_str = &match requirement {
+        Some(req) => formula::resolve_version(&remote_versions, req)?.ok_or_else(|| {
+            CaskError::VersionNotFound(format!(
+                "can not found any version of '{}' that matches '{}'",
+                &package_formula.package.name, req
+            ))
+        })?,
+        None => remote_versions
+            .first()
+            .ok_or(err_not_found_release)?
+            .clone(),
+    };
+
+    if !is_rolling {
+        let current = Version::parse(&cask_info.version).map_err(|e| {
+            eyre::format_err!("invalid semver version '{}': {}", &cask_info.version, e)
+        })?;
 
-    if latest <= current {
-        eprintln!(
-            "You are using the latest version of '{}'",
-            &package_formula.package.name
-        );
-        return Ok(());
+        let latest = Version::parse(latest_str)
+            .map_err(|e| eyre::format_err!("invalid semver version '{}': {}", latest_str, e))?;
+
+        if latest <= current {
+            match requirement {
+                Some(req) => info!(
+                    "{}",
+                    t!(
+                        "update-latest-matches",
+                        name = package_formula.package.name,
+                        requirement = req
+                    )
+                ),
+                None => info!(
+                    "{}",
+                    t!("update-latest", name = package_formula.package.name)
+                ),
+            }
+            return Ok(());
+        }
     }
 
     if is_check_only {
-        eprintln!(
-            "Found latest version {} of {}, but using {} currently",
-            latest, &package_formula.package.name, cask_info.version
-        );
+        if is_rolling {
+            info!(
+                "{}",
+                t!(
+                    "update-rolling",
+                    name = package_formula.package.name,
+                    channel = channel.unwrap_or_default()
+                )
+            );
+        } else {
+            info!(
+                "{}",
+                t!(
+                    "update-found",
+                    name = package_formula.package.name,
+                    current = cask_info.version,
+                    latest = latest_str
+                )
+            );
+        }
     } else {
-        command_install::install(
+        install::install(
             cask,
             &package_formula.package.name,
-            Some(latest_str),
-            is_verbose,
+            // the requirement is resolved to the latest version again, so that it is kept in the receipt
+            Some(requirement.unwrap_or(latest_str)),
+            &install::InstallOption {
+                force: is_force,
+                prerelease: is_prerelease,
+                verbose: is_verbose,
+                bin_dir: None,
+                link_dir: None,
+                channel,
+                git_ref,
+                keep_archive: false,
+                formula: None,
+            },
         )
         .await?;
 
-        eprintln!(
-            "Upgrade {}@{} from  to '{}' finish!",
-            &package_formula.package.name, cask_info.version, latest
+        info!(
+            "{}",
+            t!(
+                "update-upgraded",
+                name = package_formula.package.name,
+                current = cask_info.version,
+                latest = latest_str
+            )
         );
     }
 
@@ -0,0 +1,120 @@
+#![deny(warnings)]
+
+use crate::{cask, command_remote_sync, config, output, tap};
+
+use eyre::Report;
+use serde::Serialize;
+use tracing::{info, warn};
+
+// the changes of formula source, the fields are kept stable for the JSON output
+#[derive(Serialize, Debug)]
+struct SourceUpdate {
+    name: String,
+    added: usize,
+    changed: usize,
+    removed: usize,
+}
+
+impl SourceUpdate {
+    fn summary(&self) -> String {
+        if self.added + self.changed + self.removed == 0 {
+            return format!("{}: already up-to-date", self.name);
+        }
+
+        format!(
+            "{}: {} added, {} changed, {} removed",
+            self.name, self.added, self.changed, self.removed
+        )
+    }
+}
+
+// pull the build-in formula and the taps of git repository, the formula files are compared before and after it
+fn update(cask: &cask::Cask, is_verbose: bool) -> Result<Vec<SourceUpdate>, Report> {
+    if cask.settings().offline {
+        return Err(eyre::format_err!(
+            "Can not update formula sources in offline mode"
+        ));
+    }
+
+    let config = cask.config();
+
+    let mut updates = vec![];
+
+    for source in tap::list(cask, config)? {
+        let url = match &source.url {
+            Some(url) => url,
+            None => continue,
+        };
+
+        let before = tap::snapshot(&source.dir)?;
+
+        command_remote_sync::sync_source(cask, &source, url, is_verbose)?;
+
+        let changes = tap::diff(&before, &tap::snapshot(&source.dir)?);
+
+        updates.push(SourceUpdate {
+            name: source.name,
+            added: changes.added,
+            changed: changes.changed,
+            removed: changes.removed,
+        });
+    }
+
+    tap::mark_updated(cask)?;
+
+    Ok(updates)
+}
+
+pub fn update_index(
+    cask: &cask::Cask,
+    is_print_as_json: bool,
+    is_verbose: bool,
+) -> Result<(), Report> {
+    let updates = update(cask, is_verbose)?;
+
+    if is_print_as_json {
+        return output::print_json(&updates);
+    }
+
+    for u in updates {
+        println!("{}", u.summary());
+    }
+
+    Ok(())
+}
+
+// update the synced formula sources before the package is resolved if they are outdated, like 'brew update'.
+// the build-in formula that has not been synced is not cloned, and the package is installed without it if it fails
+pub fn auto_update(cask: &cask::Cask, config: &config::Config, is_verbose: bool) {
+    let ttl = cask.settings().index_ttl;
+
+    if cask.settings().offline || ttl.is_zero() || !tap::is_outdated(cask, ttl) {
+        return;
+    }
+
+    let is_synced = tap::list(cask, config)
+        .map(|sources| {
+            sources
+                .iter()
+                .any(|source| source.url.is_some() && source.dir.exists())
+        })
+        .unwrap_or(false);
+
+    if !is_synced {
+        return;
+    }
+
+    info!(
+        "Updating formula sources, it is updated every {} seconds by 'index_ttl' of config",
+        ttl.as_secs()
+    );
+
+    match update(cask, is_verbose) {
+        Ok(updates) => {
+            for u in updates {
+                info!("{}", u.summary());
+            }
+        }
+        Err(e) => warn!("update formula sources fail: {}", e),
+    }
+}
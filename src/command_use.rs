@@ -0,0 +1,64 @@
+#![deny(warnings)]
+
+use crate::{cask, error::CaskError, i18n::t, index, install, transaction};
+
+use eyre::Report;
+use tracing::info;
+
+pub async fn use_version(
+    cask: &cask::Cask,
+    package_name: &str,
+    version: &str,
+) -> Result<(), Report> {
+    let package_formula = cask.find_formula(package_name)?.ok_or_else(|| {
+        CaskError::NotInstalled(format!(
+            "can not found the installed package '{}'",
+            package_name
+        ))
+    })?;
+
+    let name = &package_formula.package.name;
+
+    let installed_versions = cask.list_installed_versions(name)?;
+
+    // the version may be specified with the prefix 'v'. eg. v1.0.0
+    let version = installed_versions
+        .iter()
+        .find(|v| v.trim_start_matches('v') == version.trim_start_matches('v'))
+        .ok_or_else(|| {
+            CaskError::NotInstalled(format!(
+                "The version '{}' of package '{}' is not installed. Try run 'cask install {}@{}' to install it.",
+                version,
+                name,
+                name,
+                version
+            ))
+        })?;
+
+    let _package_lock = cask.lock_package(name)?;
+    let _lock = cask.lock()?;
+
+    let mut transaction = transaction::new(&cask.package_version_dir(name).join(".backup"))?;
+
+    match install::activate(
+        cask,
+        name,
+        &package_formula.package.bin,
+        &cask.package_installed_dir(name, version),
+        None,
+        false,
+        &mut transaction,
+    ) {
+        Ok(()) => transaction.commit()?,
+        Err(e) => {
+            transaction.rollback()?;
+            return Err(e);
+        }
+    }
+
+    index::refresh(cask)?;
+
+    info!("{}", t!("use-in-use", name = name, version = version));
+
+    Ok(())
+}
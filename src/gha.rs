@@ -0,0 +1,129 @@
+#![deny(warnings)]
+
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::install;
+
+use eyre::Report;
+
+// the folders that have been added to $GITHUB_PATH in current run
+static ADDED_PATHS: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+// whether the GitHub Actions mode is enabled, it is resolved by the settings once
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// the data of workflow command is escaped, so the message of multiple lines is kept in one command
+pub fn escape(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// the command is written to stderr so that it keeps the order with the logs
+fn command(line: &str) {
+    downloader::suspend(|| writeln!(io::stderr(), "{}", line).ok());
+}
+
+// the logs between the start and the end of group are folded, the group ends when it is dropped
+pub struct Group;
+
+pub fn group(title: &str) -> Option<Group> {
+    if !is_enabled() {
+        return None;
+    }
+
+    command(&format!("::group::{}", escape(title)));
+
+    Some(Group)
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        command("::endgroup::");
+    }
+}
+
+// append the line to the file of environment variable, eg. $GITHUB_PATH or $GITHUB_OUTPUT
+fn append(name: &str, line: &str) -> Result<(), Report> {
+    let filepath = env::var(name)
+        .map_err(|_| eyre::format_err!("the environment variable '{}' is not set", name))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&filepath)
+        .map_err(|e| eyre::format_err!("can not open '{}': {}", filepath, e))?;
+
+    writeln!(file, "{}", line)?;
+
+    Ok(())
+}
+
+// add the folder to $PATH of the following steps, it is added once
+fn add_path(dir: &Path) -> Result<(), Report> {
+    if !is_enabled() {
+        return Ok(());
+    }
+
+    let dir = dir.display().to_string();
+    let mut added_paths = ADDED_PATHS.lock().expect("lock the added paths fail");
+
+    if added_paths.contains(&dir) {
+        return Ok(());
+    }
+
+    append("GITHUB_PATH", &dir)?;
+    added_paths.push(dir);
+
+    Ok(())
+}
+
+// the output of step, eg. '${{ steps.<id>.outputs.dvm }}' is the installed version of dvm
+fn set_output(name: &str, value: &str) -> Result<(), Report> {
+    if !is_enabled() {
+        return Ok(());
+    }
+
+    append("GITHUB_OUTPUT", &format!("{}={}", name, value))
+}
+
+// the installed packages can be used in the following steps, and their versions are the outputs of step
+pub fn export(installed: &[install::Installed]) -> Result<(), Report> {
+    for package in installed {
+        add_path(&package.bin_dir)?;
+        set_output(&package.bin, &package.version)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gha;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(gha::escape("foo"), "foo");
+        assert_eq!(
+            gha::escape("install fail: 100%\r\nLocation: src/main.rs"),
+            "install fail: 100%25%0D%0ALocation: src/main.rs"
+        );
+    }
+}
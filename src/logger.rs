@@ -0,0 +1,456 @@
+#![deny(warnings)]
+
+use crate::{cask, gha, util};
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    panic,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+};
+
+use chrono::Local;
+use eyre::Report;
+use tracing::{level_filters::LevelFilter, Event, Level, Subscriber};
+use tracing_subscriber::{
+    fmt::{
+        format::{self, FormatEvent, FormatFields},
+        FmtContext, MakeWriter,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    EnvFilter, Layer,
+};
+
+// the environment variable of log filter, it takes precedence over `--quiet` and `--verbose`.
+// eg. 'debug' or 'info,git=trace'
+pub const ENV_LOG: &str = "CASK_LOG";
+
+// the max number of log files, the oldest ones are removed
+const MAX_LOG_FILES: usize = 20;
+
+// the log file of current run, it is opened once the root of Cask is known
+static LOG_FILE: Mutex<Option<(PathBuf, File)>> = Mutex::new(None);
+
+// the level of verbosity. `--quiet` prints the errors only, `-v` prints the debug messages and `-vv` prints all
+pub fn get_default_level(is_quiet: bool, verbosity: u8) -> LevelFilter {
+    match (is_quiet, verbosity) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    }
+}
+
+// the message and the fields of event. eg. 'clone repository url=...'
+fn format_message<S, N>(ctx: &FmtContext<'_, S, N>, event: &Event<'_>) -> Result<String, fmt::Error>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let mut message = String::new();
+
+    ctx.format_fields(format::Writer::new(&mut message), event)?;
+
+    Ok(message)
+}
+
+// the info is printed as it is so that it reads like before
+struct ConsoleFormat;
+
+impl<S, N> FormatEvent<S, N> for ConsoleFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let message = format_message(ctx, event)?;
+        let metadata = event.metadata();
+
+        match *metadata.level() {
+            // the errors and warnings are annotated in the summary of workflow run
+            Level::ERROR if gha::is_enabled() => {
+                writeln!(writer, "::error::{}", gha::escape(&message))
+            }
+            Level::WARN if gha::is_enabled() => {
+                writeln!(writer, "::warning::{}", gha::escape(&message))
+            }
+            Level::ERROR => writeln!(writer, "Error: {}", message),
+            Level::WARN => writeln!(writer, "Warning: {}", message),
+            Level::INFO => writeln!(writer, "{}", message),
+            level => writeln!(writer, "[{}] {}: {}", level, metadata.target(), message),
+        }
+    }
+}
+
+// the line of log file, the following lines of message are indented. eg. the output of hook
+fn format_line(level: &Level, target: &str, message: &str) -> String {
+    format!(
+        "{} {:<5} {}: {}",
+        Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+        level,
+        target,
+        util::redact_secrets(message).replace('\n', "\n    ")
+    )
+}
+
+struct FileFormat;
+
+impl<S, N> FormatEvent<S, N> for FileFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let message = format_message(ctx, event)?;
+        let metadata = event.metadata();
+
+        writeln!(
+            writer,
+            "{}",
+            format_line(metadata.level(), metadata.target(), &message)
+        )
+    }
+}
+
+// the line is printed above the progress bars of downloading
+struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        downloader::suspend(|| io::stderr().write_all(buf))?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+// the lines are dropped until the log file is opened
+struct FileWriter;
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Ok(mut log_file) = LOG_FILE.lock() {
+            if let Some((_, file)) = log_file.as_mut() {
+                file.write_all(buf)?;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// whether the run fails, the messages of hook output are indented so they are never matched
+fn is_failure(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| line.split(' ').nth(1) == Some("ERROR"))
+}
+
+// the targets of Cask and its crates, the verbosity does not apply to the dependencies. eg. hyper
+const TARGETS: [&str; 6] = [
+    "cask",
+    "cask_core",
+    "downloader",
+    "extractor",
+    "git",
+    "shell",
+];
+
+// the filter of verbosity, the dependencies print the warnings only
+fn get_default_filter(level: LevelFilter) -> EnvFilter {
+    let directives = TARGETS
+        .iter()
+        .map(|target| format!("{}={}", target, level))
+        .collect::<Vec<String>>()
+        .join(",");
+
+    EnvFilter::builder()
+        .with_default_directive(level.min(LevelFilter::WARN).into())
+        .parse_lossy(directives)
+}
+
+// the events are printed to stderr by the filter, and the debug messages are written to the log file as well,
+// whatever the verbosity is
+fn new_subscriber<C, F>(
+    filter: EnvFilter,
+    file_level: LevelFilter,
+    console: C,
+    file: F,
+) -> impl Subscriber + Send + Sync
+where
+    C: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+    F: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .event_format(ConsoleFormat)
+                .with_writer(console)
+                .with_filter(filter),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .event_format(FileFormat)
+                .with_writer(file)
+                .with_filter(get_default_filter(file_level.max(LevelFilter::DEBUG))),
+        )
+}
+
+// install the logger for the process, the filter of `CASK_LOG` takes precedence
+pub fn init(is_quiet: bool, verbosity: u8) {
+    let level = get_default_level(is_quiet, verbosity);
+
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.min(LevelFilter::WARN).into())
+        .with_env_var(ENV_LOG)
+        .try_from_env()
+        .unwrap_or_else(|_| get_default_filter(level));
+
+    tracing::subscriber::set_global_default(new_subscriber(
+        filter,
+        level,
+        || ConsoleWriter,
+        || FileWriter,
+    ))
+    .ok();
+}
+
+// the log files, the newest first
+fn list_files(logs_dir: &Path) -> Result<Vec<PathBuf>, Report> {
+    if !logs_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = fs::read_dir(logs_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect::<Vec<PathBuf>>();
+
+    // the file name starts with the time of run
+    files.sort();
+    files.reverse();
+
+    Ok(files)
+}
+
+// remove the oldest log files, the newest `keep` files are kept
+fn rotate(logs_dir: &Path, keep: usize) -> Result<(), Report> {
+    for file in list_files(logs_dir)?.into_iter().skip(keep) {
+        fs::remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+// write the logs of current run to a new file, the command line is recorded so the run can be reproduced.
+// the panic is recorded as well, the command fails by panic
+pub fn open_file(cask: &cask::Cask, args: &[String]) -> Result<(), Report> {
+    let logs_dir = cask.logs_dir();
+
+    fs::create_dir_all(&logs_dir)?;
+    rotate(&logs_dir, MAX_LOG_FILES - 1)?;
+
+    let filepath = logs_dir.join(format!(
+        "{}-{}.log",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        process::id()
+    ));
+
+    let mut file = File::create(&filepath)?;
+
+    writeln!(file, "cask {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "command: {}", util::redact_secrets(&args.join(" ")))?;
+
+    if let Ok(mut log_file) = LOG_FILE.lock() {
+        *log_file = Some((filepath, file));
+    }
+
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        writeln!(
+            FileWriter,
+            "{}",
+            format_line(&Level::ERROR, "panic", &info.to_string())
+        )
+        .ok();
+        default_hook(info);
+    }));
+
+    Ok(())
+}
+
+// the log file of the last failing run, the current run is excluded
+pub fn find_last_failure(cask: &cask::Cask) -> Result<Option<PathBuf>, Report> {
+    let current = LOG_FILE
+        .lock()
+        .ok()
+        .and_then(|log_file| log_file.as_ref().map(|(path, _)| path.clone()));
+
+    for file in list_files(&cask.logs_dir())? {
+        if Some(&file) == current.as_ref() {
+            continue;
+        }
+
+        if is_failure(&fs::read_to_string(&file).unwrap_or_default()) {
+            return Ok(Some(file));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{level_filters::LevelFilter, Level};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use crate::{cask, logger};
+
+    // the lines that are written by the logger
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8_lossy(&self.0.lock().unwrap())
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_subscriber() {
+        let console = Buffer::default();
+        let file = Buffer::default();
+
+        let subscriber = logger::new_subscriber(
+            logger::get_default_filter(LevelFilter::INFO),
+            LevelFilter::INFO,
+            console.clone(),
+            file.clone(),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "cask::install", "installed");
+            tracing::debug!(target: "cask_core::formula", count = 3, "render");
+            tracing::info!(target: "hyper_util::client", "connect");
+            tracing::trace!(target: "git::http", "fetch");
+        });
+
+        // the debug messages and the dependencies are not printed
+        assert_eq!(console.lines(), vec!["installed"]);
+
+        let lines = file.lines();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" INFO  cask::install: installed"));
+        assert!(lines[1].ends_with(" DEBUG cask_core::formula: render count=3"));
+
+        let console = Buffer::default();
+
+        let subscriber = logger::new_subscriber(
+            logger::get_default_filter(LevelFilter::TRACE),
+            LevelFilter::TRACE,
+            console.clone(),
+            Buffer::default(),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::trace!(target: "git::http", "fetch");
+            tracing::info!(target: "hyper_util::client", "connect");
+        });
+
+        assert_eq!(console.lines(), vec!["[TRACE] git::http: fetch"]);
+    }
+
+    #[test]
+    fn test_get_default_level() {
+        assert_eq!(logger::get_default_level(true, 2), LevelFilter::ERROR);
+        assert_eq!(logger::get_default_level(false, 0), LevelFilter::INFO);
+        assert_eq!(logger::get_default_level(false, 1), LevelFilter::DEBUG);
+        assert_eq!(logger::get_default_level(false, 3), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn test_find_last_failure() {
+        let root = tempfile::tempdir().unwrap();
+        let c = cask::new(root.path());
+        let logs_dir = c.logs_dir();
+
+        fs::create_dir_all(&logs_dir).unwrap();
+
+        let ok = logger::format_line(&Level::INFO, "cask", "installed");
+        let output = logger::format_line(&Level::DEBUG, "cask::hooker", "output:\nfoo ERROR bar");
+        let fail = logger::format_line(&Level::ERROR, "panic", "install package fail!");
+
+        assert!(!logger::is_failure(&output));
+        assert!(logger::is_failure(&fail));
+
+        fs::write(logs_dir.join("20220101-000000-1.log"), &fail).unwrap();
+        fs::write(logs_dir.join("20220102-000000-1.log"), &fail).unwrap();
+        fs::write(logs_dir.join("20220103-000000-1.log"), ok + "\n" + &output).unwrap();
+        fs::write(logs_dir.join("foo.txt"), &fail).unwrap();
+
+        assert_eq!(
+            logger::find_last_failure(&c).unwrap(),
+            Some(logs_dir.join("20220102-000000-1.log"))
+        );
+
+        logger::rotate(&logs_dir, 1).unwrap();
+
+        assert_eq!(
+            logger::list_files(&logs_dir).unwrap(),
+            vec![logs_dir.join("20220103-000000-1.log")]
+        );
+        assert!(logs_dir.join("foo.txt").exists());
+        assert_eq!(logger::find_last_failure(&c).unwrap(), None);
+    }
+}
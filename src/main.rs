@@ -1,28 +1,89 @@
 #![deny(warnings)]
 
-mod cask;
+mod command_bugreport;
 mod command_check_updates;
 mod command_clean;
+mod command_config;
+mod command_env;
+mod command_exec;
+mod command_export;
+mod command_gc;
 mod command_homepage;
+mod command_import;
 mod command_info;
-mod command_install;
 mod command_list;
+mod command_manpage;
+mod command_migrate;
 mod command_relink;
 mod command_remote_list;
 mod command_remote_sync;
+mod command_search;
 mod command_self_uninstall;
 mod command_self_update;
 mod command_uninstall;
 mod command_update;
-mod formula;
-mod hooker;
-mod symlink;
-mod util;
+mod command_update_index;
+mod command_use;
+mod gha;
+mod logger;
+mod notify;
+mod output;
+mod wizard;
 
-use std::process;
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 
 use atty::{is, Stream};
-use clap::{arg, crate_version, Arg, Command};
+use cask_core::{
+    cache, cask, config, error, formula, hooker, i18n, index, install, lock, migration, project,
+    receipt, registry, retry, settings, symlink, tap, transaction, util,
+};
+use clap::{arg, crate_version, Arg, ArgAction, ArgGroup, Command};
+use eyre::Report;
+use tracing::{error, warn};
+
+// print the error and exit with its code, so that the scripts can tell the reason of failure
+fn exit_with_error(e: Report, msg: &str) -> ! {
+    error!("{}: {:?}", msg, e);
+    process::exit(error::get_exit_code(&e));
+}
+
+// load the config of root and resolve the settings once, the edits of config file do not change the running command.
+// the command that edits the config works even if the config is invalid
+fn load_settings(
+    cask: &cask::Cask,
+    flags: &settings::Flags,
+    vars: &HashMap<String, String>,
+    is_config: bool,
+) -> Result<(config::Config, settings::Settings), Report> {
+    let config = match config::load(cask) {
+        Ok(config) => config,
+        Err(e) if is_config => {
+            warn!("{}", e);
+
+            config::Config::default()
+        }
+        Err(e) => return Err(e),
+    };
+
+    let settings = settings::resolve(flags, vars, &config)?;
+
+    Ok((config, settings))
+}
+
+// read the whole stdin as text
+fn read_stdin() -> Result<String, Report> {
+    let mut buffer = Vec::new();
+    io::stdin().read_to_end(&mut buffer)?;
+
+    String::from_utf8(buffer).map_err(|e| eyre::format_err!("the stdin is not valid UTF-8: {}", e))
+}
 
 #[tokio::main]
 async fn main() {
@@ -32,61 +93,259 @@ async fn main() {
         .author(env!("CARGO_PKG_AUTHORS"))
         .subcommand_required(true)
         .allow_external_subcommands(true)
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .help("Do not access the network, use the cached formula, versions and resources")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("connect-timeout")
+                .long("connect-timeout")
+                .value_name("SECONDS")
+                .help("The timeout in seconds of connecting to the server")
+                .global(true)
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("The timeout in seconds of waiting for the response data or git command")
+                .global(true)
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("limit-rate")
+                .long("limit-rate")
+                .value_name("RATE")
+                .help("The max download speed per second. eg. 512K, 2M")
+                .global(true)
+                .value_parser(|s: &str| {
+                    util::parse_size(s)
+                        .map(|_| s.to_string())
+                        .map_err(|e| e.to_string())
+                }),
+        )
+        .arg(
+            Arg::new("insecure")
+                .long("insecure")
+                .help("Do not verify the TLS certificate of server, it is dangerous")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("DIR")
+                .help("The folder to put the packages, same as the environment variable CASK_HOME")
+                .global(true),
+        )
+        .arg(
+            Arg::new("local")
+                .long("local")
+                .help("Use the project-local packages in the folder '.cask' of the current project")
+                .global(true)
+                .conflicts_with("root")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Print verbose information. eg. the output of hooks, use -vv to print more")
+                .global(true)
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Print the errors only")
+                .global(true)
+                .conflicts_with("verbose")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print JSON instead of text for list, info, check-updates and remote list")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-hooks")
+                .long("no-hooks")
+                .help("Do not run the hooks of formula")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("When to use the colors and progress bars, they are disabled by NO_COLOR or if stderr is not a terminal in auto mode")
+                .global(true)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("no-progress")
+                .long("no-progress")
+                .help("Print the plain status lines instead of the progress bars")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("gha")
+                .long("gha")
+                .help("Run in GitHub Actions, the logs are folded, the bin folder is added to $GITHUB_PATH and the versions are set as step outputs")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Answer yes to the prompts, eg. the confirmation of hooks. The prompts are never shown in CI")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-wizard")
+                .long("no-wizard")
+                .help("Do not run the setup wizard on first run")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
         .subcommand(
             Command::new("install")
                 .visible_alias("i")
                 .about("Install package")
+                .long_about("Install package.\n\nThe package is the repository URL or the name of repository, eg. github.com/axetroy/dvm. It is resolved by the build-in formula first, then the file 'Cask.toml' of repository. The local formula file is installed by its path, eg. ./Cask.toml.\n\nThe latest version is installed if the version is not specified, and the version requirement like ^1.2 is kept on upgrade.")
                 .arg(
                     Arg::new("PACKAGE")
                         .required(is(Stream::Stdin))
+                        .num_args(1..)
+                        .help("The package name or repository url, followed by the version optionally. Use <PACKAGE>@<VERSION> to install multiple packages"),
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help("The max number of concurrent downloads when installing multiple packages, 4 by default")
+                        .value_parser(clap::value_parser!(usize))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Install the package even if it has been deprecated, or replace the files in the link folder that are not created by Cask")
+                        .num_args(0..=1),
+                )
+                .arg(
+                    Arg::new("pre")
+                        .long("pre")
+                        .help("Allow to install the pre-release version")
+                        .num_args(0..=1),
+                )
+                .arg(
+                    Arg::new("channel")
+                        .long("channel")
+                        .help("Install from the release channel of package, eg. beta/nightly")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("ref")
+                        .long("ref")
+                        .help("Fetch the formula from the branch, tag or commit of repository, it is kept on upgrade")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("bin-dir")
+                        .long("bin-dir")
+                        .help("Put the executable into the folder directly, eg. /usr/local/bin")
+                        .num_args(1)
+                        .requires("no-track"),
+                )
+                .arg(
+                    Arg::new("link-dir")
+                        .long("link-dir")
+                        .value_name("DIR")
+                        .help("Link the executable into the folder instead of the bin folder of Cask, eg. ~/.local/bin. It is kept when the package is upgraded")
                         .num_args(1)
-                        .help("The package name or repository url"),
+                        .conflicts_with_all(["bin-dir", "no-track"]),
                 )
                 .arg(
-                    Arg::new("VERSION")
-                        .required(false)
+                    Arg::new("no-track")
+                        .long("no-track")
+                        .help("Do not record the package in Cask, it can not be upgraded or uninstalled by Cask")
                         .num_args(0..=1)
-                        .help("Install specified version."),
+                        .requires("bin-dir"),
                 )
                 .arg(
-                    Arg::new("verbose")
-                        .short('v')
-                        .long("verbose")
-                        .help("Print verbose information")
+                    Arg::new("keep-archive")
+                        .long("keep-archive")
+                        .help("Download the archive into cache instead of extracting while downloading")
                         .num_args(0..=1),
                 )
+                .arg(
+                    Arg::new("from-tool-versions")
+                        .long("from-tool-versions")
+                        .help("Install the pinned versions of tools in the '.tool-versions' of asdf/mise in current directory")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["PACKAGE", "bin-dir", "no-track"]),
+                )
                 .arg_required_else_help(is(Stream::Stdin)),
         )
         .subcommand(
             Command::new("uninstall")
                 .visible_alias("rm")
                 .about("Uninstall package")
-                .arg(arg!(<PACKAGE> "The package name or the executable file name of the package"))
+                .long_about("Uninstall package.\n\nThe installed versions, the links in the bin folder and the files of package are removed. The hook 'preuninstall' and 'postuninstall' of formula run before and after it.")
+                .arg(arg!(<PACKAGE> "The package name, the executable file name or the alias of the package"))
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new("list")
                 .visible_alias("ls")
                 .about("List installed package")
+                .long_about("List installed package.\n\nThe name, executable, version and install time of packages are printed as a table. Use --json or --format for the scripts.")
                 .arg(
-                    Arg::new("json")
+                    Arg::new("print-json")
                         .short('j')
-                        .long("json")
-                        .help("Print json format instead of pretty format")
+                        .help("Same as --json")
                         .num_args(0..=1),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("TEMPLATE")
+                        .help("Print a line per package with the template, eg. \"{name}\\t{version}\". The fields are name, bin, version and install_at")
+                        .conflicts_with("print-json"),
                 ),
         )
         .subcommand(
             Command::new("info")
                 .about("Show information of package")
-                .arg(arg!(<PACKAGE> "The package name"))
+                .long_about("Show information of package.\n\nThe package is the name of installed package or the package on remote, the formula is fetched if it is not installed.")
+                .arg(arg!(<PACKAGE> "The package name or the alias of the package"))
+                .arg(
+                    Arg::new("files")
+                        .long("files")
+                        .help("Print the files that are installed by the package")
+                        .num_args(0..=1),
+                )
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new("update")
                 .visible_alias("upgrade")
                 .about("Upgrade package to latest")
-                .arg(arg!(<PACKAGE> "The package name"))
+                .long_about("Upgrade package to latest.\n\nThe package is upgraded within its release channel or version requirement that is chosen on install.")
+                .arg(arg!(<PACKAGE> "The package name or the alias of the package"))
                 .arg(
                     Arg::new("check-only")
                         .short('c')
@@ -95,14 +354,40 @@ async fn main() {
                         .num_args(0..=1),
                 )
                 .arg(
-                    Arg::new("verbose")
-                        .short('v')
-                        .long("verbose")
-                        .help("Print verbose information")
+                    Arg::new("pre")
+                        .long("pre")
+                        .help("Allow to upgrade to the pre-release version")
                         .num_args(0..=1),
                 )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Upgrade the package even if it has been deprecated, or replace the files in the link folder that are not created by Cask")
+                        .num_args(0..=1),
+                )
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("use")
+                .about("Switch to another installed version of package")
+                .long_about("Switch to another installed version of package.\n\nThe versions are kept side by side, the version is linked into the bin folder of Cask without downloading.")
+                .arg(arg!(<PACKAGE> "The package name"))
+                .arg(arg!(<VERSION> "The installed version"))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("search")
+                .about("Search package in the index of build-in formula")
+                .long_about("Search package in the index of build-in formula.\n\nThe keyword is matched with the short name, the full name and the description of packages, the popular packages are listed first. The index is synced on the first search, run 'cask update-index' to update it.\n\nThe package is installed by the short name in the index, eg. 'cask install ripgrep'.")
+                .arg(arg!(<KEYWORD> "The keyword of package"))
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("update-index")
+                .about("Update the build-in formula and taps")
+                .long_about("Update the build-in formula and taps.\n\nThe formula repositories are pulled and the number of added, changed and removed formulas is printed for each of them. The taps of local folder are not updated.\n\nThe synced formula repositories are updated automatically before 'install', 'update', 'info' and 'search' if they are not updated within 'index_ttl' of config, 86400 seconds by default. Set it or CASK_INDEX_TTL to 0 to disable it."),
+        )
         .subcommand(
             Command::new("homepage")
                 .visible_alias("home")
@@ -114,6 +399,7 @@ async fn main() {
             Command::new("check-updates")
                 .visible_alias("check-upgrades")
                 .about("Check and update packages to latest")
+                .long_about("Check and update packages to latest.\n\nThe outdated packages are printed, then they are updated unless --check-only is given. The desktop notification is shown when it finishes if 'notify' is enabled in the config.")
                 .arg(
                     Arg::new("check-only")
                         .short('c')
@@ -122,13 +408,17 @@ async fn main() {
                         .num_args(0..=1),
                 )
                 .arg(
-                    Arg::new("verbose")
-                        .short('v')
-                        .long("verbose")
-                        .help("Print verbose information")
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Upgrade the packages even if they have been deprecated, or replace the files in the link folder that are not created by Cask")
                         .num_args(0..=1),
                 ),
         )
+        .subcommand(
+            Command::new("migrate")
+                .about("Upgrade the storage of installed packages to the current layout"),
+        )
         .subcommand(
             Command::new("self-update")
                 .visible_alias("self-upgrade")
@@ -142,87 +432,475 @@ async fn main() {
                 .visible_alias("clear")
                 .about("Clear residual data"),
         )
-        .subcommand(Command::new("relink").about("Relink installed packages"))
+        .subcommand(
+            Command::new("gc")
+                .about("Remove the unused versions, download artifacts and caches")
+                .long_about("Remove the unused versions, download artifacts and caches.\n\nThe version in use is never removed. Use --dry-run to print what would be removed.")
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print what would be removed without removing it")
+                        .num_args(0..=1),
+                )
+                .arg(
+                    Arg::new("keep")
+                        .long("keep")
+                        .value_name("COUNT")
+                        .help("The number of the newest versions to keep besides the one in use, 1 by default")
+                        .value_parser(clap::value_parser!(usize))
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .value_name("DAYS")
+                        .help("Only remove the versions that are installed before the days, 30 by default")
+                        .value_parser(clap::value_parser!(u64))
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("relink")
+                .about("Relink installed packages")
+                .long_about("Relink installed packages.\n\nThe executables of active versions are linked into the bin folder again, by the strategy of 'link' in the config."),
+        )
+        .subcommand(
+            Command::new("env")
+                .about("Print the commands to add the bin folder of Cask to $PATH")
+                .long_about("Print the commands to add the bin folder of Cask to $PATH.\n\nRun eval \"$(cask env)\" for the current terminal, or --apply to update the profile of shell.")
+                .arg(
+                    Arg::new("apply")
+                        .long("apply")
+                        .help("Add the bin folder to $PATH persistently, it is the user PATH in registry on Windows")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Run the command with the project-local packages")
+                .long_about("Run the command with the project-local packages.\n\nThe packages of the project are installed into the folder '.cask' of project, its bin folder is put in front of $PATH.")
+                .arg(arg!(<COMMAND> "The command to run"))
+                .arg(
+                    Arg::new("ARGS")
+                        .help("The arguments of command")
+                        .num_args(..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export installed packages as Dockerfile or devcontainer feature")
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .help("The format of output")
+                        .value_parser(["dockerfile", "devcontainer"])
+                        .default_value("dockerfile")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("The folder of devcontainer feature, it is 'cask-packages' in the current dir by default")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Install the packages of Homebrew, Scoop or winget that have the cask equivalent")
+                .long_about("Install the packages of Homebrew, Scoop or winget that have the cask equivalent.\n\nThe packages are mapped by the files in the build-in formula repository, the packages without mapping are skipped.")
+                .arg(
+                    Arg::new("brewfile")
+                        .long("brewfile")
+                        .value_name("PATH")
+                        .help("The Brewfile to import")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("scoop")
+                        .long("scoop")
+                        .value_name("PATH")
+                        .help("The file exported by 'scoop export' to import")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("winget")
+                        .long("winget")
+                        .value_name("PATH")
+                        .help("The file exported by 'winget export' to import")
+                        .num_args(1),
+                )
+                .group(
+                    ArgGroup::new("source")
+                        .args(["brewfile", "scoop", "winget"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Get and set the configuration of Cask")
+                .long_about("Get and set the configuration of Cask in ~/.cask/config.toml.\n\nThe keys of tables are joined by the dot, eg. defaults.jobs or mirror.\"https://github.com/\". The config is validated before it is saved, and the comments of file are kept.")
+                .subcommand(
+                    Command::new("get")
+                        .about("Print the value of config")
+                        .arg(arg!(<KEY> "The key of config, eg. proxy or defaults.jobs"))
+                        .arg_required_else_help(true),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Set the value of config")
+                        .arg(arg!(<KEY> "The key of config, eg. proxy or defaults.jobs"))
+                        .arg(arg!(<VALUE> "The value of config, it is parsed as TOML value like 8, true or [\"a\"], otherwise it is a string"))
+                        .arg_required_else_help(true),
+                )
+                .subcommand(
+                    Command::new("unset")
+                        .about("Remove the value of config, the default value is used")
+                        .arg(arg!(<KEY> "The key of config, eg. proxy or defaults.jobs"))
+                        .arg_required_else_help(true),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .visible_alias("ls")
+                        .about("List the values of config that are set"),
+                ),
+        )
+        .subcommand(
+            Command::new("manpage")
+                .about("Generate the man pages of Cask")
+                .long_about("Generate the man pages of Cask.\n\nThe man pages are put into the folder 'share/man' of Cask by default, man finds it by the bin folder in $PATH, so 'man cask' works without setting $MANPATH.")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("The folder of man pages, the pages are put into its folder 'man1'")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("bugreport")
+                .about("Collect diagnostic information into an archive for bug report")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("The file path of archive")
+                        .num_args(1),
+                ),
+        )
         .subcommand(
             Command::new("remote")
                 .about("Operation for build-in formula")
                 .subcommand(
                     Command::new("sync")
-                        .about("Sync build-in formula from remote to local")
-                        .arg(
-                            Arg::new("verbose")
-                                .short('v')
-                                .long("verbose")
-                                .help("Print verbose information")
-                                .num_args(0..=1),
-                        ),
+                        .about("Sync build-in formula and taps from remote to local"),
                 )
                 .subcommand(
                     Command::new("list")
                         .visible_alias("ls")
-                        .about("List build-in formula on remote")
-                        .arg(
-                            Arg::new("verbose")
-                                .short('v')
-                                .long("verbose")
-                                .help("Print verbose information")
-                                .num_args(0..=1),
-                        ),
+                        .about("List build-in formula on remote"),
                 ),
         );
 
     let matches = app.clone().get_matches();
 
-    let home_dir = dirs::home_dir().expect("can not get home dir");
+    let verbosity = matches.get_count("verbose");
+    let is_verbose = verbosity > 0;
+
+    logger::init(matches.get_flag("quiet"), verbosity);
+
+    let flags = settings::Flags {
+        home: matches.get_one::<String>("root").map(PathBuf::from),
+        offline: matches.get_flag("offline"),
+        insecure: matches.get_flag("insecure"),
+        yes: matches.get_flag("yes"),
+        no_hooks: matches.get_flag("no-hooks"),
+        no_progress: matches.get_flag("no-progress"),
+        gha: matches.get_flag("gha"),
+        jobs: matches
+            .subcommand_matches("install")
+            .and_then(|m| m.get_one::<usize>("jobs").copied()),
+        limit_rate: matches.get_one::<String>("limit-rate").cloned(),
+        connect_timeout: matches.get_one::<u64>("connect-timeout").copied(),
+        timeout: matches.get_one::<u64>("timeout").copied(),
+    };
+
+    // the environment variables are read once, they are not changed while running
+    let vars = settings::vars();
+
+    if flags.insecure {
+        warn!("The TLS certificate verification is disabled by '--insecure'. The connection may be intercepted, use it only if you trust the network!");
+    }
+
+    let is_print_as_json = matches.get_flag("json");
+
+    // the hooks are confirmed in the terminal
+    hooker::set_confirm(Box::new(wizard::confirm_hook));
+
+    // the untracked install does not touch the cask root
+    let is_untracked_install = matches!(
+        matches.subcommand(),
+        Some(("install", sub_matches)) if sub_matches.contains_id("no-track")
+    );
+
+    // the command runs with the installed packages, it does not change the cask root
+    let is_exec = matches!(matches.subcommand(), Some(("exec", _)));
+
+    // the command adds the bin folder to $PATH, it is not in $PATH yet
+    let is_env = matches!(matches.subcommand(), Some(("env", _)));
+
+    // the command edits the config, it works even if the config is invalid
+    let is_config = matches!(matches.subcommand(), Some(("config", _)));
+
+    // the project-local packages are installed in the folder '.cask' of the current project
+    let is_local = matches.get_flag("local") || is_exec;
 
-    let cask = cask::new(&home_dir.join(".cask"));
+    let home = settings::get_home(&flags, &vars);
 
-    cask.init().expect("init cask fail");
+    let cask_root = if is_local {
+        let cwd = env::current_dir().expect("get current dir fail!");
 
-    cask.check_bin_path().unwrap_or_else(|e| {
-        eprint!("{}", e);
-        process::exit(1);
-    });
+        project::get_local_root(&cwd, &home)
+    } else {
+        home.clone()
+    };
+
+    let cask = if is_local {
+        cask::new_local(&cask_root, &home)
+    } else {
+        cask::new(&cask_root)
+    };
+
+    let (user_config, user_settings) = load_settings(&cask, &flags, &vars, is_config)
+        .unwrap_or_else(|e| exit_with_error(e, "load config fail"));
+
+    // guide the user to setup on first run, it only works in the interactive terminal
+    let is_first_run = !cask_root.exists()
+        && !is_untracked_install
+        && !is_local
+        && !matches.get_flag("no-wizard")
+        && user_settings.is_interactive();
+
+    // the root is chosen by the user, so the config of it is loaded again
+    let (cask, user_config, user_settings) = if is_first_run {
+        let cask = cask::new(&wizard::run(&home, &flags, &vars).expect("setup Cask fail!"));
+        let (user_config, user_settings) = load_settings(&cask, &flags, &vars, is_config)
+            .unwrap_or_else(|e| exit_with_error(e, "load config fail"));
+
+        (cask, user_config, user_settings)
+    } else {
+        (cask, user_config, user_settings)
+    };
+
+    // the project-local packages are linked into the bin folder of project
+    let cask = if is_local {
+        cask
+    } else {
+        let bin_dir = user_settings.get_bin_dir(&cask.root_dir());
+
+        cask.with_bin_dir(bin_dir)
+    };
+
+    let cask = cask.with_config(user_config).with_settings(user_settings);
+    let user_config = cask.config();
+    let user_settings = cask.settings();
+
+    if let Err(e) = user_config.apply_decompress() {
+        warn!("{}", e);
+    }
+
+    gha::set_enabled(user_settings.gha);
+
+    // the colors and progress bars are resolved once, `--color` takes precedence over the others.
+    // the plain status lines are printed instead of the progress bars, see https://no-color.org.
+    // the log of workflow run is not a terminal, the status lines are printed in GitHub Actions as well
+    let is_no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+    match matches.get_one::<String>("color").map(|x| x.as_str()) {
+        Some("always") => downloader::set_color(true),
+        Some("never") => downloader::set_color(false),
+        _ if is_no_color || user_settings.no_progress || user_settings.gha => {
+            downloader::set_plain()
+        }
+        _ => {}
+    }
+
+    if !is_untracked_install && !is_exec {
+        cask.init().expect("init cask fail");
+
+        // the logs help to report the issue, the command runs without them
+        if let Err(e) = logger::open_file(&cask, &env::args().collect::<Vec<String>>()) {
+            warn!("open the log file fail: {}", e);
+        }
+        migration::init(&cask).expect("init the storage layout fail");
+
+        // the storage of the old version is upgraded before it is used
+        if migration::is_outdated(&cask) && !matches!(matches.subcommand(), Some(("migrate", _))) {
+            migration::migrate(&cask).expect("migrate the storage fail!");
+        }
+
+        // the $PATH of current terminal is not updated by the wizard,
+        // and the project-local packages are used by 'cask exec'.
+        // the bin folder is added to $PATH of the following steps in GitHub Actions
+        if !is_first_run && !is_local && !is_env && !gha::is_enabled() {
+            cask.check_bin_path().unwrap_or_else(|e| {
+                error!("{}", e);
+                process::exit(1);
+            });
+        }
+    }
+
+    // the formula sources are refreshed before the package is resolved
+    if !is_untracked_install
+        && matches!(
+            matches.subcommand(),
+            Some(("install" | "update" | "info" | "search", _))
+        )
+    {
+        command_update_index::auto_update(&cask, user_config, is_verbose);
+    }
 
     match matches.subcommand() {
         Some(("install", sub_matches)) => {
-            let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
+            let args: Vec<&str> = sub_matches
+                .get_many::<String>("PACKAGE")
+                .map(|v| v.map(|x| x.as_str()).collect())
+                .unwrap_or_default();
 
-            let version = sub_matches.get_one::<String>("VERSION").map(|x| x.as_str());
-            let is_verbose = sub_matches.contains_id("verbose");
+            let packages = install::parse_install_args(&args);
+            let jobs = user_settings.jobs;
+            let is_force = sub_matches.contains_id("force");
+            let is_prerelease =
+                sub_matches.contains_id("pre") || user_config.defaults.pre == Some(true);
+            let bin_dir = sub_matches.get_one::<String>("bin-dir").map(Path::new);
+            // the links are used from anywhere, so the relative folder is resolved from the current dir
+            let link_dir = sub_matches
+                .get_one::<String>("link-dir")
+                .map(|dir| env::current_dir().expect("get current dir fail!").join(dir));
+            let channel = sub_matches.get_one::<String>("channel").map(|x| x.as_str());
+            let git_ref = sub_matches.get_one::<String>("ref").map(|x| x.as_str());
+            let is_keep_archive = sub_matches.contains_id("keep-archive")
+                || user_config.defaults.keep_archive == Some(true);
+            let is_from_tool_versions = sub_matches.get_flag("from-tool-versions");
 
-            command_install::install(&cask, package_name, version, is_verbose)
+            // the formula is piped into stdin if no package is given. eg. `cat Cask.toml | cask install`
+            let formula_content =
+                if packages.is_empty() && !is_from_tool_versions && !is(Stream::Stdin) {
+                    Some(
+                        read_stdin().unwrap_or_else(|e| {
+                            exit_with_error(e, "read formula from stdin fail!")
+                        }),
+                    )
+                } else {
+                    None
+                };
+
+            let options = install::InstallOption {
+                force: is_force,
+                prerelease: is_prerelease,
+                verbose: is_verbose,
+                bin_dir,
+                link_dir: link_dir.as_deref(),
+                channel,
+                git_ref,
+                keep_archive: is_keep_archive,
+                formula: formula_content.as_deref(),
+            };
+
+            if is_from_tool_versions {
+                command_import::import(
+                    &cask,
+                    command_import::Source::ToolVersions,
+                    Path::new(".tool-versions"),
+                    &options,
+                )
                 .await
-                .expect("install package fail!");
+                .unwrap_or_else(|e| exit_with_error(e, "install package fail!"));
+
+                return;
+            }
+
+            let _group = gha::group(format!("Install {}", args.join(" ")).trim_end());
+
+            match packages.as_slice() {
+                [] => install::install(&cask, "", None, &options)
+                    .await
+                    .map(|installed| vec![installed]),
+                [(package_name, version)] => {
+                    install::install(&cask, package_name, *version, &options)
+                        .await
+                        .map(|installed| vec![installed])
+                }
+                _ => install::install_many(&cask, &packages, &options, jobs).await,
+            }
+            .and_then(|installed| gha::export(&installed))
+            .unwrap_or_else(|e| exit_with_error(e, "install package fail!"));
         }
         Some(("uninstall", sub_matches)) => {
             let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
 
-            command_uninstall::uninstall(&cask, package_name)
+            command_uninstall::uninstall(&cask, package_name, is_verbose)
                 .await
-                .expect("uninstall package fail!");
+                .unwrap_or_else(|e| exit_with_error(e, "uninstall package fail!"));
         }
         Some(("list", sub_matches)) => {
-            let is_print_as_json = sub_matches.contains_id("json");
-            command_list::list(&cask, is_print_as_json)
+            let is_print_as_json = is_print_as_json || sub_matches.contains_id("print-json");
+            let format = sub_matches.get_one::<String>("format").map(|x| x.as_str());
+
+            command_list::list(&cask, is_print_as_json, format)
                 .await
                 .expect("list packages fail!");
         }
         Some(("info", sub_matches)) => {
             let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
+            let is_print_files = sub_matches.contains_id("files");
 
-            command_info::info(&cask, package_name)
+            command_info::info(&cask, package_name, is_print_files, is_print_as_json)
                 .await
-                .expect("info installed package fail!");
+                .unwrap_or_else(|e| exit_with_error(e, "info installed package fail!"));
         }
         Some(("update", sub_matches)) => {
             let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
             let is_check_only = sub_matches.contains_id("check-only");
-            let is_verbose = sub_matches.contains_id("verbose");
+            let is_force = sub_matches.contains_id("force");
+            let is_prerelease =
+                sub_matches.contains_id("pre") || user_config.defaults.pre == Some(true);
 
-            command_update::update(&cask, package_name, is_check_only, is_verbose)
+            command_update::update(
+                &cask,
+                package_name,
+                is_check_only,
+                is_force,
+                is_prerelease,
+                is_verbose,
+            )
+            .await
+            .unwrap_or_else(|e| exit_with_error(e, "update package fail!"));
+        }
+        Some(("use", sub_matches)) => {
+            let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
+            let version = sub_matches.get_one::<String>("VERSION").expect("required");
+
+            command_use::use_version(&cask, package_name, version)
                 .await
-                .expect("update package fail!");
+                .unwrap_or_else(|e| exit_with_error(e, "switch version of package fail!"));
+        }
+        Some(("search", sub_matches)) => {
+            let keyword = sub_matches.get_one::<String>("KEYWORD").expect("required");
+
+            command_search::search(&cask, keyword, is_print_as_json, is_verbose)
+                .await
+                .unwrap_or_else(|e| exit_with_error(e, "search package fail!"));
+        }
+        Some(("update-index", _)) => {
+            command_update_index::update_index(&cask, is_print_as_json, is_verbose)
+                .unwrap_or_else(|e| exit_with_error(e, "update formula sources fail!"));
         }
         Some(("homepage", sub_matches)) => {
             let package_name = sub_matches.get_one::<String>("PACKAGE").expect("required");
@@ -233,18 +911,150 @@ async fn main() {
         }
         Some(("check-updates", sub_matches)) => {
             let is_check_only = sub_matches.contains_id("check-only");
-            let is_verbose = sub_matches.contains_id("verbose");
+            let is_force = sub_matches.contains_id("force");
 
-            command_check_updates::check_updates(&cask, is_check_only, is_verbose)
-                .await
-                .expect("check-updates of packages fail!");
+            command_check_updates::check_updates(
+                &cask,
+                is_check_only,
+                is_force,
+                is_print_as_json,
+                is_verbose,
+            )
+            .await
+            .unwrap_or_else(|e| exit_with_error(e, "check-updates of packages fail!"));
         }
         Some(("clean", _sub_matches)) => {
             command_clean::clean(&cask).await.expect("clean fail!");
         }
+        Some(("gc", sub_matches)) => {
+            let is_dry_run = sub_matches.contains_id("dry-run");
+            let (default_keep, default_older_than) = user_config.get_retention();
+            let keep = sub_matches
+                .get_one::<usize>("keep")
+                .copied()
+                .unwrap_or(default_keep);
+            let older_than = sub_matches
+                .get_one::<u64>("older-than")
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+                .unwrap_or(default_older_than);
+
+            command_gc::gc(&cask, keep, older_than, is_dry_run)
+                .await
+                .expect("gc fail!");
+        }
         Some(("relink", _sub_matches)) => {
             command_relink::relink(&cask).await.expect("relink fail!");
         }
+        Some(("env", sub_matches)) => {
+            command_env::env(&cask, sub_matches.get_flag("apply"))
+                .await
+                .expect("setup the environment fail!");
+        }
+        Some(("export", sub_matches)) => {
+            let format = sub_matches.get_one::<String>("format").expect("required");
+
+            let output = sub_matches.get_one::<String>("output").map(Path::new);
+
+            command_export::export(&cask, format, output)
+                .await
+                .expect("export packages fail!");
+        }
+        Some(("import", sub_matches)) => {
+            let (source, filepath) = [
+                ("brewfile", command_import::Source::Brewfile),
+                ("scoop", command_import::Source::Scoop),
+                ("winget", command_import::Source::Winget),
+            ]
+            .into_iter()
+            .find_map(|(id, source)| {
+                sub_matches
+                    .get_one::<String>(id)
+                    .map(|filepath| (source, Path::new(filepath)))
+            })
+            .expect("required");
+
+            let options = install::InstallOption {
+                force: false,
+                prerelease: false,
+                verbose: is_verbose,
+                bin_dir: None,
+                link_dir: None,
+                channel: None,
+                git_ref: None,
+                keep_archive: false,
+                formula: None,
+            };
+
+            command_import::import(&cask, source, filepath, &options)
+                .await
+                .unwrap_or_else(|e| exit_with_error(e, "import packages fail!"));
+        }
+        Some(("exec", sub_matches)) => {
+            let command = sub_matches.get_one::<String>("COMMAND").expect("required");
+            let args: Vec<&str> = sub_matches
+                .get_many::<String>("ARGS")
+                .map(|v| v.map(|x| x.as_str()).collect())
+                .unwrap_or_default();
+
+            let code = command_exec::exec(&cask, command, &args)
+                .await
+                .expect("exec command fail!");
+
+            process::exit(code);
+        }
+        Some(("config", sub_matches)) => match sub_matches.subcommand() {
+            Some(("get", sub_matches)) => {
+                let key = sub_matches.get_one::<String>("KEY").expect("required");
+
+                command_config::get(&cask, key)
+                    .await
+                    .unwrap_or_else(|e| exit_with_error(e, "get config fail!"));
+            }
+            Some(("set", sub_matches)) => {
+                let key = sub_matches.get_one::<String>("KEY").expect("required");
+                let value = sub_matches.get_one::<String>("VALUE").expect("required");
+
+                command_config::set(&cask, key, value)
+                    .await
+                    .unwrap_or_else(|e| exit_with_error(e, "set config fail!"));
+            }
+            Some(("unset", sub_matches)) => {
+                let key = sub_matches.get_one::<String>("KEY").expect("required");
+
+                command_config::unset(&cask, key)
+                    .await
+                    .unwrap_or_else(|e| exit_with_error(e, "unset config fail!"));
+            }
+            Some(("list", _)) => {
+                command_config::list(&cask)
+                    .await
+                    .unwrap_or_else(|e| exit_with_error(e, "list config fail!"));
+            }
+            _ => {
+                let sub_cmd = app.find_subcommand_mut("config").unwrap();
+                sub_cmd.print_help().unwrap();
+                process::exit(0x1);
+            }
+        },
+        Some(("manpage", sub_matches)) => {
+            let output = sub_matches.get_one::<String>("output").map(Path::new);
+
+            command_manpage::manpage(&cask, &app, output)
+                .await
+                .expect("generate the man pages fail!");
+        }
+        Some(("bugreport", sub_matches)) => {
+            let output = sub_matches.get_one::<String>("output").map(Path::new);
+
+            command_bugreport::bugreport(&cask, output)
+                .await
+                .expect("generate bug report fail!");
+        }
+        Some(("migrate", _sub_matches)) => {
+            command_migrate::migrate(&cask)
+                .await
+                .expect("migrate the storage fail!");
+        }
         Some(("self-update", _sub_matches)) => {
             command_self_update::self_update(&cask)
                 .await
@@ -256,13 +1066,12 @@ async fn main() {
                 .expect("self-uninstall fail!");
         }
         Some(("remote", sub_matches)) => match sub_matches.subcommand() {
-            Some(("sync", sync_sub_matches)) => {
-                let is_verbose = sync_sub_matches.contains_id("verbose");
+            Some(("sync", _)) => {
                 command_remote_sync::sync(&cask, is_verbose).expect("sync build-in formula fail!");
             }
-            Some(("list", sync_sub_matches)) => {
-                let is_verbose = sync_sub_matches.contains_id("verbose");
-                command_remote_list::list(&cask, is_verbose).expect("list build-in formula fail!");
+            Some(("list", _)) => {
+                command_remote_list::list(&cask, is_print_as_json, is_verbose)
+                    .expect("list build-in formula fail!");
             }
             _ => {
                 let sub_cmd = app.find_subcommand_mut("remote").unwrap();
@@ -275,7 +1084,7 @@ async fn main() {
                 .get_many::<String>("")
                 .unwrap_or_default()
                 .collect::<Vec<_>>();
-            eprintln!("Unknown the command {:?} with argument {:?}", ext, args);
+            error!("Unknown the command {:?} with argument {:?}", ext, args);
             app.print_help().unwrap();
             process::exit(0x1);
         }
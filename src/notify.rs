@@ -0,0 +1,113 @@
+#![deny(warnings)]
+
+use crate::{cask, util, wizard};
+
+use std::{process::Command, time::Duration};
+
+use eyre::Report;
+use tracing::debug;
+
+// the operation that finishes sooner is not notified, the user is still watching the terminal
+const MIN_DURATION: Duration = Duration::from_secs(30);
+
+// the application ID of PowerShell, the toast of the unregistered application is not shown
+const WINDOWS_APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+// the string literal of AppleScript, the backslash and double quote are escaped
+fn quote_applescript(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn get_windows_script(title: &str, message: &str) -> String {
+    [
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null".to_string(),
+        "$template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)".to_string(),
+        "$texts = $template.GetElementsByTagName('text')".to_string(),
+        format!("$texts.Item(0).AppendChild($template.CreateTextNode({})) > $null", wizard::quote_powershell(title)),
+        format!("$texts.Item(1).AppendChild($template.CreateTextNode({})) > $null", wizard::quote_powershell(message)),
+        format!(
+            "[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($template))",
+            wizard::quote_powershell(WINDOWS_APP_ID)
+        ),
+    ]
+    .join("\n")
+}
+
+// show the notification by the tool of system, it is 'notify-send' of libnotify on Linux
+fn show(title: &str, message: &str) -> Result<(), Report> {
+    if cfg!(windows) {
+        wizard::run_powershell(&get_windows_script(title, message))?;
+
+        return Ok(());
+    }
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            quote_applescript(message),
+            quote_applescript(title)
+        ));
+
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+
+        command.arg(title).arg(message);
+
+        command
+    };
+
+    let output = command.output()?;
+
+    if !output.status.success() {
+        return Err(eyre::format_err!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+// notify the result of the operation that takes a long time, if it is enabled by `notify` of config.
+// the notification is not shown in CI, and its failure does not fail the operation
+pub fn notify(cask: &cask::Cask, elapsed: Duration, message: &str) {
+    if elapsed < MIN_DURATION || util::is_ci() {
+        return;
+    }
+
+    let is_enabled = cask.config().notify.unwrap_or(false);
+
+    if !is_enabled {
+        return;
+    }
+
+    if let Err(e) = show("Cask", message) {
+        debug!("show the desktop notification fail: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notify;
+
+    #[test]
+    fn test_quote_applescript() {
+        assert_eq!(notify::quote_applescript("foo"), "\"foo\"");
+        assert_eq!(
+            notify::quote_applescript(r#"say "hi" \o/"#),
+            r#""say \"hi\" \\o/""#
+        );
+    }
+
+    #[test]
+    fn test_get_windows_script() {
+        let script = notify::get_windows_script("Cask", "it's done");
+
+        assert!(script.contains("CreateTextNode('Cask')"));
+        assert!(script.contains("CreateTextNode('it''s done')"));
+    }
+}
@@ -0,0 +1,11 @@
+#![deny(warnings)]
+
+use eyre::Report;
+use serde::Serialize;
+
+// print the value as JSON in one line, it is the output of `--json` for the other programs
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Report> {
+    println!("{}", serde_json::to_string(value)?);
+
+    Ok(())
+}
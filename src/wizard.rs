@@ -0,0 +1,510 @@
+#![deny(warnings)]
+
+use crate::{cask, command_remote_sync, config, settings};
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use eyre::Report;
+use tracing::{info, warn};
+
+const GITHUB_URL: &str = "https://github.com/";
+
+// the user environment variables of Windows, they are inherited by the new processes
+const WINDOWS_ENV_KEY: &str = r"HKCU:\Environment";
+
+// broadcast WM_SETTINGCHANGE, so that Explorer and the new terminals reload the environment variables
+const WINDOWS_BROADCAST_SCRIPT: &str = r#"
+Add-Type -Namespace Cask -Name Native -MemberDefinition '[DllImport("user32.dll", CharSet = CharSet.Unicode)] public static extern IntPtr SendMessageTimeout(IntPtr hWnd, uint Msg, UIntPtr wParam, string lParam, uint fuFlags, uint uTimeout, out UIntPtr lpdwResult);'
+$result = [UIntPtr]::Zero
+[Cask.Native]::SendMessageTimeout([IntPtr]0xffff, 0x1a, [UIntPtr]::Zero, 'Environment', 2, 5000, [ref]$result) | Out-Null
+"#;
+
+// the root of Cask without the environment variables
+fn get_default_root() -> PathBuf {
+    dirs::home_dir()
+        .expect("can not get home dir")
+        .join(".cask")
+}
+
+#[derive(Debug, PartialEq)]
+enum Shell {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+fn detect_shell() -> Option<Shell> {
+    let shell = env::var("SHELL").ok()?;
+
+    let name = Path::new(&shell).file_name()?.to_string_lossy().to_string();
+
+    match name.as_str() {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "sh" | "dash" | "ksh" => Some(Shell::Sh),
+        _ => None,
+    }
+}
+
+fn get_profile(shell: &Shell, home: &Path) -> PathBuf {
+    match shell {
+        Shell::Sh => home.join(".profile"),
+        Shell::Bash => {
+            if cfg!(target_os = "macos") {
+                home.join(".bash_profile")
+            } else {
+                home.join(".bashrc")
+            }
+        }
+        Shell::Zsh => home.join(".zshrc"),
+        Shell::Fish => home.join(".config").join("fish").join("config.fish"),
+    }
+}
+
+// the lines append to the shell profile
+fn get_profile_content(shell: &Shell, root: Option<&Path>, bin_dir: &Path) -> String {
+    let mut lines = vec!["".to_string(), "# Cask".to_string()];
+
+    match shell {
+        Shell::Fish => {
+            if let Some(root) = root {
+                lines.push(format!(
+                    r#"set -gx {} "{}""#,
+                    settings::ENV_HOME,
+                    root.display()
+                ));
+            }
+            lines.push(format!(r#"fish_add_path "{}""#, bin_dir.display()));
+        }
+        _ => {
+            if let Some(root) = root {
+                lines.push(format!(
+                    r#"export {}="{}""#,
+                    settings::ENV_HOME,
+                    root.display()
+                ));
+            }
+            lines.push(format!(r#"export PATH="{}:$PATH""#, bin_dir.display()));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// the lines to setup the current PowerShell session
+fn get_powershell_content(root: Option<&Path>, bin_dir: &Path) -> String {
+    let mut lines = vec![];
+
+    if let Some(root) = root {
+        lines.push(format!(
+            "$env:{} = {}",
+            settings::ENV_HOME,
+            quote_powershell(&root.display().to_string())
+        ));
+    }
+
+    lines.push(format!(
+        "$env:Path = {} + $env:Path",
+        quote_powershell(&format!("{};", bin_dir.display()))
+    ));
+
+    lines.join("\n") + "\n"
+}
+
+// the single quote is escaped by doubling it in the single-quoted string of PowerShell
+pub fn quote_powershell(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+// append the folder to the PATH of Windows, None if it is in the PATH already
+fn append_windows_path(paths: &str, dir: &Path) -> Option<String> {
+    let dir = dir.display().to_string();
+
+    // the path of Windows is case-insensitive
+    let normalize = |p: &str| p.trim_end_matches(['\\', '/']).to_lowercase();
+
+    if paths.split(';').any(|p| normalize(p) == normalize(&dir)) {
+        return None;
+    }
+
+    let paths = paths.trim_end_matches(';');
+
+    if paths.is_empty() {
+        Some(dir)
+    } else {
+        Some(format!("{};{}", paths, dir))
+    }
+}
+
+pub fn run_powershell(script: &str) -> Result<String, Report> {
+    let mut buf = vec![];
+
+    shell::run_with(
+        shell::Terminal::PowerShell,
+        &env::temp_dir(),
+        script,
+        &mut shell::Output::Writer(&mut buf),
+        HashMap::new(),
+    )
+    .map_err(|e| eyre::format_err!("{}: {}", e, String::from_utf8_lossy(&buf).trim()))?;
+
+    Ok(String::from_utf8_lossy(&buf).trim().to_string())
+}
+
+// add the bin folder to the user PATH in registry, there is no shell profile on Windows.
+// return false if it is in the PATH already
+fn setup_windows_path(root: Option<&Path>, bin_dir: &Path) -> Result<bool, Report> {
+    let key = quote_powershell(WINDOWS_ENV_KEY);
+
+    // the variables in PATH such as '%USERPROFILE%' are kept as they are
+    let paths = run_powershell(&format!(
+        "(Get-Item -Path {}).GetValue('Path', '', 'DoNotExpandEnvironmentNames')",
+        key
+    ))?;
+
+    let paths = match append_windows_path(&paths, bin_dir) {
+        Some(paths) => paths,
+        None => return Ok(false),
+    };
+
+    let mut script = vec![format!(
+        "Set-ItemProperty -Path {} -Name 'Path' -Value {} -Type ExpandString",
+        key,
+        quote_powershell(&paths)
+    )];
+
+    if let Some(root) = root {
+        script.push(format!(
+            "Set-ItemProperty -Path {} -Name {} -Value {} -Type String",
+            key,
+            quote_powershell(settings::ENV_HOME),
+            quote_powershell(&root.display().to_string())
+        ));
+    }
+
+    script.push(WINDOWS_BROADCAST_SCRIPT.to_string());
+
+    run_powershell(&script.join("\n"))?;
+
+    Ok(true)
+}
+
+// append the lines to the shell profile, return false if the bin folder is in the profile already
+fn setup_profile(
+    shell: &Shell,
+    profile: &Path,
+    root: Option<&Path>,
+    bin_dir: &Path,
+) -> Result<bool, Report> {
+    let content = fs::read_to_string(profile).unwrap_or_default();
+
+    if content.contains(&bin_dir.display().to_string()) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = profile.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(profile)?;
+
+    file.write_all(get_profile_content(shell, root, bin_dir).as_bytes())?;
+
+    Ok(true)
+}
+
+// the lines to setup the environment of current terminal, eg. eval "$(cask env)"
+pub fn get_env_content(root: &Path, bin_dir: &Path) -> String {
+    let default_root = get_default_root();
+    let custom_root = if root == default_root {
+        None
+    } else {
+        Some(root)
+    };
+
+    if cfg!(windows) {
+        get_powershell_content(custom_root, bin_dir)
+    } else {
+        let shell = detect_shell().unwrap_or(Shell::Sh);
+
+        get_profile_content(&shell, custom_root, bin_dir)
+            .trim_start()
+            .to_string()
+    }
+}
+
+// add the bin folder to $PATH persistently without prompt.
+// return where the PATH is saved, None if it is in the PATH already
+pub fn apply_path(root: &Path, bin_dir: &Path) -> Result<Option<String>, Report> {
+    let default_root = get_default_root();
+    let custom_root = if root == default_root {
+        None
+    } else {
+        Some(root)
+    };
+
+    if cfg!(windows) {
+        return Ok(setup_windows_path(custom_root, bin_dir)?
+            .then(|| "the user environment variables".to_string()));
+    }
+
+    let shell = detect_shell().ok_or_else(|| {
+        eyre::format_err!(
+            "can not detect your shell, add '{}' to your $PATH environment variable manually",
+            bin_dir.display()
+        )
+    })?;
+
+    let home = dirs::home_dir().ok_or_else(|| eyre::format_err!("can not get home dir"))?;
+    let profile = get_profile(&shell, &home);
+
+    Ok(setup_profile(&shell, &profile, custom_root, bin_dir)?
+        .then(|| format!("'{}'", profile.display())))
+}
+
+fn prompt(question: &str, default: &str) -> Result<String, Report> {
+    if default.is_empty() {
+        eprint!("{}: ", question);
+    } else {
+        eprint!("{} [{}]: ", question, default);
+    }
+
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    let answer = answer.trim();
+
+    if answer.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
+
+fn confirm(question: &str) -> Result<bool, Report> {
+    let answer = prompt(question, "Y/n")?;
+
+    Ok(!matches!(answer.to_lowercase().as_str(), "n" | "no"))
+}
+
+// print the script of hook and ask whether to run it, the default answer is no
+pub fn confirm_hook(hook_name: &str, script: &str) -> Result<bool, Report> {
+    eprintln!("The '{}' hook will run the script:", hook_name);
+    eprintln!();
+
+    for line in script.trim().lines() {
+        eprintln!("    {}", line);
+    }
+
+    eprintln!();
+
+    let answer = prompt(&format!("Run the '{}' hook? [y/N]", hook_name), "")?;
+
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn setup_path(root: &Path, bin_dir: &Path, default_root: &Path) -> Result<(), Report> {
+    let home = dirs::home_dir().ok_or_else(|| eyre::format_err!("can not get home dir"))?;
+
+    let custom_root = if root == default_root {
+        None
+    } else {
+        Some(root)
+    };
+
+    let print_manual_setup = || {
+        info!(
+            "Add '{}' to your $PATH environment variable manually.",
+            bin_dir.display()
+        );
+
+        if let Some(root) = custom_root {
+            info!(
+                "Set the environment variable {}='{}' to use the custom root.",
+                settings::ENV_HOME,
+                root.display()
+            );
+        }
+    };
+
+    if cfg!(windows) {
+        if !confirm(&format!(
+            "Add '{}' to the user PATH in registry?",
+            bin_dir.display()
+        ))? {
+            print_manual_setup();
+            return Ok(());
+        }
+
+        setup_windows_path(custom_root, bin_dir)?;
+
+        info!("The user PATH has been updated, restart your terminal to make it work.");
+
+        return Ok(());
+    }
+
+    let shell = match detect_shell() {
+        Some(shell) if cfg!(unix) => shell,
+        _ => {
+            warn!("Can not detect your shell.");
+            print_manual_setup();
+            return Ok(());
+        }
+    };
+
+    let profile = get_profile(&shell, &home);
+
+    if !confirm(&format!(
+        "Add '{}' to $PATH in '{}'?",
+        bin_dir.display(),
+        profile.display()
+    ))? {
+        print_manual_setup();
+        return Ok(());
+    }
+
+    setup_profile(&shell, &profile, custom_root, bin_dir)?;
+
+    info!(
+        "'{}' has been updated, restart your terminal to make it work.",
+        profile.display()
+    );
+
+    Ok(())
+}
+
+// guide the user to setup Cask on first run, return the root of Cask
+pub fn run(
+    default_root: &Path,
+    flags: &settings::Flags,
+    vars: &HashMap<String, String>,
+) -> Result<PathBuf, Report> {
+    eprintln!("Welcome to Cask! Let's setup it for the first run.");
+    eprintln!("Press Enter to accept the default value.");
+
+    let root = PathBuf::from(prompt(
+        "Where to put the packages?",
+        &default_root.display().to_string(),
+    )?);
+
+    let mut config = config::load(&cask::new(&root))?;
+    let bin_dir = settings::resolve(flags, vars, &config)?.get_bin_dir(&root);
+
+    let cask = cask::new(&root).with_bin_dir(bin_dir);
+
+    cask.init()?;
+
+    setup_path(&root, &cask.bin_dir(), default_root)?;
+
+    let proxy = prompt("The proxy for downloading, leave empty to skip", "")?;
+    let github_mirror = prompt(
+        &format!("The mirror of '{}', leave empty to skip", GITHUB_URL),
+        "",
+    )?;
+    let github_token = prompt(
+        "The GitHub token for private packages, leave empty to skip",
+        "",
+    )?;
+
+    if !proxy.is_empty() || !github_mirror.is_empty() || !github_token.is_empty() {
+        if !proxy.is_empty() {
+            config.proxy = Some(proxy);
+        }
+
+        if !github_mirror.is_empty() {
+            config.mirror.insert(GITHUB_URL.to_string(), github_mirror);
+        }
+
+        if !github_token.is_empty() {
+            config.github_token = Some(github_token);
+        }
+
+        config::save(&cask, &config)?;
+
+        info!(
+            "The config has been saved to '{}'.",
+            cask.config_file().display()
+        );
+    }
+
+    if confirm("Sync the build-in formula now?")? {
+        let settings = settings::resolve(flags, vars, &config)?;
+        let cask = cask.with_config(config).with_settings(settings);
+
+        if let Err(e) = command_remote_sync::sync(&cask, false) {
+            warn!(
+                "Sync build-in formula fail: {}. Try run 'cask remote sync' later.",
+                e
+            );
+        }
+    }
+
+    info!("Cask is ready!");
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::wizard::{append_windows_path, get_powershell_content, get_profile_content, Shell};
+
+    #[test]
+    fn test_get_profile_content() {
+        assert_eq!(
+            get_profile_content(&Shell::Bash, None, Path::new("/home/foo/.cask/bin")),
+            "\n# Cask\nexport PATH=\"/home/foo/.cask/bin:$PATH\"\n"
+        );
+
+        assert_eq!(
+            get_profile_content(
+                &Shell::Fish,
+                Some(Path::new("/opt/cask")),
+                Path::new("/opt/cask/bin")
+            ),
+            "\n# Cask\nset -gx CASK_HOME \"/opt/cask\"\nfish_add_path \"/opt/cask/bin\"\n"
+        );
+    }
+
+    #[test]
+    fn test_get_powershell_content() {
+        assert_eq!(
+            get_powershell_content(
+                Some(Path::new(r"C:\Users\O'Neil\cask")),
+                Path::new(r"C:\Users\O'Neil\cask\bin")
+            ),
+            "$env:CASK_HOME = 'C:\\Users\\O''Neil\\cask'\n$env:Path = 'C:\\Users\\O''Neil\\cask\\bin;' + $env:Path\n"
+        );
+    }
+
+    #[test]
+    fn test_append_windows_path() {
+        let bin_dir = Path::new(r"C:\Users\foo\.cask\bin");
+
+        assert_eq!(
+            append_windows_path(r"%USERPROFILE%\bin;C:\tools;", bin_dir),
+            Some(r"%USERPROFILE%\bin;C:\tools;C:\Users\foo\.cask\bin".to_string())
+        );
+        assert_eq!(
+            append_windows_path("", bin_dir),
+            Some(r"C:\Users\foo\.cask\bin".to_string())
+        );
+
+        // the path is in the PATH already
+        assert_eq!(
+            append_windows_path(r"C:\tools;c:\users\foo\.cask\bin\", bin_dir),
+            None
+        );
+    }
+}